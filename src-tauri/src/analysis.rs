@@ -0,0 +1,535 @@
+use rayon::prelude::*;
+use realfft::RealFftPlanner;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::decode::{self, DecodeOptions, Pcm};
+use crate::keys::MusicalKey;
+use crate::path_codec;
+use crate::settings::AppSettings;
+
+/// Rate audio is decoded at for analysis; tempo and key live well below
+/// its 11 kHz Nyquist limit, and half of 44.1 kHz keeps resampling cheap.
+pub const ANALYSIS_RATE: u32 = 22050;
+
+/// Spectral frame size and hop at `ANALYSIS_RATE` for onsets: ~93 ms
+/// windows every ~23 ms.
+const FRAME_SIZE: usize = 2048;
+const HOP_SIZE: usize = 512;
+
+/// Key detection trades time for frequency resolution: 2.7 Hz bins tell
+/// semitones apart down to about 100 Hz.
+const KEY_FRAME_SIZE: usize = 8192;
+const KEY_HOP_SIZE: usize = 4096;
+
+/// Pitches outside this range (Hz) say little about the key: below it the
+/// bins are too coarse, above it harmonics and cymbals dominate.
+const KEY_PITCH_RANGE: (f32, f32) = (100.0, 5000.0);
+
+/// Krumhansl-Kessler probe-tone profiles, tonic first.
+const MAJOR_PROFILE: [f32; 12] = [6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88];
+const MINOR_PROFILE: [f32; 12] = [6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17];
+
+/// Tempos are searched in this range, then doubled or halved into
+/// `BPM_FOLD`, where dance music is counted; 140 half-time stays 140.
+const BPM_SEARCH: (f32, f32) = (50.0, 220.0);
+const BPM_FOLD: (f32, f32) = (70.0, 180.0);
+
+/// Tempo candidates are weighted towards this, an octave either side
+/// counting about half as much, to settle double/half-time ambiguity.
+const BPM_PRIOR_CENTER: f32 = 120.0;
+
+/// Multiples of a candidate beat period checked for correlation.
+const TEMPO_HARMONICS: usize = 4;
+
+/// Less onset envelope than this (about ten seconds) is too little to
+/// measure a tempo from.
+const MIN_TEMPO_FRAMES: usize = 430;
+
+/// First argument that starts the executable as a headless analysis worker
+/// instead of the app; see `run_worker`.
+pub const WORKER_ARG: &str = "--analysis-worker";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnalysisKind {
+    /// Decoding alone, to tell I/O and codec cost from analysis cost.
+    Decode,
+    /// Decoding plus the onset envelope that tempo detection works from.
+    Onsets,
+    Bpm,
+    Key,
+}
+
+impl AnalysisKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            AnalysisKind::Decode => "decode",
+            AnalysisKind::Onsets => "onsets",
+            AnalysisKind::Bpm => "bpm",
+            AnalysisKind::Key => "key",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "decode" => Some(AnalysisKind::Decode),
+            "onsets" => Some(AnalysisKind::Onsets),
+            "bpm" => Some(AnalysisKind::Bpm),
+            "key" => Some(AnalysisKind::Key),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalysisResult {
+    #[serde(with = "crate::path_codec")]
+    pub file_path: PathBuf,
+    /// Length of the audio analyzed.
+    pub audio_seconds: f64,
+    #[serde(default)]
+    pub bpm: Option<f32>,
+    #[serde(default)]
+    pub key: Option<KeyEstimate>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct KeyEstimate {
+    pub key: MusicalKey,
+    /// How far the best key's profile correlation is ahead of the runner-up,
+    /// 0 to 1; below about 0.05 the audio is ambiguous (relative keys,
+    /// atonal passages).
+    pub confidence: f32,
+}
+
+/// How a batch is spread: `workers` threads in each of `processes`
+/// processes. A single process means the app itself; more are child
+/// worker processes.
+#[derive(Debug, Clone, Copy)]
+pub struct AnalysisPlan {
+    pub workers: usize,
+    pub processes: usize,
+}
+
+impl AnalysisPlan {
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        AnalysisPlan {
+            workers: settings.analysis_workers,
+            processes: settings.analysis_processes.max(1),
+        }
+    }
+
+    /// Threads per process; 0 leaves one per core across all processes.
+    fn workers_per_process(&self) -> usize {
+        match self.workers {
+            0 => (std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1) / self.processes).max(1),
+            workers => workers,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalysisBenchmark {
+    pub kind: AnalysisKind,
+    pub files: usize,
+    pub failed: usize,
+    pub audio_seconds: f64,
+    pub elapsed_ms: u64,
+    pub files_per_second: f64,
+    /// Seconds of audio analyzed per second of wall time.
+    pub realtime_factor: f64,
+    pub workers: usize,
+    pub processes: usize,
+}
+
+/// Mono audio at `ANALYSIS_RATE`, the input every analysis works from.
+pub fn decode_for_analysis(path: &Path) -> Result<Pcm, String> {
+    decode::decode(path, &DecodeOptions {
+        sample_rate: Some(ANALYSIS_RATE),
+        mono: true,
+        ..Default::default()
+    })
+}
+
+/// Calls `visit` with the magnitude spectrum of each Hann-windowed frame of
+/// mono `samples`. The FFT plan and buffers are made once per call and
+/// reused for every frame.
+pub fn for_each_spectrum(samples: &[f32], frame_size: usize, hop_size: usize, mut visit: impl FnMut(&[f32])) {
+    if samples.len() < frame_size {
+        return;
+    }
+
+    let fft = RealFftPlanner::<f32>::new().plan_fft_forward(frame_size);
+    let window: Vec<f32> = (0..frame_size)
+        .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / frame_size as f32).cos())
+        .collect();
+    let mut input = fft.make_input_vec();
+    let mut spectrum = fft.make_output_vec();
+    let mut scratch = fft.make_scratch_vec();
+    let mut magnitudes = vec![0.0f32; spectrum.len()];
+
+    for frame in samples.windows(frame_size).step_by(hop_size) {
+        for ((slot, sample), weight) in input.iter_mut().zip(frame).zip(&window) {
+            *slot = sample * weight;
+        }
+        // Only fails on buffers of the wrong length, which these are not.
+        if fft.process_with_scratch(&mut input, &mut spectrum, &mut scratch).is_err() {
+            return;
+        }
+        for (magnitude, bin) in magnitudes.iter_mut().zip(&spectrum) {
+            *magnitude = bin.norm();
+        }
+        visit(&magnitudes);
+    }
+}
+
+/// Spectral flux per hop: how much louder each frame is than the last,
+/// summed over frequency bins on a log scale. Peaks mark note onsets.
+pub fn onset_envelope(samples: &[f32]) -> Vec<f32> {
+    let mut envelope = Vec::with_capacity(samples.len() / HOP_SIZE);
+    let mut previous: Vec<f32> = Vec::new();
+
+    for_each_spectrum(samples, FRAME_SIZE, HOP_SIZE, |magnitudes| {
+        let current: Vec<f32> = magnitudes.iter().map(|m| m.ln_1p()).collect();
+        let flux = if previous.is_empty() {
+            0.0
+        } else {
+            current.iter().zip(&previous).map(|(now, before)| (now - before).max(0.0)).sum()
+        };
+        envelope.push(flux);
+        previous = current;
+    });
+    envelope
+}
+
+/// Tempo of mono `samples` at `ANALYSIS_RATE`, from the autocorrelation
+/// of their onset envelope, rounded to a tenth of a beat per minute.
+/// `None` for audio too short or without a steady pulse.
+pub fn estimate_bpm(samples: &[f32]) -> Option<f32> {
+    let envelope = onset_envelope(samples);
+    if envelope.len() < MIN_TEMPO_FRAMES {
+        return None;
+    }
+
+    // Subtracting a one-second moving average leaves the peaks, so
+    // sustained loud passages do not correlate with everything.
+    let frame_rate = ANALYSIS_RATE as f32 / HOP_SIZE as f32;
+    let radius = (frame_rate / 2.0) as usize;
+    let peaks: Vec<f32> = (0..envelope.len())
+        .map(|i| {
+            let window = &envelope[i.saturating_sub(radius)..(i + radius + 1).min(envelope.len())];
+            let mean = window.iter().sum::<f32>() / window.len() as f32;
+            (envelope[i] - mean).max(0.0)
+        })
+        .collect();
+
+    let min_lag = (frame_rate * 60.0 / BPM_SEARCH.1).floor() as usize;
+    let max_lag = (frame_rate * 60.0 / BPM_SEARCH.0).ceil() as usize;
+    let correlation: Vec<f32> = (0..=(TEMPO_HARMONICS * (max_lag + 1)).min(peaks.len() / 2))
+        .map(|lag| {
+            let pairs = peaks.len() - lag;
+            peaks[..pairs].iter().zip(&peaks[lag..]).map(|(a, b)| a * b).sum::<f32>() / pairs as f32
+        })
+        .collect();
+
+    // Strongest correlation within `reach` frames of `lag`, since a true
+    // period between frames drifts further off whole multiples.
+    let near = |lag: usize, reach: usize| -> Option<(usize, f32)> {
+        (lag.saturating_sub(reach)..=lag + reach)
+            .filter_map(|l| correlation.get(l).map(|&c| (l, c)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    };
+
+    // A beat period correlates at every multiple of itself, and music
+    // divides a beat in two, so the half period counts for something too.
+    // Three hat hits make a period as regular as two but do not halve,
+    // which keeps them from passing for a tempo of two thirds.
+    let &(lag, _) = (min_lag..=max_lag.min(correlation.len() - 1))
+        .map(|lag| {
+            let comb: f32 = (1..=TEMPO_HARMONICS)
+                .filter_map(|k| near(k * lag, k / 2).map(|(_, c)| c))
+                .chain(near(lag / 2, 1).map(|(_, c)| c * 0.5))
+                .sum();
+            let octaves = (60.0 * frame_rate / lag as f32 / BPM_PRIOR_CENTER).log2();
+            (lag, comb * (-0.5 * octaves * octaves).exp())
+        })
+        .collect::<Vec<_>>()
+        .iter()
+        .filter(|(_, score)| *score > 0.0)
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    // The period is measured at its highest multiple in range, where a
+    // frame of error is spread over several beats, and pinned between
+    // frames with a parabola through the neighbouring lags.
+    let multiple = (1..=TEMPO_HARMONICS).rev()
+        .find(|k| k * lag + k / 2 < correlation.len() - 1)?;
+    let (peak, value) = near(multiple * lag, multiple / 2)?;
+    let offset = match (peak.checked_sub(1).map(|l| correlation[l]), correlation.get(peak + 1)) {
+        (Some(before), Some(&after)) => {
+            let curvature = before - 2.0 * value + after;
+            if curvature < 0.0 { 0.5 * (before - after) / curvature } else { 0.0 }
+        }
+        _ => 0.0,
+    };
+
+    let mut bpm = 60.0 * frame_rate * multiple as f32 / (peak as f32 + offset);
+    while bpm < BPM_FOLD.0 {
+        bpm *= 2.0;
+    }
+    while bpm > BPM_FOLD.1 {
+        bpm /= 2.0;
+    }
+    Some((bpm * 10.0).round() / 10.0)
+}
+
+/// Energy per pitch class (C = 0) over the whole of mono `samples`, each
+/// frame normalized so loud passages do not outweigh quiet ones.
+fn chromagram(samples: &[f32]) -> [f32; 12] {
+    let bin_hz = ANALYSIS_RATE as f32 / KEY_FRAME_SIZE as f32;
+    let first_bin = (KEY_PITCH_RANGE.0 / bin_hz).ceil() as usize;
+    let last_bin = (KEY_PITCH_RANGE.1 / bin_hz).floor() as usize;
+    let pitch_classes: Vec<usize> = (first_bin..=last_bin)
+        .map(|bin| {
+            let midi = 69.0 + 12.0 * (bin as f32 * bin_hz / 440.0).log2();
+            (midi.round() as i32).rem_euclid(12) as usize
+        })
+        .collect();
+
+    let mut chroma = [0.0f32; 12];
+    for_each_spectrum(samples, KEY_FRAME_SIZE, KEY_HOP_SIZE, |magnitudes| {
+        let mut frame = [0.0f32; 12];
+        for (magnitude, &pitch_class) in magnitudes[first_bin..=last_bin].iter().zip(&pitch_classes) {
+            frame[pitch_class] += magnitude;
+        }
+        let total: f32 = frame.iter().sum();
+        if total > 0.0 {
+            for (sum, value) in chroma.iter_mut().zip(frame) {
+                *sum += value / total;
+            }
+        }
+    });
+    chroma
+}
+
+fn correlation(a: &[f32; 12], b: &[f32; 12]) -> f32 {
+    let mean_a = a.iter().sum::<f32>() / 12.0;
+    let mean_b = b.iter().sum::<f32>() / 12.0;
+    let (mut covariance, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        covariance += (x - mean_a) * (y - mean_b);
+        var_a += (x - mean_a) * (x - mean_a);
+        var_b += (y - mean_b) * (y - mean_b);
+    }
+    if var_a == 0.0 || var_b == 0.0 {
+        return 0.0;
+    }
+    covariance / (var_a * var_b).sqrt()
+}
+
+/// Key of mono `samples` at `ANALYSIS_RATE`: the major or minor
+/// Krumhansl-Kessler profile, in any of the twelve transpositions, that
+/// correlates best with the chromagram. `None` for audio too short or
+/// without pitched content.
+pub fn estimate_key(samples: &[f32]) -> Option<KeyEstimate> {
+    let chroma = chromagram(samples);
+    if chroma.iter().all(|&c| c == 0.0) {
+        return None;
+    }
+
+    let mut scores: Vec<(MusicalKey, f32)> = Vec::with_capacity(24);
+    for tonic in 0..12 {
+        for (profile, minor) in [(&MAJOR_PROFILE, false), (&MINOR_PROFILE, true)] {
+            let mut rotated = [0.0f32; 12];
+            for (offset, weight) in profile.iter().enumerate() {
+                rotated[(tonic + offset) % 12] = *weight;
+            }
+            let key = MusicalKey { pitch_class: tonic as u8, minor };
+            scores.push((key, correlation(&chroma, &rotated)));
+        }
+    }
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let (key, best) = scores[0];
+    if best <= 0.0 {
+        return None;
+    }
+    Some(KeyEstimate {
+        key,
+        confidence: (best - scores[1].1).clamp(0.0, 1.0),
+    })
+}
+
+fn analyze(kind: AnalysisKind, path: &Path) -> AnalysisResult {
+    let mut result = AnalysisResult {
+        file_path: path.to_path_buf(),
+        audio_seconds: 0.0,
+        bpm: None,
+        key: None,
+        error: None,
+    };
+
+    match decode_for_analysis(path) {
+        Ok(pcm) => {
+            result.audio_seconds = pcm.frames() as f64 / pcm.sample_rate as f64;
+            match kind {
+                AnalysisKind::Decode => {}
+                AnalysisKind::Onsets => {
+                    std::hint::black_box(onset_envelope(&pcm.samples));
+                }
+                AnalysisKind::Bpm => result.bpm = estimate_bpm(&pcm.samples),
+                AnalysisKind::Key => result.key = estimate_key(&pcm.samples),
+            }
+        }
+        Err(e) => result.error = Some(e),
+    }
+    result
+}
+
+fn analyze_in_process(kind: AnalysisKind, paths: &[PathBuf], workers: usize) -> Result<Vec<AnalysisResult>, String> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(workers)
+        .build()
+        .map_err(|e| format!("Failed to start analysis workers: {}", e))?;
+
+    Ok(pool.install(|| paths.par_iter().map(|path| analyze(kind, path)).collect()))
+}
+
+/// Runs one shard in a child copy of this executable, feeding it paths on
+/// stdin and reading a JSON result per line from its stdout. Paths go in as
+/// JSON strings in `path_codec` form, so neither newlines nor bytes that
+/// are not UTF-8 can break them.
+fn spawn_shard(kind: AnalysisKind, paths: Vec<PathBuf>, workers: usize) -> Result<std::thread::JoinHandle<Result<Vec<AnalysisResult>, String>>, String> {
+    let exe = std::env::current_exe()
+        .map_err(|e| format!("Failed to locate executable: {}", e))?;
+    let mut child = Command::new(exe)
+        .args([WORKER_ARG, kind.as_str(), &workers.to_string()])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to start analysis process: {}", e))?;
+
+    let mut stdin = child.stdin.take().ok_or("Analysis process has no stdin")?;
+    let stdout = child.stdout.take().ok_or("Analysis process has no stdout")?;
+
+    Ok(std::thread::spawn(move || {
+        // Paths go in from a separate thread so a full stdout pipe cannot
+        // deadlock the two processes.
+        let feeder = std::thread::spawn(move || {
+            for path in &paths {
+                let Ok(line) = serde_json::to_string(&path_codec::encode(path)) else { continue };
+                if writeln!(stdin, "{}", line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let results = BufReader::new(stdout)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| serde_json::from_str(&line).ok())
+            .collect();
+
+        let _ = feeder.join();
+        child.wait()
+            .map_err(|e| format!("Analysis process failed: {}", e))?;
+        Ok(results)
+    }))
+}
+
+/// Analyzes every file per `plan`. With more than one process the files are
+/// dealt round-robin into shards, each analyzed by a worker process; files
+/// a crashed worker never reported on come back with an error.
+pub fn analyze_batch(kind: AnalysisKind, paths: &[PathBuf], plan: AnalysisPlan) -> Result<Vec<AnalysisResult>, String> {
+    let workers = plan.workers_per_process();
+    let processes = plan.processes.min(paths.len()).max(1);
+    if processes == 1 {
+        return analyze_in_process(kind, paths, workers);
+    }
+
+    let mut shards = vec![Vec::new(); processes];
+    for (index, path) in paths.iter().enumerate() {
+        shards[index % processes].push(path.clone());
+    }
+
+    let handles = shards
+        .into_iter()
+        .map(|shard| spawn_shard(kind, shard, workers))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut results = Vec::with_capacity(paths.len());
+    for handle in handles {
+        let shard = handle.join()
+            .map_err(|_| "Analysis shard panicked".to_string())??;
+        results.extend(shard);
+    }
+
+    let reported: std::collections::HashSet<PathBuf> = results.iter().map(|r| r.file_path.clone()).collect();
+    for path in paths.iter().filter(|path| !reported.contains(*path)) {
+        results.push(AnalysisResult {
+            file_path: path.clone(),
+            audio_seconds: 0.0,
+            bpm: None,
+            key: None,
+            error: Some("Analysis process exited before finishing this file".to_string()),
+        });
+    }
+    Ok(results)
+}
+
+/// Times `analyze_batch` over `paths`.
+pub fn benchmark(kind: AnalysisKind, paths: &[PathBuf], plan: AnalysisPlan) -> Result<AnalysisBenchmark, String> {
+    let started = Instant::now();
+    let results = analyze_batch(kind, paths, plan)?;
+    let elapsed = started.elapsed().max(Duration::from_millis(1));
+
+    let audio_seconds: f64 = results.iter().map(|r| r.audio_seconds).sum();
+    Ok(AnalysisBenchmark {
+        kind,
+        files: results.len(),
+        failed: results.iter().filter(|r| r.error.is_some()).count(),
+        audio_seconds,
+        elapsed_ms: elapsed.as_millis() as u64,
+        files_per_second: results.len() as f64 / elapsed.as_secs_f64(),
+        realtime_factor: audio_seconds / elapsed.as_secs_f64(),
+        workers: plan.workers_per_process(),
+        processes: plan.processes.min(paths.len()).max(1),
+    })
+}
+
+/// Entry point of a worker process: `<exe> --analysis-worker <kind>
+/// <threads>`. Reads one encoded path per line from stdin and writes
+/// each result to stdout as a JSON line as soon as it is ready.
+pub fn run_worker(args: &[String]) {
+    let Some(kind) = args.first().and_then(|kind| AnalysisKind::parse(kind)) else {
+        return;
+    };
+    let workers = args.get(1).and_then(|n| n.parse().ok()).unwrap_or(1);
+
+    let paths: Vec<PathBuf> = std::io::stdin()
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<String>(&line).ok())
+        .map(|path| path_codec::decode(&path))
+        .collect();
+
+    let stdout = std::sync::Mutex::new(std::io::stdout());
+    let Ok(pool) = rayon::ThreadPoolBuilder::new().num_threads(workers).build() else {
+        return;
+    };
+    pool.install(|| {
+        paths.par_iter().for_each(|path| {
+            if let Ok(line) = serde_json::to_string(&analyze(kind, path)) {
+                let mut out = stdout.lock().unwrap();
+                let _ = writeln!(out, "{}", line);
+                let _ = out.flush();
+            }
+        });
+    });
+}
@@ -0,0 +1,94 @@
+use id3::frame::ExtendedText;
+use id3::TagLike;
+use lofty::config::ParseOptions;
+use lofty::prelude::*;
+use lofty::probe::Probe;
+use lofty::tag::TagType;
+use std::path::Path;
+
+use crate::simulation::{self, SimulatedOperation};
+
+/// Reads the APEv2 tag of an MP3 as (key, value) text pairs. Stray APE tags
+/// are usually left behind by MP3Gain and similar tools.
+pub fn read_items(path: &Path) -> Result<Option<Vec<(String, String)>>, String> {
+    let tagged_file = Probe::open(path)
+        .map_err(|e| format!("Failed to open MP3 file: {}", e))?
+        .options(ParseOptions::new())
+        .read()
+        .map_err(|e| format!("Failed to read MP3 file: {}", e))?;
+
+    Ok(tagged_file.tag(TagType::Ape).map(|tag| {
+        tag.items()
+            .filter_map(|item| {
+                let key = item.key().map_key(TagType::Ape, true)?;
+                let value = item.value().text()?;
+                Some((key.to_string(), value.to_string()))
+            })
+            .collect()
+    }))
+}
+
+pub fn has_ape_tag(path: &Path) -> bool {
+    matches!(read_items(path), Ok(Some(_)))
+}
+
+/// Copies APE fields into the ID3v2 tag without overwriting existing values:
+/// standard fields fill empty ID3 fields, everything else becomes a TXXX frame.
+fn migrate_to_id3(path: &Path, items: &[(String, String)]) -> Result<(), String> {
+    let mut tag = id3::Tag::read_from_path(path)
+        .unwrap_or_else(|_| id3::Tag::new());
+
+    for (key, value) in items {
+        match key.to_lowercase().as_str() {
+            "title" if tag.title().is_none() => tag.set_title(value),
+            "artist" if tag.artist().is_none() => tag.set_artist(value),
+            "album" if tag.album().is_none() => tag.set_album(value),
+            "genre" if tag.genre().is_none() => tag.set_genre(value),
+            "year" if tag.year().is_none() => {
+                if let Ok(year) = value.trim().parse() {
+                    tag.set_year(year);
+                }
+            }
+            "title" | "artist" | "album" | "genre" | "year" => {}
+            _ => {
+                let exists = tag.extended_texts().any(|t| t.description.eq_ignore_ascii_case(key));
+                if !exists {
+                    tag.add_frame(ExtendedText {
+                        description: key.clone(),
+                        value: value.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    tag.write_to_path(path, id3::Version::Id3v24)
+        .map_err(|e| format!("Failed to write ID3 tags: {}", e))
+}
+
+/// Removes the APE tag, optionally migrating its fields into ID3v2 first.
+/// Returns false when the file had no APE tag. Neither change is journaled,
+/// so undo cannot bring the APE tag back.
+pub fn clean_ape_tag(path: &Path, migrate: bool) -> Result<bool, String> {
+    let items = match read_items(path)? {
+        Some(items) => items,
+        None => return Ok(false),
+    };
+
+    if simulation::is_enabled() {
+        simulation::record(SimulatedOperation::RemoveTag {
+            path: path.to_path_buf(),
+            tag: "APEv2".to_string(),
+        });
+        return Ok(true);
+    }
+
+    if migrate {
+        migrate_to_id3(path, &items)?;
+    }
+
+    TagType::Ape.remove_from_path(path)
+        .map_err(|e| format!("Failed to remove APE tag: {}", e))?;
+
+    Ok(true)
+}
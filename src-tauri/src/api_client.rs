@@ -1,33 +1,316 @@
 use serde::{Deserialize, Serialize};
-use reqwest::Client;
+use reqwest::{Client, StatusCode};
 use std::collections::HashMap;
-use std::time::{SystemTime, UNIX_EPOCH};
-use std::sync::Mutex;
+use std::future::Future;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
 
-static SPOTIFY_TOKEN_CACHE: Mutex<Option<TokenCache>> = Mutex::new(None);
-static BEATPORT_TOKEN_CACHE: Mutex<Option<TokenCache>> = Mutex::new(None);
+use crate::artwork::{self, ArtworkCandidate};
+use crate::consensus::ResolvedMetadata;
+use crate::scanner::{ExternalIds, ReleaseType};
+use crate::settings;
+use crate::stats::{self, Counter};
+
+// Async locks, held across a refresh; see `cached_token`.
+static SPOTIFY_TOKEN_CACHE: Mutex<Option<TokenCache>> = Mutex::const_new(None);
+static BEATPORT_TOKEN_CACHE: Mutex<Option<TokenCache>> = Mutex::const_new(None);
+
+const BEATPORT_CLIENT_ID: &str = "oeGScrHHsv1K1vO2Mby3sHQ7oZNWpViH";
 
 #[derive(Debug, Clone)]
 struct TokenCache {
     access_token: String,
     expires_at: u64,
+    /// `settings::generation()` when the token was issued; a later save
+    /// may have changed the credentials it belongs to.
+    settings_generation: u64,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Token bucket for the requests made to one provider: bursts of up to
+/// `capacity` requests, refilled at `per_second`. Every request to a
+/// provider acquires from its bucket first, so concurrent lookups keep to
+/// the provider's limit without coordinating.
+pub(crate) struct RateLimiter {
+    capacity: f64,
+    per_second: f64,
+    bucket: Mutex<Option<Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    pub(crate) const fn new(capacity: f64, per_second: f64) -> Self {
+        RateLimiter {
+            capacity,
+            per_second,
+            bucket: Mutex::const_new(None),
+        }
+    }
+
+    /// Takes a token, waiting for one to be refilled if the bucket is
+    /// empty. Waiters queue on the lock, so they are let through in order.
+    pub(crate) async fn acquire(&self) {
+        stats::bump(Counter::ApiCalls);
+        let mut bucket = self.bucket.lock().await;
+        let now = Instant::now();
+        let bucket = bucket.get_or_insert(Bucket {
+            tokens: self.capacity,
+            refilled_at: now,
+        });
+
+        let refilled = now.duration_since(bucket.refilled_at).as_secs_f64() * self.per_second;
+        bucket.tokens = (bucket.tokens + refilled).min(self.capacity);
+        bucket.refilled_at = now;
+
+        if bucket.tokens < 1.0 {
+            tokio::time::sleep(Duration::from_secs_f64((1.0 - bucket.tokens) / self.per_second)).await;
+            bucket.tokens = 1.0;
+            bucket.refilled_at = Instant::now();
+        }
+        bucket.tokens -= 1.0;
+    }
+}
+
+// Documented limits: MusicBrainz one request per second, Last.fm five per
+// second, Deezer 50 per five seconds. Spotify and Beatport publish none, so
+// they get a conservative rate.
+static MUSICBRAINZ_LIMIT: RateLimiter = RateLimiter::new(1.0, 1.0);
+static LASTFM_LIMIT: RateLimiter = RateLimiter::new(5.0, 5.0);
+static DEEZER_LIMIT: RateLimiter = RateLimiter::new(50.0, 10.0);
+static SPOTIFY_LIMIT: RateLimiter = RateLimiter::new(10.0, 5.0);
+static BEATPORT_LIMIT: RateLimiter = RateLimiter::new(5.0, 3.0);
+
+/// The cached token if it is still valid and settings have not been saved
+/// since, else a new one from `fetch`. The
+/// cache stays locked while fetching, so requests that find the token
+/// expired at the same time wait for one refresh instead of each making
+/// their own token call.
+async fn cached_token<F, Fut>(cache: &Mutex<Option<TokenCache>>, fetch: F) -> Result<String, String>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<TokenCache, String>>,
+{
+    let mut cache = cache.lock().await;
+    if let Some(cached) = cache.as_ref() {
+        if cached.expires_at > now_secs() && cached.settings_generation == settings::generation() {
+            return Ok(cached.access_token.clone());
+        }
+    }
+
+    let fresh = fetch().await?;
+    let access_token = fresh.access_token.clone();
+    *cache = Some(fresh);
+    Ok(access_token)
+}
+
+/// Drops the cached token if it is still `rejected`; another request may
+/// already have replaced it.
+async fn invalidate_token(cache: &Mutex<Option<TokenCache>>, rejected: &str) {
+    let mut cache = cache.lock().await;
+    if cache.as_ref().is_some_and(|cached| cached.access_token == rejected) {
+        *cache = None;
+    }
+}
+
+/// Sends an API request authorized with the token in `cache`. A 401 means
+/// the cached token was revoked or expired early, so it is dropped and the
+/// request retried once with a fresh token from `fetch`.
+async fn send_authorized<F, Fut>(
+    cache: &Mutex<Option<TokenCache>>,
+    limit: &RateLimiter,
+    fetch: F,
+    context: &str,
+    request: impl Fn(&str) -> reqwest::RequestBuilder,
+) -> Result<reqwest::Response, String>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<TokenCache, String>>,
+{
+    let access_token = cached_token(cache, &fetch).await?;
+    limit.acquire().await;
+    let response = request(&access_token)
+        .send()
+        .await
+        .map_err(|e| format!("{}: {}", context, e))?;
+
+    if response.status() != reqwest::StatusCode::UNAUTHORIZED {
+        return Ok(response);
+    }
+
+    invalidate_token(cache, &access_token).await;
+    let access_token = cached_token(cache, &fetch).await?;
+    limit.acquire().await;
+    request(&access_token)
+        .send()
+        .await
+        .map_err(|e| format!("{}: {}", context, e))
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MetadataResult {
+    /// The provider's main genre for the match.
     pub genre: Option<String>,
+    /// Every genre the provider gave, main genre first.
+    #[serde(default)]
+    pub genres: Vec<String>,
+    /// The provider's own name for `genre` when the genre mapping changed
+    /// it.
+    #[serde(default)]
+    pub source_genre: Option<String>,
     pub artist: Option<String>,
     pub confidence: Confidence,
     pub source: String,
+    /// Provider-specific id of the matched track, used for pinning.
+    #[serde(default)]
+    pub track_id: Option<String>,
+    /// Ids to write into the file's tags when this match is applied.
+    #[serde(default)]
+    pub external_ids: ExternalIds,
+    /// Release date as precise as the provider knows it (YYYY[-MM[-DD]]).
+    #[serde(default)]
+    pub release_date: Option<String>,
+    #[serde(default)]
+    pub release_type: Option<ReleaseType>,
+    /// Cover art for the matched release, if the provider has any.
+    #[serde(default)]
+    pub artwork_url: Option<String>,
+}
+
+/// Everything a metadata fetch found: suggestions in priority order, plus
+/// the providers that were searched successfully but had no match.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetadataLookup {
+    pub results: Vec<MetadataResult>,
+    pub no_match: Vec<String>,
+    /// How each provider's search went, including the ones that failed.
+    #[serde(default)]
+    pub providers: Vec<ProviderReport>,
+    /// The genre the results agree on, weighted by source.
+    #[serde(default)]
+    pub resolved: Option<ResolvedMetadata>,
+}
+
+impl MetadataLookup {
+    /// Adds a provider's search outcome. Failed searches are left out of
+    /// `results` and `no_match`, since they say nothing about whether a
+    /// match exists, and reported in `providers` instead.
+    pub fn record(&mut self, source: &str, outcome: Result<Option<MetadataResult>, String>) {
+        let report = match outcome {
+            Ok(Some(result)) => {
+                self.results.push(result);
+                ProviderOutcome::Matched
+            }
+            Ok(None) => {
+                self.no_match.push(source.to_string());
+                ProviderOutcome::NoMatch
+            }
+            Err(message) => ProviderOutcome::Error {
+                code: ProviderErrorCode::classify(&message),
+                message,
+            },
+        };
+        self.providers.push(ProviderReport {
+            source: source.to_string(),
+            outcome: report,
+        });
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderReport {
+    pub source: String,
+    #[serde(flatten)]
+    pub outcome: ProviderOutcome,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ProviderOutcome {
+    Matched,
+    NoMatch,
+    Error { code: ProviderErrorCode, message: String },
+}
+
+/// Broad reason a provider search failed, so the UI can tell a missing key
+/// from bad credentials or an outage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderErrorCode {
+    NotConfigured,
+    Auth,
+    RateLimited,
+    Unreachable,
+    BadResponse,
+    Other,
+}
+
+impl ProviderErrorCode {
+    /// Classifies the error messages the provider clients produce.
+    pub fn classify(message: &str) -> Self {
+        let lower = message.to_lowercase();
+        if lower.contains("not configured") {
+            ProviderErrorCode::NotConfigured
+        } else if lower.contains("auth failed") || lower.contains("401") || lower.contains("403") {
+            ProviderErrorCode::Auth
+        } else if lower.contains("429") {
+            ProviderErrorCode::RateLimited
+        } else if lower.contains("failed to parse") {
+            ProviderErrorCode::BadResponse
+        } else if lower.contains("search failed") || lower.contains("lookup failed") || lower.contains("failed to request") || lower.contains("failed to fetch") {
+            ProviderErrorCode::Unreachable
+        } else {
+            ProviderErrorCode::Other
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Confidence {
     High,
     Medium,
     Low,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", content = "message", rename_all = "snake_case")]
+pub enum ProviderStatus {
+    Ok,
+    NotConfigured,
+    AuthError(String),
+    Unreachable(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderHealth {
+    pub source: String,
+    pub status: ProviderStatus,
+}
+
+impl ProviderStatus {
+    fn from_response(result: Result<reqwest::Response, reqwest::Error>) -> Self {
+        match result {
+            Ok(response) if response.status().is_success() => ProviderStatus::Ok,
+            Ok(response) => match response.status() {
+                StatusCode::BAD_REQUEST | StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                    ProviderStatus::AuthError(response.status().to_string())
+                }
+                status => ProviderStatus::Unreachable(status.to_string()),
+            },
+            Err(e) => ProviderStatus::Unreachable(e.to_string()),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct SpotifyTokenResponse {
     access_token: String,
@@ -47,6 +330,61 @@ struct SpotifyTracks {
 struct SpotifyTrack {
     artists: Vec<SpotifyArtist>,
     id: String,
+    #[serde(default)]
+    album: Option<SpotifyAlbum>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SpotifyAlbum {
+    #[serde(default)]
+    release_date: Option<String>,
+    #[serde(default)]
+    album_type: Option<String>,
+    #[serde(default)]
+    images: Vec<SpotifyImage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SpotifyImage {
+    url: String,
+}
+
+impl SpotifyTrack {
+    fn release_date(&self) -> Option<String> {
+        self.album.as_ref()
+            .and_then(|album| album.release_date.clone())
+            .filter(|d| !d.is_empty())
+    }
+
+    fn release_type(&self) -> Option<ReleaseType> {
+        self.album.as_ref()
+            .and_then(|album| album.album_type.as_deref())
+            .and_then(ReleaseType::parse)
+    }
+
+    /// Spotify lists album images largest first.
+    fn artwork_url(&self) -> Option<String> {
+        self.album.as_ref()
+            .and_then(|album| album.images.first())
+            .map(|image| image.url.clone())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SpotifyAlbumSearchResponse {
+    albums: SpotifyAlbums,
+}
+
+#[derive(Debug, Deserialize)]
+struct SpotifyAlbums {
+    items: Vec<SpotifyAlbumItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SpotifyAlbumItem {
+    name: String,
+    #[serde(default)]
+    images: Vec<SpotifyImage>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -60,33 +398,40 @@ struct SpotifyArtistDetails {
     genres: Vec<String>,
 }
 
+/// A quoted field value for Spotify and Deezer search filters
+/// (`artist:"..."`). Neither has an escape syntax, so quotes inside the
+/// value are dropped rather than ending the phrase early.
+fn quoted_field(value: &str) -> String {
+    let value: String = value.chars().map(|c| if c == '"' { ' ' } else { c }).collect();
+    format!("\"{}\"", value.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
 pub struct SpotifyClient {
     client_id: Option<String>,
     client_secret: Option<String>,
+    market: Option<String>,
 }
 
 impl SpotifyClient {
-    pub fn new(client_id: Option<String>, client_secret: Option<String>) -> Self {
+    /// `market` (ISO 3166-1 alpha-2, e.g. "DE") limits matches to tracks
+    /// available in that country; anything else is ignored.
+    pub fn new(client_id: Option<String>, client_secret: Option<String>, market: Option<String>) -> Self {
         SpotifyClient {
             client_id,
             client_secret,
+            market: market
+                .map(|m| m.trim().to_ascii_uppercase())
+                .filter(|m| m.len() == 2 && m.chars().all(|c| c.is_ascii_alphabetic())),
         }
     }
 
-    async fn get_access_token(&self) -> Result<String, String> {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+    fn market_query(&self) -> Vec<(&str, &str)> {
+        self.market.iter().map(|market| ("market", market.as_str())).collect()
+    }
 
-        {
-            let cache = SPOTIFY_TOKEN_CACHE.lock().unwrap();
-            if let Some(cached) = cache.as_ref() {
-                if cached.expires_at > now {
-                    return Ok(cached.access_token.clone());
-                }
-            }
-        }
+    async fn request_token(&self) -> Result<TokenCache, String> {
+        let now = now_secs();
+        let settings_generation = settings::generation();
 
         let client_id = self.client_id.as_ref()
             .ok_or("Spotify client ID not configured")?;
@@ -97,6 +442,7 @@ impl SpotifyClient {
         let mut params = HashMap::new();
         params.insert("grant_type", "client_credentials");
 
+        SPOTIFY_LIMIT.acquire().await;
         let response = client
             .post("https://accounts.spotify.com/api/token")
             .basic_auth(client_id, Some(client_secret))
@@ -114,47 +460,61 @@ impl SpotifyClient {
             .await
             .map_err(|e| format!("Failed to parse token response: {}", e))?;
 
-        let expires_at = now + 3000;
-        
-        {
-            let mut cache = SPOTIFY_TOKEN_CACHE.lock().unwrap();
-            *cache = Some(TokenCache {
-                access_token: token_response.access_token.clone(),
-                expires_at,
-            });
-        }
+        Ok(TokenCache {
+            access_token: token_response.access_token,
+            expires_at: now + 3000,
+            settings_generation,
+        })
+    }
 
-        Ok(token_response.access_token)
+    async fn send_authorized(
+        &self,
+        context: &str,
+        request: impl Fn(&str) -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, String> {
+        send_authorized(&SPOTIFY_TOKEN_CACHE, &SPOTIFY_LIMIT, || self.request_token(), context, request).await
     }
 
+    pub async fn health_check(&self) -> ProviderStatus {
+        let (client_id, client_secret) = match (&self.client_id, &self.client_secret) {
+            (Some(id), Some(secret)) => (id, secret),
+            _ => return ProviderStatus::NotConfigured,
+        };
+
+        SPOTIFY_LIMIT.acquire().await;
+        let result = Client::new()
+            .post("https://accounts.spotify.com/api/token")
+            .basic_auth(client_id, Some(client_secret))
+            .form(&[("grant_type", "client_credentials")])
+            .send()
+            .await;
+
+        ProviderStatus::from_response(result)
+    }
+
+    /// Best match for the artist and title; `None` when the search ran but
+    /// found nothing.
     pub async fn search_track(
         &self,
         artist: &str,
         title: &str,
-    ) -> Result<MetadataResult, String> {
+    ) -> Result<Option<MetadataResult>, String> {
         if self.client_id.is_none() || self.client_secret.is_none() {
             return Err("Spotify API credentials not configured".to_string());
         }
 
-        let access_token = self.get_access_token().await?;
         let client = Client::new();
 
-        let quote_if_multiword = |s: &str| {
-            if s.contains(' ') {
-                format!("\"{}\"", s)
-            } else {
-                s.to_string()
-            }
-        };
-
-        let query = format!("artist:{} track:{}", quote_if_multiword(artist), quote_if_multiword(title));
-        let response = client
-            .get("https://api.spotify.com/v1/search")
-            .bearer_auth(&access_token)
-            .query(&[("q", query.as_str()), ("type", "track"), ("limit", "1")])
-            .send()
-            .await
-            .map_err(|e| format!("Spotify search failed: {}", e))?;
+        let query = format!("artist:{} track:{}", quoted_field(artist), quoted_field(title));
+        let response = self
+            .send_authorized("Spotify search failed", |token| {
+                client
+                    .get("https://api.spotify.com/v1/search")
+                    .bearer_auth(token)
+                    .query(&[("q", query.as_str()), ("type", "track"), ("limit", "1")])
+                    .query(&self.market_query())
+            })
+            .await?;
 
         if !response.status().is_success() {
             return Err(format!("Spotify API error: {}", response.status()));
@@ -166,31 +526,106 @@ impl SpotifyClient {
             .map_err(|e| format!("Failed to parse search response: {}", e))?;
 
         if search_response.tracks.items.is_empty() {
-            return Ok(MetadataResult {
-                genre: None,
-                artist: Some(artist.to_string()),
-                confidence: Confidence::Low,
-                source: "Spotify (No match)".to_string(),
-            });
+            return Ok(None);
         }
 
         let track = &search_response.tracks.items[0];
-        let artist_id = &track.artists[0].id;
-        let artist_name = &track.artists[0].name;
+        self.result_for_track(&client, track).await.map(Some)
+    }
 
-        let artist_response = client
-            .get(format!("https://api.spotify.com/v1/artists/{}", artist_id))
-            .bearer_auth(&access_token)
-            .send()
+    /// Largest cover of the best-matching album; `None` when the search
+    /// ran but found nothing.
+    pub async fn search_album_art(&self, artist: &str, album: &str) -> Result<Option<ArtworkCandidate>, String> {
+        if self.client_id.is_none() || self.client_secret.is_none() {
+            return Err("Spotify API credentials not configured".to_string());
+        }
+
+        let client = Client::new();
+        let query = format!("artist:{} album:{}", quoted_field(artist), quoted_field(album));
+        let response = self
+            .send_authorized("Spotify search failed", |token| {
+                client
+                    .get("https://api.spotify.com/v1/search")
+                    .bearer_auth(token)
+                    .query(&[("q", query.as_str()), ("type", "album"), ("limit", "1")])
+                    .query(&self.market_query())
+            })
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(format!("Spotify API error: {}", response.status()));
+        }
+
+        let search_response: SpotifyAlbumSearchResponse = response
+            .json()
             .await
-            .map_err(|e| format!("Failed to fetch artist details: {}", e))?;
+            .map_err(|e| format!("Failed to parse search response: {}", e))?;
+
+        Ok(search_response.albums.items.into_iter().next().and_then(|item| {
+            Some(ArtworkCandidate {
+                source: "Spotify".to_string(),
+                url: item.images.first()?.url.clone(),
+                confidence: artwork::album_confidence(album, &item.name),
+            })
+        }))
+    }
+
+    /// Looks up a track by Spotify id, bypassing search (used for pinned matches).
+    pub async fn lookup_track(&self, track_id: &str) -> Result<MetadataResult, String> {
+        let client = Client::new();
+
+        let response = self
+            .send_authorized("Spotify track lookup failed", |token| {
+                client
+                    .get(format!("https://api.spotify.com/v1/tracks/{}", track_id))
+                    .bearer_auth(token)
+                    .query(&self.market_query())
+            })
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(format!("Spotify API error: {}", response.status()));
+        }
+
+        let track: SpotifyTrack = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse track response: {}", e))?;
+
+        self.result_for_track(&client, &track).await
+    }
+
+    async fn result_for_track(
+        &self,
+        client: &Client,
+        track: &SpotifyTrack,
+    ) -> Result<MetadataResult, String> {
+        let track_artist = track.artists.first()
+            .ok_or("Spotify track has no artists")?;
+        let artist_id = &track_artist.id;
+        let artist_name = &track_artist.name;
+
+        let artist_response = self
+            .send_authorized("Failed to fetch artist details", |token| {
+                client
+                    .get(format!("https://api.spotify.com/v1/artists/{}", artist_id))
+                    .bearer_auth(token)
+            })
+            .await?;
 
         if !artist_response.status().is_success() {
             return Ok(MetadataResult {
                 genre: None,
+                genres: Vec::new(),
+                source_genre: None,
                 artist: Some(artist_name.clone()),
                 confidence: Confidence::Medium,
                 source: "Spotify".to_string(),
+                track_id: Some(track.id.clone()),
+                external_ids: ExternalIds::default(),
+                release_date: track.release_date(),
+                release_type: track.release_type(),
+                artwork_url: track.artwork_url(),
             });
         }
 
@@ -199,7 +634,8 @@ impl SpotifyClient {
             .await
             .map_err(|e| format!("Failed to parse artist details: {}", e))?;
 
-        let genre = artist_details.genres.first().cloned();
+        let genres: Vec<String> = artist_details.genres.iter().map(|g| normalize_genre_name(g)).collect();
+        let genre = genres.first().cloned();
         let confidence = if genre.is_some() {
             Confidence::High
         } else {
@@ -208,9 +644,16 @@ impl SpotifyClient {
 
         Ok(MetadataResult {
             genre,
+            genres,
+            source_genre: None,
             artist: Some(artist_name.clone()),
             confidence,
             source: "Spotify".to_string(),
+            track_id: Some(track.id.clone()),
+            external_ids: ExternalIds::default(),
+            release_date: track.release_date(),
+            release_type: track.release_type(),
+            artwork_url: track.artwork_url(),
         })
     }
 }
@@ -222,15 +665,34 @@ struct MusicBrainzSearchResponse {
 
 #[derive(Debug, Deserialize)]
 struct MusicBrainzRecording {
+    id: String,
     #[serde(rename = "artist-credit")]
     artist_credit: Vec<MusicBrainzArtistCredit>,
     tags: Option<Vec<MusicBrainzTag>>,
     genres: Option<Vec<MusicBrainzGenre>>,
+    #[serde(default)]
+    releases: Vec<MusicBrainzRelease>,
+    #[serde(rename = "first-release-date", default)]
+    first_release_date: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct MusicBrainzArtistCredit {
     name: String,
+    #[serde(default)]
+    artist: Option<MusicBrainzArtist>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MusicBrainzArtist {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MusicBrainzRelease {
+    id: String,
+    #[serde(rename = "release-group", default)]
+    release_group: Option<MusicBrainzReleaseGroup>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -240,28 +702,202 @@ struct MusicBrainzTag {
 
 #[derive(Debug, Deserialize)]
 struct MusicBrainzGenre {
+    #[serde(default)]
+    id: Option<String>,
     name: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct MusicBrainzGenreDetails {
+    #[serde(default)]
+    aliases: Vec<MusicBrainzAlias>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MusicBrainzAlias {
+    name: String,
+    locale: Option<String>,
+    #[serde(default)]
+    primary: Option<bool>,
+}
+
+/// Trims and collapses internal whitespace so translated and English genre
+/// names compare and display the same way.
+pub fn normalize_genre_name(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Capitalization enforced on genres written to tags and folder names.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GenreCase {
+    /// Keep the provider's or user's spelling.
+    #[default]
+    AsIs,
+    /// "drum and bass"
+    Lower,
+    /// "Drum And Bass"
+    Title,
+    /// "Drum & Bass": title case with "and" written as "&".
+    TitleAmpersand,
+}
+
+/// Applies a capitalization style on top of `normalize_genre_name`; `AsIs`
+/// leaves the name untouched. Title styles leave all-caps words ("UK",
+/// "EDM", "R&B") alone and capitalize each part of hyphenated words
+/// ("Hip-Hop").
+pub fn apply_genre_case(name: &str, case: GenreCase) -> String {
+    if case == GenreCase::AsIs {
+        return name.to_string();
+    }
+    let name = normalize_genre_name(name);
+    let title_word = |word: &str| -> String {
+        if word.chars().any(|c| c.is_alphabetic()) && !word.chars().any(|c| c.is_lowercase()) {
+            return word.to_string();
+        }
+        word.split('-')
+            .map(|part| {
+                let mut chars = part.chars();
+                match chars.next() {
+                    Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+                    None => String::new(),
+                }
+            })
+            .collect::<Vec<String>>()
+            .join("-")
+    };
+
+    match case {
+        GenreCase::AsIs => name,
+        GenreCase::Lower => name.to_lowercase(),
+        GenreCase::Title => name.split(' ').map(title_word).collect::<Vec<_>>().join(" "),
+        GenreCase::TitleAmpersand => name
+            .split(' ')
+            .map(|word| if word.eq_ignore_ascii_case("and") { "&".to_string() } else { title_word(word) })
+            .collect::<Vec<_>>()
+            .join(" "),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct MusicBrainzReleaseGroupSearchResponse {
+    #[serde(rename = "release-groups")]
+    release_groups: Vec<MusicBrainzReleaseGroup>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MusicBrainzReleaseGroup {
+    id: String,
+    #[serde(default)]
+    title: String,
+    #[serde(rename = "primary-type", default)]
+    primary_type: Option<String>,
+    #[serde(rename = "secondary-types", default)]
+    secondary_types: Vec<String>,
+}
+
+impl MusicBrainzReleaseGroup {
+    fn release_type(&self) -> Option<ReleaseType> {
+        let mut types = self.secondary_types.clone();
+        types.extend(self.primary_type.clone());
+        ReleaseType::parse(&types.join(";"))
+    }
+}
+
+/// A Lucene phrase for MusicBrainz searches. Quoting keeps `:`, `/`, `&`
+/// and brackets from being read as query syntax; quotes and backslashes in
+/// the value are escaped.
+fn lucene_phrase(value: &str) -> String {
+    let mut phrase = String::with_capacity(value.len() + 2);
+    phrase.push('"');
+    for c in value.chars() {
+        if c == '"' || c == '\\' {
+            phrase.push('\\');
+        }
+        phrase.push(c);
+    }
+    phrase.push('"');
+    phrase
+}
+
 pub struct MusicBrainzClient {
     base_url: String,
+    locale: Option<String>,
 }
 
 impl MusicBrainzClient {
-    pub fn new() -> Self {
+    /// `locale` (e.g. "de", "fr_FR") selects translated genre names from
+    /// MusicBrainz aliases; English names are used when none match.
+    pub fn new(locale: Option<String>) -> Self {
         MusicBrainzClient {
             base_url: "https://musicbrainz.org/ws/2".to_string(),
+            locale: locale.filter(|l| !l.is_empty() && !l.eq_ignore_ascii_case("en")),
+        }
+    }
+
+    async fn localized_genre(&self, client: &Client, genre_id: &str) -> Option<String> {
+        let locale = self.locale.as_ref()?;
+        let language = locale.split(['_', '-']).next().unwrap_or(locale);
+
+        MUSICBRAINZ_LIMIT.acquire().await;
+        let details: MusicBrainzGenreDetails = client
+            .get(format!("{}/genre/{}", self.base_url, genre_id))
+            .query(&[("inc", "aliases"), ("fmt", "json")])
+            .header("User-Agent", "AutoGenrePro/0.1.0 ( contact@example.com )")
+            .send()
+            .await
+            .ok()?
+            .json()
+            .await
+            .ok()?;
+
+        let matches_locale = |alias: &&MusicBrainzAlias, exact: bool| {
+            alias.locale.as_deref()
+                .map(|l| if exact { l.eq_ignore_ascii_case(locale) } else { l.split(['_', '-']).next() == Some(language) })
+                .unwrap_or(false)
+        };
+
+        let mut candidates: Vec<&MusicBrainzAlias> = details.aliases
+            .iter()
+            .filter(|alias| matches_locale(alias, true))
+            .collect();
+        if candidates.is_empty() {
+            candidates = details.aliases
+                .iter()
+                .filter(|alias| matches_locale(alias, false))
+                .collect();
         }
+
+        candidates
+            .iter()
+            .find(|alias| alias.primary.unwrap_or(false))
+            .or_else(|| candidates.first())
+            .map(|alias| alias.name.clone())
+    }
+
+    pub async fn health_check(&self) -> ProviderStatus {
+        MUSICBRAINZ_LIMIT.acquire().await;
+        let result = Client::new()
+            .get(format!("{}/genre/all", self.base_url))
+            .query(&[("fmt", "json"), ("limit", "1")])
+            .header("User-Agent", "AutoGenrePro/0.1.0 ( contact@example.com )")
+            .send()
+            .await;
+
+        ProviderStatus::from_response(result)
     }
 
+    /// Best match for the artist and title; `None` when the search ran but
+    /// found nothing.
     pub async fn search_track(
         &self,
         artist: &str,
         title: &str,
-    ) -> Result<MetadataResult, String> {
+    ) -> Result<Option<MetadataResult>, String> {
         let client = Client::new();
         
-        let query = format!("artist:{} AND recording:{}", artist, title);
+        let query = format!("artist:{} AND recording:{}", lucene_phrase(artist), lucene_phrase(title));
+        MUSICBRAINZ_LIMIT.acquire().await;
         let response = client
             .get(format!("{}/recording", self.base_url))
             .query(&[("query", query.as_str()), ("fmt", "json"), ("limit", "1"), ("inc", "tags+genres")])
@@ -280,29 +916,122 @@ impl MusicBrainzClient {
             .map_err(|e| format!("Failed to parse MusicBrainz response: {}", e))?;
 
         if search_response.recordings.is_empty() {
-            return Ok(MetadataResult {
-                genre: None,
-                artist: Some(artist.to_string()),
-                confidence: Confidence::Low,
-                source: "MusicBrainz (No match)".to_string(),
-            });
+            return Ok(None);
         }
 
         let recording = &search_response.recordings[0];
-        
+        Ok(Some(self.result_for_recording(&client, recording, artist).await))
+    }
+
+    /// Front cover from the Cover Art Archive for the best-matching release
+    /// group; `None` when there is no such release group or it has no art.
+    pub async fn search_album_art(&self, artist: &str, album: &str) -> Result<Option<ArtworkCandidate>, String> {
+        let client = Client::new();
+
+        let query = format!("artist:{} AND releasegroup:{}", lucene_phrase(artist), lucene_phrase(album));
+        MUSICBRAINZ_LIMIT.acquire().await;
+        let response = client
+            .get(format!("{}/release-group", self.base_url))
+            .query(&[("query", query.as_str()), ("fmt", "json"), ("limit", "1")])
+            .header("User-Agent", "AutoGenrePro/0.1.0 ( contact@example.com )")
+            .send()
+            .await
+            .map_err(|e| format!("MusicBrainz search failed: {}", e))?;
+
+        if !response.status().is_success() {
+            return Err(format!("MusicBrainz API error: {}", response.status()));
+        }
+
+        let search_response: MusicBrainzReleaseGroupSearchResponse = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse MusicBrainz response: {}", e))?;
+
+        let Some(release_group) = search_response.release_groups.into_iter().next() else {
+            return Ok(None);
+        };
+
+        // The archive redirects to the image, or answers 404 when the
+        // release group has no front cover.
+        let url = format!("https://coverartarchive.org/release-group/{}/front", release_group.id);
+        let response = client
+            .head(&url)
+            .header("User-Agent", "AutoGenrePro/0.1.0 ( contact@example.com )")
+            .send()
+            .await
+            .map_err(|e| format!("Cover Art Archive request failed: {}", e))?;
+
+        match response.status() {
+            status if status.is_success() => Ok(Some(ArtworkCandidate {
+                source: "Cover Art Archive".to_string(),
+                url,
+                confidence: artwork::album_confidence(album, &release_group.title),
+            })),
+            StatusCode::NOT_FOUND => Ok(None),
+            status => Err(format!("Cover Art Archive error: {}", status)),
+        }
+    }
+
+    /// Looks up a recording by MBID, bypassing search (used for pinned matches).
+    pub async fn lookup_track(&self, recording_id: &str) -> Result<MetadataResult, String> {
+        let client = Client::new();
+
+        MUSICBRAINZ_LIMIT.acquire().await;
+        let response = client
+            .get(format!("{}/recording/{}", self.base_url, recording_id))
+            .query(&[("fmt", "json"), ("inc", "artist-credits+releases+release-groups+tags+genres")])
+            .header("User-Agent", "AutoGenrePro/0.1.0 ( contact@example.com )")
+            .send()
+            .await
+            .map_err(|e| format!("MusicBrainz lookup failed: {}", e))?;
+
+        if !response.status().is_success() {
+            return Err(format!("MusicBrainz API error: {}", response.status()));
+        }
+
+        let recording: MusicBrainzRecording = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse MusicBrainz response: {}", e))?;
+
+        Ok(self.result_for_recording(&client, &recording, "").await)
+    }
+
+    async fn result_for_recording(
+        &self,
+        client: &Client,
+        recording: &MusicBrainzRecording,
+        fallback_artist: &str,
+    ) -> MetadataResult {
         let artist_name = recording.artist_credit
             .first()
             .map(|ac| ac.name.clone())
-            .unwrap_or_else(|| artist.to_string());
+            .unwrap_or_else(|| fallback_artist.to_string());
         
-        let genre = recording.genres.as_ref()
-            .and_then(|genres| genres.first())
-            .map(|genre| genre.name.clone())
+        let top_genre = recording.genres.as_ref()
+            .and_then(|genres| genres.first());
+
+        let localized = match top_genre.and_then(|genre| genre.id.as_deref()) {
+            Some(id) => self.localized_genre(client, id).await,
+            None => None,
+        };
+
+        let genre = localized
+            .or_else(|| top_genre.map(|genre| genre.name.clone()))
             .or_else(|| {
                 recording.tags.as_ref()
                     .and_then(|tags| tags.first())
                     .map(|tag| tag.name.clone())
-            });
+            })
+            .map(|genre| normalize_genre_name(&genre));
+
+        let mut genres: Vec<String> = genre.iter().cloned().collect();
+        let others = recording.genres.iter().flatten().skip(1).map(|genre| normalize_genre_name(&genre.name));
+        for other in others {
+            if !genres.iter().any(|g| g.eq_ignore_ascii_case(&other)) {
+                genres.push(other);
+            }
+        }
 
         let confidence = if genre.is_some() {
             Confidence::Medium
@@ -310,12 +1039,30 @@ impl MusicBrainzClient {
             Confidence::Low
         };
 
-        Ok(MetadataResult {
+        MetadataResult {
             genre,
-            artist: Some(artist_name),
+            genres,
+            source_genre: None,
+            artist: Some(artist_name).filter(|a| !a.is_empty()),
             confidence,
             source: "MusicBrainz".to_string(),
-        })
+            track_id: Some(recording.id.clone()),
+            external_ids: ExternalIds {
+                musicbrainz_recording_id: Some(recording.id.clone()),
+                musicbrainz_release_id: recording.releases.first().map(|release| release.id.clone()),
+                musicbrainz_artist_id: recording.artist_credit
+                    .first()
+                    .and_then(|ac| ac.artist.as_ref())
+                    .map(|artist| artist.id.clone()),
+                ..Default::default()
+            },
+            release_date: recording.first_release_date.clone().filter(|d| !d.is_empty()),
+            release_type: recording.releases.first()
+                .and_then(|release| release.release_group.as_ref())
+                .and_then(MusicBrainzReleaseGroup::release_type),
+            artwork_url: recording.releases.first()
+                .map(|release| format!("https://coverartarchive.org/release/{}/front-500", release.id)),
+        }
     }
 }
 
@@ -332,11 +1079,29 @@ struct BeatportSearchResponse {
 
 #[derive(Debug, Deserialize)]
 struct BeatportTrack {
+    id: u64,
+    #[serde(default)]
+    catalog_number: Option<String>,
+    #[serde(default)]
+    publish_date: Option<String>,
     #[serde(default)]
     genre: Option<BeatportGenre>,
     #[serde(default)]
     sub_genre: Option<BeatportGenre>,
     artists: Vec<BeatportArtist>,
+    #[serde(default)]
+    release: Option<BeatportRelease>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BeatportRelease {
+    #[serde(default)]
+    image: Option<BeatportImage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BeatportImage {
+    uri: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -362,20 +1127,17 @@ impl BeatportClient {
         }
     }
 
-    async fn get_access_token(&self) -> Result<String, String> {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+    async fn send_authorized(
+        &self,
+        context: &str,
+        request: impl Fn(&str) -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, String> {
+        send_authorized(&BEATPORT_TOKEN_CACHE, &BEATPORT_LIMIT, || self.request_token(), context, request).await
+    }
 
-        {
-            let cache = BEATPORT_TOKEN_CACHE.lock().unwrap();
-            if let Some(cached) = cache.as_ref() {
-                if cached.expires_at > now {
-                    return Ok(cached.access_token.clone());
-                }
-            }
-        }
+    async fn request_token(&self) -> Result<TokenCache, String> {
+        let now = now_secs();
+        let settings_generation = settings::generation();
 
         let username = self.username.as_ref()
             .ok_or("Beatport username not configured")?;
@@ -384,14 +1146,13 @@ impl BeatportClient {
 
         let client = Client::new();
         
-        let client_id = "oeGScrHHsv1K1vO2Mby3sHQ7oZNWpViH";
-        
         let mut params = HashMap::new();
         params.insert("grant_type", "password");
-        params.insert("client_id", client_id);
+        params.insert("client_id", BEATPORT_CLIENT_ID);
         params.insert("username", username.as_str());
         params.insert("password", password.as_str());
 
+        BEATPORT_LIMIT.acquire().await;
         let response = client
             .post("https://api.beatport.com/v4/auth/o/token/")
             .form(&params)
@@ -411,40 +1172,57 @@ impl BeatportClient {
             .map_err(|e| format!("Failed to parse Beatport token response: {}", e))?;
 
         let expires_in = token_response.expires_in.unwrap_or(3600);
-        let expires_at = now + expires_in - 300;
-        
-        {
-            let mut cache = BEATPORT_TOKEN_CACHE.lock().unwrap();
-            *cache = Some(TokenCache {
-                access_token: token_response.access_token.clone(),
-                expires_at,
-            });
-        }
 
-        Ok(token_response.access_token)
+        Ok(TokenCache {
+            access_token: token_response.access_token,
+            expires_at: now + expires_in - 300,
+            settings_generation,
+        })
+    }
+
+    pub async fn health_check(&self) -> ProviderStatus {
+        let (username, password) = match (&self.username, &self.password) {
+            (Some(username), Some(password)) => (username, password),
+            _ => return ProviderStatus::NotConfigured,
+        };
+
+        BEATPORT_LIMIT.acquire().await;
+        let result = Client::new()
+            .post("https://api.beatport.com/v4/auth/o/token/")
+            .form(&[
+                ("grant_type", "password"),
+                ("client_id", BEATPORT_CLIENT_ID),
+                ("username", username.as_str()),
+                ("password", password.as_str()),
+            ])
+            .send()
+            .await;
+
+        ProviderStatus::from_response(result)
     }
 
+    /// Best match for the artist and title; `None` when the search ran but
+    /// found nothing.
     pub async fn search_track(
         &self,
         artist: &str,
         title: &str,
-    ) -> Result<MetadataResult, String> {
+    ) -> Result<Option<MetadataResult>, String> {
         if self.username.is_none() || self.password.is_none() {
             return Err("Beatport credentials not configured".to_string());
         }
 
-        let access_token = self.get_access_token().await?;
         let client = Client::new();
 
         let query = format!("{} {}", artist, title);
-        
-        let response = client
-            .get("https://api.beatport.com/v4/catalog/tracks/")
-            .bearer_auth(&access_token)
-            .query(&[("q", query.as_str()), ("per_page", "1")])
-            .send()
-            .await
-            .map_err(|e| format!("Beatport search failed: {}", e))?;
+        let response = self
+            .send_authorized("Beatport search failed", |token| {
+                client
+                    .get("https://api.beatport.com/v4/catalog/tracks/")
+                    .bearer_auth(token)
+                    .query(&[("q", query.as_str()), ("per_page", "1")])
+            })
+            .await?;
 
         if !response.status().is_success() {
             return Err(format!("Beatport API error: {}", response.status()));
@@ -456,37 +1234,405 @@ impl BeatportClient {
             .map_err(|e| format!("Failed to parse Beatport response: {}", e))?;
 
         if search_response.results.is_empty() {
-            return Ok(MetadataResult {
-                genre: None,
-                artist: Some(artist.to_string()),
-                confidence: Confidence::Low,
-                source: "Beatport (No match)".to_string(),
-            });
+            return Ok(None);
         }
 
         let track = &search_response.results[0];
+        Ok(Some(Self::result_for_track(track, artist)))
+    }
+
+    /// Looks up a track by Beatport id, bypassing search (used for pinned matches).
+    pub async fn lookup_track(&self, track_id: &str) -> Result<MetadataResult, String> {
+        let client = Client::new();
+
+        let response = self
+            .send_authorized("Beatport track lookup failed", |token| {
+                client
+                    .get(format!("https://api.beatport.com/v4/catalog/tracks/{}/", track_id))
+                    .bearer_auth(token)
+            })
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(format!("Beatport API error: {}", response.status()));
+        }
+
+        let track: BeatportTrack = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse Beatport response: {}", e))?;
+
+        Ok(Self::result_for_track(&track, ""))
+    }
+
+    fn result_for_track(track: &BeatportTrack, fallback_artist: &str) -> MetadataResult {
         let artist_name = track.artists
             .first()
             .map(|a| a.name.clone())
-            .unwrap_or_else(|| artist.to_string());
+            .unwrap_or_else(|| fallback_artist.to_string());
 
-        let genre = track.sub_genre.as_ref()
-            .or(track.genre.as_ref())
-            .map(|g| g.name.clone());
+        let mut genres: Vec<String> = Vec::new();
+        for name in [&track.sub_genre, &track.genre].into_iter().flatten().map(|g| normalize_genre_name(&g.name)) {
+            if !genres.contains(&name) {
+                genres.push(name);
+            }
+        }
+        let genre = genres.first().cloned();
 
-        let confidence = if track.sub_genre.is_some() {
-            Confidence::High
-        } else if track.genre.is_some() {
+        let confidence = if genre.is_some() {
             Confidence::High
         } else {
             Confidence::Low
         };
 
-        Ok(MetadataResult {
+        MetadataResult {
             genre,
-            artist: Some(artist_name),
+            genres,
+            source_genre: None,
+            artist: Some(artist_name).filter(|a| !a.is_empty()),
             confidence,
             source: "Beatport".to_string(),
+            track_id: Some(track.id.to_string()),
+            external_ids: ExternalIds {
+                beatport_track_id: Some(track.id.to_string()),
+                catalog_number: track.catalog_number.clone().filter(|c| !c.trim().is_empty()),
+                ..Default::default()
+            },
+            release_date: track.publish_date.clone().filter(|d| !d.is_empty()),
+            release_type: None,
+            artwork_url: track.release.as_ref()
+                .and_then(|release| release.image.as_ref())
+                .map(|image| image.uri.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct LastFmTopTagsResponse {
+    #[serde(default)]
+    toptags: Option<LastFmTopTags>,
+    #[serde(default)]
+    error: Option<u32>,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LastFmTopTags {
+    #[serde(default)]
+    tag: Vec<LastFmTag>,
+    #[serde(rename = "@attr", default)]
+    attr: Option<LastFmTagsAttr>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LastFmTag {
+    name: String,
+    /// Relative weight, scaled so the top tag is 100.
+    #[serde(default)]
+    count: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct LastFmTagsAttr {
+    #[serde(default)]
+    artist: Option<String>,
+}
+
+/// Last.fm error code for an unknown artist or track.
+const LASTFM_NOT_FOUND: u32 = 6;
+
+/// Last.fm tags are kept as genres while their count is at least a quarter
+/// of the top tag's, up to five of them.
+const LASTFM_GENRE_SHARE: u32 = 4;
+const LASTFM_MAX_GENRES: usize = 5;
+
+pub struct LastFmClient {
+    api_key: Option<String>,
+}
+
+impl LastFmClient {
+    pub fn new(api_key: Option<String>) -> Self {
+        LastFmClient { api_key }
+    }
+
+    pub async fn health_check(&self) -> ProviderStatus {
+        let api_key = match &self.api_key {
+            Some(api_key) => api_key,
+            None => return ProviderStatus::NotConfigured,
+        };
+
+        LASTFM_LIMIT.acquire().await;
+        let result = Client::new()
+            .get("https://ws.audioscrobbler.com/2.0/")
+            .query(&[("method", "chart.getTopTags"), ("api_key", api_key.as_str()), ("format", "json"), ("limit", "1")])
+            .send()
+            .await;
+
+        ProviderStatus::from_response(result)
+    }
+
+    /// The most popular tag on the track, or on the artist when the track
+    /// has none; `None` when neither is known or tagged.
+    pub async fn search_track(
+        &self,
+        artist: &str,
+        title: &str,
+    ) -> Result<Option<MetadataResult>, String> {
+        if let Some(result) = self.top_tags(&[("method", "track.getTopTags"), ("artist", artist), ("track", title)]).await? {
+            return Ok(Some(Self::result_for_tags(result, artist, true)));
+        }
+        let artist_tags = self.top_tags(&[("method", "artist.getTopTags"), ("artist", artist)]).await?;
+        Ok(artist_tags.map(|result| Self::result_for_tags(result, artist, false)))
+    }
+
+    async fn top_tags(&self, params: &[(&str, &str)]) -> Result<Option<LastFmTopTags>, String> {
+        let api_key = self.api_key.as_deref()
+            .ok_or("Last.fm API key not configured")?;
+
+        LASTFM_LIMIT.acquire().await;
+        let response = Client::new()
+            .get("https://ws.audioscrobbler.com/2.0/")
+            .query(params)
+            .query(&[("api_key", api_key), ("autocorrect", "1"), ("format", "json")])
+            .send()
+            .await
+            .map_err(|e| format!("Last.fm search failed: {}", e))?;
+
+        let status = response.status();
+        let top_tags: LastFmTopTagsResponse = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse Last.fm response: {}", e))?;
+
+        match top_tags.error {
+            Some(LASTFM_NOT_FOUND) => return Ok(None),
+            Some(code) => {
+                return Err(format!("Last.fm API error {}: {}", code, top_tags.message.unwrap_or_default()));
+            }
+            None if !status.is_success() => return Err(format!("Last.fm API error: {}", status)),
+            None => {}
+        }
+
+        Ok(top_tags.toptags.filter(|tags| !tags.tag.is_empty()))
+    }
+
+    /// Track tags are Medium confidence when the top tag clearly leads the
+    /// runner-up and Low when they are close; artist tags describe the
+    /// artist rather than the track, so they are always Low.
+    fn result_for_tags(tags: LastFmTopTags, fallback_artist: &str, track_level: bool) -> MetadataResult {
+        let top = tags.tag.first();
+        let runner_up = tags.tag.get(1).map(|tag| tag.count).unwrap_or(0);
+
+        let confidence = match top {
+            Some(top) if track_level && runner_up * 2 <= top.count => Confidence::Medium,
+            _ => Confidence::Low,
+        };
+
+        // Tags far behind the top one are mostly noise ("seen live").
+        let genres = tags.tag.iter()
+            .take_while(|tag| top.is_some_and(|top| tag.count * LASTFM_GENRE_SHARE >= top.count))
+            .take(LASTFM_MAX_GENRES)
+            .map(|tag| normalize_genre_name(&tag.name))
+            .collect();
+
+        MetadataResult {
+            genre: top.map(|tag| normalize_genre_name(&tag.name)),
+            genres,
+            source_genre: None,
+            artist: tags.attr
+                .and_then(|attr| attr.artist)
+                .or_else(|| Some(fallback_artist.to_string()))
+                .filter(|a| !a.is_empty()),
+            confidence,
+            source: "Last.fm".to_string(),
+            track_id: None,
+            external_ids: ExternalIds::default(),
+            release_date: None,
+            release_type: None,
+            artwork_url: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DeezerSearchResponse {
+    #[serde(default)]
+    data: Vec<DeezerTrack>,
+    #[serde(default)]
+    error: Option<DeezerError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeezerTrack {
+    id: u64,
+    artist: DeezerArtist,
+    album: DeezerAlbumRef,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeezerArtist {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeezerAlbumRef {
+    id: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeezerAlbum {
+    #[serde(default)]
+    genres: DeezerGenres,
+    #[serde(default)]
+    release_date: Option<String>,
+    #[serde(default)]
+    record_type: Option<String>,
+    #[serde(default)]
+    cover_xl: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct DeezerGenres {
+    #[serde(default)]
+    data: Vec<DeezerGenre>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeezerGenre {
+    name: String,
+}
+
+/// Deezer reports errors in the body of a 200 response.
+#[derive(Debug, Deserialize)]
+struct DeezerError {
+    #[serde(default)]
+    message: String,
+}
+
+/// Deezer's public API; needs no credentials, so it works out of the box.
+pub struct DeezerClient {
+    base_url: String,
+}
+
+impl DeezerClient {
+    pub fn new() -> Self {
+        DeezerClient {
+            base_url: "https://api.deezer.com".to_string(),
+        }
+    }
+
+    pub async fn health_check(&self) -> ProviderStatus {
+        DEEZER_LIMIT.acquire().await;
+        let result = Client::new()
+            .get(format!("{}/genre", self.base_url))
+            .send()
+            .await;
+
+        ProviderStatus::from_response(result)
+    }
+
+    /// Best match for the artist and title; `None` when the search ran but
+    /// found nothing.
+    pub async fn search_track(
+        &self,
+        artist: &str,
+        title: &str,
+    ) -> Result<Option<MetadataResult>, String> {
+        let client = Client::new();
+
+        let query = format!("artist:{} track:{}", quoted_field(artist), quoted_field(title));
+        DEEZER_LIMIT.acquire().await;
+        let response = client
+            .get(format!("{}/search", self.base_url))
+            .query(&[("q", query.as_str()), ("limit", "1")])
+            .send()
+            .await
+            .map_err(|e| format!("Deezer search failed: {}", e))?;
+
+        if !response.status().is_success() {
+            return Err(format!("Deezer API error: {}", response.status()));
+        }
+
+        let search_response: DeezerSearchResponse = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse Deezer response: {}", e))?;
+
+        if let Some(error) = search_response.error {
+            return Err(format!("Deezer API error: {}", error.message));
+        }
+
+        match search_response.data.first() {
+            Some(track) => self.result_for_track(&client, track).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Looks up a track by Deezer id, bypassing search (used for pinned matches).
+    pub async fn lookup_track(&self, track_id: &str) -> Result<MetadataResult, String> {
+        let client = Client::new();
+
+        DEEZER_LIMIT.acquire().await;
+        let response = client
+            .get(format!("{}/track/{}", self.base_url, track_id))
+            .send()
+            .await
+            .map_err(|e| format!("Deezer track lookup failed: {}", e))?;
+
+        if !response.status().is_success() {
+            return Err(format!("Deezer API error: {}", response.status()));
+        }
+
+        let body: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse Deezer response: {}", e))?;
+
+        if let Some(error) = body.get("error") {
+            let error: DeezerError = serde_json::from_value(error.clone()).unwrap_or(DeezerError { message: error.to_string() });
+            return Err(format!("Deezer API error: {}", error.message));
+        }
+
+        let track: DeezerTrack = serde_json::from_value(body)
+            .map_err(|e| format!("Failed to parse Deezer response: {}", e))?;
+
+        self.result_for_track(&client, &track).await
+    }
+
+    /// Deezer only has genres per album, and broad ones ("Dance",
+    /// "Electro"), so a genre is Medium confidence at best.
+    async fn result_for_track(&self, client: &Client, track: &DeezerTrack) -> Result<MetadataResult, String> {
+        DEEZER_LIMIT.acquire().await;
+        let album: DeezerAlbum = client
+            .get(format!("{}/album/{}", self.base_url, track.album.id))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to fetch Deezer album: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse Deezer album: {}", e))?;
+
+        let genres: Vec<String> = album.genres.data.iter().map(|g| normalize_genre_name(&g.name)).collect();
+        let genre = genres.first().cloned();
+        let confidence = if genre.is_some() {
+            Confidence::Medium
+        } else {
+            Confidence::Low
+        };
+
+        Ok(MetadataResult {
+            genre,
+            genres,
+            source_genre: None,
+            artist: Some(track.artist.name.clone()).filter(|a| !a.is_empty()),
+            confidence,
+            source: "Deezer".to_string(),
+            track_id: Some(track.id.to_string()),
+            external_ids: ExternalIds::default(),
+            release_date: album.release_date.filter(|d| !d.is_empty() && d != "0000-00-00"),
+            release_type: album.record_type.as_deref().and_then(ReleaseType::parse),
+            artwork_url: album.cover_xl.filter(|url| !url.is_empty()),
         })
     }
 }
@@ -0,0 +1,74 @@
+use crate::settings::AppSettings;
+
+/// How a combined artist credit ("A & B", "A x B", "A, B") is split into
+/// individual artists, and how individual artists are joined back into one.
+#[derive(Debug, Clone)]
+pub struct ArtistRules {
+    pub separators: Vec<String>,
+    pub join: String,
+}
+
+impl ArtistRules {
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        ArtistRules {
+            separators: settings.artist_separators.clone(),
+            join: settings.artist_join.clone(),
+        }
+    }
+
+    /// The individual artists of a credit, in order. Separators match
+    /// ASCII-case-insensitively; empty parts and repeats are dropped.
+    pub fn split(&self, credit: &str) -> Vec<String> {
+        let lower = credit.to_ascii_lowercase();
+        let separators: Vec<String> = self.separators
+            .iter()
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_ascii_lowercase())
+            .collect();
+
+        let mut artists: Vec<String> = Vec::new();
+        let mut start = 0;
+        loop {
+            // Earliest separator wins; the longest one on a tie, so " x " is
+            // not cut short by a shorter separator starting at the same place.
+            let next = separators
+                .iter()
+                .filter_map(|separator| {
+                    lower[start..].find(separator.as_str()).map(|at| (start + at, separator.len()))
+                })
+                .min_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+
+            let end = next.map(|(at, _)| at).unwrap_or(credit.len());
+            let artist = credit[start..end].trim();
+            if !artist.is_empty() && !artists.iter().any(|a| a.eq_ignore_ascii_case(artist)) {
+                artists.push(artist.to_string());
+            }
+
+            match next {
+                Some((at, len)) => start = at + len,
+                None => break,
+            }
+        }
+        artists
+    }
+
+    pub fn join(&self, artists: &[String]) -> String {
+        artists.join(&self.join)
+    }
+}
+
+impl Default for ArtistRules {
+    fn default() -> Self {
+        ArtistRules {
+            separators: default_separators(),
+            join: " & ".to_string(),
+        }
+    }
+}
+
+pub fn default_separators() -> Vec<String> {
+    [" & ", " x ", ", ", " feat. ", " ft. ", " vs. "]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
@@ -0,0 +1,258 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::DynamicImage;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+
+use crate::api_client::{Confidence, MetadataResult, RateLimiter};
+use crate::settings::AppSettings;
+
+/// Apple asks for no more than about twenty searches a minute.
+static ITUNES_LIMIT: RateLimiter = RateLimiter::new(1.0, 0.3);
+
+// Below this JPEG quality artefacts show on a CDJ screen; shrink instead.
+const MIN_JPEG_QUALITY: u8 = 60;
+// Largest cover `download` accepts. Real covers are a few MB at most, so
+// anything bigger is a wrong URL or a hostile server.
+const MAX_DOWNLOAD_BYTES: usize = 25 * 1024 * 1024;
+
+/// Limits for embedded artwork; zero disables a limit.
+#[derive(Debug, Clone, Copy)]
+pub struct ArtworkPolicy {
+    pub max_dimension: u32,
+    pub max_bytes: usize,
+    pub jpeg_quality: u8,
+}
+
+impl ArtworkPolicy {
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        ArtworkPolicy {
+            max_dimension: settings.artwork_max_dimension,
+            max_bytes: settings.artwork_max_kb as usize * 1024,
+            jpeg_quality: settings.artwork_jpeg_quality.clamp(1, 100),
+        }
+    }
+
+    pub fn unlimited() -> Self {
+        ArtworkPolicy {
+            max_dimension: 0,
+            max_bytes: 0,
+            jpeg_quality: 90,
+        }
+    }
+
+    fn is_unlimited(&self) -> bool {
+        self.max_dimension == 0 && self.max_bytes == 0
+    }
+
+    fn too_large(&self, width: u32, height: u32) -> bool {
+        self.max_dimension > 0 && width.max(height) > self.max_dimension
+    }
+
+    fn too_heavy(&self, len: usize) -> bool {
+        self.max_bytes > 0 && len > self.max_bytes
+    }
+
+    /// Re-encodes an image that breaks the policy as a JPEG that fits it,
+    /// stepping the quality down and then the size until it does. Returns
+    /// `None` for images already within limits or that cannot be decoded,
+    /// which are left as they are.
+    pub fn fit(&self, data: &[u8]) -> Option<Vec<u8>> {
+        if self.is_unlimited() {
+            return None;
+        }
+
+        let image = image::load_from_memory(data).ok()?;
+        if !self.too_large(image.width(), image.height()) && !self.too_heavy(data.len()) {
+            return None;
+        }
+
+        let mut image = match self.max_dimension {
+            0 => image,
+            max => image.resize(max, max, FilterType::Lanczos3),
+        };
+        let mut quality = self.jpeg_quality;
+
+        loop {
+            let encoded = encode_jpeg(&image, quality)?;
+            if !self.too_heavy(encoded.len()) {
+                return Some(encoded);
+            }
+
+            if quality > MIN_JPEG_QUALITY {
+                quality = quality.saturating_sub(10).max(MIN_JPEG_QUALITY);
+            } else if image.width().max(image.height()) > 64 {
+                let (width, height) = (image.width() * 3 / 4, image.height() * 3 / 4);
+                image = image.resize(width, height, FilterType::Lanczos3);
+            } else {
+                return Some(encoded);
+            }
+        }
+    }
+}
+
+fn encode_jpeg(image: &DynamicImage, quality: u8) -> Option<Vec<u8>> {
+    let mut encoded = Vec::new();
+    // JPEG has no alpha channel.
+    let rgb = image.to_rgb8();
+    JpegEncoder::new_with_quality(&mut encoded, quality)
+        .encode_image(&rgb)
+        .ok()?;
+    Some(encoded)
+}
+
+/// Width and height from the image header, without decoding the pixels.
+pub fn dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    image::ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()
+}
+
+/// A cover a provider offered for a file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArtworkCandidate {
+    pub source: String,
+    pub url: String,
+    pub confidence: Confidence,
+}
+
+pub enum ArtworkChoice {
+    Embed(ArtworkCandidate),
+    Review(Vec<ArtworkCandidate>),
+    NotFound,
+}
+
+/// Decides what to do with the covers offered for a file. The best match's
+/// cover is embedded unattended only when that match is high-confidence;
+/// anything less certain is put up for review with every cover on offer.
+pub fn choose(results: &[MetadataResult]) -> ArtworkChoice {
+    let mut candidates: Vec<ArtworkCandidate> = results
+        .iter()
+        .filter_map(|result| {
+            Some(ArtworkCandidate {
+                source: result.source.clone(),
+                url: result.artwork_url.clone()?,
+                confidence: result.confidence.clone(),
+            })
+        })
+        .collect();
+
+    let best_is_certain = results.first().is_some_and(|best| {
+        matches!(best.confidence, Confidence::High) && best.artwork_url.is_some()
+    });
+
+    if candidates.is_empty() {
+        ArtworkChoice::NotFound
+    } else if best_is_certain {
+        ArtworkChoice::Embed(candidates.swap_remove(0))
+    } else {
+        ArtworkChoice::Review(candidates)
+    }
+}
+
+/// Fetches the image at `url`, refusing anything over `MAX_DOWNLOAD_BYTES`
+/// whether or not the server says how big it is.
+pub async fn download(url: &str) -> Result<Vec<u8>, String> {
+    let mut response = Client::new()
+        .get(url)
+        .header("User-Agent", "AutoGenrePro/0.1.0 ( contact@example.com )")
+        .send()
+        .await
+        .map_err(|e| format!("Failed to download artwork: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("Artwork download failed: {}", response.status()));
+    }
+
+    let too_large = || format!("Artwork at {} is larger than {} MB", url, MAX_DOWNLOAD_BYTES / (1024 * 1024));
+    if response.content_length().is_some_and(|length| length > MAX_DOWNLOAD_BYTES as u64) {
+        return Err(too_large());
+    }
+
+    let mut data = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("Failed to download artwork: {}", e))?
+    {
+        if data.len() + chunk.len() > MAX_DOWNLOAD_BYTES {
+            return Err(too_large());
+        }
+        data.extend_from_slice(&chunk);
+    }
+
+    image::guess_format(&data)
+        .map_err(|_| format!("Artwork at {} is not an image", url))?;
+    Ok(data)
+}
+
+/// How sure an album search hit is the requested album: High for the same
+/// title, Medium when one title contains the other ("Discovery (Deluxe)"),
+/// Low otherwise.
+pub fn album_confidence(requested: &str, found: &str) -> Confidence {
+    let fold = |s: &str| {
+        s.to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let (requested, found) = (fold(requested), fold(found));
+
+    if requested == found {
+        Confidence::High
+    } else if !requested.is_empty() && (found.contains(&requested) || requested.contains(&found)) {
+        Confidence::Medium
+    } else {
+        Confidence::Low
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ItunesSearchResponse {
+    results: Vec<ItunesAlbum>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ItunesAlbum {
+    collection_name: String,
+    #[serde(default)]
+    artwork_url100: Option<String>,
+}
+
+/// Cover of the best-matching album in the iTunes Store; `None` when the
+/// search ran but found nothing.
+pub async fn search_itunes(artist: &str, album: &str) -> Result<Option<ArtworkCandidate>, String> {
+    let term = format!("{} {}", artist, album);
+    ITUNES_LIMIT.acquire().await;
+    let response = Client::new()
+        .get("https://itunes.apple.com/search")
+        .query(&[("term", term.as_str()), ("entity", "album"), ("media", "music"), ("limit", "1")])
+        .send()
+        .await
+        .map_err(|e| format!("iTunes search failed: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("iTunes API error: {}", response.status()));
+    }
+
+    let search_response: ItunesSearchResponse = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse iTunes response: {}", e))?;
+
+    // The listed URL is a 100px thumbnail; the same path serves larger
+    // renditions.
+    Ok(search_response.results.into_iter().next().and_then(|result| {
+        Some(ArtworkCandidate {
+            source: "iTunes".to_string(),
+            url: result.artwork_url100?.replace("100x100bb", "600x600bb"),
+            confidence: album_confidence(album, &result.collection_name),
+        })
+    }))
+}
@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::scanner::{AudioFile, Metadata};
+use crate::transforms::{self, TransformPreview};
+
+/// Fewest tagged siblings a majority is taken from; one other track is
+/// not a pattern.
+const MIN_TAGGED_SIBLINGS: usize = 2;
+
+/// A genre inferred for an untagged track from the other tracks in its
+/// folder.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenreBackfill {
+    pub preview: TransformPreview,
+    /// Siblings with the suggested genre.
+    pub agreeing: usize,
+    /// Siblings with any genre.
+    pub tagged_siblings: usize,
+}
+
+fn has_genre(metadata: &Metadata) -> bool {
+    metadata.genre.as_deref().is_some_and(|genre| !genre.trim().is_empty())
+}
+
+/// Suggests the majority genre of each folder for its tracks without one,
+/// where at least `min_share` of the folder's tagged tracks agree on it.
+/// Genres are compared ignoring case; the most common spelling is used.
+pub fn suggest(files: &[AudioFile], min_share: f32) -> Vec<GenreBackfill> {
+    let mut folders: HashMap<&Path, Vec<&AudioFile>> = HashMap::new();
+    for file in files {
+        if let Some(folder) = file.path.parent() {
+            folders.entry(folder).or_default().push(file);
+        }
+    }
+
+    let mut suggestions = Vec::new();
+    for siblings in folders.values() {
+        let genres: Vec<&str> = siblings
+            .iter()
+            .filter_map(|file| file.current_metadata.as_ref())
+            .filter(|metadata| has_genre(metadata))
+            .filter_map(|metadata| metadata.genre.as_deref())
+            .map(str::trim)
+            .collect();
+        if genres.len() < MIN_TAGGED_SIBLINGS || genres.len() == siblings.len() {
+            continue;
+        }
+
+        let mut counts: HashMap<String, (usize, HashMap<&str, usize>)> = HashMap::new();
+        for genre in &genres {
+            let (count, spellings) = counts.entry(genre.to_lowercase()).or_default();
+            *count += 1;
+            *spellings.entry(genre).or_insert(0) += 1;
+        }
+        let Some((agreeing, spellings)) = counts.values().max_by_key(|(count, _)| *count) else {
+            continue;
+        };
+        if (*agreeing as f32) < min_share * genres.len() as f32 {
+            continue;
+        }
+        let Some(genre) = spellings
+            .iter()
+            .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+            .map(|(spelling, _)| spelling.to_string())
+        else {
+            continue;
+        };
+
+        let untagged: Vec<AudioFile> = siblings
+            .iter()
+            .filter(|file| file.current_metadata.as_ref().is_some_and(|metadata| !has_genre(metadata)))
+            .map(|file| (*file).clone())
+            .collect();
+        let previews = transforms::preview(&untagged, |metadata| Metadata {
+            genre: Some(genre.clone()),
+            genres: vec![genre.clone()],
+            ..metadata.clone()
+        });
+        suggestions.extend(previews.into_iter().map(|preview| GenreBackfill {
+            preview,
+            agreeing: *agreeing,
+            tagged_siblings: genres.len(),
+        }));
+    }
+
+    suggestions.sort_by(|a, b| a.preview.path.cmp(&b.preview.path));
+    suggestions
+}
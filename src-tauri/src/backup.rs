@@ -0,0 +1,430 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::library_db::LibraryDb;
+use crate::restore::{self, FieldChange};
+use crate::scanner::{FileScanner, Metadata};
+use crate::simulation::{self, SimulatedOperation};
+
+/// Folder, inside each audio folder, holding backups of the files in it.
+pub const BACKUP_DIR: &str = ".autogenre_backups";
+
+/// Current backup format. Version 1 backups are a bare `Metadata` object
+/// without a `version` field; version 3 added the raw ID3 tag.
+pub const BACKUP_VERSION: u32 = 3;
+
+/// Folder, next to the backups, holding embedded artwork by content hash so
+/// a cover shared by a whole album is stored once.
+const ARTWORK_DIR: &str = "artwork";
+
+/// Folder, next to the backups, holding raw ID3 tags by content hash.
+const RAW_TAG_DIR: &str = "tags";
+
+/// A file's state before the app changed it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Backup {
+    pub version: u32,
+    pub metadata: Metadata,
+    /// Every text field of the file's tag by the format's own field name,
+    /// including those `Metadata` does not model (track number, composer).
+    #[serde(default)]
+    pub tags: BTreeMap<String, Vec<String>>,
+    /// The front cover at backup time, if the file had one.
+    #[serde(default)]
+    pub artwork: Option<ArtworkRef>,
+    /// The complete ID3v2 tag of an MP3 or DSF file, with every frame
+    /// `tags` leaves out (comments, ratings, private frames, every picture),
+    /// so restoring puts the tag back as it was.
+    #[serde(default)]
+    pub raw_tag: Option<RawTagRef>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArtworkRef {
+    pub hash: String,
+    pub mime_type: String,
+    /// File name of the stored image under the backup folder's `artwork`
+    /// directory.
+    pub blob: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RawTagRef {
+    pub hash: String,
+    /// File name of the stored tag under the backup folder's `tags`
+    /// directory.
+    pub blob: String,
+}
+
+impl Backup {
+    pub fn parse(data: &str) -> Result<Self, String> {
+        let value: Value = serde_json::from_str(data)
+            .map_err(|e| format!("Failed to parse backup data: {}", e))?;
+
+        if value.get("version").is_some() {
+            serde_json::from_value(value)
+                .map_err(|e| format!("Failed to parse backup data: {}", e))
+        } else {
+            let metadata = serde_json::from_value(value)
+                .map_err(|e| format!("Failed to parse backup data: {}", e))?;
+            Ok(Backup {
+                version: 1,
+                metadata,
+                tags: BTreeMap::new(),
+                artwork: None,
+                raw_tag: None,
+            })
+        }
+    }
+}
+
+/// Hex FNV-1a hash of `bytes`.
+pub fn content_hash(bytes: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+fn blob_dir(backup_path: &Path, dir_name: &str) -> Result<PathBuf, String> {
+    backup_path.parent()
+        .map(|dir| dir.join(dir_name))
+        .ok_or_else(|| "Cannot determine backup directory".to_string())
+}
+
+/// Writes `data` as `blob` in the backup folder's `dir_name` directory
+/// unless it is already there.
+fn store_blob(backup_path: &Path, dir_name: &str, blob: &str, data: &[u8]) -> Result<(), String> {
+    let dir = blob_dir(backup_path, dir_name)?;
+    let blob_path = dir.join(blob);
+    if !blob_path.exists() {
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create {} backup directory: {}", dir_name, e))?;
+        fs::write(&blob_path, data)
+            .map_err(|e| format!("Failed to write {} backup: {}", dir_name, e))?;
+    }
+    Ok(())
+}
+
+fn load_blob(backup_path: &Path, dir_name: &str, blob: &str) -> Result<Vec<u8>, String> {
+    let blob_path = blob_dir(backup_path, dir_name)?.join(blob);
+    fs::read(&blob_path)
+        .map_err(|e| format!("Failed to read {} backup: {}", dir_name, e))
+}
+
+fn blob_extension(mime_type: &str) -> &'static str {
+    match mime_type {
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/bmp" => "bmp",
+        _ => "jpg",
+    }
+}
+
+/// Stores `data` beside the backup at `backup_path` unless an identical
+/// image is already there.
+pub fn store_artwork(backup_path: &Path, mime_type: &str, data: &[u8]) -> Result<ArtworkRef, String> {
+    let hash = content_hash(data);
+    let blob = format!("{}.{}", hash, blob_extension(mime_type));
+    store_blob(backup_path, ARTWORK_DIR, &blob, data)?;
+
+    Ok(ArtworkRef {
+        hash,
+        mime_type: mime_type.to_string(),
+        blob,
+    })
+}
+
+pub fn load_artwork(backup_path: &Path, artwork: &ArtworkRef) -> Result<Vec<u8>, String> {
+    load_blob(backup_path, ARTWORK_DIR, &artwork.blob)
+}
+
+/// Stores an encoded ID3 tag beside the backup at `backup_path`, once per
+/// distinct tag.
+pub fn store_raw_tag(backup_path: &Path, data: &[u8]) -> Result<RawTagRef, String> {
+    let hash = content_hash(data);
+    let blob = format!("{}.id3", hash);
+    store_blob(backup_path, RAW_TAG_DIR, &blob, data)?;
+    Ok(RawTagRef { hash, blob })
+}
+
+pub fn load_raw_tag(backup_path: &Path, raw_tag: &RawTagRef) -> Result<Vec<u8>, String> {
+    load_blob(backup_path, RAW_TAG_DIR, &raw_tag.blob)
+}
+
+/// Deletes the raw tags stored beside `backup_path` other than the
+/// `referenced` blobs.
+pub fn prune_raw_tags(backup_path: &Path, referenced: &HashSet<String>) -> Result<(), String> {
+    prune_dir(&blob_dir(backup_path, RAW_TAG_DIR)?, referenced)
+}
+
+/// A backup whose file is no longer where it was backed up from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrphanedBackup {
+    #[serde(with = "crate::path_codec")]
+    pub backup_path: PathBuf,
+    #[serde(with = "crate::path_codec")]
+    pub original_path: PathBuf,
+    /// Where the library database says the file was moved to, if a file
+    /// still exists there.
+    #[serde(default, with = "crate::path_codec::option")]
+    pub relocated_to: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OrphanCleanup {
+    pub relinked: usize,
+    pub removed: usize,
+    pub errors: Vec<String>,
+}
+
+/// A restore point for one file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupEntry {
+    #[serde(with = "crate::path_codec")]
+    pub backup_path: PathBuf,
+    /// Unix seconds, from the backup's name.
+    pub created_at: u64,
+    /// Fields restoring it would change on the file as it is now.
+    pub changes: Vec<FieldChange>,
+    /// Why the backup could not be read.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BackupDeletion {
+    pub deleted: usize,
+    pub errors: Vec<String>,
+}
+
+/// The file a backup was made from, going by its location and name
+/// (`<folder>/.autogenre_backups/<file name>.<unix seconds>.json`).
+pub fn original_path(backup_path: &Path) -> Option<PathBuf> {
+    let backup_dir = backup_path.parent()?;
+    if backup_dir.file_name()? != BACKUP_DIR {
+        return None;
+    }
+    FileScanner::backup_timestamp(backup_path)?;
+    // Taken apart as an `OsStr`, so names that are not UTF-8 survive.
+    let file_name = Path::new(backup_path.file_stem()?).file_stem()?;
+    Some(backup_dir.parent()?.join(file_name))
+}
+
+/// The name of a backup taken at `timestamp` of the file named `file_name`.
+pub fn backup_file_name(file_name: &OsStr, timestamp: u64) -> OsString {
+    let mut name = file_name.to_os_string();
+    name.push(format!(".{}.json", timestamp));
+    name
+}
+
+fn backups_under(root: &Path) -> impl Iterator<Item = PathBuf> {
+    WalkDir::new(root)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .filter(|path| original_path(path).is_some())
+}
+
+pub fn find_orphans(roots: &[PathBuf], db: &LibraryDb) -> Vec<OrphanedBackup> {
+    roots
+        .iter()
+        .flat_map(|root| backups_under(root))
+        .filter_map(|backup_path| {
+            let original_path = original_path(&backup_path)?;
+            if original_path.exists() {
+                return None;
+            }
+            let relocated_to = db.relocated(&original_path).filter(|path| path.is_file());
+            Some(OrphanedBackup { backup_path, original_path, relocated_to })
+        })
+        .collect()
+}
+
+/// Moves an orphaned backup beside the file it was relocated to, keeping
+/// its timestamp, and copies its artwork and raw tag along.
+fn relink(orphan: &OrphanedBackup, relocated_to: &Path) -> Result<PathBuf, String> {
+    let file_name = relocated_to.file_name()
+        .ok_or("Cannot determine filename")?;
+    let timestamp = FileScanner::backup_timestamp(&orphan.backup_path)
+        .ok_or("Cannot determine backup timestamp")?;
+    let backup_dir = relocated_to.parent()
+        .ok_or("Cannot determine parent directory")?
+        .join(BACKUP_DIR);
+    let new_path = backup_dir.join(backup_file_name(file_name, timestamp));
+
+    if simulation::is_enabled() {
+        simulation::record(SimulatedOperation::Move {
+            from: orphan.backup_path.clone(),
+            to: new_path.clone(),
+        });
+        return Ok(new_path);
+    }
+
+    fs::create_dir_all(&backup_dir)
+        .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+
+    let data = fs::read_to_string(&orphan.backup_path)
+        .map_err(|e| format!("Failed to read backup file: {}", e))?;
+    let backup = Backup::parse(&data)?;
+    if let Some(artwork) = backup.artwork {
+        let image = load_artwork(&orphan.backup_path, &artwork)?;
+        store_artwork(&new_path, &artwork.mime_type, &image)?;
+    }
+    if let Some(raw_tag) = backup.raw_tag {
+        store_raw_tag(&new_path, &load_raw_tag(&orphan.backup_path, &raw_tag)?)?;
+    }
+
+    fs::rename(&orphan.backup_path, &new_path)
+        .or_else(|_| {
+            fs::write(&new_path, &data)?;
+            fs::remove_file(&orphan.backup_path)
+        })
+        .map_err(|e| format!("Failed to move backup: {}", e))?;
+
+    Ok(new_path)
+}
+
+fn remove(backup_path: &Path) -> Result<(), String> {
+    if simulation::is_enabled() {
+        simulation::record(SimulatedOperation::Delete { path: backup_path.to_path_buf() });
+        return Ok(());
+    }
+    fs::remove_file(backup_path)
+        .map_err(|e| format!("Failed to remove backup {}: {}", backup_path.display(), e))
+}
+
+/// Deletes stored artwork and raw tags no backup in `backup_dir` refers to
+/// any more.
+fn prune_blobs(backup_dir: &Path) -> Result<(), String> {
+    if simulation::is_enabled() {
+        return Ok(());
+    }
+
+    // A backup that cannot be read may still reference blobs, so any such
+    // backup leaves every blob in place.
+    let backups: Vec<Backup> = fs::read_dir(backup_dir)
+        .map_err(|e| format!("Failed to read backup directory: {}", e))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .filter(|path| path.as_ref().map_or(true, |path| path.is_file() && FileScanner::backup_timestamp(path).is_some()))
+        .map(|path| {
+            let path = path.map_err(|e| format!("Failed to read backup directory: {}", e))?;
+            fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|data| Backup::parse(&data))
+                .map_err(|e| format!("Kept all backup blobs, {} could not be read: {}", path.display(), e))
+        })
+        .collect::<Result<_, String>>()?;
+
+    let artwork = backups.iter().filter_map(|backup| Some(backup.artwork.as_ref()?.blob.clone())).collect();
+    prune_dir(&backup_dir.join(ARTWORK_DIR), &artwork)?;
+    let raw_tags = backups.iter().filter_map(|backup| Some(backup.raw_tag.as_ref()?.blob.clone())).collect();
+    prune_dir(&backup_dir.join(RAW_TAG_DIR), &raw_tags)
+}
+
+fn prune_dir(dir: &Path, referenced: &HashSet<String>) -> Result<(), String> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    let blobs = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read backup directory {}: {}", dir.display(), e))?;
+    for blob in blobs.filter_map(|entry| entry.ok()) {
+        if !referenced.contains(&*blob.file_name().to_string_lossy()) {
+            fs::remove_file(blob.path())
+                .map_err(|e| format!("Failed to remove backup {}: {}", blob.path().display(), e))?;
+        }
+    }
+    Ok(())
+}
+
+/// The backups of the file at `file_path`, newest first.
+pub fn list_for_file(scanner: &FileScanner, file_path: &Path) -> Result<Vec<BackupEntry>, String> {
+    let backup_dir = file_path.parent()
+        .ok_or("Cannot determine parent directory")?
+        .join(BACKUP_DIR);
+    if !backup_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let current = scanner.read_metadata(file_path).unwrap_or_default();
+    let mut backups: Vec<BackupEntry> = fs::read_dir(&backup_dir)
+        .map_err(|e| format!("Failed to read backup directory: {}", e))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|backup_path| original_path(backup_path).as_deref() == Some(file_path))
+        .filter_map(|backup_path| {
+            let created_at = FileScanner::backup_timestamp(&backup_path)?;
+            let (changes, error) = match scanner.read_backup(&backup_path) {
+                Ok(backup) => (restore::field_changes(&current, &backup.metadata), None),
+                Err(e) => (Vec::new(), Some(e)),
+            };
+            Some(BackupEntry { backup_path, created_at, changes, error })
+        })
+        .collect();
+    backups.sort_by_key(|backup| std::cmp::Reverse(backup.created_at));
+    Ok(backups)
+}
+
+/// Deletes the backups under `roots` taken before `cutoff` (Unix seconds),
+/// and the artwork and tags only they used.
+pub fn delete_older_than(roots: &[PathBuf], cutoff: u64) -> BackupDeletion {
+    let mut deletion = BackupDeletion::default();
+    let mut touched_dirs = HashSet::new();
+
+    let expired = roots
+        .iter()
+        .flat_map(|root| backups_under(root))
+        .filter(|backup_path| FileScanner::backup_timestamp(backup_path).is_some_and(|created| created < cutoff));
+    for backup_path in expired {
+        match remove(&backup_path) {
+            Ok(()) => deletion.deleted += 1,
+            Err(e) => deletion.errors.push(e),
+        }
+        if let Some(dir) = backup_path.parent() {
+            touched_dirs.insert(dir.to_path_buf());
+        }
+    }
+
+    for dir in touched_dirs {
+        if let Err(e) = prune_blobs(&dir) {
+            deletion.errors.push(e);
+        }
+    }
+    deletion
+}
+
+/// Relinks orphans that were relocated when `relink_moved` is set and
+/// deletes the rest.
+pub fn clean_orphans(orphans: &[OrphanedBackup], relink_moved: bool) -> OrphanCleanup {
+    let mut cleanup = OrphanCleanup::default();
+    let mut touched_dirs = HashSet::new();
+
+    for orphan in orphans {
+        let outcome = match &orphan.relocated_to {
+            Some(relocated_to) if relink_moved => relink(orphan, relocated_to).map(|_| true),
+            _ => remove(&orphan.backup_path).map(|_| false),
+        };
+        match outcome {
+            Ok(true) => cleanup.relinked += 1,
+            Ok(false) => cleanup.removed += 1,
+            Err(e) => cleanup.errors.push(e),
+        }
+        if let Some(dir) = orphan.backup_path.parent() {
+            touched_dirs.insert(dir.to_path_buf());
+        }
+    }
+
+    for dir in touched_dirs {
+        if let Err(e) = prune_blobs(&dir) {
+            cleanup.errors.push(e);
+        }
+    }
+    cleanup
+}
@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+// Fixed-size fields of the EBU Tech 3285 `bext` chunk, in order.
+const DESCRIPTION_LEN: usize = 256;
+const ORIGINATOR_LEN: usize = 32;
+const ORIGINATOR_REFERENCE_LEN: usize = 32;
+const ORIGINATION_DATE_LEN: usize = 10;
+const ORIGINATION_TIME_LEN: usize = 8;
+// Time reference (8), version (2), UMID (64), loudness fields (10), reserved (180).
+const CODING_HISTORY_OFFSET: usize = 602;
+// Largest `bext` chunk read into memory. The coding history is a few lines
+// of text per processing step, so a bigger chunk is corrupt.
+const MAX_BEXT_LEN: u64 = 1024 * 1024;
+
+/// Broadcast Wave Format metadata written by field recorders and DAWs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BextChunk {
+    pub description: String,
+    pub originator: String,
+    pub originator_reference: String,
+    pub origination_date: String,
+    pub origination_time: String,
+    pub time_reference: u64,
+    pub coding_history: String,
+}
+
+fn fixed_string(data: &[u8]) -> String {
+    let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).trim().to_string()
+}
+
+/// Returns the raw payload of the `bext` chunk, if the file has one. A
+/// chunk claiming more bytes than the file holds, or over `MAX_BEXT_LEN`,
+/// is an error rather than an allocation.
+pub fn read_raw_bext(path: &Path) -> Result<Option<Vec<u8>>, String> {
+    let mut file = File::open(path)
+        .map_err(|e| format!("Failed to open WAV file: {}", e))?;
+    let file_len = file.metadata()
+        .map_err(|e| format!("Failed to read WAV file metadata: {}", e))?
+        .len();
+
+    let mut header = [0u8; 12];
+    if file.read_exact(&mut header).is_err() || &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+        return Ok(None);
+    }
+
+    let mut chunk_header = [0u8; 8];
+    while file.read_exact(&mut chunk_header).is_ok() {
+        let size = u32::from_le_bytes([chunk_header[4], chunk_header[5], chunk_header[6], chunk_header[7]]) as u64;
+
+        if &chunk_header[0..4] == b"bext" {
+            let position = file.stream_position()
+                .map_err(|e| format!("Failed to read WAV chunks: {}", e))?;
+            if size > file_len.saturating_sub(position) {
+                return Err("The bext chunk runs past the end of the file".to_string());
+            }
+            if size > MAX_BEXT_LEN {
+                return Err(format!("The bext chunk is {} bytes, more than a bext chunk can sensibly hold", size));
+            }
+            let mut data = vec![0u8; size as usize];
+            file.read_exact(&mut data)
+                .map_err(|e| format!("Failed to read bext chunk: {}", e))?;
+            return Ok(Some(data));
+        }
+
+        // Chunks are word aligned.
+        let skip = size + (size & 1);
+        file.seek(SeekFrom::Current(skip as i64))
+            .map_err(|e| format!("Failed to read WAV chunks: {}", e))?;
+    }
+
+    Ok(None)
+}
+
+pub fn parse_bext(data: &[u8]) -> Option<BextChunk> {
+    if data.len() < CODING_HISTORY_OFFSET {
+        return None;
+    }
+
+    let mut offset = 0;
+    let mut take = |len: usize| {
+        let field = &data[offset..offset + len];
+        offset += len;
+        field
+    };
+
+    let description = fixed_string(take(DESCRIPTION_LEN));
+    let originator = fixed_string(take(ORIGINATOR_LEN));
+    let originator_reference = fixed_string(take(ORIGINATOR_REFERENCE_LEN));
+    let origination_date = fixed_string(take(ORIGINATION_DATE_LEN));
+    let origination_time = fixed_string(take(ORIGINATION_TIME_LEN));
+    let low = take(4);
+    let high = take(4);
+    let time_reference = u32::from_le_bytes([low[0], low[1], low[2], low[3]]) as u64
+        | (u32::from_le_bytes([high[0], high[1], high[2], high[3]]) as u64) << 32;
+
+    Some(BextChunk {
+        description,
+        originator,
+        originator_reference,
+        origination_date,
+        origination_time,
+        time_reference,
+        coding_history: fixed_string(&data[CODING_HISTORY_OFFSET..]),
+    })
+}
+
+pub fn read_bext(path: &Path) -> Result<Option<BextChunk>, String> {
+    Ok(read_raw_bext(path)?.as_deref().and_then(parse_bext))
+}
+
+/// Re-appends a `bext` chunk captured before a tag write if the writer
+/// dropped it, fixing up the RIFF size.
+pub fn ensure_bext(path: &Path, data: &[u8]) -> Result<(), String> {
+    if read_raw_bext(path)?.is_some() {
+        return Ok(());
+    }
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open WAV file: {}", e))?;
+
+    let mut chunk = Vec::with_capacity(data.len() + 9);
+    chunk.extend_from_slice(b"bext");
+    chunk.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    chunk.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        chunk.push(0);
+    }
+
+    let file_len = fs::metadata(path)
+        .map_err(|e| format!("Failed to read WAV file size: {}", e))?
+        .len();
+    let riff_size = u32::try_from(file_len - 8 + chunk.len() as u64)
+        .map_err(|_| "WAV file too large to restore bext chunk".to_string())?;
+
+    file.seek(SeekFrom::End(0))
+        .and_then(|_| file.write_all(&chunk))
+        .and_then(|_| file.seek(SeekFrom::Start(4)))
+        .and_then(|_| file.write_all(&riff_size.to_le_bytes()))
+        .map_err(|e| format!("Failed to restore bext chunk: {}", e))
+}
@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::field_scope::FieldGuard;
+use crate::scanner::{FileScanner, Metadata};
+
+const CHANGESET_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingChange {
+    #[serde(with = "crate::path_codec")]
+    pub file_path: PathBuf,
+    pub metadata: Metadata,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeSetEntry {
+    #[serde(with = "crate::path_codec")]
+    pub file_path: PathBuf,
+    /// Tags as they were when the change-set was exported, used to detect
+    /// files edited on the target machine since.
+    pub original: Option<Metadata>,
+    pub metadata: Metadata,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeSet {
+    pub version: u32,
+    pub created_at: u64,
+    pub changes: Vec<ChangeSetEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", content = "message", rename_all = "snake_case")]
+pub enum ChangeOutcome {
+    Applied,
+    Conflict(String),
+    Failed(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeResult {
+    #[serde(with = "crate::path_codec")]
+    pub file_path: PathBuf,
+    pub outcome: ChangeOutcome,
+}
+
+pub fn export_changeset(changes: Vec<PendingChange>, output_path: &Path) -> Result<usize, String> {
+    let scanner = FileScanner::new();
+
+    let created_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let changeset = ChangeSet {
+        version: CHANGESET_VERSION,
+        created_at,
+        changes: changes
+            .into_iter()
+            .map(|change| ChangeSetEntry {
+                original: scanner.read_metadata(&change.file_path).ok(),
+                file_path: change.file_path,
+                metadata: change.metadata,
+            })
+            .collect(),
+    };
+
+    let json = serde_json::to_string_pretty(&changeset)
+        .map_err(|e| format!("Failed to serialize change-set: {}", e))?;
+
+    fs::write(output_path, json)
+        .map_err(|e| format!("Failed to write change-set file: {}", e))?;
+
+    Ok(changeset.changes.len())
+}
+
+pub fn load_changeset(path: &Path) -> Result<ChangeSet, String> {
+    let json = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read change-set file: {}", e))?;
+
+    let changeset: ChangeSet = serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse change-set: {}", e))?;
+
+    if changeset.version > CHANGESET_VERSION {
+        return Err(format!("Unsupported change-set version: {}", changeset.version));
+    }
+
+    Ok(changeset)
+}
+
+/// Applies every entry of a change-set. Files whose tags changed since export
+/// are reported as conflicts and left alone unless `force` is set; fields
+/// `guard` protects keep the file's values.
+pub fn apply_changeset(scanner: &FileScanner, changeset: &ChangeSet, backup: bool, force: bool, guard: &FieldGuard) -> Vec<ChangeResult> {
+    changeset.changes
+        .iter()
+        .map(|entry| ChangeResult {
+            file_path: entry.file_path.clone(),
+            outcome: apply_entry(scanner, entry, backup, force, guard),
+        })
+        .collect()
+}
+
+fn apply_entry(scanner: &FileScanner, entry: &ChangeSetEntry, backup: bool, force: bool, guard: &FieldGuard) -> ChangeOutcome {
+    if !entry.file_path.is_file() {
+        return ChangeOutcome::Failed(format!("File not found: {}", entry.file_path.display()));
+    }
+
+    let current = match scanner.read_metadata(&entry.file_path) {
+        Ok(current) => current,
+        Err(e) => return ChangeOutcome::Failed(e),
+    };
+
+    if !force && entry.original.as_ref().is_some_and(|original| *original != current) {
+        return ChangeOutcome::Conflict("Tags changed since the change-set was exported".to_string());
+    }
+
+    if backup {
+        if let Err(e) = scanner.backup_metadata(&entry.file_path, &current) {
+            return ChangeOutcome::Failed(e);
+        }
+    }
+
+    // Locked and scope-protected fields keep the file's values, whatever
+    // the change-set says.
+    let metadata = guard.reconcile(&entry.file_path, &current, &entry.metadata);
+    match scanner.write_metadata(&entry.file_path, &metadata) {
+        Ok(()) => ChangeOutcome::Applied,
+        Err(e) => ChangeOutcome::Failed(e),
+    }
+}
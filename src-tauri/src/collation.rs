@@ -0,0 +1,58 @@
+use deunicode::deunicode;
+
+/// Leading articles moved to the end of a name when building its sort form,
+/// per locale. Elided forms ("L'") attach to the next word without a space.
+fn articles(locale: &str) -> &'static [&'static str] {
+    let language = locale.split(['-', '_']).next().unwrap_or("").to_lowercase();
+    match language.as_str() {
+        "de" => &["Der", "Die", "Das"],
+        "es" => &["El", "La", "Los", "Las"],
+        "fr" => &["Les", "Le", "La", "L'"],
+        "it" => &["Gli", "Il", "Lo", "La", "Le", "L'", "I"],
+        "nl" => &["De", "Het"],
+        "pt" => &["Os", "As", "O", "A"],
+        _ => &["The"],
+    }
+}
+
+/// The name as it should be filed: "The Beatles" becomes "Beatles, The" and
+/// "L'Impératrice" becomes "Impératrice, L'". Names that are nothing but an
+/// article are left alone.
+pub fn sort_name(name: &str, locale: &str) -> String {
+    let name = name.trim();
+
+    for article in articles(locale) {
+        let elided = article.ends_with('\'');
+        let Some(prefix) = name.get(..article.len()) else {
+            continue;
+        };
+        if !prefix.eq_ignore_ascii_case(article) {
+            continue;
+        }
+
+        let rest = &name[article.len()..];
+        let rest = if elided {
+            rest
+        } else {
+            match rest.strip_prefix(' ') {
+                Some(rest) => rest,
+                None => continue,
+            }
+        };
+        let rest = rest.trim_start();
+        if !rest.is_empty() {
+            return format!("{}, {}", rest, prefix);
+        }
+    }
+
+    name.to_string()
+}
+
+/// A key that orders names the way a record shop would: articles moved to the
+/// end, accents folded ("Étienne" files under E, "Öona" under O), case and
+/// leading punctuation ignored.
+pub fn sort_key(name: &str, locale: &str) -> String {
+    deunicode(&sort_name(name, locale))
+        .trim_start_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase()
+}
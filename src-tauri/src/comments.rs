@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+
+use crate::settings::AppSettings;
+
+/// How generated text (key, energy, match source) may change a file's
+/// comment, which users often fill with purchase info and cue notes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommentPolicy {
+    Preserve,
+    Append,
+    Replace,
+}
+
+#[derive(Debug, Clone)]
+pub struct CommentRule {
+    pub policy: CommentPolicy,
+    pub separator: String,
+}
+
+impl CommentRule {
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        CommentRule {
+            policy: settings.comment_policy,
+            separator: settings.comment_separator.clone(),
+        }
+    }
+
+    /// The comment to write given the file's `existing` one and the `new`
+    /// text, or `None` to leave the field alone. Appending text the comment
+    /// already contains is a no-op, so re-runs do not repeat it.
+    pub fn merge(&self, existing: Option<&str>, new: Option<&str>) -> Option<String> {
+        let new = new.map(str::trim).filter(|n| !n.is_empty())?;
+        let existing = existing.map(str::trim).filter(|e| !e.is_empty());
+
+        match (self.policy, existing) {
+            (CommentPolicy::Preserve, _) => None,
+            (CommentPolicy::Replace, _) | (CommentPolicy::Append, None) => Some(new.to_string()),
+            (CommentPolicy::Append, Some(existing)) if existing.contains(new) => None,
+            (CommentPolicy::Append, Some(existing)) => {
+                Some(format!("{}{}{}", existing, self.separator, new))
+            }
+        }
+    }
+}
+
+impl Default for CommentRule {
+    fn default() -> Self {
+        CommentRule {
+            policy: CommentPolicy::Append,
+            separator: " | ".to_string(),
+        }
+    }
+}
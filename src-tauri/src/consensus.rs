@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::api_client::{Confidence, MetadataResult};
+use crate::settings::AppSettings;
+
+// How far each provider's genre is trusted relative to the others, unless
+// settings say otherwise. Beatport and Discogs curate genres per release;
+// Spotify and Last.fm only have artist-level or crowd tags.
+const SOURCE_WEIGHTS: &[(&str, f32)] = &[
+    (crate::LOCAL_HISTORY_SOURCE, 1.5),
+    ("Beatport", 1.0),
+    ("Discogs", 0.9),
+    ("MusicBrainz", 0.8),
+    ("Deezer", 0.6),
+    ("Spotify", 0.5),
+    ("Last.fm", 0.4),
+];
+const UNKNOWN_SOURCE_WEIGHT: f32 = 0.5;
+
+// Share of the total vote the winning genre needs for each confidence.
+// High also needs a second source to agree; one provider alone can be
+// wrong with full conviction.
+const HIGH_AGREEMENT: f32 = 0.75;
+const MEDIUM_AGREEMENT: f32 = 0.5;
+
+/// One provider's say in the consensus.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenreVote {
+    pub source: String,
+    pub genre: String,
+    pub weight: f32,
+    /// Whether this vote went to the recommended genre.
+    pub agrees: bool,
+}
+
+/// The single genre recommended from all providers' results.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolvedMetadata {
+    pub genre: String,
+    /// Confidence from how strongly the providers agree, not from any one
+    /// provider's match.
+    pub confidence: Confidence,
+    /// Share of the total vote (0 to 1) behind `genre`.
+    pub agreement: f32,
+    pub votes: Vec<GenreVote>,
+    /// The most trusted agreeing result with `genre` and `confidence`
+    /// applied, ready to write.
+    pub result: MetadataResult,
+}
+
+/// The built-in provider weights, which settings start out with.
+pub fn default_source_weights() -> HashMap<String, f32> {
+    SOURCE_WEIGHTS
+        .iter()
+        .map(|(name, weight)| (name.to_string(), *weight))
+        .collect()
+}
+
+pub fn validate_weights(weights: &HashMap<String, f32>) -> Result<(), String> {
+    let mut sources = HashSet::new();
+    for (source, weight) in weights {
+        if !weight.is_finite() || *weight < 0.0 {
+            return Err(format!("Invalid weight for {}: {}", source, weight));
+        }
+        // Names are matched ignoring case, so two spellings would clash.
+        if !sources.insert(source.to_lowercase()) {
+            return Err(format!("Weight for {} is given twice", source));
+        }
+    }
+    Ok(())
+}
+
+/// How much each provider's genre counts in the vote: the configured
+/// weight, else the built-in one.
+#[derive(Debug, Clone, Default)]
+pub struct SourceWeights {
+    // Keyed by lowercased provider name.
+    configured: HashMap<String, f32>,
+}
+
+impl SourceWeights {
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        SourceWeights {
+            configured: settings.source_weights
+                .iter()
+                .map(|(source, weight)| (source.to_lowercase(), *weight))
+                .collect(),
+        }
+    }
+
+    fn weight(&self, source: &str) -> f32 {
+        self.configured
+            .get(&source.to_lowercase())
+            .copied()
+            .or_else(|| {
+                SOURCE_WEIGHTS
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(source))
+                    .map(|(_, weight)| *weight)
+            })
+            .unwrap_or(UNKNOWN_SOURCE_WEIGHT)
+    }
+}
+
+fn confidence_weight(confidence: &Confidence) -> f32 {
+    match confidence {
+        Confidence::High => 1.0,
+        Confidence::Medium => 0.6,
+        Confidence::Low => 0.3,
+    }
+}
+
+/// Merges the providers' results into one recommended genre. Each result
+/// votes for its main genre with its source's weight scaled by its match
+/// confidence; genres are compared ignoring case. `None` when no result has
+/// a genre, or every vote weighs nothing.
+pub fn resolve_metadata(results: &[MetadataResult], weights: &SourceWeights) -> Option<ResolvedMetadata> {
+    let mut votes: Vec<(GenreVote, &MetadataResult)> = results
+        .iter()
+        .filter_map(|result| {
+            let genre = result.genre.as_deref().map(str::trim).filter(|g| !g.is_empty())?;
+            let vote = GenreVote {
+                source: result.source.clone(),
+                genre: genre.to_string(),
+                weight: weights.weight(&result.source) * confidence_weight(&result.confidence),
+                agrees: false,
+            };
+            Some((vote, result))
+        })
+        .collect();
+
+    let total: f32 = votes.iter().map(|(vote, _)| vote.weight).sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let support = |genre: &str| -> f32 {
+        votes.iter()
+            .filter(|(vote, _)| vote.genre.eq_ignore_ascii_case(genre))
+            .map(|(vote, _)| vote.weight)
+            .sum()
+    };
+    let winner = votes
+        .iter()
+        .map(|(vote, _)| (vote.genre.clone(), support(&vote.genre)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    for (vote, _) in &mut votes {
+        vote.agrees = vote.genre.eq_ignore_ascii_case(&winner.0);
+    }
+    // Spelled as by the most trusted agreeing source, whose result is also
+    // the one carried over.
+    let (lead, lead_result) = votes
+        .iter()
+        .filter(|(vote, _)| vote.agrees)
+        .max_by(|a, b| a.0.weight.total_cmp(&b.0.weight))?;
+
+    let agreement = winner.1 / total;
+    let agreeing_sources = votes.iter().filter(|(vote, _)| vote.agrees).count();
+    let confidence = if agreement >= HIGH_AGREEMENT && agreeing_sources > 1 {
+        Confidence::High
+    } else if agreement >= MEDIUM_AGREEMENT {
+        Confidence::Medium
+    } else {
+        Confidence::Low
+    };
+
+    let genre = lead.genre.clone();
+    let mut result = (*lead_result).clone();
+    result.genre = Some(genre.clone());
+    result.confidence = confidence.clone();
+    Some(ResolvedMetadata {
+        genre,
+        confidence,
+        agreement,
+        votes: votes.into_iter().map(|(vote, _)| vote).collect(),
+        result,
+    })
+}
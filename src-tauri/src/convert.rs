@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::changeset::ChangeOutcome;
+use crate::diskspace::{self, SpaceRequirements};
+use crate::scanner::{file_id, FileScanner};
+use crate::settings::AppSettings;
+use crate::simulation::{self, SimulatedOperation};
+use crate::workdir;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversionResult {
+    #[serde(with = "crate::path_codec")]
+    pub file_path: PathBuf,
+    #[serde(default, with = "crate::path_codec::option")]
+    pub output_path: Option<PathBuf>,
+    pub outcome: ChangeOutcome,
+}
+
+/// The ffmpeg binary configured in settings; conversion is disabled when
+/// none is set.
+pub fn ffmpeg_binary(settings: &AppSettings) -> Option<PathBuf> {
+    let path = settings.ffmpeg_path.trim();
+    if path.is_empty() {
+        None
+    } else {
+        Some(PathBuf::from(path))
+    }
+}
+
+/// Runs a prepared ffmpeg command, turning a missing binary or a non-zero
+/// exit into a readable error.
+pub fn run_ffmpeg(command: &mut Command) -> Result<(), String> {
+    let output = command
+        .output()
+        .map_err(|e| match e.kind() {
+            ErrorKind::NotFound => "ffmpeg not found; install it or set its path in settings".to_string(),
+            _ => format!("Failed to run ffmpeg: {}", e),
+        })?;
+
+    if !output.status.success() {
+        return Err(format!("ffmpeg failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    Ok(())
+}
+
+fn codec_args(target_format: &str) -> Result<&'static [&'static str], String> {
+    match target_format {
+        "flac" => Ok(&["-c:a", "flac"]),
+        "mp3" => Ok(&["-c:a", "libmp3lame", "-q:a", "0"]),
+        "m4a" => Ok(&["-c:a", "aac", "-b:a", "256k"]),
+        "ogg" => Ok(&["-c:a", "libvorbis", "-q:a", "6"]),
+        "wav" => Ok(&["-c:a", "pcm_s24le"]),
+        other => Err(format!("Unsupported conversion target: {}", other)),
+    }
+}
+
+/// Runs ffmpeg to encode `source` into `output_path` in `target_format`,
+/// carrying over ffmpeg-mapped tags and embedded artwork where possible.
+/// No filters are applied, so levels are unchanged.
+pub fn transcode(ffmpeg: &Path, source: &Path, output_path: &Path, target_format: &str) -> Result<(), String> {
+    let codec = codec_args(target_format)?;
+
+    if simulation::is_enabled() {
+        simulation::record(SimulatedOperation::Encode {
+            from: source.to_path_buf(),
+            to: output_path.to_path_buf(),
+        });
+        return Ok(());
+    }
+
+    let mut command = Command::new(ffmpeg);
+    command
+        .args(["-nostdin", "-v", "error", "-i"])
+        .arg(source)
+        .args(["-map", "0:a", "-map_metadata", "0"]);
+
+    // Embedded artwork travels as a video stream in formats that allow it.
+    if matches!(target_format, "flac" | "mp3" | "m4a") {
+        command.args(["-map", "0:v?", "-c:v", "copy", "-disposition:v", "attached_pic"]);
+    }
+
+    let result = run_ffmpeg(command.args(codec).arg(output_path));
+    if result.is_err() {
+        let _ = fs::remove_file(output_path);
+    }
+    result
+}
+
+/// Transcodes `path` next to itself with the target extension, then writes
+/// the source's tags onto the result so nothing depends on ffmpeg's own tag
+/// mapping. Encoding and tagging happen in `work_dir`, so a failed or
+/// interrupted conversion never leaves a partial file beside the source.
+/// The source is removed unless `keep_original` is set; the removal is not
+/// journaled, so undo cannot bring it back.
+pub fn convert_file(
+    ffmpeg: &Path,
+    scanner: &FileScanner,
+    path: &Path,
+    target_format: &str,
+    keep_original: bool,
+    work_dir: &Path,
+) -> Result<PathBuf, String> {
+    let target_format = target_format.trim_start_matches('.').to_lowercase();
+    codec_args(&target_format)?;
+
+    let source_format = path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if source_format == target_format {
+        return Err(format!("File is already {}", target_format));
+    }
+
+    let output_path = path.with_extension(&target_format);
+    if output_path.exists() {
+        return Err(format!("File already exists at destination: {}", output_path.display()));
+    }
+
+    let metadata = scanner.read_metadata(path).ok();
+
+    if simulation::is_enabled() {
+        transcode(ffmpeg, path, &output_path, &target_format)?;
+        if let Some(metadata) = metadata {
+            scanner.write_metadata(&output_path, &metadata)?;
+        }
+    } else {
+        let filename = output_path.file_name().ok_or("Cannot determine filename")?;
+        let scratch = work_dir.join(format!("{}-{}", file_id(path), filename.to_string_lossy()));
+
+        transcode(ffmpeg, path, &scratch, &target_format)?;
+        if let Some(metadata) = metadata {
+            if let Err(e) = scanner.write_metadata(&scratch, &metadata) {
+                let _ = fs::remove_file(&scratch);
+                return Err(e);
+            }
+        }
+        workdir::move_into_place(&scratch, &output_path)?;
+        let source = fs::metadata(path)
+            .map_err(|e| format!("Failed to read file metadata: {}", e))?;
+        scanner.permissions().apply_to_file(&source, &output_path)?;
+    }
+
+    if !keep_original {
+        if simulation::is_enabled() {
+            simulation::record(SimulatedOperation::Delete { path: path.to_path_buf() });
+        } else {
+            fs::remove_file(path)
+                .map_err(|e| format!("Failed to remove original file: {}", e))?;
+        }
+    }
+
+    Ok(output_path)
+}
+
+/// Checks there is room for converting every file in `paths` before any is
+/// started: each output beside its source, and the largest one in
+/// `work_dir`, where outputs are encoded one at a time.
+pub fn check_space(paths: &[PathBuf], target_format: &str, work_dir: &Path) -> Result<(), String> {
+    let target_format = target_format.trim_start_matches('.').to_lowercase();
+    let mut requirements = SpaceRequirements::new();
+    let mut largest = 0;
+
+    for path in paths {
+        let size = diskspace::estimate_encoded_size(path, &target_format);
+        if let Some(dir) = path.parent() {
+            requirements.add(dir, size);
+        }
+        largest = largest.max(size);
+    }
+    requirements.add(work_dir, largest);
+    requirements.check()
+}
@@ -0,0 +1,280 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::convert::run_ffmpeg;
+use crate::permissions::FilePermissions;
+use crate::simulation::{self, SimulatedOperation};
+
+// CUE timestamps are mm:ss:ff with 75 frames per second.
+const FRAMES_PER_SECOND: u64 = 75;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CueTrack {
+    pub number: u32,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    /// Start of INDEX 01, in CUE frames.
+    pub start_frames: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CueSheet {
+    #[serde(with = "crate::path_codec")]
+    pub audio_file: PathBuf,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub genre: Option<String>,
+    pub date: Option<String>,
+    pub tracks: Vec<CueTrack>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitProgress {
+    pub track: u32,
+    pub total_tracks: usize,
+    #[serde(with = "crate::path_codec")]
+    pub output_path: PathBuf,
+    pub done: bool,
+}
+
+/// CUE sheets from rippers are often Windows-1252 rather than UTF-8; fall
+/// back to Latin-1 so they still parse.
+fn decode_cue(bytes: &[u8]) -> String {
+    let bytes = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(bytes);
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.iter().map(|&b| b as char).collect(),
+    }
+}
+
+/// Splits a CUE line into its command and arguments, honouring quotes.
+fn tokenize(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in line.trim().chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+fn parse_timestamp(value: &str) -> Option<u64> {
+    let parts: Vec<u64> = value
+        .split(':')
+        .map(|part| part.parse().ok())
+        .collect::<Option<Vec<u64>>>()?;
+
+    match parts.as_slice() {
+        [minutes, seconds, frames] => Some((minutes * 60 + seconds) * FRAMES_PER_SECOND + frames),
+        _ => None,
+    }
+}
+
+impl CueSheet {
+    pub fn load(cue_path: &Path) -> Result<Self, String> {
+        let bytes = fs::read(cue_path)
+            .map_err(|e| format!("Failed to read CUE sheet: {}", e))?;
+        let base_dir = cue_path.parent().unwrap_or(Path::new("."));
+        Self::parse(&decode_cue(&bytes), base_dir)
+    }
+
+    pub fn parse(text: &str, base_dir: &Path) -> Result<Self, String> {
+        let mut audio_file: Option<PathBuf> = None;
+        let mut sheet = CueSheet {
+            audio_file: PathBuf::new(),
+            title: None,
+            performer: None,
+            genre: None,
+            date: None,
+            tracks: Vec::new(),
+        };
+
+        for line in text.lines() {
+            let tokens = tokenize(line);
+            let command = match tokens.first() {
+                Some(command) => command.to_uppercase(),
+                None => continue,
+            };
+            let value = tokens.get(1).cloned();
+
+            match command.as_str() {
+                "FILE" => {
+                    if audio_file.is_some() {
+                        return Err("CUE sheet references more than one audio file; only single-image albums can be split".to_string());
+                    }
+                    let name = value.ok_or("CUE FILE entry has no filename")?;
+                    audio_file = Some(base_dir.join(name));
+                }
+                "TRACK" => {
+                    let number = value
+                        .and_then(|n| n.parse().ok())
+                        .ok_or_else(|| format!("Invalid CUE TRACK entry: {}", line.trim()))?;
+                    sheet.tracks.push(CueTrack {
+                        number,
+                        title: None,
+                        performer: None,
+                        start_frames: 0,
+                    });
+                }
+                "TITLE" => match sheet.tracks.last_mut() {
+                    Some(track) => track.title = value,
+                    None => sheet.title = value,
+                },
+                "PERFORMER" => match sheet.tracks.last_mut() {
+                    Some(track) => track.performer = value,
+                    None => sheet.performer = value,
+                },
+                "INDEX" if value.as_deref() == Some("01") => {
+                    let start = tokens.get(2)
+                        .and_then(|t| parse_timestamp(t))
+                        .ok_or_else(|| format!("Invalid CUE INDEX entry: {}", line.trim()))?;
+                    if let Some(track) = sheet.tracks.last_mut() {
+                        track.start_frames = start;
+                    }
+                }
+                "REM" => {
+                    let field = value.map(|v| v.to_uppercase());
+                    let rem_value = tokens.get(2).cloned();
+                    match field.as_deref() {
+                        Some("GENRE") => sheet.genre = rem_value,
+                        Some("DATE") => sheet.date = rem_value,
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        sheet.audio_file = audio_file.ok_or("CUE sheet has no FILE entry")?;
+        if sheet.tracks.is_empty() {
+            return Err("CUE sheet has no tracks".to_string());
+        }
+        Ok(sheet)
+    }
+
+    pub fn output_path(&self, track: &CueTrack, output_dir: &Path) -> PathBuf {
+        let sanitize = |s: &str| -> String {
+            s.chars()
+                .map(|c| if c.is_alphanumeric() || c == ' ' || c == '-' || c == '.' { c } else { '_' })
+                .collect()
+        };
+
+        let title = track.title.as_deref()
+            .map(sanitize)
+            .unwrap_or_else(|| format!("Track {}", track.number));
+
+        output_dir.join(format!("{:02} - {}.flac", track.number, title))
+    }
+}
+
+fn seconds(frames: u64) -> String {
+    format!("{:.3}", frames as f64 / FRAMES_PER_SECOND as f64)
+}
+
+/// Cuts the disc image into one tagged FLAC per CUE track by shelling out
+/// to ffmpeg, reporting progress before and after each track.
+pub fn split(
+    ffmpeg: &Path,
+    sheet: &CueSheet,
+    output_dir: &Path,
+    permissions: &FilePermissions,
+    mut on_progress: impl FnMut(SplitProgress),
+) -> Result<Vec<PathBuf>, String> {
+    if !sheet.audio_file.is_file() {
+        return Err(format!("Disc image not found: {}", sheet.audio_file.display()));
+    }
+
+    if !output_dir.is_dir() {
+        if simulation::is_enabled() {
+            simulation::record(SimulatedOperation::CreateDir { path: output_dir.to_path_buf() });
+        } else {
+            permissions.create_dir_all(output_dir)?;
+        }
+    }
+    let source = fs::metadata(&sheet.audio_file)
+        .map_err(|e| format!("Failed to read file metadata: {}", e))?;
+
+    let total_tracks = sheet.tracks.len();
+    let mut outputs = Vec::with_capacity(total_tracks);
+
+    for (i, track) in sheet.tracks.iter().enumerate() {
+        let output_path = sheet.output_path(track, output_dir);
+        if output_path.exists() {
+            return Err(format!("File already exists at destination: {}", output_path.display()));
+        }
+
+        on_progress(SplitProgress {
+            track: track.number,
+            total_tracks,
+            output_path: output_path.clone(),
+            done: false,
+        });
+
+        if simulation::is_enabled() {
+            simulation::record(SimulatedOperation::Encode {
+                from: sheet.audio_file.clone(),
+                to: output_path.clone(),
+            });
+        } else {
+            let mut command = Command::new(ffmpeg);
+            command
+                .args(["-nostdin", "-v", "error", "-i"])
+                .arg(&sheet.audio_file)
+                .args(["-ss", &seconds(track.start_frames)]);
+
+            if let Some(next) = sheet.tracks.get(i + 1) {
+                command.args(["-to", &seconds(next.start_frames)]);
+            }
+
+            let tags = [
+                ("title", track.title.as_ref()),
+                ("artist", track.performer.as_ref().or(sheet.performer.as_ref())),
+                ("album_artist", sheet.performer.as_ref()),
+                ("album", sheet.title.as_ref()),
+                ("genre", sheet.genre.as_ref()),
+                ("date", sheet.date.as_ref()),
+            ];
+            for (key, value) in tags {
+                if let Some(value) = value {
+                    command.arg("-metadata").arg(format!("{}={}", key, value));
+                }
+            }
+
+            command
+                .arg("-metadata")
+                .arg(format!("track={}/{}", track.number, total_tracks))
+                .args(["-map_metadata", "-1", "-c:a", "flac"])
+                .arg(&output_path);
+
+            if let Err(e) = run_ffmpeg(&mut command) {
+                let _ = fs::remove_file(&output_path);
+                return Err(format!("Track {}: {}", track.number, e));
+            }
+            permissions.apply_to_file(&source, &output_path)?;
+        }
+
+        on_progress(SplitProgress {
+            track: track.number,
+            total_tracks,
+            output_path: output_path.clone(),
+            done: true,
+        });
+        outputs.push(output_path);
+    }
+
+    Ok(outputs)
+}
@@ -0,0 +1,224 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::Path;
+use std::time::Duration;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{CodecParameters, Decoder, DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use symphonia::core::units::Time;
+
+/// Stream properties of a file's first audio track.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioInfo {
+    pub sample_rate: u32,
+    pub channels: usize,
+    /// Seconds, when the container states it.
+    pub duration: Option<f64>,
+}
+
+/// Which part of a file to decode and in what shape. The default decodes
+/// the whole file at its own rate and channel layout.
+#[derive(Debug, Clone, Default)]
+pub struct DecodeOptions {
+    /// Where to start; decoding begins at the exact sample.
+    pub start: Option<Duration>,
+    /// How much to decode from `start`.
+    pub length: Option<Duration>,
+    /// Sample rate to resample to.
+    pub sample_rate: Option<u32>,
+    /// Average the channels down to one.
+    pub mono: bool,
+}
+
+/// Decoded audio as interleaved `f32` samples in [-1, 1].
+#[derive(Debug, Clone)]
+pub struct Pcm {
+    pub sample_rate: u32,
+    pub channels: usize,
+    pub samples: Vec<f32>,
+}
+
+impl Pcm {
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels.max(1)
+    }
+
+    /// Peak amplitude of each of `points` equal slices, for drawing a
+    /// waveform.
+    pub fn peaks(&self, points: usize) -> Vec<f32> {
+        let channels = self.channels.max(1);
+        let frames = self.frames();
+        if frames == 0 || points == 0 {
+            return Vec::new();
+        }
+        let per_point = frames.div_ceil(points);
+        self.samples
+            .chunks(per_point * channels)
+            .map(|slice| slice.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs())))
+            .collect()
+    }
+
+    fn into_mono(self) -> Pcm {
+        if self.channels <= 1 {
+            return self;
+        }
+        let samples = self.samples
+            .chunks_exact(self.channels)
+            .map(|frame| frame.iter().sum::<f32>() / self.channels as f32)
+            .collect();
+        Pcm { sample_rate: self.sample_rate, channels: 1, samples }
+    }
+
+    /// Linear-interpolation resampling; enough for analysis, not for
+    /// listening-quality output.
+    fn resampled(self, sample_rate: u32) -> Pcm {
+        if sample_rate == self.sample_rate || self.samples.is_empty() {
+            return Pcm { sample_rate, ..self };
+        }
+
+        let channels = self.channels.max(1);
+        let frames = self.frames();
+        let ratio = self.sample_rate as f64 / sample_rate as f64;
+        let out_frames = (frames as f64 / ratio).floor() as usize;
+
+        let mut samples = Vec::with_capacity(out_frames * channels);
+        for i in 0..out_frames {
+            let position = i as f64 * ratio;
+            let index = position.floor() as usize;
+            let next = (index + 1).min(frames - 1);
+            let fraction = (position - index as f64) as f32;
+            for channel in 0..channels {
+                let a = self.samples[index * channels + channel];
+                let b = self.samples[next * channels + channel];
+                samples.push(a + (b - a) * fraction);
+            }
+        }
+        Pcm { sample_rate, channels, samples }
+    }
+}
+
+struct OpenTrack {
+    format: Box<dyn FormatReader>,
+    track_id: u32,
+    params: CodecParameters,
+}
+
+fn open(path: &Path) -> Result<OpenTrack, String> {
+    let file = File::open(path)
+        .map_err(|e| format!("Failed to open audio file: {}", e))?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|s| s.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    let probed = symphonia::default::get_probe()
+        .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+        .map_err(|e| format!("Unsupported audio format: {}", e))?;
+
+    let format = probed.format;
+    let track = format.tracks()
+        .iter()
+        .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or("No audio track found")?;
+
+    Ok(OpenTrack {
+        track_id: track.id,
+        params: track.codec_params.clone(),
+        format,
+    })
+}
+
+/// Reads the stream properties without decoding any audio.
+pub fn probe(path: &Path) -> Result<AudioInfo, String> {
+    let track = open(path)?;
+    let sample_rate = track.params.sample_rate
+        .ok_or("Audio track has no sample rate")?;
+
+    Ok(AudioInfo {
+        sample_rate,
+        channels: track.params.channels.map(|c| c.count()).unwrap_or(1),
+        duration: track.params.n_frames.map(|frames| frames as f64 / sample_rate as f64),
+    })
+}
+
+/// Decodes the file's first audio track to PCM. Corrupt packets are
+/// skipped rather than failing the whole decode.
+pub fn decode(path: &Path, options: &DecodeOptions) -> Result<Pcm, String> {
+    let OpenTrack { mut format, track_id, params } = open(path)?;
+    let sample_rate = params.sample_rate
+        .ok_or("Audio track has no sample rate")?;
+
+    let mut decoder: Box<dyn Decoder> = symphonia::default::get_codecs()
+        .make(&params, &DecoderOptions::default())
+        .map_err(|e| format!("Unsupported audio codec: {}", e))?;
+
+    // Seeking lands on a packet boundary; frames before the requested one
+    // are dropped as they are decoded. Timestamps count frames for the
+    // codecs supported here.
+    let mut skip_until = 0;
+    if let Some(start) = options.start.filter(|start| !start.is_zero()) {
+        let seeked = format
+            .seek(SeekMode::Accurate, SeekTo::Time { time: Time::from(start.as_secs_f64()), track_id: Some(track_id) })
+            .map_err(|e| format!("Failed to seek: {}", e))?;
+        decoder.reset();
+        skip_until = seeked.required_ts;
+    }
+
+    let mut channels = params.channels.map(|c| c.count()).unwrap_or(1);
+    let max_frames = options.length
+        .map(|length| (length.as_secs_f64() * sample_rate as f64).ceil() as usize);
+    let mut samples: Vec<f32> = Vec::new();
+
+    loop {
+        if max_frames.is_some_and(|max| samples.len() / channels >= max) {
+            break;
+        }
+
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(SymphoniaError::ResetRequired) => {
+                decoder.reset();
+                continue;
+            }
+            Err(e) => return Err(format!("Failed to read audio: {}", e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(format!("Failed to decode audio: {}", e)),
+        };
+
+        let spec = *decoded.spec();
+        channels = spec.channels.count().max(1);
+        let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        buffer.copy_interleaved_ref(decoded);
+
+        let skip_frames = skip_until.saturating_sub(packet.ts()) as usize;
+        let skip = (skip_frames * channels).min(buffer.samples().len());
+        samples.extend_from_slice(&buffer.samples()[skip..]);
+    }
+
+    if let Some(max) = max_frames {
+        samples.truncate(max * channels);
+    }
+
+    let mut pcm = Pcm { sample_rate, channels, samples };
+    if options.mono {
+        pcm = pcm.into_mono();
+    }
+    if let Some(target) = options.sample_rate {
+        pcm = pcm.resampled(target);
+    }
+    Ok(pcm)
+}
@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::decode;
+use crate::simulation;
+
+/// Estimates of encoded size are rough, so this much room is kept spare on
+/// top of them.
+const ESTIMATE_MARGIN: f64 = 1.1;
+
+/// Space an operation needs on one volume against what it has.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VolumeSpace {
+    /// A folder on the volume the operation writes to.
+    #[serde(with = "crate::path_codec")]
+    pub path: PathBuf,
+    pub required: u64,
+    pub available: u64,
+}
+
+/// Bytes to be written, summed per volume.
+#[derive(Debug, Default)]
+pub struct SpaceRequirements {
+    volumes: BTreeMap<String, VolumeSpace>,
+}
+
+impl SpaceRequirements {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `bytes` to be written into `dir`, which need not exist yet.
+    pub fn add(&mut self, dir: &Path, bytes: u64) {
+        let existing = existing_ancestor(dir);
+        let entry = self.volumes
+            .entry(volume_id(&existing))
+            .or_insert_with(|| VolumeSpace { path: existing, required: 0, available: 0 });
+        entry.required += bytes;
+    }
+
+    /// Fails with a report of every volume that is short, before anything
+    /// has been written. Nothing is checked in simulation mode, which
+    /// writes nothing.
+    pub fn check(self) -> Result<(), String> {
+        if simulation::is_enabled() {
+            return Ok(());
+        }
+
+        let mut short = Vec::new();
+        for mut volume in self.volumes.into_values() {
+            volume.available = fs4::available_space(&volume.path)
+                .map_err(|e| format!("Failed to read free space on {}: {}", volume.path.display(), e))?;
+            if volume.required > volume.available {
+                short.push(volume);
+            }
+        }
+
+        if short.is_empty() {
+            return Ok(());
+        }
+        let report: Vec<String> = short
+            .iter()
+            .map(|volume| format!(
+                "{} needs {} but has {} free",
+                volume.path.display(),
+                format_bytes(volume.required),
+                format_bytes(volume.available),
+            ))
+            .collect();
+        Err(format!("Not enough disk space: {}", report.join("; ")))
+    }
+}
+
+/// The nearest folder at or above `path` that exists, which is where free
+/// space can be asked about.
+fn existing_ancestor(path: &Path) -> PathBuf {
+    path.ancestors()
+        .find(|dir| dir.is_dir())
+        .unwrap_or(path)
+        .to_path_buf()
+}
+
+#[cfg(unix)]
+fn volume_id(path: &Path) -> String {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path)
+        .map(|metadata| metadata.dev().to_string())
+        .unwrap_or_else(|_| path.display().to_string())
+}
+
+/// The drive or share a path is on, going by its prefix.
+#[cfg(not(unix))]
+fn volume_id(path: &Path) -> String {
+    match path.components().next() {
+        Some(std::path::Component::Prefix(prefix)) => prefix.as_os_str().to_string_lossy().to_uppercase(),
+        _ => path.display().to_string(),
+    }
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+pub fn file_size(path: &Path) -> u64 {
+    fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+/// Expected size of `source` once encoded as `target_format` by
+/// `convert::transcode`, from its duration and channel layout. Falls back
+/// to the source's own size when the stream cannot be probed.
+pub fn estimate_encoded_size(source: &Path, target_format: &str) -> u64 {
+    let Ok(info) = decode::probe(source) else {
+        return file_size(source);
+    };
+    let Some(duration) = info.duration else {
+        return file_size(source);
+    };
+
+    let pcm_24bit = duration * info.sample_rate as f64 * info.channels as f64 * 3.0;
+    let bytes = match target_format {
+        "wav" => pcm_24bit,
+        // Lossless compression of typical music lands near 60% of 16-bit PCM.
+        "flac" => pcm_24bit * 2.0 / 3.0 * 0.6,
+        // Bitrates from the encoder settings in `convert::codec_args`; V0
+        // MP3 averages about 245 kbit/s and Vorbis q6 about 192.
+        "mp3" => duration * 245_000.0 / 8.0,
+        "m4a" => duration * 256_000.0 / 8.0,
+        "ogg" => duration * 192_000.0 / 8.0,
+        _ => return file_size(source),
+    };
+    (bytes * ESTIMATE_MARGIN) as u64
+}
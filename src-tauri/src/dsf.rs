@@ -0,0 +1,53 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+// The "DSD " chunk: id (4), chunk size (8), total file size (8), offset of
+// the ID3v2 metadata chunk or 0 when there is none (8).
+const DSD_CHUNK_LEN: usize = 28;
+const FILE_SIZE_OFFSET: u64 = 12;
+const METADATA_OFFSET: u64 = 20;
+
+/// The offset of the metadata chunk, 0 when there is none.
+fn metadata_offset(file: &mut File) -> id3::Result<u64> {
+    let mut chunk = [0u8; DSD_CHUNK_LEN];
+    file.read_exact(&mut chunk)?;
+    if &chunk[0..4] != b"DSD " {
+        return Err(id3::Error::new(id3::ErrorKind::InvalidInput, "Not a DSF file"));
+    }
+    let offset = METADATA_OFFSET as usize;
+    Ok(u64::from_le_bytes(chunk[offset..offset + 8].try_into().unwrap()))
+}
+
+/// The ID3v2 tag DSF files keep at the end, after the audio.
+pub fn read_tag(path: &Path) -> id3::Result<id3::Tag> {
+    let mut file = File::open(path)?;
+    let metadata_offset = metadata_offset(&mut file)?;
+    if metadata_offset == 0 {
+        return Err(id3::Error::new(id3::ErrorKind::NoTag, "DSF file has no metadata chunk"));
+    }
+
+    let mut data = Vec::new();
+    file.seek(SeekFrom::Start(metadata_offset))?;
+    file.read_to_end(&mut data)?;
+    id3::Tag::read_from2(Cursor::new(data))
+}
+
+/// Replaces the file's trailing ID3v2 tag and points the header at it.
+pub fn write_tag(path: &Path, tag: &id3::Tag) -> id3::Result<()> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let tag_start = match metadata_offset(&mut file)? {
+        0 => file.metadata()?.len(),
+        offset => offset,
+    };
+
+    file.set_len(tag_start)?;
+    file.seek(SeekFrom::Start(tag_start))?;
+    tag.write_to(&mut file, id3::Version::Id3v24)?;
+    let file_size = file.stream_position()?;
+
+    file.seek(SeekFrom::Start(FILE_SIZE_OFFSET))?;
+    file.write_all(&file_size.to_le_bytes())?;
+    file.write_all(&tag_start.to_le_bytes())?;
+    Ok(())
+}
@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::changeset::ChangeOutcome;
+use crate::journal;
+use crate::scanner::audio_bitrate;
+use crate::simulation::{self, SimulatedOperation};
+use crate::workdir;
+
+/// What happens to every copy in a duplicate group but the one kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateAction {
+    /// Moved to the quarantine folder, to be checked and emptied by hand.
+    Quarantine,
+    Delete,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemovedDuplicate {
+    #[serde(with = "crate::path_codec")]
+    pub path: PathBuf,
+    pub bitrate: u32,
+    /// Where the file was moved under quarantine; `None` when deleted.
+    #[serde(default, with = "crate::path_codec::option")]
+    pub quarantined_to: Option<PathBuf>,
+    /// `None` in a dry run, where nothing is touched.
+    pub outcome: Option<ChangeOutcome>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateResolution {
+    /// The highest-bitrate copy; the first listed on a tie.
+    #[serde(with = "crate::path_codec")]
+    pub kept: PathBuf,
+    pub kept_bitrate: u32,
+    pub removed: Vec<RemovedDuplicate>,
+}
+
+/// Keeps the highest-bitrate file of each group and quarantines or deletes
+/// the rest. A dry run only reports what would happen.
+pub fn resolve(groups: &[Vec<PathBuf>], action: DuplicateAction, quarantine: &Path, dry_run: bool) -> Result<Vec<DuplicateResolution>, String> {
+    if action == DuplicateAction::Quarantine && !dry_run && !simulation::is_enabled() {
+        fs::create_dir_all(quarantine)
+            .map_err(|e| format!("Failed to create quarantine folder: {}", e))?;
+    }
+
+    // Quarantined files land side by side, so names taken earlier in this
+    // run count as taken even when nothing has been moved yet.
+    let mut taken = HashSet::new();
+    Ok(groups
+        .iter()
+        .map(|group| distinct_files(group))
+        .filter(|group| group.len() > 1)
+        .map(|group| {
+            let mut copies: Vec<(PathBuf, u32)> = group
+                .into_iter()
+                .map(|path| {
+                    let bitrate = audio_bitrate(&path);
+                    (path, bitrate)
+                })
+                .collect();
+            let best = copies
+                .iter()
+                .enumerate()
+                .max_by_key(|(i, (_, bitrate))| (*bitrate, std::cmp::Reverse(*i)))
+                .map(|(i, _)| i)
+                .unwrap_or(0);
+            let (kept, kept_bitrate) = copies.remove(best);
+
+            let removed = copies
+                .into_iter()
+                .map(|(path, bitrate)| {
+                    let quarantined_to = match action {
+                        DuplicateAction::Quarantine => Some(quarantine_path(quarantine, &path, &mut taken)),
+                        DuplicateAction::Delete => None,
+                    };
+                    let outcome = (!dry_run).then(|| match remove(&path, quarantined_to.as_deref()) {
+                        Ok(()) => ChangeOutcome::Applied,
+                        Err(e) => ChangeOutcome::Failed(e),
+                    });
+                    RemovedDuplicate { path, bitrate, quarantined_to, outcome }
+                })
+                .collect();
+
+            DuplicateResolution { kept, kept_bitrate, removed }
+        })
+        .collect())
+}
+
+/// The group with every path that resolves to a file listed earlier
+/// dropped, so a symlink, a differently cased path, a `..` path or a
+/// repeated entry can never be removed as a copy of the file kept.
+fn distinct_files(group: &[PathBuf]) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    group
+        .iter()
+        .filter(|path| seen.insert(file_identity(path)))
+        .cloned()
+        .collect()
+}
+
+/// What makes two paths the same file: the device and inode where there
+/// are any, since case-insensitive filesystems canonicalize paths in the
+/// case they were given, and the canonical path otherwise.
+#[cfg(unix)]
+fn file_identity(path: &Path) -> (u64, u64, PathBuf) {
+    use std::os::unix::fs::MetadataExt;
+
+    match fs::metadata(path) {
+        Ok(metadata) => (metadata.dev(), metadata.ino(), PathBuf::new()),
+        Err(_) => (0, 0, path.to_path_buf()),
+    }
+}
+
+#[cfg(not(unix))]
+fn file_identity(path: &Path) -> (u64, u64, PathBuf) {
+    (0, 0, fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()))
+}
+
+/// Moves one file into the quarantine folder under a free name, journaled,
+/// and returns where it went.
+pub fn quarantine_file(quarantine: &Path, path: &Path) -> Result<PathBuf, String> {
+    if !simulation::is_enabled() {
+        fs::create_dir_all(quarantine)
+            .map_err(|e| format!("Failed to create quarantine folder: {}", e))?;
+    }
+    let destination = quarantine_path(quarantine, path, &mut HashSet::new());
+    remove(path, Some(&destination))?;
+    Ok(destination)
+}
+
+/// A free "name (2).ext"-style path in the quarantine folder.
+fn quarantine_path(quarantine: &Path, path: &Path, taken: &mut HashSet<PathBuf>) -> PathBuf {
+    let name = path.file_name().unwrap_or_default();
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let extension = path.extension().map(|ext| format!(".{}", ext.to_string_lossy())).unwrap_or_default();
+
+    let destination = std::iter::once(quarantine.join(name))
+        .chain((2..).map(|n| quarantine.join(format!("{} ({}){}", stem, n, extension))))
+        .find(|candidate| !candidate.exists() && !taken.contains(candidate))
+        .unwrap_or_else(|| quarantine.join(name));
+    taken.insert(destination.clone());
+    destination
+}
+
+fn remove(path: &Path, quarantined_to: Option<&Path>) -> Result<(), String> {
+    if simulation::is_enabled() {
+        simulation::record(match quarantined_to {
+            Some(to) => SimulatedOperation::Move { from: path.to_path_buf(), to: to.to_path_buf() },
+            None => SimulatedOperation::Delete { path: path.to_path_buf() },
+        });
+        return Ok(());
+    }
+
+    match quarantined_to {
+        Some(to) => {
+            workdir::move_into_place(path, to)?;
+            journal::record_move(path, to);
+            Ok(())
+        }
+        None => fs::remove_file(path)
+            .map_err(|e| format!("Failed to delete {}: {}", path.display(), e)),
+    }
+}
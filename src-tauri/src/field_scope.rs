@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::scanner::{ExternalIds, Metadata};
+
+/// How far processing may change one field of a file's tags.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldPolicy {
+    #[default]
+    Overwrite,
+    /// Only written when the file has no value yet.
+    FillEmpty,
+    /// The file's value is never changed.
+    Keep,
+}
+
+/// Fields a scope may name. "artist", "genre" and "year" cover their
+/// multi-valued and full-date counterparts too.
+pub const FIELDS: &[&str] = &[
+    "title", "artist", "album_artist", "album", "track", "disc", "composer", "label",
+    "genre", "year", "release_type", "bpm", "key", "comment", "external_ids",
+];
+
+pub fn check_field(field: &str) -> Result<(), String> {
+    if FIELDS.contains(&field) {
+        Ok(())
+    } else {
+        Err(format!("Unknown field: {}", field))
+    }
+}
+
+pub fn validate(scope: &HashMap<String, FieldPolicy>) -> Result<(), String> {
+    scope.keys().try_for_each(|field| check_field(field))
+}
+
+fn has_text(value: &Option<String>) -> bool {
+    value.as_deref().is_some_and(|v| !v.trim().is_empty())
+}
+
+/// Whether a write must leave `field` as it is, given whether the file
+/// already has a value for it.
+fn protects(scope: &HashMap<String, FieldPolicy>, locked: &BTreeSet<String>, field: &str, has_value: bool) -> bool {
+    let policy = match locked.contains(field) {
+        true => FieldPolicy::Keep,
+        false => scope.get(field).copied().unwrap_or_default(),
+    };
+    match policy {
+        FieldPolicy::Overwrite => false,
+        FieldPolicy::FillEmpty => has_value,
+        FieldPolicy::Keep => true,
+    }
+}
+
+/// The field scope together with every file's locked fields, for the
+/// automatic writes that run outside `pipeline::process_file`.
+#[derive(Debug, Clone, Default)]
+pub struct FieldGuard {
+    scope: HashMap<String, FieldPolicy>,
+    locked: HashMap<PathBuf, BTreeSet<String>>,
+}
+
+impl FieldGuard {
+    pub fn new(scope: HashMap<String, FieldPolicy>, locked: HashMap<PathBuf, BTreeSet<String>>) -> Self {
+        FieldGuard { scope, locked }
+    }
+
+    fn locked(&self, path: &Path) -> &BTreeSet<String> {
+        static NONE: BTreeSet<String> = BTreeSet::new();
+        self.locked.get(path).unwrap_or(&NONE)
+    }
+
+    /// `proposed` for the file at `path`, reconciled as `reconcile` does.
+    pub fn reconcile(&self, path: &Path, current: &Metadata, proposed: &Metadata) -> Metadata {
+        reconcile(&self.scope, self.locked(path), current, proposed)
+    }
+
+    /// Whether `field` of the file at `path` may be written, given whether
+    /// the file already has a value for it.
+    pub fn allows(&self, path: &Path, field: &str, has_value: bool) -> bool {
+        !protects(&self.scope, self.locked(path), field, has_value)
+    }
+}
+
+/// Reconciles `proposed` with the file's `current` tags: fields the scope
+/// protects, and the file's `locked` fields, are given their current values
+/// back, so writing leaves them as they are and rename/organize see what the
+/// file will actually hold.
+pub fn reconcile(scope: &HashMap<String, FieldPolicy>, locked: &BTreeSet<String>, current: &Metadata, proposed: &Metadata) -> Metadata {
+    let mut reconciled = proposed.clone();
+    let protected = |field: &str, has_value: bool| protects(scope, locked, field, has_value);
+
+    if protected("title", has_text(&current.title)) {
+        reconciled.title = current.title.clone();
+    }
+    if protected("artist", has_text(&current.artist)) {
+        reconciled.artist = current.artist.clone();
+        reconciled.artists = current.artists.clone();
+    }
+    if protected("album_artist", has_text(&current.album_artist)) {
+        reconciled.album_artist = current.album_artist.clone();
+    }
+    if protected("album", has_text(&current.album)) {
+        reconciled.album = current.album.clone();
+    }
+    if protected("track", current.track_number.is_some()) {
+        reconciled.track_number = current.track_number;
+    }
+    if protected("disc", current.disc_number.is_some()) {
+        reconciled.disc_number = current.disc_number;
+    }
+    if protected("composer", has_text(&current.composer)) {
+        reconciled.composer = current.composer.clone();
+    }
+    if protected("label", has_text(&current.label)) {
+        reconciled.label = current.label.clone();
+    }
+    if protected("genre", has_text(&current.genre)) {
+        reconciled.genre = current.genre.clone();
+        reconciled.genres = current.genres.clone();
+    }
+    if protected("year", current.year.is_some() || has_text(&current.date)) {
+        reconciled.year = current.year;
+        reconciled.date = current.date.clone();
+    }
+    if protected("release_type", current.release_type.is_some()) {
+        reconciled.release_type = current.release_type;
+    }
+    if protected("bpm", current.bpm.is_some()) {
+        reconciled.bpm = current.bpm;
+    }
+    if protected("key", has_text(&current.key)) {
+        reconciled.key = current.key.clone();
+    }
+    // The comment is merged into rather than replaced, so a protected one
+    // is left out of the write altogether.
+    if protected("comment", has_text(&current.comment)) {
+        reconciled.comment = None;
+    }
+    if protected("external_ids", current.external_ids != ExternalIds::default()) {
+        reconciled.external_ids = current.external_ids.clone();
+    }
+    reconciled
+}
@@ -0,0 +1,179 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::scanner::Metadata;
+use crate::transforms::clean_text;
+
+/// A regex replacement applied to a file stem before it is split into artist
+/// and title. Replacements may use `$1`-style capture references.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NameRule {
+    pub pattern: String,
+    pub replacement: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilenameGuess {
+    #[serde(with = "crate::path_codec")]
+    pub file_path: PathBuf,
+    pub cleaned_name: String,
+    pub artist: Option<String>,
+    pub title: Option<String>,
+}
+
+fn rule(pattern: &str, replacement: &str) -> NameRule {
+    NameRule {
+        pattern: pattern.to_string(),
+        replacement: replacement.to_string(),
+    }
+}
+
+/// Rules for names as promo pools and mail attachments hand them out, e.g.
+/// `01_artist_-_track_name_(extended_mix)__PREMIERE`.
+pub fn default_rules() -> Vec<NameRule> {
+    vec![
+        // Leading track numbers: zero-padded ones ("01_", "02 - ", "03 "),
+        // and others only before "." or before " - " when another " - "
+        // follows ("3. ", "7 - "), so "808 State", "2 Unlimited" and "112"
+        // keep their names.
+        rule(r"^0\d{1,2}(?:[_\s]*[-.][_\s]*|[_\s]+)", ""),
+        rule(r"^\d{1,3}\.[_\s]+", ""),
+        rule(r"^\d{1,3}[_\s]*-[_\s]*(.+[_\s]-[_\s].+)$", "$1"),
+        // Trailing promo markers
+        rule(r"(?i)[\s_-]*[\[(]?(?:premiere|promo(?:[\s_]only)?|exclusive|free[\s_]download|out[\s_]now)[\])]?[\s_!]*$", ""),
+        // "_-_" as the artist/title separator
+        rule(r"_+-_+", " - "),
+        rule(r"_+", " "),
+        rule(r"\(\s+", "("),
+        rule(r"\s+\)", ")"),
+    ]
+}
+
+// The track-number rule `default_rules` used to ship, which also ate the
+// digits of artists like "808 State".
+const OLD_TRACK_NUMBER_RULE: &str = r"^\d{1,3}(?:\s*[-.]\s*|[_\s]+)";
+
+/// Swaps the old track-number rule in saved settings for the current ones.
+pub fn upgrade_rules(rules: &mut Vec<NameRule>) {
+    if let Some(index) = rules.iter().position(|rule| rule.pattern == OLD_TRACK_NUMBER_RULE && rule.replacement.is_empty()) {
+        rules.splice(index..=index, default_rules().into_iter().take(3));
+    }
+}
+
+fn compile(rules: &[NameRule]) -> Result<Vec<(Regex, &str)>, String> {
+    rules
+        .iter()
+        .map(|rule| {
+            Regex::new(&rule.pattern)
+                .map(|re| (re, rule.replacement.as_str()))
+                .map_err(|e| format!("Invalid filename rule '{}': {}", rule.pattern, e))
+        })
+        .collect()
+}
+
+fn apply_rules(stem: &str, rules: &[(Regex, &str)]) -> String {
+    let cleaned = rules
+        .iter()
+        .fold(stem.to_string(), |name, (re, replacement)| re.replace_all(&name, *replacement).into_owned());
+    clean_text(&cleaned)
+}
+
+fn split_artist_title(name: &str) -> (Option<String>, Option<String>) {
+    let non_empty = |s: &str| {
+        let s = s.trim();
+        if s.is_empty() { None } else { Some(s.to_string()) }
+    };
+
+    match name.split_once(" - ") {
+        Some((artist, title)) => (non_empty(artist), non_empty(title)),
+        None => (None, non_empty(name)),
+    }
+}
+
+/// Cleans each file's name and splits it on the first " - " into artist and
+/// title; names without a separator are taken as the title alone.
+pub fn guess_all(paths: &[PathBuf], rules: &[NameRule]) -> Result<Vec<FilenameGuess>, String> {
+    let compiled = compile(rules)?;
+
+    Ok(paths
+        .iter()
+        .map(|path| {
+            let stem = path.file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let cleaned_name = apply_rules(&stem, &compiled);
+            let (artist, title) = split_artist_title(&cleaned_name);
+            FilenameGuess {
+                file_path: path.clone(),
+                cleaned_name,
+                artist,
+                title,
+            }
+        })
+        .collect())
+}
+
+/// Fills a missing artist or title from the file's cleaned name.
+pub fn fill_from_name(path: &Path, metadata: &mut Metadata, rules: &[NameRule]) -> Result<(), String> {
+    if metadata.artist.is_some() && metadata.title.is_some() {
+        return Ok(());
+    }
+
+    let guess = guess_all(&[path.to_path_buf()], rules)?.remove(0);
+    if metadata.artist.is_none() {
+        metadata.artist = guess.artist;
+    }
+    if metadata.title.is_none() {
+        metadata.title = guess.title;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guess(stem: &str) -> (Option<String>, Option<String>) {
+        let path = PathBuf::from(format!("{}.mp3", stem));
+        let guess = guess_all(&[path], &default_rules()).unwrap().remove(0);
+        (guess.artist, guess.title)
+    }
+
+    fn named(artist: &str, title: &str) -> (Option<String>, Option<String>) {
+        (Some(artist.to_string()), Some(title.to_string()))
+    }
+
+    #[test]
+    fn strips_track_numbers() {
+        assert_eq!(guess("01 - Orbital - Chime"), named("Orbital", "Chime"));
+        assert_eq!(guess("02. Orbital - Chime"), named("Orbital", "Chime"));
+        assert_eq!(guess("03 Orbital - Chime"), named("Orbital", "Chime"));
+        assert_eq!(guess("3. Orbital - Chime"), named("Orbital", "Chime"));
+        assert_eq!(guess("7 - Orbital - Chime"), named("Orbital", "Chime"));
+        assert_eq!(guess("01_orbital_-_chime_(extended_mix)__PREMIERE"), named("orbital", "chime (extended mix)"));
+    }
+
+    #[test]
+    fn keeps_artists_starting_with_digits() {
+        assert_eq!(guess("808 State - Pacific State"), named("808 State", "Pacific State"));
+        assert_eq!(guess("2 Unlimited - No Limit"), named("2 Unlimited", "No Limit"));
+        assert_eq!(guess("112 - Cupid"), named("112", "Cupid"));
+        assert_eq!(guess("01 - 808 State - Cubik"), named("808 State", "Cubik"));
+        assert_eq!(guess("2 Unlimited_-_Get_Ready_For_This"), named("2 Unlimited", "Get Ready For This"));
+    }
+
+    #[test]
+    fn upgrades_the_old_track_number_rule() {
+        let mut rules = vec![rule(OLD_TRACK_NUMBER_RULE, ""), rule("x", "y")];
+        upgrade_rules(&mut rules);
+        let patterns: Vec<String> = rules.into_iter().map(|rule| rule.pattern).collect();
+        let expected: Vec<String> = default_rules()
+            .into_iter()
+            .take(3)
+            .map(|rule| rule.pattern)
+            .chain(["x".to_string()])
+            .collect();
+        assert_eq!(patterns, expected);
+    }
+}
@@ -0,0 +1,146 @@
+use regex::Regex;
+use reqwest::Client;
+use serde::Deserialize;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+use crate::api_client::RateLimiter;
+use crate::settings::AppSettings;
+
+/// AcoustID matches scoring below this are too uncertain to look up.
+const MIN_SCORE: f64 = 0.8;
+
+/// Titles that say nothing about the recording: "Track 01", "Audio Track
+/// 3", "Unknown", bare numbers.
+static JUNK_TITLE: OnceLock<Regex> = OnceLock::new();
+
+/// AcoustID allows three requests per second.
+static ACOUSTID_LIMIT: RateLimiter = RateLimiter::new(3.0, 3.0);
+
+/// A Chromaprint fingerprint as printed by `fpcalc -json`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Fingerprint {
+    pub duration: f64,
+    pub fingerprint: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AcoustIdResponse {
+    status: String,
+    #[serde(default)]
+    results: Vec<AcoustIdResult>,
+    #[serde(default)]
+    error: Option<AcoustIdError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AcoustIdResult {
+    score: f64,
+    #[serde(default)]
+    recordings: Vec<AcoustIdRecording>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AcoustIdRecording {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AcoustIdError {
+    message: String,
+}
+
+/// The fpcalc binary configured in settings, or `fpcalc` on the PATH.
+pub fn fpcalc_binary(settings: &AppSettings) -> PathBuf {
+    let path = settings.fpcalc_path.trim();
+    if path.is_empty() {
+        PathBuf::from("fpcalc")
+    } else {
+        PathBuf::from(path)
+    }
+}
+
+/// Whether the tags are too thin for a text search to find the recording.
+pub fn tags_are_junk(artist: &str, title: &str) -> bool {
+    let junk_title = JUNK_TITLE.get_or_init(|| {
+        Regex::new(r"(?i)^\s*((audio\s+)?track|piste|unknown|untitled)?\s*\d*\s*$").unwrap()
+    });
+    artist.trim().is_empty()
+        || artist.trim().eq_ignore_ascii_case("unknown artist")
+        || junk_title.is_match(title)
+}
+
+/// Runs fpcalc on `path`. Blocking; decodes up to the first two minutes.
+pub fn compute(fpcalc: &Path, path: &Path) -> Result<Fingerprint, String> {
+    let output = Command::new(fpcalc)
+        .arg("-json")
+        .arg(path)
+        .output()
+        .map_err(|e| match e.kind() {
+            ErrorKind::NotFound => "fpcalc not found; install Chromaprint or set its path in settings".to_string(),
+            _ => format!("Failed to run fpcalc: {}", e),
+        })?;
+
+    if !output.status.success() {
+        return Err(format!("fpcalc failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse fpcalc output: {}", e))
+}
+
+pub struct AcoustIdClient {
+    api_key: Option<String>,
+}
+
+impl AcoustIdClient {
+    pub fn new(api_key: Option<String>) -> Self {
+        AcoustIdClient { api_key }
+    }
+
+    pub fn is_configured(&self) -> bool {
+        self.api_key.is_some()
+    }
+
+    /// MusicBrainz recording id of the best-scoring match, if any scores
+    /// at least `MIN_SCORE`.
+    pub async fn lookup(&self, fingerprint: &Fingerprint) -> Result<Option<String>, String> {
+        let api_key = self.api_key.as_deref()
+            .ok_or("AcoustID API key not configured")?;
+
+        let duration = (fingerprint.duration.round() as u64).to_string();
+        ACOUSTID_LIMIT.acquire().await;
+        // Fingerprints are too long for a query string, so they go as a form.
+        let response = Client::new()
+            .post("https://api.acoustid.org/v2/lookup")
+            .form(&[
+                ("client", api_key),
+                ("meta", "recordingids"),
+                ("duration", duration.as_str()),
+                ("fingerprint", fingerprint.fingerprint.as_str()),
+                ("format", "json"),
+            ])
+            .send()
+            .await
+            .map_err(|e| format!("AcoustID lookup failed: {}", e))?;
+
+        let lookup: AcoustIdResponse = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse AcoustID response: {}", e))?;
+
+        if lookup.status != "ok" {
+            let message = lookup.error.map(|e| e.message).unwrap_or(lookup.status);
+            return Err(format!("AcoustID API error: {}", message));
+        }
+
+        Ok(lookup.results
+            .into_iter()
+            .filter(|result| result.score >= MIN_SCORE)
+            .max_by(|a, b| a.score.total_cmp(&b.score))
+            .and_then(|result| result.recordings.into_iter().next())
+            .map(|recording| recording.id))
+    }
+}
@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::journal;
+use crate::scanner::{free_destination, same_content};
+use crate::simulation::{self, SimulatedOperation};
+
+/// Sibling folders whose names differ only in case or punctuation
+/// ("Deadmau5", "deadmau5", "Deadmau5_").
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EquivalentFolders {
+    /// The folder the others are merged into: the one holding the most.
+    #[serde(with = "crate::path_codec")]
+    pub target: PathBuf,
+    #[serde(with = "crate::path_codec::vec")]
+    pub others: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MergeSummary {
+    pub moved: usize,
+    /// Files that met a different file of the same name and were moved in
+    /// as "name (2).ext", at their new paths.
+    #[serde(with = "crate::path_codec::vec")]
+    pub renamed: Vec<PathBuf>,
+    /// Files identical to one already in the target, deleted.
+    pub duplicates_removed: usize,
+    /// Folders left empty by the merge and removed.
+    #[serde(with = "crate::path_codec::vec")]
+    pub folders_removed: Vec<PathBuf>,
+}
+
+/// Lowercase letters and digits only, so sanitized and differently cased
+/// spellings of a name compare equal.
+fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn folder_key(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_string_lossy();
+    if name.starts_with('.') {
+        return None;
+    }
+    Some(normalize_name(&name)).filter(|key| !key.is_empty())
+}
+
+fn entry_count(dir: &Path) -> usize {
+    WalkDir::new(dir).into_iter().filter_map(|e| e.ok()).count()
+}
+
+/// Every set of equivalent sibling folders under `base`, shallowest first.
+pub fn find_equivalent(base: &Path) -> Result<Vec<EquivalentFolders>, String> {
+    if !base.is_dir() {
+        return Err(format!("Folder not found: {}", base.display()));
+    }
+
+    let mut siblings: BTreeMap<(usize, PathBuf, String), Vec<PathBuf>> = BTreeMap::new();
+    for entry in WalkDir::new(base).min_depth(1).sort_by_file_name().into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+        let path = entry.into_path();
+        let (Some(parent), Some(key)) = (path.parent(), folder_key(&path)) else { continue };
+        let depth = path.components().count();
+        siblings.entry((depth, parent.to_path_buf(), key)).or_default().push(path);
+    }
+
+    Ok(siblings
+        .into_values()
+        .filter(|folders| folders.len() > 1)
+        .map(|mut folders| {
+            // Most entries wins; the first by name on a tie.
+            let target_index = folders
+                .iter()
+                .enumerate()
+                .max_by_key(|(i, folder)| (entry_count(folder), std::cmp::Reverse(*i)))
+                .map(|(i, _)| i)
+                .unwrap_or(0);
+            let target = folders.remove(target_index);
+            EquivalentFolders { target, others: folders }
+        })
+        .collect())
+}
+
+/// Moves everything in each group's other folders into its target and
+/// removes them once empty. Equivalent subfolders are merged the same way;
+/// groups whose folders are already gone are skipped.
+pub fn merge(groups: &[EquivalentFolders]) -> Result<MergeSummary, String> {
+    let mut summary = MergeSummary::default();
+    for group in groups {
+        if !group.target.is_dir() {
+            continue;
+        }
+        for other in group.others.iter().filter(|other| other.is_dir()) {
+            merge_into(other, &group.target, &mut summary)?;
+        }
+    }
+    Ok(summary)
+}
+
+fn merge_into(from: &Path, into: &Path, summary: &mut MergeSummary) -> Result<(), String> {
+    let entries = fs::read_dir(from)
+        .map_err(|e| format!("Failed to read folder {}: {}", from.display(), e))?;
+    let mut entries: Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+    entries.sort();
+
+    for entry in entries {
+        let Some(name) = entry.file_name() else { continue };
+        if entry.is_dir() {
+            match equivalent_folder(into, &entry)? {
+                Some(existing) => merge_into(&entry, &existing, summary)?,
+                None => {
+                    move_entry(&entry, &into.join(name))?;
+                    summary.moved += 1;
+                }
+            }
+            continue;
+        }
+
+        let destination = into.join(name);
+        if destination.is_file() && same_content(&entry, &destination)? {
+            remove(&entry)?;
+            summary.duplicates_removed += 1;
+        } else if destination.exists() {
+            let renamed = free_destination(&destination);
+            move_entry(&entry, &renamed)?;
+            summary.renamed.push(renamed);
+            summary.moved += 1;
+        } else {
+            move_entry(&entry, &destination)?;
+            summary.moved += 1;
+        }
+    }
+
+    remove(from)?;
+    summary.folders_removed.push(from.to_path_buf());
+    Ok(())
+}
+
+/// A folder in `dir` equivalent to `folder`, if there is one.
+fn equivalent_folder(dir: &Path, folder: &Path) -> Result<Option<PathBuf>, String> {
+    let Some(key) = folder_key(folder) else {
+        return Ok(None);
+    };
+    let entries = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read folder {}: {}", dir.display(), e))?;
+    Ok(entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .find(|path| path.is_dir() && folder_key(path).as_deref() == Some(key.as_str())))
+}
+
+fn move_entry(from: &Path, to: &Path) -> Result<(), String> {
+    if simulation::is_enabled() {
+        simulation::record(SimulatedOperation::Move { from: from.to_path_buf(), to: to.to_path_buf() });
+        return Ok(());
+    }
+    fs::rename(from, to)
+        .map_err(|e| format!("Failed to move {}: {}", from.display(), e))?;
+    journal::record_move(from, to);
+    Ok(())
+}
+
+/// Deletes a file, or a folder the merge has emptied.
+fn remove(path: &Path) -> Result<(), String> {
+    if simulation::is_enabled() {
+        simulation::record(SimulatedOperation::Delete { path: path.to_path_buf() });
+        return Ok(());
+    }
+    let result = if path.is_dir() { fs::remove_dir(path) } else { fs::remove_file(path) };
+    result.map_err(|e| format!("Failed to remove {}: {}", path.display(), e))
+}
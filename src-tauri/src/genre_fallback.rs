@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::settings::AppSettings;
+
+const UNKNOWN_GENRE: &str = "Unknown";
+
+/// The genre a file gets when no provider found one and it has none.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GenreFallback {
+    /// No genre; organize files it as the pattern does any missing field.
+    #[default]
+    Blank,
+    Unknown,
+    /// The name of the folder the file is in, unless that is the library
+    /// folder itself.
+    ParentFolder,
+}
+
+/// The configured fallback for each library folder, and for files in none
+/// of them.
+#[derive(Debug, Clone, Default)]
+pub struct GenreFallbacks {
+    default: GenreFallback,
+    libraries: Vec<(PathBuf, GenreFallback)>,
+}
+
+impl GenreFallbacks {
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        let mut libraries: Vec<(PathBuf, GenreFallback)> = settings.library_folders
+            .iter()
+            .map(|folder| {
+                let fallback = settings.library_genre_fallbacks.get(folder).copied();
+                (PathBuf::from(folder), fallback.unwrap_or(settings.genre_fallback))
+            })
+            .collect();
+        for (folder, fallback) in &settings.library_genre_fallbacks {
+            if !settings.library_folders.contains(folder) {
+                libraries.push((PathBuf::from(folder), *fallback));
+            }
+        }
+        GenreFallbacks { default: settings.genre_fallback, libraries }
+    }
+
+    /// The fallback genre for the file at `path`, from the innermost library
+    /// folder holding it; `None` when it is left blank.
+    pub fn genre_for(&self, path: &Path) -> Option<String> {
+        let library = self.libraries
+            .iter()
+            .filter(|(folder, _)| path.starts_with(folder))
+            .max_by_key(|(folder, _)| folder.components().count());
+        let fallback = library.map_or(self.default, |(_, fallback)| *fallback);
+
+        match fallback {
+            GenreFallback::Blank => None,
+            GenreFallback::Unknown => Some(UNKNOWN_GENRE.to_string()),
+            GenreFallback::ParentFolder => {
+                let parent = path.parent()?;
+                if library.is_some_and(|(folder, _)| folder == parent) {
+                    return None;
+                }
+                parent.file_name()
+                    .map(|name| name.to_string_lossy().trim().to_string())
+                    .filter(|name| !name.is_empty())
+            }
+        }
+    }
+}
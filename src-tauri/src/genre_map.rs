@@ -0,0 +1,150 @@
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use tauri::AppHandle;
+
+use crate::api_client::MetadataResult;
+use crate::settings::get_config_path;
+
+const GENRE_MAP_FILE: &str = "genre_map.json";
+
+/// Maps raw provider genres matching `pattern` (a case-insensitive regex)
+/// to `genre`, which may use `$1`-style capture references. `source`
+/// limits the rule to one provider ("Spotify", "Beatport", ...).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenreRule {
+    pub pattern: String,
+    pub genre: String,
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+/// The user's genre taxonomy: rules tried in order, first match wins.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenreMapping {
+    pub rules: Vec<GenreRule>,
+    /// Keep genres no rule matches as the provider gave them; otherwise
+    /// they are dropped.
+    #[serde(default = "keep_unmapped_default")]
+    pub keep_unmapped: bool,
+}
+
+fn keep_unmapped_default() -> bool {
+    true
+}
+
+fn rule(pattern: &str, genre: &str, source: Option<&str>) -> GenreRule {
+    GenreRule {
+        pattern: pattern.to_string(),
+        genre: genre.to_string(),
+        source: source.map(str::to_string),
+    }
+}
+
+impl Default for GenreMapping {
+    /// Starter rules for the two noisiest sources: Beatport's style
+    /// qualifiers and Spotify's micro-genres.
+    fn default() -> Self {
+        GenreMapping {
+            rules: vec![
+                // "Techno (Peak Time / Driving)" -> "Techno"
+                rule(r"^(.+?)\s*\([^)]*\)$", "$1", Some("Beatport")),
+                // "german dark minimal techno" -> "Minimal Techno"
+                rule(r"\bminimal techno\b", "Minimal Techno", Some("Spotify")),
+                rule(r"\bmelodic techno\b", "Melodic Techno", Some("Spotify")),
+                rule(r"\btech house\b", "Tech House", Some("Spotify")),
+                rule(r"\bdeep house\b", "Deep House", Some("Spotify")),
+                rule(r"\bprogressive house\b", "Progressive House", Some("Spotify")),
+                rule(r"\btechno$", "Techno", Some("Spotify")),
+                rule(r"\bhouse$", "House", Some("Spotify")),
+                rule(r"\btrance$", "Trance", Some("Spotify")),
+            ],
+            keep_unmapped: true,
+        }
+    }
+}
+
+struct CompiledRule<'a> {
+    regex: Regex,
+    rule: &'a GenreRule,
+}
+
+impl GenreMapping {
+    fn compile(&self) -> Result<Vec<CompiledRule<'_>>, String> {
+        self.rules
+            .iter()
+            .map(|rule| {
+                RegexBuilder::new(&rule.pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .map(|regex| CompiledRule { regex, rule })
+                    .map_err(|e| format!("Invalid genre rule '{}': {}", rule.pattern, e))
+            })
+            .collect()
+    }
+
+    /// Rewrites each result's genres into the taxonomy, keeping the
+    /// provider's main genre in `source_genre` when it changed. Genres that
+    /// map to the same name collapse into one.
+    pub fn apply(&self, results: &mut [MetadataResult]) -> Result<(), String> {
+        let rules = self.compile()?;
+        let map = |raw: &str, source: &str| -> Option<String> {
+            let matched = rules.iter()
+                .filter(|compiled| compiled.rule.source.as_deref().is_none_or(|s| s.eq_ignore_ascii_case(source)))
+                .find_map(|compiled| {
+                    let captures = compiled.regex.captures(raw)?;
+                    let mut genre = String::new();
+                    captures.expand(&compiled.rule.genre, &mut genre);
+                    Some(genre.trim().to_string())
+                });
+            match matched {
+                Some(genre) => Some(genre).filter(|g| !g.is_empty()),
+                None if self.keep_unmapped => Some(raw.to_string()),
+                None => None,
+            }
+        };
+
+        for result in results {
+            let mut genres: Vec<String> = Vec::new();
+            for raw in &result.genres {
+                if let Some(genre) = map(raw, &result.source) {
+                    if !genres.iter().any(|g| g.eq_ignore_ascii_case(&genre)) {
+                        genres.push(genre);
+                    }
+                }
+            }
+            let genre = result.genre.as_deref().and_then(|raw| map(raw, &result.source));
+            if genre != result.genre {
+                result.source_genre = result.genre.take();
+            }
+            result.genre = genre;
+            result.genres = genres;
+        }
+        Ok(())
+    }
+}
+
+/// The mapping from the settings dir, written out with the starter rules
+/// the first time so there is a file to edit.
+pub fn load(app: &AppHandle) -> Result<GenreMapping, String> {
+    let path = get_config_path(app, GENRE_MAP_FILE)?;
+    if !path.exists() {
+        let mapping = GenreMapping::default();
+        save(app, &mapping)?;
+        return Ok(mapping);
+    }
+
+    let data = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read genre mapping: {}", e))?;
+    serde_json::from_str(&data)
+        .map_err(|e| format!("Failed to parse genre mapping: {}", e))
+}
+
+/// Saves the mapping after checking every rule compiles.
+pub fn save(app: &AppHandle, mapping: &GenreMapping) -> Result<(), String> {
+    mapping.compile()?;
+    let json = serde_json::to_string_pretty(mapping)
+        .map_err(|e| format!("Failed to serialize genre mapping: {}", e))?;
+    fs::write(get_config_path(app, GENRE_MAP_FILE)?, json)
+        .map_err(|e| format!("Failed to write genre mapping: {}", e))
+}
@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+
+use crate::settings::AppSettings;
+
+/// How several genres are stored in a tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GenreStorage {
+    /// One value joined with the genre join string, which every tag format
+    /// and player understands.
+    Joined,
+    /// Separate values where the format has them (ID3v2.4 TCON, Vorbis
+    /// GENRE fields); joined elsewhere.
+    MultiValue,
+}
+
+/// How a combined genre ("Techno; Acid") is split into individual genres,
+/// and how they are joined and stored again.
+#[derive(Debug, Clone)]
+pub struct GenreRules {
+    pub separators: Vec<String>,
+    pub join: String,
+    pub storage: GenreStorage,
+}
+
+impl GenreRules {
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        GenreRules {
+            separators: settings.genre_separators.clone(),
+            join: settings.genre_join.clone(),
+            storage: settings.genre_storage,
+        }
+    }
+
+    /// The individual genres of a combined value, in order. Empty parts and
+    /// case-insensitive repeats are dropped.
+    pub fn split(&self, value: &str) -> Vec<String> {
+        let mut parts = vec![value.to_string()];
+        for separator in self.separators.iter().filter(|s| !s.is_empty()) {
+            parts = parts
+                .iter()
+                .flat_map(|part| part.split(separator.as_str()))
+                .map(str::to_string)
+                .collect();
+        }
+
+        let mut genres: Vec<String> = Vec::new();
+        for part in parts {
+            let genre = part.trim();
+            if !genre.is_empty() && !genres.iter().any(|g| g.eq_ignore_ascii_case(genre)) {
+                genres.push(genre.to_string());
+            }
+        }
+        genres
+    }
+
+    pub fn join(&self, genres: &[String]) -> String {
+        genres.join(&self.join)
+    }
+
+    /// The genres to write. The combined `genre` string wins when set,
+    /// since that is what gets edited; `genres` is used when it is absent.
+    pub fn for_writing(&self, genre: Option<&str>, genres: &[String]) -> Vec<String> {
+        match genre {
+            Some(genre) => self.split(genre),
+            None => genres.to_vec(),
+        }
+    }
+}
+
+impl Default for GenreRules {
+    fn default() -> Self {
+        GenreRules {
+            separators: default_separators(),
+            join: "; ".to_string(),
+            storage: GenreStorage::Joined,
+        }
+    }
+}
+
+/// Separators that only ever divide genres. "/" and "," are left out since
+/// names such as "Hip-Hop/Rap" and "Rock, Pop & Soul" use them.
+pub fn default_separators() -> Vec<String> {
+    [";", " / "]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
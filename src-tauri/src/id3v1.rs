@@ -0,0 +1,145 @@
+use id3::v1;
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::scanner::Metadata;
+
+// ID3v1 genre ids run up to the Winamp extensions; 255 means "none".
+const MAX_GENRE_ID: u8 = 191;
+const NO_GENRE: u8 = 255;
+
+/// What to do with a legacy ID3v1 tag when writing ID3v2 tags to an MP3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Id3v1Policy {
+    Keep,
+    Strip,
+    Sync,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagConflict {
+    pub field: String,
+    pub id3v1: String,
+    pub id3v2: String,
+}
+
+pub fn read(path: &Path) -> Option<v1::Tag> {
+    v1::Tag::read_from_path(path).ok()
+}
+
+/// ID3v1 fields are limited to 30 Latin-1 characters, so a v1 value that is
+/// a truncated prefix of the v2 value is not a disagreement.
+fn agrees(v1_value: &str, v2_value: &str) -> bool {
+    let v1_value = v1_value.trim();
+    let v2_value = v2_value.trim();
+    if v1_value.eq_ignore_ascii_case(v2_value) {
+        return true;
+    }
+    v1_value.chars().count() >= 28
+        && v2_value.to_lowercase().starts_with(&v1_value.to_lowercase())
+}
+
+pub fn conflicts(v1_tag: &v1::Tag, metadata: &Metadata) -> Vec<TagConflict> {
+    let year = metadata.year.map(|y| y.to_string());
+    let v1_genre = v1_tag.genre_str.as_deref()
+        .filter(|g| !g.is_empty())
+        .or_else(|| v1_tag.genre());
+
+    let fields = [
+        ("title", Some(v1_tag.title.as_str()), metadata.title.as_deref()),
+        ("artist", Some(v1_tag.artist.as_str()), metadata.artist.as_deref()),
+        ("album", Some(v1_tag.album.as_str()), metadata.album.as_deref()),
+        ("year", Some(v1_tag.year.as_str()), year.as_deref()),
+        ("genre", v1_genre, metadata.genre.as_deref()),
+    ];
+
+    fields
+        .iter()
+        .filter_map(|(field, v1_value, v2_value)| {
+            let v1_value = v1_value.unwrap_or("").trim();
+            let v2_value = v2_value.unwrap_or("").trim();
+            // An empty v1 field is just missing data, not a conflicting value.
+            if v1_value.is_empty() || agrees(v1_value, v2_value) {
+                return None;
+            }
+            Some(TagConflict {
+                field: field.to_string(),
+                id3v1: v1_value.to_string(),
+                id3v2: v2_value.to_string(),
+            })
+        })
+        .collect()
+}
+
+pub fn strip(path: &Path) -> Result<(), String> {
+    v1::Tag::remove_from_path(path)
+        .map(|_| ())
+        .map_err(|e| format!("Failed to remove ID3v1 tag: {}", e))
+}
+
+fn latin1_field(value: Option<&str>, len: usize) -> Vec<u8> {
+    let mut bytes: Vec<u8> = value
+        .unwrap_or("")
+        .chars()
+        .map(|c| if (c as u32) < 0x100 { c as u8 } else { b'?' })
+        .take(len)
+        .collect();
+    bytes.resize(len, 0);
+    bytes
+}
+
+fn genre_id(genre: Option<&str>) -> u8 {
+    let genre = match genre {
+        Some(g) => g.trim(),
+        None => return NO_GENRE,
+    };
+
+    (0..=MAX_GENRE_ID)
+        .find(|&id| {
+            let candidate = v1::Tag { genre_id: id, ..v1::Tag::new() };
+            candidate.genre().map(|name| name.eq_ignore_ascii_case(genre)).unwrap_or(false)
+        })
+        .unwrap_or(NO_GENRE)
+}
+
+/// Replaces any ID3v1 tag with one mirroring the given metadata, keeping the
+/// comment, and the track number when the metadata has none, from the old
+/// tag.
+pub fn sync(path: &Path, metadata: &Metadata) -> Result<(), String> {
+    let previous = read(path);
+    strip(path)?;
+
+    let year = metadata.year.map(|y| y.to_string());
+    let comment = previous.as_ref().map(|t| t.comment.as_str());
+    let track = metadata.track_number
+        .and_then(|n| u8::try_from(n).ok())
+        .or_else(|| previous.as_ref().and_then(|t| t.track));
+
+    let mut tag = Vec::with_capacity(128);
+    tag.extend_from_slice(b"TAG");
+    tag.extend(latin1_field(metadata.title.as_deref(), 30));
+    tag.extend(latin1_field(metadata.artist.as_deref(), 30));
+    tag.extend(latin1_field(metadata.album.as_deref(), 30));
+    tag.extend(latin1_field(year.as_deref(), 4));
+    match track {
+        Some(track) => {
+            tag.extend(latin1_field(comment, 28));
+            tag.push(0);
+            tag.push(track);
+        }
+        None => tag.extend(latin1_field(comment, 30)),
+    }
+    tag.push(genre_id(metadata.genre.as_deref()));
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open MP3 file: {}", e))?;
+
+    file.seek(SeekFrom::End(0))
+        .and_then(|_| file.write_all(&tag))
+        .map_err(|e| format!("Failed to write ID3v1 tag: {}", e))
+}
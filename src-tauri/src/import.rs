@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::changeset::ChangeOutcome;
+use crate::filename;
+use crate::pipeline::{self, ProcessResult};
+use crate::scanner::FileScanner;
+use crate::settings::AppSettings;
+use crate::simulation;
+use crate::workdir;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportedFile {
+    #[serde(with = "crate::path_codec")]
+    pub file_path: PathBuf,
+    pub result: Option<ProcessResult>,
+    pub outcome: ChangeOutcome,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportSummary {
+    #[serde(with = "crate::path_codec")]
+    pub extracted_to: PathBuf,
+    pub files: Vec<ImportedFile>,
+}
+
+fn unused_dir(parent: &Path, name: &str) -> PathBuf {
+    let mut candidate = parent.join(name);
+    let mut n = 2;
+    while candidate.exists() {
+        candidate = parent.join(format!("{} ({})", name, n));
+        n += 1;
+    }
+    candidate
+}
+
+/// Extracts every regular entry of a zip archive below `target`, skipping
+/// entries whose names would escape it and macOS resource forks.
+pub fn extract_zip(archive_path: &Path, target: &Path) -> Result<usize, String> {
+    let file = File::open(archive_path)
+        .map_err(|e| format!("Failed to open archive: {}", e))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| format!("Failed to read archive: {}", e))?;
+
+    let mut extracted = 0;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)
+            .map_err(|e| format!("Failed to read archive entry: {}", e))?;
+
+        let relative = match entry.enclosed_name() {
+            Some(name) if !name.starts_with("__MACOSX") => name,
+            _ => continue,
+        };
+        let out_path = target.join(relative);
+
+        if entry.is_dir() {
+            fs::create_dir_all(&out_path)
+                .map_err(|e| format!("Failed to create folder: {}", e))?;
+            continue;
+        }
+
+        if let Some(parent) = out_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create folder: {}", e))?;
+        }
+        let mut out = File::create(&out_path)
+            .map_err(|e| format!("Failed to create {}: {}", out_path.display(), e))?;
+        io::copy(&mut entry, &mut out)
+            .map_err(|e| format!("Failed to extract {}: {}", out_path.display(), e))?;
+        extracted += 1;
+    }
+
+    Ok(extracted)
+}
+
+/// Unpacks a purchased release into the inbox and runs every audio file in
+/// it through the processing pipeline using the tags the store shipped,
+/// guessing artist and title from the filename where those are missing.
+/// In simulation mode the archive is unpacked to the working directory instead,
+/// leaving the inbox untouched.
+pub fn import_archive(
+    scanner: &FileScanner,
+    settings: &AppSettings,
+    archive_path: &Path,
+    inbox: &Path,
+    base_folder: Option<&Path>,
+) -> Result<ImportSummary, String> {
+    let name = archive_path.file_stem()
+        .ok_or("Cannot determine archive name")?
+        .to_string_lossy()
+        .to_string();
+
+    let parent = if simulation::is_enabled() {
+        workdir::ensure(settings, "import")?
+    } else {
+        inbox.to_path_buf()
+    };
+    let extracted_to = unused_dir(&parent, &name);
+    fs::create_dir_all(&extracted_to)
+        .map_err(|e| format!("Failed to create import folder: {}", e))?;
+
+    extract_zip(archive_path, &extracted_to)?;
+
+    let files = scanner.scan_directory(&extracted_to)?
+        .into_iter()
+        .map(|file| {
+            // Promo downloads often ship untagged; fall back to the filename.
+            let mut metadata = file.current_metadata.unwrap_or_default();
+            if let Err(e) = filename::fill_from_name(&file.path, &mut metadata, &settings.filename_rules) {
+                return ImportedFile {
+                    file_path: file.path,
+                    result: None,
+                    outcome: ChangeOutcome::Failed(e),
+                };
+            }
+
+            match pipeline::process_file(scanner, settings, &file.path, &metadata, &BTreeSet::new(), base_folder) {
+                Ok(result) => ImportedFile {
+                    file_path: file.path,
+                    outcome: result.failure().map_or(ChangeOutcome::Applied, ChangeOutcome::Failed),
+                    result: Some(result),
+                },
+                Err(e) => ImportedFile {
+                    file_path: file.path,
+                    result: None,
+                    outcome: ChangeOutcome::Failed(e),
+                },
+            }
+        })
+        .collect();
+
+    Ok(ImportSummary { extracted_to, files })
+}
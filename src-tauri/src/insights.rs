@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::keys::MusicalKey;
+use crate::scanner::AudioFile;
+
+const BPM_BUCKET_WIDTH: u32 = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryInsights {
+    pub track_count: usize,
+    pub tracks_without_bpm: usize,
+    pub tracks_without_key: usize,
+    pub bpm_histogram: Vec<BpmBucket>,
+    pub camelot_wheel: Vec<CamelotSlice>,
+    pub years: Vec<YearSummary>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BpmBucket {
+    pub start: u32,
+    pub end: u32,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CamelotSlice {
+    pub camelot: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct YearSummary {
+    pub year: i32,
+    pub track_count: usize,
+    pub average_bpm: Option<f32>,
+}
+
+pub fn library_insights(files: &[AudioFile]) -> LibraryInsights {
+    let mut bpm_counts: BTreeMap<u32, usize> = BTreeMap::new();
+    let mut key_counts: BTreeMap<String, usize> = BTreeMap::new();
+    let mut year_stats: BTreeMap<i32, (usize, f32, usize)> = BTreeMap::new();
+    let mut tracks_without_bpm = 0;
+    let mut tracks_without_key = 0;
+
+    for file in files {
+        let metadata = file.current_metadata.as_ref();
+        let bpm = metadata.and_then(|m| m.bpm).filter(|bpm| *bpm > 0.0);
+        let key = metadata
+            .and_then(|m| m.key.as_deref())
+            .and_then(MusicalKey::parse);
+
+        match bpm {
+            Some(bpm) => {
+                let bucket = (bpm.round() as u32 / BPM_BUCKET_WIDTH) * BPM_BUCKET_WIDTH;
+                *bpm_counts.entry(bucket).or_insert(0) += 1;
+            }
+            None => tracks_without_bpm += 1,
+        }
+
+        match key {
+            Some(key) => *key_counts.entry(key.camelot()).or_insert(0) += 1,
+            None => tracks_without_key += 1,
+        }
+
+        if let Some(year) = metadata.and_then(|m| m.year) {
+            let stats = year_stats.entry(year).or_insert((0, 0.0, 0));
+            stats.0 += 1;
+            if let Some(bpm) = bpm {
+                stats.1 += bpm;
+                stats.2 += 1;
+            }
+        }
+    }
+
+    // Fill empty buckets between the slowest and fastest track so charts
+    // render a continuous axis.
+    let bpm_histogram = match (bpm_counts.keys().next(), bpm_counts.keys().next_back()) {
+        (Some(&min), Some(&max)) => (min..=max)
+            .step_by(BPM_BUCKET_WIDTH as usize)
+            .map(|start| BpmBucket {
+                start,
+                end: start + BPM_BUCKET_WIDTH,
+                count: bpm_counts.get(&start).copied().unwrap_or(0),
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let camelot_wheel = (1..=12)
+        .flat_map(|number| ['A', 'B'].map(|letter| format!("{}{}", number, letter)))
+        .map(|camelot| CamelotSlice {
+            count: key_counts.get(&camelot).copied().unwrap_or(0),
+            camelot,
+        })
+        .collect();
+
+    let years = year_stats
+        .into_iter()
+        .map(|(year, (track_count, bpm_total, bpm_count))| YearSummary {
+            year,
+            track_count,
+            average_bpm: if bpm_count > 0 {
+                Some(bpm_total / bpm_count as f32)
+            } else {
+                None
+            },
+        })
+        .collect();
+
+    LibraryInsights {
+        track_count: files.len(),
+        tracks_without_bpm,
+        tracks_without_key,
+        bpm_histogram,
+        camelot_wheel,
+        years,
+    }
+}
@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+use crate::api_client::MetadataLookup;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+/// A metadata lookup waiting in the batch queue.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FetchJob {
+    pub file_path: String,
+    pub artist: String,
+    pub title: String,
+    #[serde(default)]
+    pub priority: JobPriority,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobResult {
+    pub file_path: String,
+    pub lookup: Option<MetadataLookup>,
+    pub error: Option<String>,
+}
+
+/// One track of a `fetch_metadata_batch` call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackQuery {
+    pub artist: String,
+    pub title: String,
+    #[serde(default)]
+    pub file_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackResult {
+    /// Position of the track in the batch; results arrive as they finish.
+    pub index: usize,
+    pub query: TrackQuery,
+    pub lookup: Option<MetadataLookup>,
+    pub error: Option<String>,
+}
+
+struct JobQueue {
+    jobs: Vec<FetchJob>,
+    worker_running: bool,
+}
+
+static JOB_QUEUE: Mutex<JobQueue> = Mutex::new(JobQueue {
+    jobs: Vec::new(),
+    worker_running: false,
+});
+
+/// Queues jobs behind those already waiting, replacing any queued job for
+/// the same file. Returns true if the caller must start a worker.
+pub fn enqueue(new_jobs: Vec<FetchJob>) -> bool {
+    let mut queue = JOB_QUEUE.lock().unwrap();
+    for job in new_jobs {
+        queue.jobs.retain(|queued| queued.file_path != job.file_path);
+        queue.jobs.push(job);
+    }
+
+    let start_worker = !queue.worker_running && !queue.jobs.is_empty();
+    if start_worker {
+        queue.worker_running = true;
+    }
+    start_worker
+}
+
+/// Takes the first job of the highest waiting priority. When the queue is
+/// empty the worker is marked stopped under the same lock, so a concurrent
+/// `enqueue` knows to start a new one.
+pub fn next_job() -> Option<FetchJob> {
+    let mut queue = JOB_QUEUE.lock().unwrap();
+    let index = queue.jobs
+        .iter()
+        .enumerate()
+        .max_by(|(a_index, a), (b_index, b)| a.priority.cmp(&b.priority).then(b_index.cmp(a_index)))
+        .map(|(index, _)| index);
+
+    match index {
+        Some(index) => Some(queue.jobs.remove(index)),
+        None => {
+            queue.worker_running = false;
+            None
+        }
+    }
+}
+
+pub fn pending() -> Vec<FetchJob> {
+    JOB_QUEUE.lock().unwrap().jobs.clone()
+}
+
+pub fn set_priority(file_path: &str, priority: JobPriority) -> Result<(), String> {
+    let mut queue = JOB_QUEUE.lock().unwrap();
+    let job = queue.jobs
+        .iter_mut()
+        .find(|job| job.file_path == file_path)
+        .ok_or_else(|| format!("No queued job for {}", file_path))?;
+    job.priority = priority;
+    Ok(())
+}
+
+/// Moves a queued job to `position` within the queue. Priority still wins,
+/// so this orders jobs among those of the same priority.
+pub fn move_to(file_path: &str, position: usize) -> Result<(), String> {
+    let mut queue = JOB_QUEUE.lock().unwrap();
+    let index = queue.jobs
+        .iter()
+        .position(|job| job.file_path == file_path)
+        .ok_or_else(|| format!("No queued job for {}", file_path))?;
+
+    let job = queue.jobs.remove(index);
+    let position = position.min(queue.jobs.len());
+    queue.jobs.insert(position, job);
+    Ok(())
+}
+
+/// Drops every waiting job; the one in flight still finishes.
+pub fn clear() -> usize {
+    let mut queue = JOB_QUEUE.lock().unwrap();
+    let dropped = queue.jobs.len();
+    queue.jobs.clear();
+    dropped
+}
@@ -0,0 +1,279 @@
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::backup::{self, RawTagRef};
+use crate::changeset::ChangeOutcome;
+use crate::scanner::{FileScanner, Metadata};
+use crate::simulation;
+use crate::workdir;
+
+// Oldest entries beyond this are dropped when the journal is opened.
+const MAX_ENTRIES: usize = 10_000;
+
+static JOURNAL: Mutex<Option<Journal>> = Mutex::new(None);
+
+thread_local! {
+    // Set while undoing, so the operations that undo others are not
+    // journaled themselves.
+    static REPLAYING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// A file operation as it happened, with what undoing it needs. Removing
+/// an APE tag, an MP3's ID3v1 tag changes and deleting a converted file's
+/// source are not journaled and cannot be undone.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JournalOperation {
+    /// A move or rename.
+    Move {
+        #[serde(with = "crate::path_codec")]
+        from: PathBuf,
+        #[serde(with = "crate::path_codec")]
+        to: PathBuf,
+    },
+    Copy {
+        #[serde(with = "crate::path_codec")]
+        from: PathBuf,
+        #[serde(with = "crate::path_codec")]
+        to: PathBuf,
+    },
+    /// A tag write, with the file's tags from just before it. ID3 tags are
+    /// also kept whole, so undoing puts back comments, ids and pictures too.
+    WriteTags {
+        #[serde(with = "crate::path_codec")]
+        path: PathBuf,
+        metadata: Box<Metadata>,
+        tags: BTreeMap<String, Vec<String>>,
+        #[serde(default)]
+        raw_tag: Option<RawTagRef>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub id: u64,
+    /// When the app was started, shared by everything done in that run.
+    pub session: u64,
+    pub recorded_at: u64,
+    pub operation: JournalOperation,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UndoneOperation {
+    pub operation: JournalOperation,
+    pub outcome: ChangeOutcome,
+}
+
+/// Operations in the order they happened, kept in the app data dir as one
+/// JSON entry per line so recording only appends.
+struct Journal {
+    path: PathBuf,
+    session: u64,
+    entries: Vec<JournalEntry>,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl Journal {
+    fn rewrite(&self) -> Result<(), String> {
+        let mut data = String::new();
+        for entry in &self.entries {
+            let line = serde_json::to_string(entry)
+                .map_err(|e| format!("Failed to serialize journal: {}", e))?;
+            data.push_str(&line);
+            data.push('\n');
+        }
+        fs::write(&self.path, data)
+            .map_err(|e| format!("Failed to write journal: {}", e))
+    }
+
+    fn append(&mut self, operation: JournalOperation) -> Result<(), String> {
+        let entry = JournalEntry {
+            id: self.entries.last().map_or(1, |last| last.id + 1),
+            session: self.session,
+            recorded_at: now_secs(),
+            operation,
+        };
+        let mut line = serde_json::to_string(&entry)
+            .map_err(|e| format!("Failed to serialize journal: {}", e))?;
+        line.push('\n');
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|e| format!("Failed to write journal: {}", e))?;
+        self.entries.push(entry);
+        Ok(())
+    }
+}
+
+/// Starts journaling to `path` for a new session, reading the entries
+/// earlier sessions left there. Until then nothing is recorded.
+pub fn open(path: PathBuf) -> Result<(), String> {
+    let read = fs::read_to_string(&path);
+    let failed = matches!(&read, Err(e) if e.kind() != std::io::ErrorKind::NotFound);
+    let data = read.unwrap_or_default();
+    let mut entries: Vec<JournalEntry> = data.lines().filter_map(|line| serde_json::from_str(line).ok()).collect();
+    let unreadable = failed || data.lines().count() > entries.len();
+    let excess = entries.len().saturating_sub(MAX_ENTRIES);
+    entries.drain(..excess);
+
+    let journal = Journal { path, session: now_secs(), entries };
+    if excess > 0 {
+        journal.rewrite()?;
+    }
+    // Raw tags of entries trimmed or undone since are no longer needed. A
+    // journal or entry that could not be read may still reference one, so
+    // then all stay.
+    let referenced: HashSet<String> = journal.entries
+        .iter()
+        .filter_map(|entry| match &entry.operation {
+            JournalOperation::WriteTags { raw_tag: Some(raw_tag), .. } => Some(raw_tag.blob.clone()),
+            _ => None,
+        })
+        .collect();
+    if !unreadable {
+        backup::prune_raw_tags(&journal.path, &referenced)?;
+    }
+    *JOURNAL.lock().unwrap() = Some(journal);
+    Ok(())
+}
+
+/// Whether operations are being recorded right now.
+pub fn is_recording() -> bool {
+    !REPLAYING.get() && JOURNAL.lock().unwrap().is_some()
+}
+
+/// Records an operation that has just happened. Failing to journal it is
+/// not worth failing the operation over, so errors are dropped.
+pub fn record(operation: JournalOperation) {
+    if REPLAYING.get() {
+        return;
+    }
+    if let Some(journal) = JOURNAL.lock().unwrap().as_mut() {
+        let _ = journal.append(operation);
+    }
+}
+
+/// Records a tag write to `path` given the file's tags from before it: the
+/// modelled `metadata`, the `tags` snapshot and, for ID3, the `raw_tag`
+/// bytes, stored beside the journal as backups store theirs.
+pub fn record_tag_write(path: &Path, metadata: Metadata, tags: BTreeMap<String, Vec<String>>, raw_tag: Option<Vec<u8>>) {
+    if REPLAYING.get() {
+        return;
+    }
+    if let Some(journal) = JOURNAL.lock().unwrap().as_mut() {
+        let raw_tag = match raw_tag.map(|data| backup::store_raw_tag(&journal.path, &data)).transpose() {
+            Ok(raw_tag) => raw_tag,
+            // Without its raw tag the write could only be partly undone.
+            Err(_) => return,
+        };
+        let _ = journal.append(JournalOperation::WriteTags {
+            path: path.to_path_buf(),
+            metadata: Box::new(metadata),
+            tags,
+            raw_tag,
+        });
+    }
+}
+
+/// Records that `from` was just moved or renamed to `to`.
+pub fn record_move(from: &Path, to: &Path) {
+    record(JournalOperation::Move { from: from.to_path_buf(), to: to.to_path_buf() });
+}
+
+/// The session the running app records under.
+pub fn current_session() -> Option<u64> {
+    JOURNAL.lock().unwrap().as_ref().map(|journal| journal.session)
+}
+
+/// Undoes the most recent operation still in the journal; `None` when
+/// there is none. A failed undo stays in the journal.
+pub fn undo_last(scanner: &FileScanner) -> Result<Option<UndoneOperation>, String> {
+    Ok(undo_while(scanner, |_| true, 1)?.pop())
+}
+
+/// Undoes everything `session` recorded, newest first, stopping at the
+/// first operation that cannot be undone.
+pub fn undo_session(scanner: &FileScanner, session: u64) -> Result<Vec<UndoneOperation>, String> {
+    undo_while(scanner, |entry| entry.session == session, usize::MAX)
+}
+
+fn undo_while(scanner: &FileScanner, matches: impl Fn(&JournalEntry) -> bool, limit: usize) -> Result<Vec<UndoneOperation>, String> {
+    if simulation::is_enabled() {
+        return Err("Undo is not available in simulation mode".to_string());
+    }
+    let mut guard = JOURNAL.lock().unwrap();
+    let journal = guard.as_mut().ok_or("The operation journal is not open")?;
+
+    let mut undone = Vec::new();
+    REPLAYING.set(true);
+    while undone.len() < limit {
+        let Some(entry) = journal.entries.last().filter(|entry| matches(entry)) else { break };
+        let operation = entry.operation.clone();
+        let result = undo(scanner, &journal.path, &operation);
+        let failed = result.is_err();
+        if !failed {
+            journal.entries.pop();
+        }
+        undone.push(UndoneOperation {
+            operation,
+            outcome: match result {
+                Ok(()) => ChangeOutcome::Applied,
+                Err(e) => ChangeOutcome::Failed(e),
+            },
+        });
+        if failed {
+            break;
+        }
+    }
+    REPLAYING.set(false);
+
+    journal.rewrite()?;
+    Ok(undone)
+}
+
+fn undo(scanner: &FileScanner, journal_path: &Path, operation: &JournalOperation) -> Result<(), String> {
+    match operation {
+        JournalOperation::Move { from, to } => {
+            if from.exists() {
+                return Err(format!("Cannot move {} back: {} exists", to.display(), from.display()));
+            }
+            if let Some(parent) = from.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create folder {}: {}", parent.display(), e))?;
+            }
+            // Folders created along the way are left behind.
+            workdir::move_into_place(to, from)
+        }
+        JournalOperation::Copy { to, .. } => fs::remove_file(to)
+            .map_err(|e| format!("Failed to delete {}: {}", to.display(), e)),
+        // Nothing to put back on a file since deleted, such as a
+        // conversion's scratch copy.
+        JournalOperation::WriteTags { path, .. } if !path.exists() => Ok(()),
+        JournalOperation::WriteTags { path, raw_tag: Some(raw_tag), .. } if FileScanner::has_id3_tag(path) => {
+            scanner.restore_raw_id3_tag(path, &backup::load_raw_tag(journal_path, raw_tag)?)
+        }
+        JournalOperation::WriteTags { path, metadata, tags, .. } => {
+            // Fields the write added are listed empty, so they are removed.
+            let mut tags = tags.clone();
+            for key in scanner.read_tag_snapshot(path)?.into_keys() {
+                tags.entry(key).or_default();
+            }
+            scanner.restore_tags(path, metadata, &tags)
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+
+// Camelot wheel numbers indexed by pitch class (C = 0) for major and minor keys.
+const MAJOR_CAMELOT: [u8; 12] = [8, 3, 10, 5, 12, 7, 2, 9, 4, 11, 6, 1];
+const MINOR_CAMELOT: [u8; 12] = [5, 12, 7, 2, 9, 4, 11, 6, 1, 8, 3, 10];
+
+// Note names indexed by pitch class, spelled the way keys are usually
+// written: flats for Db, Eb, Ab and Bb major, sharps for C#, F# and G# minor.
+const MAJOR_NAMES: [&str; 12] = ["C", "Db", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B"];
+const MINOR_NAMES: [&str; 12] = ["C", "C#", "D", "Eb", "E", "F", "F#", "G", "G#", "A", "Bb", "B"];
+
+/// How detected keys are written to tags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyNotation {
+    /// "Am", "F#"
+    Standard,
+    /// "8A", "2B"
+    Camelot,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MusicalKey {
+    pub pitch_class: u8,
+    pub minor: bool,
+}
+
+impl MusicalKey {
+    /// Parses Camelot ("8A"), standard ("Am", "F#", "Gbm", "A minor") and
+    /// Open Key ("1m", "6d") notations as found in DJ software tags.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if value.is_empty() {
+            return None;
+        }
+
+        Self::parse_wheel(value).or_else(|| Self::parse_standard(value))
+    }
+
+    fn parse_wheel(value: &str) -> Option<Self> {
+        let upper = value.to_uppercase();
+        let (split, _) = upper.char_indices().last()?;
+        let (number, letter) = upper.split_at(split);
+        let number: u8 = number.parse().ok()?;
+        if !(1..=12).contains(&number) {
+            return None;
+        }
+
+        match letter {
+            "A" => Self::from_camelot(number, true),
+            "B" => Self::from_camelot(number, false),
+            // Open Key is the Camelot wheel rotated by seven steps.
+            "M" => Self::from_camelot((number + 6) % 12 + 1, true),
+            "D" => Self::from_camelot((number + 6) % 12 + 1, false),
+            _ => None,
+        }
+    }
+
+    fn parse_standard(value: &str) -> Option<Self> {
+        let mut chars = value.chars();
+        let root = chars.next()?.to_ascii_uppercase();
+        let mut pitch_class: i32 = match root {
+            'C' => 0,
+            'D' => 2,
+            'E' => 4,
+            'F' => 5,
+            'G' => 7,
+            'A' => 9,
+            'B' => 11,
+            _ => return None,
+        };
+
+        let mut rest: String = chars.collect();
+        if rest.starts_with('#') || rest.starts_with('♯') {
+            pitch_class += 1;
+            rest = rest.chars().skip(1).collect();
+        } else if rest.starts_with('b') || rest.starts_with('♭') {
+            pitch_class -= 1;
+            rest = rest.chars().skip(1).collect();
+        }
+
+        let quality = rest.trim().to_lowercase();
+        let minor = match quality.as_str() {
+            "" | "maj" | "major" => false,
+            "m" | "min" | "minor" => true,
+            _ => return None,
+        };
+
+        Some(MusicalKey {
+            pitch_class: pitch_class.rem_euclid(12) as u8,
+            minor,
+        })
+    }
+
+    fn from_camelot(number: u8, minor: bool) -> Option<Self> {
+        let table = if minor { &MINOR_CAMELOT } else { &MAJOR_CAMELOT };
+        table
+            .iter()
+            .position(|&n| n == number)
+            .map(|pitch_class| MusicalKey {
+                pitch_class: pitch_class as u8,
+                minor,
+            })
+    }
+
+    pub fn camelot_number(&self) -> u8 {
+        if self.minor {
+            MINOR_CAMELOT[self.pitch_class as usize]
+        } else {
+            MAJOR_CAMELOT[self.pitch_class as usize]
+        }
+    }
+
+    /// Conventional name with the usual DJ spellings: "Am", "F#", "Bbm".
+    pub fn standard(&self) -> String {
+        let names = if self.minor { &MINOR_NAMES } else { &MAJOR_NAMES };
+        let name = names[self.pitch_class as usize];
+        if self.minor {
+            format!("{}m", name)
+        } else {
+            name.to_string()
+        }
+    }
+
+    pub fn camelot(&self) -> String {
+        format!("{}{}", self.camelot_number(), if self.minor { 'A' } else { 'B' })
+    }
+
+    pub fn format(&self, notation: KeyNotation) -> String {
+        match notation {
+            KeyNotation::Standard => self.standard(),
+            KeyNotation::Camelot => self.camelot(),
+        }
+    }
+
+    /// Harmonic compatibility on the Camelot wheel: 1.0 for the same key,
+    /// 0.8 for neighbours and the relative major/minor, fading out with distance.
+    pub fn compatibility(&self, other: &MusicalKey) -> f32 {
+        let a = self.camelot_number() as i32;
+        let b = other.camelot_number() as i32;
+        let diff = (a - b).rem_euclid(12);
+        let steps = diff.min(12 - diff);
+
+        match (steps, self.minor == other.minor) {
+            (0, true) => 1.0,
+            (0, false) | (1, true) => 0.8,
+            (1, false) | (2, true) => 0.5,
+            _ => 0.0,
+        }
+    }
+}
@@ -1,24 +1,245 @@
 mod scanner;
+mod analysis;
+mod bwf;
+mod collation;
+mod consensus;
+mod decode;
+mod diskspace;
+mod dsf;
+mod duplicates;
+mod comments;
+mod id3v1;
+mod import;
+mod jobs;
+mod filename;
+mod fingerprint;
+mod folder_merge;
+mod field_scope;
+mod genre_fallback;
+mod journal;
+mod ape;
+mod artists;
+mod artwork;
+mod backfill;
+mod genres;
+mod genre_map;
+mod backup;
+mod convert;
+mod cue;
+mod library_db;
+mod lookup_cache;
 mod api_client;
 mod settings;
+mod keys;
+mod similarity;
+mod stats;
+mod insights;
+mod maintenance;
+mod changeset;
+mod simulation;
+mod library_lock;
+mod permissions;
+mod path_codec;
+mod pattern;
+mod pipeline;
+mod provenance;
+mod report;
+mod restore;
+mod transforms;
+mod usb_export;
+mod workdir;
+#[cfg(test)]
+mod test_fixtures;
 
-use scanner::{AudioFile, FileScanner, Metadata};
-use api_client::{SpotifyClient, MusicBrainzClient, BeatportClient};
+use scanner::{AudioFile, DuplicateStrategy, FileScanner, Metadata};
+use api_client::{SpotifyClient, MusicBrainzClient, BeatportClient, DeezerClient, LastFmClient, ProviderHealth};
+use artists::ArtistRules;
+use provenance::Provenance;
 use settings::{save_settings, load_settings};
-use std::path::PathBuf;
-use id3::TagLike;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::Emitter;
 
+// Tag writes on files this large can rewrite the whole file and take long
+// enough that the UI needs feedback.
+const LARGE_WRITE_BYTES: u64 = 256 * 1024 * 1024;
+
+// Scan events go out every this many files, so large libraries do not flood
+// the frontend with one event per file.
+const SCAN_EVENT_INTERVAL: usize = 25;
+
+// Source of genres proposed from the artist's confirmed genres.
+const LOCAL_HISTORY_SOURCE: &str = "Local history";
+
+static SCAN_CANCEL: AtomicBool = AtomicBool::new(false);
+
+fn configured_scanner(app: &tauri::AppHandle) -> FileScanner {
+    load_settings(app.clone())
+        .map(|settings| organizing_scanner(app, &settings))
+        .unwrap_or_else(|_| FileScanner::new())
+}
+
+/// Locks every configured library folder that exists, for undo, which may
+/// touch files anywhere in the library.
+fn lock_libraries(settings: &settings::AppSettings) -> Result<Vec<library_lock::LibraryLock>, String> {
+    let folders = settings.library_folders.iter().map(Path::new).filter(|folder| folder.is_dir());
+    library_lock::acquire_all(folders, &settings.library_folders)
+}
+
+/// The field scope and locked fields every automatic tag write keeps to.
+fn field_guard(app: &tauri::AppHandle, settings: &settings::AppSettings) -> Result<field_scope::FieldGuard, String> {
+    Ok(field_scope::FieldGuard::new(settings.field_scope.clone(), library_db::read(app)?.locked_fields))
+}
+
+/// Where quarantined files go: the configured folder, or the app data dir's
+/// `quarantine` folder.
+fn quarantine_folder(app: &tauri::AppHandle, settings: &settings::AppSettings) -> Result<PathBuf, String> {
+    if settings.quarantine_folder.trim().is_empty() {
+        settings::get_data_path(app, "quarantine")
+    } else {
+        Ok(PathBuf::from(&settings.quarantine_folder))
+    }
+}
+
+/// A scanner for commands that organize or rename, so KeepBest has
+/// somewhere to move the copy it drops.
+fn organizing_scanner(app: &tauri::AppHandle, settings: &settings::AppSettings) -> FileScanner {
+    let scanner = FileScanner::from_settings(settings);
+    match quarantine_folder(app, settings) {
+        Ok(folder) => scanner.with_quarantine(folder),
+        Err(_) => scanner,
+    }
+}
+
+/// Runs filesystem-heavy command bodies on the blocking pool so neither the
+/// IPC thread nor the async runtime stalls on large libraries.
+async fn run_blocking<T, F>(work: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(work)
+        .await
+        .map_err(|e| format!("Background task failed: {}", e))?
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct TagWriteProgress {
+    #[serde(with = "crate::path_codec")]
+    file_path: PathBuf,
+    size_bytes: u64,
+    stage: &'static str,
+    elapsed_ms: u64,
+}
+
+/// Runs a tag write on the blocking pool. For large files it emits
+/// `tags://write_progress` when the write starts, once a second while it
+/// runs, and when it finishes.
+async fn run_tag_write<T, F>(app: &tauri::AppHandle, path: &Path, work: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+{
+    let size_bytes = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let large = size_bytes >= LARGE_WRITE_BYTES;
+    let started = Instant::now();
+    let emit = |stage: &'static str| {
+        if large {
+            let _ = app.emit("tags://write_progress", TagWriteProgress {
+                file_path: path.to_path_buf(),
+                size_bytes,
+                stage,
+                elapsed_ms: started.elapsed().as_millis() as u64,
+            });
+        }
+    };
+    
+    emit("started");
+    let task = run_blocking(work);
+    tokio::pin!(task);
+    let mut ticker = tokio::time::interval(Duration::from_secs(1));
+    ticker.tick().await;
+    
+    let result = loop {
+        tokio::select! {
+            result = &mut task => break result,
+            _ = ticker.tick() => emit("working"),
+        }
+    };
+    emit("finished");
+    
+    result
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ScanFileFound {
+    #[serde(with = "crate::path_codec")]
+    path: PathBuf,
+    found: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ScanProgress {
+    #[serde(with = "crate::path_codec")]
+    path: PathBuf,
+    done: usize,
+    total: usize,
+}
+
+/// Scans a folder, emitting `scan://file-found` while walking it and
+/// `scan://progress` while reading tags (every `SCAN_EVENT_INTERVAL` files
+/// and for the last one). Fails with "Scan cancelled" after `cancel_scan`.
 #[tauri::command]
-fn scan_folder(path: String) -> Result<Vec<AudioFile>, String> {
-    let scanner = FileScanner::new();
-    let folder_path = PathBuf::from(path);
-    scanner.scan_directory(&folder_path)
+async fn scan_folder(app: tauri::AppHandle, path: String, sort: Option<scanner::ScanSort>) -> Result<Vec<AudioFile>, String> {
+    let scanner = configured_scanner(&app);
+    let folder_path = path_codec::decode(&path);
+    SCAN_CANCEL.store(false, Ordering::SeqCst);
+    run_blocking(move || {
+        let on_found = |path: &Path, found: usize| {
+            if found.is_multiple_of(SCAN_EVENT_INTERVAL) {
+                let _ = app.emit("scan://file-found", ScanFileFound { path: path.to_path_buf(), found });
+            }
+        };
+        let on_read = |path: &Path, done: usize, total: usize| {
+            if done.is_multiple_of(SCAN_EVENT_INTERVAL) || done == total {
+                let _ = app.emit("scan://progress", ScanProgress { path: path.to_path_buf(), done, total });
+            }
+        };
+        let monitor = scanner::ScanMonitor {
+            cancel: &SCAN_CANCEL,
+            on_found: &on_found,
+            on_read: &on_read,
+        };
+        let mut files = scanner.scan_directory_monitored(&folder_path, &monitor)?;
+        scanner.sort_files(&mut files, sort.unwrap_or(scanner::ScanSort::Path));
+        Ok(files)
+    })
+    .await
 }
 
+/// Stops a running `scan_folder` at the next file.
 #[tauri::command]
-async fn fetch_metadata(app: tauri::AppHandle, artist: String, title: String) -> Result<Vec<api_client::MetadataResult>, String> {
-    let mut results = Vec::new();
-    
+fn cancel_scan() {
+    SCAN_CANCEL.store(true, Ordering::SeqCst);
+}
+
+#[tauri::command]
+async fn scan_folders(app: tauri::AppHandle, paths: Vec<String>, sort: Option<scanner::ScanSort>) -> Result<scanner::MultiScanResult, String> {
+    let scanner = configured_scanner(&app);
+    let roots: Vec<PathBuf> = paths.iter().map(|path| path_codec::decode(path)).collect();
+    run_blocking(move || {
+        let mut result = scanner.scan_roots(&roots)?;
+        scanner.sort_files(&mut result.files, sort.unwrap_or(scanner::ScanSort::Path));
+        Ok(result)
+    })
+    .await
+}
+
+fn provider_clients(app: &tauri::AppHandle) -> (SpotifyClient, BeatportClient, MusicBrainzClient, DeezerClient, LastFmClient) {
     let settings = load_settings(app.clone()).ok();
     
     let client_id = std::env::var("SPOTIFY_CLIENT_ID")
@@ -38,106 +259,1416 @@ async fn fetch_metadata(app: tauri::AppHandle, artist: String, title: String) ->
     let beatport_username = std::env::var("BEATPORT_USERNAME").ok();
     let beatport_password = std::env::var("BEATPORT_PASSWORD").ok();
     
-    let spotify_client = SpotifyClient::new(client_id, client_secret);
-    if let Ok(result) = spotify_client.search_track(&artist, &title).await {
-        results.push(result);
+    let lastfm_api_key = std::env::var("LASTFM_API_KEY")
+        .ok()
+        .or_else(|| {
+            settings.as_ref()
+                .and_then(|s| if s.lastfm_api_key.is_empty() { None } else { Some(s.lastfm_api_key.clone()) })
+        });
+    
+    let genre_locale = settings.as_ref().map(|s| s.genre_locale.clone());
+    let market = settings.as_ref().map(|s| s.market.clone());
+    
+    (
+        SpotifyClient::new(client_id, client_secret, market),
+        BeatportClient::new(beatport_username, beatport_password),
+        MusicBrainzClient::new(genre_locale),
+        DeezerClient::new(),
+        LastFmClient::new(lastfm_api_key),
+    )
+}
+
+#[tauri::command]
+async fn fetch_metadata(app: tauri::AppHandle, artist: String, title: String, file_path: Option<String>) -> Result<api_client::MetadataLookup, String> {
+    lookup_genre(&app, &artist, &title, file_path.as_deref()).await
+}
+
+/// `lookup_metadata`, unless the artist's genre has been confirmed often
+/// enough to propose it from local history without asking any provider.
+/// Pinned files are always looked up.
+async fn lookup_genre(app: &tauri::AppHandle, artist: &str, title: &str, file_path: Option<&str>) -> Result<api_client::MetadataLookup, String> {
+    let settings = load_settings(app.clone())?;
+    let db = library_db::read(app)?;
+    let pinned = file_path.is_some_and(|path| db.pins.contains_key(&path_codec::decode(path)));
+    let remembered = db.remembered_genre(artist, settings.artist_genre_confirmations).filter(|_| !pinned);
+    let Some(genre) = remembered else {
+        return lookup_metadata(app, artist, title, file_path).await;
+    };
+
+    let mut lookup = api_client::MetadataLookup::default();
+    lookup.record(LOCAL_HISTORY_SOURCE, Ok(Some(api_client::MetadataResult {
+        genres: vec![genre.clone()],
+        genre: Some(genre),
+        source_genre: None,
+        artist: Some(artist.to_string()),
+        confidence: api_client::Confidence::High,
+        source: LOCAL_HISTORY_SOURCE.to_string(),
+        track_id: None,
+        external_ids: Default::default(),
+        release_date: None,
+        release_type: None,
+        artwork_url: None,
+    })));
+    lookup.resolved = consensus::resolve_metadata(&lookup.results, &consensus::SourceWeights::from_settings(&settings));
+    Ok(lookup)
+}
+
+/// Records that the user settled on `metadata`'s genre for its artist.
+fn confirm_artist_genre(app: &tauri::AppHandle, metadata: &Metadata) {
+    if let (Some(artist), Some(genre)) = (&metadata.artist, &metadata.genre) {
+        let _ = library_db::update(app, |db| db.confirm_genre(artist, genre));
     }
+}
+
+/// Counts a finished tag write in the session stats and sends the new
+/// totals.
+fn count_write(app: &tauri::AppHandle, metadata: &Metadata, succeeded: bool) {
+    if !succeeded {
+        stats::bump(stats::Counter::Errors);
+    } else if metadata.genre.is_some() {
+        stats::bump(stats::Counter::GenresWritten);
+    }
+    stats::emit(app);
+}
+
+async fn lookup_metadata(app: &tauri::AppHandle, artist: &str, title: &str, file_path: Option<&str>) -> Result<api_client::MetadataLookup, String> {
+    let mut lookup = api_client::MetadataLookup::default();
     
-    let beatport_client = BeatportClient::new(beatport_username, beatport_password);
-    if let Ok(result) = beatport_client.search_track(&artist, &title).await {
-        results.push(result);
+    let (spotify_client, beatport_client, mb_client, deezer_client, lastfm_client) = provider_clients(app);
+    
+    let pin = match file_path {
+        Some(file_path) => library_db::read(app)?.pins.get(&path_codec::decode(file_path)).cloned(),
+        None => None,
+    };
+    let pinned_source = pin.as_ref().map(|p| p.source.as_str());
+    
+    // Provider ids already in the tags make those lookups exact.
+    let tagged_ids = file_path
+        .and_then(|path| FileScanner::new().read_metadata(&path_codec::decode(path)).ok())
+        .map(|metadata| metadata.external_ids)
+        .unwrap_or_default();
+    
+    if let Some(pin) = &pin {
+        let pinned = match pin.source.as_str() {
+            "Spotify" => spotify_client.lookup_track(&pin.track_id).await,
+            "Beatport" => beatport_client.lookup_track(&pin.track_id).await,
+            "MusicBrainz" => mb_client.lookup_track(&pin.track_id).await,
+            "Deezer" => deezer_client.lookup_track(&pin.track_id).await,
+            other => Err(format!("Unknown pinned provider: {}", other)),
+        };
+        let pinned = pinned.map(|mut result| {
+            result.confidence = api_client::Confidence::High;
+            Some(result)
+        });
+        lookup.record(&pin.source, pinned);
     }
     
-    let mb_client = MusicBrainzClient::new();
-    if let Ok(result) = mb_client.search_track(&artist, &title).await {
-        results.push(result);
+    // Combined credits ("A x B") rarely match as a whole, so each credited
+    // artist is tried after the full credit.
+    let settings = load_settings(app.clone()).ok();
+    let artist_rules = settings.as_ref()
+        .map(ArtistRules::from_settings)
+        .unwrap_or_default();
+    let weights = settings.as_ref()
+        .map(consensus::SourceWeights::from_settings)
+        .unwrap_or_default();
+    let cache_days = settings.map(|settings| settings.lookup_cache_days).unwrap_or(0);
+    let mut credits = vec![artist.to_string()];
+    let individual = artist_rules.split(artist);
+    if individual.len() > 1 {
+        credits.extend(individual);
     }
     
-    Ok(results)
-}
-
-#[tauri::command]
-fn update_metadata(file_path: String, metadata: Metadata, backup: bool) -> Result<(), String> {
-    let scanner = FileScanner::new();
-    let path = PathBuf::from(&file_path);
-    
-    if backup {
-        let ext = path.extension().and_then(|s| s.to_str());
-        let current_metadata = match ext {
-            Some("mp3") => {
-                id3::Tag::read_from_path(&path).ok().map(|tag| Metadata {
-                    title: tag.title().map(|s| s.to_string()),
-                    artist: tag.artist().map(|s| s.to_string()),
-                    album: tag.album().map(|s| s.to_string()),
-                    genre: tag.genre().map(|s| s.to_string()),
-                    year: tag.year(),
-                    bpm: None,
+    if pinned_source != Some("Spotify") {
+        let spotify = &spotify_client;
+        let result = cached_search(app, cache_days, "Spotify", &credits, title, move |credit| async move {
+            spotify.search_track(&credit, title).await
+        })
+        .await;
+        lookup.record("Spotify", result);
+    }
+    
+    if pinned_source != Some("Beatport") {
+        let tagged = match &tagged_ids.beatport_track_id {
+            Some(track_id) => beatport_client.lookup_track(track_id).await.ok(),
+            None => None,
+        };
+        let result = match tagged {
+            Some(result) => Ok(Some(result)),
+            None => {
+                let beatport = &beatport_client;
+                cached_search(app, cache_days, "Beatport", &credits, title, move |credit| async move {
+                    beatport.search_track(&credit, title).await
                 })
-            },
-            Some("flac") | Some("wav") | Some("ogg") | Some("m4a") => {
-                use lofty::prelude::*;
-                use lofty::config::ParseOptions;
-                use lofty::probe::Probe;
-                Probe::open(&path)
-                    .ok()
-                    .and_then(|probe| probe.options(ParseOptions::new()).read().ok())
-                    .and_then(|file| file.primary_tag().or_else(|| file.first_tag()).map(|tag| Metadata {
-                        title: tag.title().map(|s| s.to_string()),
-                        artist: tag.artist().map(|s| s.to_string()),
-                        album: tag.album().map(|s| s.to_string()),
-                        genre: tag.genre().map(|s| s.to_string()),
-                        year: tag.year().map(|y| y as i32),
-                        bpm: None,
-                    }))
-            },
-            _ => None
+                .await
+            }
         };
+        lookup.record("Beatport", result);
+    }
+    
+    if pinned_source != Some("MusicBrainz") {
+        // Junk tags ("Track 01") make a text search pointless, so the audio
+        // is identified instead; otherwise that is the fallback.
+        let mut recording_id = tagged_ids.musicbrainz_recording_id.clone();
+        let fingerprinted = recording_id.is_none() && fingerprint::tags_are_junk(artist, title);
+        if fingerprinted {
+            recording_id = fingerprint_recording(app, file_path).await;
+        }
         
-        if let Some(current) = current_metadata {
-            scanner.backup_metadata(&path, &current)?;
-        } else {
-            return Err("Cannot read current metadata for backup".to_string());
+        let tagged = match &recording_id {
+            Some(recording_id) => mb_client.lookup_track(recording_id).await.ok(),
+            None => None,
+        };
+        let mut result = match tagged {
+            Some(result) => Ok(Some(result)),
+            None => {
+                let musicbrainz = &mb_client;
+                cached_search(app, cache_days, "MusicBrainz", &credits, title, move |credit| async move {
+                    musicbrainz.search_track(&credit, title).await
+                })
+                .await
+            }
+        };
+        if matches!(result, Ok(None)) && !fingerprinted {
+            if let Some(recording_id) = fingerprint_recording(app, file_path).await {
+                result = mb_client.lookup_track(&recording_id).await.map(Some);
+            }
+        }
+        lookup.record("MusicBrainz", result);
+    }
+    
+    if pinned_source != Some("Deezer") {
+        let deezer = &deezer_client;
+        let result = cached_search(app, cache_days, "Deezer", &credits, title, move |credit| async move {
+            deezer.search_track(&credit, title).await
+        })
+        .await;
+        lookup.record("Deezer", result);
+    }
+    
+    // Last.fm has no stable track ids, so its matches cannot be pinned.
+    let lastfm = &lastfm_client;
+    let result = cached_search(app, cache_days, "Last.fm", &credits, title, move |credit| async move {
+        lastfm.search_track(&credit, title).await
+    })
+    .await;
+    lookup.record("Last.fm", result);
+    
+    // Mapped after the cache so that it keeps the providers' own genres and
+    // rule changes apply to cached lookups too.
+    genre_map::load(app)?.apply(&mut lookup.results)?;
+    lookup.resolved = consensus::resolve_metadata(&lookup.results, &weights);
+    
+    Ok(lookup)
+}
+
+/// MusicBrainz recording id of the file's audio going by its AcoustID
+/// fingerprint; `None` when AcoustID is not configured, fpcalc fails or
+/// nothing matches well enough.
+async fn fingerprint_recording(app: &tauri::AppHandle, file_path: Option<&str>) -> Option<String> {
+    let path = path_codec::decode(file_path?);
+    let settings = load_settings(app.clone()).ok()?;
+    
+    let api_key = std::env::var("ACOUSTID_API_KEY")
+        .ok()
+        .or_else(|| Some(settings.acoustid_api_key.clone()).filter(|key| !key.is_empty()));
+    let acoustid = fingerprint::AcoustIdClient::new(api_key);
+    if !acoustid.is_configured() {
+        return None;
+    }
+    
+    let fpcalc = fingerprint::fpcalc_binary(&settings);
+    let print = run_blocking(move || fingerprint::compute(&fpcalc, &path)).await.ok()?;
+    acoustid.lookup(&print).await.ok().flatten()
+}
+
+/// `search_credits` for `source`, answered from the lookup cache while it
+/// holds an outcome younger than `cache_days` for the full credit and
+/// title. Failed searches are not cached so they are retried next time.
+async fn cached_search<F, Fut>(app: &tauri::AppHandle, cache_days: u64, source: &str, credits: &[String], title: &str, search: F) -> Result<Option<api_client::MetadataResult>, String>
+where
+    F: Fn(String) -> Fut,
+    Fut: std::future::Future<Output = Result<Option<api_client::MetadataResult>, String>>,
+{
+    if cache_days == 0 {
+        return search_credits(credits, search).await;
+    }
+    
+    let key = lookup_cache::cache_key(credits.first().map(String::as_str).unwrap_or_default(), title);
+    if let Some(cached) = lookup_cache::get(app, source, &key, cache_days) {
+        stats::bump(stats::Counter::CacheHits);
+        return Ok(cached);
+    }
+    
+    let outcome = search_credits(credits, search).await;
+    if let Ok(result) = &outcome {
+        let _ = lookup_cache::put(app, source, &key, result.clone());
+    }
+    outcome
+}
+
+/// Runs `search` for each artist credit in turn until one finds a match or
+/// fails.
+async fn search_credits<F, Fut>(credits: &[String], search: F) -> Result<Option<api_client::MetadataResult>, String>
+where
+    F: Fn(String) -> Fut,
+    Fut: std::future::Future<Output = Result<Option<api_client::MetadataResult>, String>>,
+{
+    let mut outcome = Ok(None);
+    for credit in credits {
+        outcome = search(credit.clone()).await;
+        if !matches!(outcome, Ok(None)) {
+            break;
+        }
+    }
+    outcome
+}
+
+/// Looks up every track, `fetch_concurrency` at a time, emitting
+/// `metadata://batch-result` for each as it finishes and
+/// `metadata://batch-done` with the count at the end. Returns the number of
+/// tracks started without waiting for them.
+#[tauri::command]
+async fn fetch_metadata_batch(app: tauri::AppHandle, tracks: Vec<jobs::TrackQuery>) -> Result<usize, String> {
+    let concurrency = load_settings(app.clone())?.fetch_concurrency.max(1);
+    let total = tracks.len();
+    
+    tauri::async_runtime::spawn(async move {
+        let slots = Arc::new(tokio::sync::Semaphore::new(concurrency));
+        let mut lookups = Vec::with_capacity(total);
+        for (index, query) in tracks.into_iter().enumerate() {
+            let Ok(slot) = slots.clone().acquire_owned().await else {
+                break;
+            };
+            let app = app.clone();
+            lookups.push(tauri::async_runtime::spawn(async move {
+                let outcome = lookup_genre(&app, &query.artist, &query.title, query.file_path.as_deref()).await;
+                drop(slot);
+                count_lookup(&app, &outcome);
+                let (lookup, error) = match outcome {
+                    Ok(lookup) => (Some(lookup), None),
+                    Err(e) => (None, Some(e)),
+                };
+                let _ = app.emit("metadata://batch-result", jobs::TrackResult { index, query, lookup, error });
+            }));
         }
+        for lookup in lookups {
+            let _ = lookup.await;
+        }
+        let _ = stats::persist(&app);
+        let _ = app.emit("metadata://batch-done", total);
+    });
+    
+    Ok(total)
+}
+
+/// Counts a finished lookup in the session stats and sends the new totals.
+fn count_lookup(app: &tauri::AppHandle, outcome: &Result<api_client::MetadataLookup, String>) {
+    stats::bump(stats::Counter::FilesProcessed);
+    if outcome.is_err() {
+        stats::bump(stats::Counter::Errors);
+    }
+    stats::emit(app);
+}
+
+/// Drains the batch queue one lookup at a time, emitting `jobs://result` for
+/// each file and `jobs://idle` once nothing is left.
+fn spawn_job_worker(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        while let Some(job) = jobs::next_job() {
+            let outcome = lookup_genre(&app, &job.artist, &job.title, Some(&job.file_path)).await;
+            count_lookup(&app, &outcome);
+            let (lookup, error) = match outcome {
+                Ok(lookup) => (Some(lookup), None),
+                Err(e) => (None, Some(e)),
+            };
+            let _ = app.emit("jobs://result", jobs::JobResult {
+                file_path: job.file_path,
+                lookup,
+                error,
+            });
+        }
+        let _ = stats::persist(&app);
+        let _ = app.emit("jobs://idle", ());
+    });
+}
+
+/// Whether the app already tagged the file from a High-confidence match,
+/// going by the library database or, failing that, the file's provenance tag.
+fn already_processed(processed: &HashMap<PathBuf, Provenance>, path: &Path) -> bool {
+    let recorded = processed.get(path).cloned().or_else(|| {
+        FileScanner::new().read_metadata(path).ok().and_then(|metadata| metadata.provenance)
+    });
+    recorded.is_some_and(|provenance| provenance.confidence == api_client::Confidence::High)
+}
+
+/// Queues lookups for a batch run. Unless `force` is set, files already
+/// tagged from a High-confidence match are left out so re-runs only do new
+/// work; their paths are returned.
+#[tauri::command]
+async fn enqueue_fetch_jobs(app: tauri::AppHandle, jobs: Vec<jobs::FetchJob>, force: Option<bool>) -> Result<Vec<String>, String> {
+    let (jobs, skipped) = if force.unwrap_or(false) {
+        (jobs, Vec::new())
+    } else {
+        let processed = library_db::read(&app)?.processed;
+        run_blocking(move || {
+            Ok(jobs.into_iter().partition::<Vec<_>, _>(|job| {
+                !already_processed(&processed, &path_codec::decode(&job.file_path))
+            }))
+        })
+        .await?
+    };
+
+    if jobs::enqueue(jobs) {
+        spawn_job_worker(app);
+    }
+    Ok(skipped.into_iter().map(|job| job.file_path).collect())
+}
+
+#[tauri::command]
+fn list_fetch_jobs() -> Vec<jobs::FetchJob> {
+    jobs::pending()
+}
+
+#[tauri::command]
+fn set_job_priority(file_path: String, priority: jobs::JobPriority) -> Result<(), String> {
+    jobs::set_priority(&file_path, priority)
+}
+
+#[tauri::command]
+fn move_job(file_path: String, position: usize) -> Result<(), String> {
+    jobs::move_to(&file_path, position)
+}
+
+#[tauri::command]
+fn clear_fetch_jobs() -> usize {
+    jobs::clear()
+}
+
+#[tauri::command]
+fn pin_provider_match(app: tauri::AppHandle, file_path: String, source: String, track_id: String) -> Result<(), String> {
+    if !matches!(source.as_str(), "Spotify" | "Beatport" | "MusicBrainz" | "Deezer") {
+        return Err(format!("Unknown provider: {}", source));
     }
     
-    scanner.write_metadata(&path, &metadata)?;
+    let pin = library_db::ProviderPin {
+        source,
+        track_id,
+        pinned_at: library_db::now_secs(),
+    };
+    library_db::update(&app, |db| {
+        db.pins.insert(path_codec::decode(&file_path), pin);
+    })
+}
+
+#[tauri::command]
+fn unpin_provider_match(app: tauri::AppHandle, file_path: String) -> Result<(), String> {
+    library_db::update(&app, |db| {
+        db.pins.remove(&path_codec::decode(&file_path));
+    })
+}
+
+/// Genres confirmed per artist (lowercased) with how often each was chosen.
+#[tauri::command]
+fn list_artist_genres(app: tauri::AppHandle) -> Result<HashMap<String, HashMap<String, u32>>, String> {
+    Ok(library_db::read(&app)?.artist_genres)
+}
+
+/// Forgets the genres confirmed for `artist`, so their tracks are looked up
+/// again.
+#[tauri::command]
+fn forget_artist_genre(app: tauri::AppHandle, artist: String) -> Result<(), String> {
+    library_db::update(&app, |db| {
+        db.artist_genres.remove(&artist.trim().to_lowercase());
+    })
+}
+
+/// Locks fields of a file corrected by hand, so processing never
+/// overwrites them until they are unlocked.
+#[tauri::command]
+fn lock_fields(app: tauri::AppHandle, file_path: String, fields: Vec<String>) -> Result<(), String> {
+    for field in &fields {
+        field_scope::check_field(field)?;
+    }
+    library_db::update(&app, |db| {
+        db.locked_fields.entry(path_codec::decode(&file_path)).or_default().extend(fields);
+    })
+}
+
+#[tauri::command]
+fn unlock_fields(app: tauri::AppHandle, file_path: String, fields: Vec<String>) -> Result<(), String> {
+    let path = path_codec::decode(&file_path);
+    library_db::update(&app, |db| {
+        if let Some(locked) = db.locked_fields.get_mut(&path) {
+            for field in &fields {
+                locked.remove(field);
+            }
+            if locked.is_empty() {
+                db.locked_fields.remove(&path);
+            }
+        }
+    })
+}
+
+#[tauri::command]
+fn get_locked_fields(app: tauri::AppHandle, file_path: String) -> Result<Vec<String>, String> {
+    Ok(library_db::read(&app)?
+        .locked_fields
+        .remove(&path_codec::decode(&file_path))
+        .map(|locked| locked.into_iter().collect())
+        .unwrap_or_default())
+}
+
+#[tauri::command]
+fn get_provider_pin(app: tauri::AppHandle, file_path: String) -> Result<Option<library_db::ProviderPin>, String> {
+    Ok(library_db::read(&app)?.pins.get(&path_codec::decode(&file_path)).cloned())
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+struct ArtworkSummary {
+    #[serde(with = "crate::path_codec::vec")]
+    embedded: Vec<PathBuf>,
+    #[serde(with = "crate::path_codec::vec")]
+    queued_for_review: Vec<PathBuf>,
+    #[serde(with = "crate::path_codec::vec")]
+    not_found: Vec<PathBuf>,
+    #[serde(with = "crate::path_codec::vec")]
+    already_present: Vec<PathBuf>,
+    errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ArtworkProgress {
+    #[serde(with = "crate::path_codec")]
+    file_path: PathBuf,
+    index: usize,
+    total: usize,
+}
+
+enum ArtworkOutcome {
+    Embedded,
+    QueuedForReview,
+    NotFound,
+    AlreadyPresent,
+}
+
+/// Downloads the cover at `url` and embeds it, backing up the file's tags
+/// first when backups are on, as `embed_cover_art` does.
+async fn download_and_embed(app: &tauri::AppHandle, settings: &settings::AppSettings, path: &Path, url: &str) -> Result<(), String> {
+    let data = artwork::download(url).await?;
+    let scanner = FileScanner::from_settings(settings);
+    let backup = settings.backup_before_changes;
+    let write_path = path.to_path_buf();
+    run_tag_write(app, path, move || {
+        if backup {
+            scanner.backup_current_metadata(&write_path)?;
+        }
+        scanner.embed_artwork(&write_path, &data)
+    })
+    .await
+}
+
+async fn fetch_artwork_for(app: &tauri::AppHandle, settings: &settings::AppSettings, path: &Path) -> Result<ArtworkOutcome, String> {
+    let scanner = FileScanner::from_settings(settings);
+    let file_path = path.to_path_buf();
+    let (has_artwork, metadata) = run_blocking(move || {
+        Ok((scanner.has_artwork(&file_path)?, scanner.read_metadata(&file_path)?))
+    })
+    .await?;
+    if has_artwork {
+        return Ok(ArtworkOutcome::AlreadyPresent);
+    }
+
+    let artist = metadata.artist.unwrap_or_default();
+    let title = metadata.title.unwrap_or_default();
+    if artist.is_empty() || title.is_empty() {
+        return Err(format!("{}: artist and title are needed to find artwork", path.display()));
+    }
+
+    let lookup = lookup_metadata(app, &artist, &title, Some(&path_codec::encode(path))).await?;
+    match artwork::choose(&lookup.results) {
+        artwork::ArtworkChoice::Embed(candidate) => {
+            download_and_embed(app, settings, path, &candidate.url).await?;
+            Ok(ArtworkOutcome::Embedded)
+        }
+        artwork::ArtworkChoice::Review(candidates) => {
+            library_db::update(app, |db| {
+                db.artwork_reviews.insert(path.to_path_buf(), candidates);
+            })?;
+            Ok(ArtworkOutcome::QueuedForReview)
+        }
+        artwork::ArtworkChoice::NotFound => Ok(ArtworkOutcome::NotFound),
+    }
+}
+
+/// Finds artwork for those of the files that have none embedded. Covers of
+/// high-confidence matches are embedded straight away; the rest wait in the
+/// review queue. Emits `artwork://progress` before each file.
+#[tauri::command]
+async fn fetch_missing_artwork(app: tauri::AppHandle, file_paths: Vec<String>) -> Result<ArtworkSummary, String> {
+    let settings = load_settings(app.clone())?;
+    let total = file_paths.len();
+    let mut summary = ArtworkSummary::default();
+
+    for (index, file_path) in file_paths.into_iter().enumerate() {
+        let path = path_codec::decode(&file_path);
+        let _ = app.emit("artwork://progress", ArtworkProgress {
+            file_path: path.clone(),
+            index,
+            total,
+        });
+
+        match fetch_artwork_for(&app, &settings, &path).await {
+            Ok(ArtworkOutcome::Embedded) => summary.embedded.push(path),
+            Ok(ArtworkOutcome::QueuedForReview) => summary.queued_for_review.push(path),
+            Ok(ArtworkOutcome::NotFound) => summary.not_found.push(path),
+            Ok(ArtworkOutcome::AlreadyPresent) => summary.already_present.push(path),
+            Err(e) => summary.errors.push(e),
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Covers for an album from the Cover Art Archive, Spotify and iTunes, the
+/// most certain first. Fails only when every source does.
+#[tauri::command]
+async fn fetch_cover_art(app: tauri::AppHandle, artist: String, album: String) -> Result<Vec<artwork::ArtworkCandidate>, String> {
+    let (spotify_client, _, mb_client, _, _) = provider_clients(&app);
+    
+    let (musicbrainz, spotify, itunes) = tokio::join!(
+        mb_client.search_album_art(&artist, &album),
+        spotify_client.search_album_art(&artist, &album),
+        artwork::search_itunes(&artist, &album),
+    );
+    
+    let mut candidates = Vec::new();
+    let mut errors = Vec::new();
+    for outcome in [musicbrainz, spotify, itunes] {
+        match outcome {
+            Ok(Some(candidate)) => candidates.push(candidate),
+            Ok(None) => {}
+            Err(e) => errors.push(e),
+        }
+    }
+    if candidates.is_empty() && errors.len() == 3 {
+        return Err(errors.join("; "));
+    }
+    
+    let rank = |confidence: &api_client::Confidence| match confidence {
+        api_client::Confidence::High => 0,
+        api_client::Confidence::Medium => 1,
+        api_client::Confidence::Low => 2,
+    };
+    candidates.sort_by_key(|candidate| rank(&candidate.confidence));
+    Ok(candidates)
+}
+
+/// Embeds `image_bytes` as the file's front cover (APIC, FLAC/Vorbis
+/// picture or MP4 covr), backing up the current tags first when the
+/// settings ask for it.
+#[tauri::command]
+async fn embed_cover_art(app: tauri::AppHandle, file_path: String, image_bytes: Vec<u8>) -> Result<(), String> {
+    image::guess_format(&image_bytes)
+        .map_err(|_| "Cover art is not a supported image".to_string())?;
+    
+    let settings = load_settings(app.clone())?;
+    let scanner = FileScanner::from_settings(&settings);
+    let path = path_codec::decode(&file_path);
+    let write_path = path.clone();
+    
+    run_tag_write(&app, &path, move || {
+        if settings.backup_before_changes {
+            scanner.backup_current_metadata(&write_path)?;
+        }
+        scanner.embed_artwork(&write_path, &image_bytes)
+    })
+    .await
+}
+
+#[tauri::command]
+fn list_artwork_reviews(app: tauri::AppHandle) -> Result<HashMap<String, Vec<artwork::ArtworkCandidate>>, String> {
+    let reviews = library_db::read(&app)?.artwork_reviews;
+    Ok(reviews.into_iter().map(|(path, candidates)| (path_codec::encode(&path), candidates)).collect())
+}
+
+/// Settles a queued review: embeds the cover at `url`, or with no url just
+/// drops the file from the queue.
+#[tauri::command]
+async fn resolve_artwork_review(app: tauri::AppHandle, file_path: String, url: Option<String>) -> Result<(), String> {
+    let path = path_codec::decode(&file_path);
+    if let Some(url) = url {
+        let settings = load_settings(app.clone())?;
+        download_and_embed(&app, &settings, &path, &url).await?;
+    }
+    library_db::update(&app, |db| {
+        db.artwork_reviews.remove(&path);
+    })
+}
+
+/// Runs an analysis over `file_paths` with the configured workers and
+/// processes and reports the throughput, so the settings can be tuned for
+/// the machine before analyzing a whole library.
+#[tauri::command]
+async fn benchmark_analysis(app: tauri::AppHandle, file_paths: Vec<String>, kind: Option<analysis::AnalysisKind>) -> Result<analysis::AnalysisBenchmark, String> {
+    let plan = analysis::AnalysisPlan::from_settings(&load_settings(app)?);
+    let kind = kind.unwrap_or(analysis::AnalysisKind::Onsets);
+    let paths: Vec<PathBuf> = file_paths.iter().map(|path| path_codec::decode(path)).collect();
+    
+    run_blocking(move || analysis::benchmark(kind, &paths, plan)).await
+}
+
+/// Tempo of the file's audio, for filling in `Metadata.bpm`.
+#[tauri::command]
+async fn analyze_bpm(file_path: String) -> Result<f32, String> {
+    run_blocking(move || {
+        let pcm = analysis::decode_for_analysis(&path_codec::decode(&file_path))?;
+        analysis::estimate_bpm(&pcm.samples)
+            .ok_or_else(|| "No steady beat found".to_string())
+    }).await
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct KeyAnalysis {
+    /// In the notation from settings, as written to tags.
+    key: String,
+    standard: String,
+    camelot: String,
+    confidence: f32,
+}
+
+/// Detects the file's musical key and, with `write`, stores it in the key
+/// tag, unless the file's locks or the field scope keep the key.
+#[tauri::command]
+async fn analyze_key(app: tauri::AppHandle, file_path: String, write: Option<bool>) -> Result<KeyAnalysis, String> {
+    let settings = load_settings(app.clone())?;
+    let path = path_codec::decode(&file_path);
+    let estimate = run_blocking(move || {
+        let pcm = analysis::decode_for_analysis(&path_codec::decode(&file_path))?;
+        analysis::estimate_key(&pcm.samples)
+            .ok_or_else(|| "No tonal content found".to_string())
+    }).await?;
+
+    let analysis = KeyAnalysis {
+        key: estimate.key.format(settings.key_notation),
+        standard: estimate.key.standard(),
+        camelot: estimate.key.camelot(),
+        confidence: estimate.confidence,
+    };
+
+    if write.unwrap_or(false) {
+        let scanner = FileScanner::from_settings(&settings);
+        let guard = field_guard(&app, &settings)?;
+        let key = analysis.key.clone();
+        let write_path = path.clone();
+        run_tag_write(&app, &path, move || {
+            let current = scanner.read_metadata(&write_path)?;
+            let has_key = current.key.as_deref().is_some_and(|key| !key.trim().is_empty());
+            if !guard.allows(&write_path, "key", has_key) {
+                return Err("The key of this file is locked or kept by the field scope".to_string());
+            }
+            if settings.backup_before_changes {
+                scanner.backup_metadata(&write_path, &current)?;
+            }
+            scanner.write_key(&write_path, &key)
+        })
+        .await?;
+    }
+    Ok(analysis)
+}
+
+#[tauri::command]
+async fn probe_audio(file_path: String) -> Result<decode::AudioInfo, String> {
+    run_blocking(move || decode::probe(&path_codec::decode(&file_path))).await
+}
+
+/// Peak levels across the whole file for drawing a waveform. Decodes at a
+/// low mono rate since only the envelope is needed.
+#[tauri::command]
+async fn audio_waveform(file_path: String, points: usize) -> Result<Vec<f32>, String> {
+    run_blocking(move || {
+        let options = decode::DecodeOptions {
+            sample_rate: Some(8000),
+            mono: true,
+            ..Default::default()
+        };
+        Ok(decode::decode(&path_codec::decode(&file_path), &options)?.peaks(points))
+    }).await
+}
+
+/// Drops every cached provider search so the next lookups query the
+/// providers again. Returns how many entries were cached.
+#[tauri::command]
+fn clear_lookup_cache(app: tauri::AppHandle) -> Result<usize, String> {
+    lookup_cache::clear(&app)
+}
+
+/// Writes the lookup cache to a file to share or take to another machine.
+/// Returns how many entries were exported.
+#[tauri::command]
+async fn export_lookup_cache(app: tauri::AppHandle, output_path: String) -> Result<usize, String> {
+    run_blocking(move || lookup_cache::export(&app, &path_codec::decode(&output_path))).await
+}
+
+/// Merges an exported lookup cache into this one, so lookups it covers
+/// need no provider. Returns how many entries were taken.
+#[tauri::command]
+async fn import_lookup_cache(app: tauri::AppHandle, input_path: String) -> Result<usize, String> {
+    run_blocking(move || lookup_cache::import(&app, &path_codec::decode(&input_path))).await
+}
+
+#[tauri::command]
+async fn check_provider_health(app: tauri::AppHandle) -> Vec<ProviderHealth> {
+    let (spotify_client, beatport_client, mb_client, deezer_client, lastfm_client) = provider_clients(&app);
+    
+    let (spotify, beatport, musicbrainz, deezer, lastfm) = tokio::join!(
+        spotify_client.health_check(),
+        beatport_client.health_check(),
+        mb_client.health_check(),
+        deezer_client.health_check(),
+        lastfm_client.health_check(),
+    );
+    
+    vec![
+        ProviderHealth { source: "Spotify".to_string(), status: spotify },
+        ProviderHealth { source: "Beatport".to_string(), status: beatport },
+        ProviderHealth { source: "MusicBrainz".to_string(), status: musicbrainz },
+        ProviderHealth { source: "Deezer".to_string(), status: deezer },
+        ProviderHealth { source: "Last.fm".to_string(), status: lastfm },
+    ]
+}
+
+#[tauri::command]
+async fn update_metadata(app: tauri::AppHandle, file_path: String, metadata: Metadata, backup: bool) -> Result<(), String> {
+    let scanner = configured_scanner(&app);
+    let path = path_codec::decode(&file_path);
+    let write_path = path.clone();
+    let written = metadata.clone();
+    
+    let result = run_tag_write(&app, &path, move || {
+        if backup {
+            scanner.backup_current_metadata(&write_path)?;
+        }
+        
+        scanner.write_metadata(&write_path, &written)
+    })
+    .await;
+    count_write(&app, &metadata, result.is_ok());
+    result?;
+    confirm_artist_genre(&app, &metadata);
     Ok(())
 }
 
 #[tauri::command]
-fn organize_files(app: tauri::AppHandle, file_path: String, metadata: Metadata, base_folder: String) -> Result<String, String> {
-    let scanner = FileScanner::new();
-    let path = PathBuf::from(file_path);
-    let base = PathBuf::from(base_folder);
+async fn process_file(app: tauri::AppHandle, file_path: String, metadata: Metadata, base_folder: Option<String>) -> Result<pipeline::ProcessResult, String> {
+    let settings = load_settings(app.clone())?;
+    let path = path_codec::decode(&file_path);
+    let base = base_folder.map(|folder| path_codec::decode(&folder));
     
+    let provenance = metadata.provenance.clone();
+    let locked = library_db::read(&app)?.locked_fields.remove(&path).unwrap_or_default();
+    let pipeline_settings = settings.clone();
+    let scanner = organizing_scanner(&app, &settings);
+    let write_path = path.clone();
+    let written = metadata.clone();
+    let result = run_tag_write(&app, &path, move || {
+        pipeline::process_file(&scanner, &pipeline_settings, &write_path, &written, &locked, base.as_deref())
+    })
+    .await;
+    stats::bump(stats::Counter::FilesProcessed);
+    count_write(&app, result.as_ref().map_or(&metadata, |result| &result.metadata), result.is_ok());
+    let result = result?;
+    confirm_artist_genre(&app, &result.metadata);
+    if result.converted {
+        let keep_original = settings.keep_converted_originals;
+        let _ = library_db::update(&app, |db| db.move_path(&result.original_path, &result.final_path, keep_original));
+    }
+    if let Some(provenance) = provenance {
+        let _ = library_db::update(&app, |db| {
+            db.processed.insert(result.final_path.clone(), provenance.stamped());
+        });
+    }
+    Ok(result)
+}
+
+#[tauri::command]
+async fn organize_files(app: tauri::AppHandle, file_path: String, metadata: Metadata, base_folder: String) -> Result<scanner::Placement, String> {
+    let settings = load_settings(app.clone())?;
+    let scanner = organizing_scanner(&app, &settings);
+    let path = path_codec::decode(&file_path);
+    let base = path_codec::decode(&base_folder);
+    
+    run_blocking(move || {
+        let _lock = library_lock::LibraryLock::acquire(&library_lock::lock_root(&base, &settings.library_folders))?;
+        scanner.organize_file(&path, &metadata, &base, &settings.folder_pattern)
+    })
+    .await
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct FileWithMetadata {
+    #[serde(with = "crate::path_codec")]
+    file_path: PathBuf,
+    metadata: Metadata,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct OrganizedFile {
+    #[serde(with = "crate::path_codec")]
+    file_path: PathBuf,
+    placement: Option<scanner::Placement>,
+    outcome: changeset::ChangeOutcome,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct OrganizeProgress {
+    #[serde(with = "crate::path_codec")]
+    file_path: PathBuf,
+    index: usize,
+    total: usize,
+}
+
+/// Organizes a whole selection under one library lock, emitting
+/// `organize://progress` before each file. A file that fails is reported
+/// and the rest carry on.
+#[tauri::command]
+async fn organize_files_batch(app: tauri::AppHandle, files: Vec<FileWithMetadata>, base_folder: String) -> Result<Vec<OrganizedFile>, String> {
+    let settings = load_settings(app.clone())?;
+    let scanner = organizing_scanner(&app, &settings);
+    let base = path_codec::decode(&base_folder);
+    let total = files.len();
+
+    run_blocking(move || {
+        let _lock = library_lock::LibraryLock::acquire(&library_lock::lock_root(&base, &settings.library_folders))?;
+        let results = files
+            .into_iter()
+            .enumerate()
+            .map(|(index, file)| {
+                let _ = app.emit("organize://progress", OrganizeProgress {
+                    file_path: file.file_path.clone(),
+                    index,
+                    total,
+                });
+                match scanner.organize_file(&file.file_path, &file.metadata, &base, &settings.folder_pattern) {
+                    Ok(placement) => OrganizedFile {
+                        file_path: file.file_path,
+                        placement: Some(placement),
+                        outcome: changeset::ChangeOutcome::Applied,
+                    },
+                    Err(e) => OrganizedFile {
+                        file_path: file.file_path,
+                        placement: None,
+                        outcome: changeset::ChangeOutcome::Failed(e),
+                    },
+                }
+            })
+            .collect();
+        Ok(results)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn preview_organize(app: tauri::AppHandle, files: Vec<AudioFile>, base_folder: String) -> Result<Vec<scanner::PathPreview>, String> {
     let settings = load_settings(app)?;
-    let new_path = scanner.organize_file(&path, &metadata, &base, &settings.folder_pattern)?;
-    Ok(new_path.to_string_lossy().to_string())
+    let scanner = FileScanner::from_settings(&settings);
+    run_blocking(move || Ok(scanner.preview_organize(&files, &path_codec::decode(&base_folder), &settings.folder_pattern))).await
 }
 
 #[tauri::command]
-fn rename_file(file_path: String, metadata: Metadata) -> Result<String, String> {
-    let scanner = FileScanner::new();
-    let path = PathBuf::from(file_path);
+async fn preview_rename(app: tauri::AppHandle, files: Vec<AudioFile>) -> Result<Vec<scanner::PathPreview>, String> {
+    let scanner = configured_scanner(&app);
+    run_blocking(move || Ok(scanner.preview_rename(&files))).await
+}
+
+#[tauri::command]
+async fn find_misplaced_files(app: tauri::AppHandle, files: Vec<AudioFile>, base_folder: String) -> Result<Vec<scanner::MisplacedFile>, String> {
+    let settings = load_settings(app)?;
+    let scanner = FileScanner::from_settings(&settings);
+    run_blocking(move || Ok(scanner.find_misplaced(&files, &path_codec::decode(&base_folder), &settings.folder_pattern))).await
+}
+
+/// Organizes the given files again from their current tags, for the ones
+/// `find_misplaced_files` flagged.
+#[tauri::command]
+async fn reorganize_files(app: tauri::AppHandle, file_paths: Vec<String>, base_folder: String) -> Result<Vec<changeset::ChangeResult>, String> {
+    let settings = load_settings(app.clone())?;
+    let scanner = organizing_scanner(&app, &settings);
+    let base = path_codec::decode(&base_folder);
+
+    run_blocking(move || {
+        let _lock = library_lock::LibraryLock::acquire(&library_lock::lock_root(&base, &settings.library_folders))?;
+        let results = file_paths
+            .into_iter()
+            .map(|file_path| {
+                let path = path_codec::decode(&file_path);
+                let outcome = match scanner.read_metadata(&path)
+                    .and_then(|metadata| scanner.organize_file(&path, &metadata, &base, &settings.folder_pattern))
+                {
+                    Ok(_) => changeset::ChangeOutcome::Applied,
+                    Err(e) => changeset::ChangeOutcome::Failed(e),
+                };
+                changeset::ChangeResult { file_path: path, outcome }
+            })
+            .collect();
+        Ok(results)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn find_duplicate_folders(base_folder: String) -> Result<Vec<folder_merge::EquivalentFolders>, String> {
+    run_blocking(move || folder_merge::find_equivalent(&path_codec::decode(&base_folder))).await
+}
+
+#[tauri::command]
+async fn merge_duplicate_folders(app: tauri::AppHandle, base_folder: String, groups: Vec<folder_merge::EquivalentFolders>) -> Result<folder_merge::MergeSummary, String> {
+    let settings = load_settings(app)?;
+    let base = path_codec::decode(&base_folder);
+    run_blocking(move || {
+        let _lock = library_lock::LibraryLock::acquire(&library_lock::lock_root(&base, &settings.library_folders))?;
+        folder_merge::merge(&groups)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn rename_file(app: tauri::AppHandle, file_path: String, metadata: Metadata) -> Result<scanner::Placement, String> {
+    let settings = load_settings(app.clone())?;
+    let scanner = organizing_scanner(&app, &settings);
+    let path = path_codec::decode(&file_path);
     
-    let new_path = scanner.rename_file(&path, &metadata)?;
-    Ok(new_path.to_string_lossy().to_string())
+    run_blocking(move || {
+        let _locks = library_lock::acquire_all(path.parent(), &settings.library_folders)?;
+        scanner.rename_file(&path, &metadata)
+    })
+    .await
 }
 
+/// Undoes the most recent move, rename or tag write still in the journal.
+/// `None` when there is nothing left to undo.
 #[tauri::command]
-fn restore_from_backup(backup_path: String, original_path: String) -> Result<(), String> {
-    let scanner = FileScanner::new();
-    let backup = PathBuf::from(backup_path);
-    let original = PathBuf::from(original_path);
+async fn undo_last_operation(app: tauri::AppHandle) -> Result<Option<journal::UndoneOperation>, String> {
+    let settings = load_settings(app.clone())?;
+    let scanner = organizing_scanner(&app, &settings);
+    run_blocking(move || {
+        let _locks = lock_libraries(&settings)?;
+        journal::undo_last(&scanner)
+    })
+    .await
+}
+
+/// Undoes everything recorded in `session`, this run of the app unless
+/// given, newest first. Stops at the first operation that cannot be undone.
+#[tauri::command]
+async fn undo_session(app: tauri::AppHandle, session: Option<u64>) -> Result<Vec<journal::UndoneOperation>, String> {
+    let settings = load_settings(app.clone())?;
+    let scanner = organizing_scanner(&app, &settings);
+    run_blocking(move || {
+        let _locks = lock_libraries(&settings)?;
+        let session = session
+            .or_else(journal::current_session)
+            .ok_or("The operation journal is not open")?;
+        journal::undo_session(&scanner, session)
+    })
+    .await
+}
+
+/// Restores a backup onto its file and returns the fields that changed. With
+/// `preview` set nothing is written and the fields that would change are
+/// returned instead.
+#[tauri::command]
+async fn restore_from_backup(app: tauri::AppHandle, backup_path: String, original_path: String, preview: Option<bool>) -> Result<restore::RestorePreview, String> {
+    let scanner = configured_scanner(&app);
+    let request = restore::RestoreRequest {
+        file_path: path_codec::decode(&original_path),
+        backup_path: path_codec::decode(&backup_path),
+    };
     
-    scanner.restore_from_backup(&backup, &original)?;
-    Ok(())
+    run_blocking(move || {
+        let diff = restore::preview(&scanner, &request);
+        if let Some(error) = &diff.error {
+            return Err(error.clone());
+        }
+        if !preview.unwrap_or(false) {
+            scanner.restore_from_backup(&request.backup_path, &request.file_path)?;
+        }
+        Ok(diff)
+    })
+    .await
 }
 
+/// Per-file diffs for a bulk restore, so files edited on purpose since
+/// their backup can be left out before `restore_backups`.
 #[tauri::command]
-fn find_duplicates(files: Vec<AudioFile>) -> Vec<Vec<usize>> {
+async fn preview_restore(app: tauri::AppHandle, restores: Vec<restore::RestoreRequest>) -> Result<Vec<restore::RestorePreview>, String> {
+    let scanner = configured_scanner(&app);
+    run_blocking(move || {
+        Ok(restores.iter().map(|request| restore::preview(&scanner, request)).collect())
+    })
+    .await
+}
+
+/// Backups under `folders` whose file is gone, with where the app moved
+/// each file if it did.
+#[tauri::command]
+async fn list_orphaned_backups(app: tauri::AppHandle, folders: Vec<String>) -> Result<Vec<backup::OrphanedBackup>, String> {
+    let db = library_db::read(&app)?;
+    let roots: Vec<PathBuf> = folders.iter().map(|folder| path_codec::decode(folder)).collect();
+    run_blocking(move || Ok(backup::find_orphans(&roots, &db))).await
+}
+
+#[tauri::command]
+async fn clean_orphaned_backups(orphans: Vec<backup::OrphanedBackup>, relink: bool) -> Result<backup::OrphanCleanup, String> {
+    run_blocking(move || Ok(backup::clean_orphans(&orphans, relink))).await
+}
+
+/// Restore points for a file, newest first, with what restoring each would
+/// change.
+#[tauri::command]
+async fn list_backups(app: tauri::AppHandle, file_path: String) -> Result<Vec<backup::BackupEntry>, String> {
+    let scanner = configured_scanner(&app);
+    run_blocking(move || backup::list_for_file(&scanner, &path_codec::decode(&file_path))).await
+}
+
+/// Deletes backups taken before `older_than` (Unix seconds) in `folders`,
+/// or in every library folder when none are given.
+#[tauri::command]
+async fn delete_backups(app: tauri::AppHandle, older_than: u64, folders: Option<Vec<String>>) -> Result<backup::BackupDeletion, String> {
+    let roots: Vec<PathBuf> = match folders {
+        Some(folders) => folders.iter().map(|folder| path_codec::decode(folder)).collect(),
+        None => load_settings(app)?.library_folders.iter().map(PathBuf::from).collect(),
+    };
+    run_blocking(move || Ok(backup::delete_older_than(&roots, older_than))).await
+}
+
+#[tauri::command]
+async fn restore_backups(app: tauri::AppHandle, restores: Vec<restore::RestoreRequest>) -> Result<Vec<changeset::ChangeResult>, String> {
+    let settings = load_settings(app.clone())?;
+    let scanner = organizing_scanner(&app, &settings);
+    run_blocking(move || {
+        let folders = restores.iter().filter_map(|request| request.file_path.parent());
+        let _locks = library_lock::acquire_all(folders, &settings.library_folders)?;
+        Ok(restore::restore_all(&scanner, &restores))
+    })
+    .await
+}
+
+#[tauri::command]
+async fn find_duplicates(files: Vec<AudioFile>, strategy: Option<DuplicateStrategy>) -> Result<Vec<Vec<usize>>, String> {
     let scanner = FileScanner::new();
-    scanner.find_duplicates(&files)
+    run_blocking(move || Ok(scanner.find_duplicates(&files, strategy.unwrap_or_default()))).await
+}
+
+/// Keeps the best copy in each group of duplicate paths and quarantines or
+/// deletes the others; `dry_run` reports the plan without touching files.
+#[tauri::command]
+async fn resolve_duplicates(app: tauri::AppHandle, groups: Vec<Vec<String>>, action: duplicates::DuplicateAction, dry_run: bool) -> Result<Vec<duplicates::DuplicateResolution>, String> {
+    let settings = load_settings(app.clone())?;
+    let quarantine = quarantine_folder(&app, &settings)?;
+    let groups: Vec<Vec<PathBuf>> = groups
+        .iter()
+        .map(|group| group.iter().map(|path| path_codec::decode(path)).collect())
+        .collect();
+
+    run_blocking(move || {
+        let folders = groups.iter().flatten().filter_map(|path| path.parent());
+        let _locks = library_lock::acquire_all(folders, &settings.library_folders)?;
+        duplicates::resolve(&groups, action, &quarantine, dry_run)
+    })
+    .await
+}
+
+#[tauri::command]
+fn find_similar_tracks(files: Vec<AudioFile>, file_path: String, limit: usize) -> Result<Vec<similarity::SimilarTrack>, String> {
+    let target = files.iter()
+        .position(|f| f.path == path_codec::decode(&file_path))
+        .ok_or_else(|| format!("File not found in library: {}", file_path))?;
+    
+    Ok(similarity::find_similar(&files, target, limit))
+}
+
+#[tauri::command]
+fn library_insights(files: Vec<AudioFile>) -> insights::LibraryInsights {
+    insights::library_insights(&files)
+}
+
+#[tauri::command]
+async fn run_maintenance(app: tauri::AppHandle) -> Result<maintenance::MaintenanceSummary, String> {
+    run_blocking(move || maintenance::run_for_app(&app)).await
+}
+
+#[tauri::command]
+async fn export_changeset(changes: Vec<changeset::PendingChange>, output_path: String) -> Result<usize, String> {
+    run_blocking(move || changeset::export_changeset(changes, &path_codec::decode(&output_path))).await
+}
+
+#[tauri::command]
+async fn apply_changeset(app: tauri::AppHandle, changeset_path: String, backup: bool, force: bool) -> Result<Vec<changeset::ChangeResult>, String> {
+    let settings = load_settings(app.clone())?;
+    let scanner = FileScanner::from_settings(&settings);
+    let guard = field_guard(&app, &settings)?;
+    
+    run_blocking(move || {
+        let changeset = changeset::load_changeset(&path_codec::decode(&changeset_path))?;
+        let folders = changeset.changes.iter().filter_map(|entry| entry.file_path.parent());
+        let _locks = library_lock::acquire_all(folders, &settings.library_folders)?;
+        Ok(changeset::apply_changeset(&scanner, &changeset, backup, force, &guard))
+    })
+    .await
+}
+
+#[tauri::command]
+fn set_simulation_mode(app: tauri::AppHandle, enabled: bool) -> Result<(), String> {
+    let mut settings = load_settings(app.clone())?;
+    settings.simulate = enabled;
+    save_settings(app, settings)
+}
+
+#[tauri::command]
+fn get_simulation_log() -> Vec<simulation::SimulatedOperation> {
+    simulation::take_log()
+}
+
+#[tauri::command]
+fn library_lock_status(app: tauri::AppHandle, base_folder: String) -> Result<Option<library_lock::LockOwner>, String> {
+    let settings = load_settings(app)?;
+    Ok(library_lock::read_lock(&library_lock::lock_root(&path_codec::decode(&base_folder), &settings.library_folders)))
+}
+
+#[tauri::command]
+fn break_library_lock(app: tauri::AppHandle, base_folder: String) -> Result<(), String> {
+    let settings = load_settings(app)?;
+    library_lock::break_lock(&library_lock::lock_root(&path_codec::decode(&base_folder), &settings.library_folders))
+}
+
+/// Writes the library report, with this session's totals or, before
+/// anything has been counted, those of the last finished run.
+#[tauri::command]
+async fn generate_report(app: tauri::AppHandle, files: Vec<AudioFile>, output_path: String, title: Option<String>) -> Result<(), String> {
+    let title = title.unwrap_or_else(|| "Library Report".to_string());
+    let session = stats::snapshot();
+    let session = if session.started_at != 0 {
+        let _ = stats::persist(&app);
+        Some(session)
+    } else {
+        stats::last_run(&app)
+    };
+    run_blocking(move || report::generate_report(&files, &title, session.as_ref(), &path_codec::decode(&output_path))).await
+}
+
+#[tauri::command]
+fn get_session_stats() -> stats::SessionStats {
+    stats::snapshot()
+}
+
+#[tauri::command]
+fn reset_session_stats(app: tauri::AppHandle) {
+    stats::reset();
+    stats::emit(&app);
+}
+
+#[tauri::command]
+fn preview_tag_cleanup(files: Vec<AudioFile>) -> Vec<transforms::TransformPreview> {
+    transforms::preview(&files, transforms::cleanup_metadata)
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct GenreBackfillReport {
+    suggestions: Vec<backfill::GenreBackfill>,
+    /// Write outcomes, when the suggestions were applied.
+    results: Vec<changeset::ChangeResult>,
+}
+
+/// Offline pass filling blank genres from the folder's majority genre.
+/// With `apply` the suggestions are written straight away.
+#[tauri::command]
+async fn backfill_genres(app: tauri::AppHandle, files: Vec<AudioFile>, apply: bool) -> Result<GenreBackfillReport, String> {
+    let settings = load_settings(app.clone())?;
+    let scanner = FileScanner::from_settings(&settings);
+    let guard = field_guard(&app, &settings)?;
+    run_blocking(move || {
+        let suggestions = backfill::suggest(&files, settings.genre_backfill_share);
+        let results = if apply {
+            let previews: Vec<transforms::TransformPreview> = suggestions
+                .iter()
+                .map(|suggestion| suggestion.preview.clone())
+                .collect();
+            transforms::apply(&scanner, &previews, settings.backup_before_changes, &guard)
+        } else {
+            Vec::new()
+        };
+        Ok(GenreBackfillReport { suggestions, results })
+    })
+    .await
+}
+
+/// The rules mapping provider genres to the user's taxonomy, from
+/// genre_map.json in the settings dir.
+#[tauri::command]
+fn get_genre_mapping(app: tauri::AppHandle) -> Result<genre_map::GenreMapping, String> {
+    genre_map::load(&app)
+}
+
+#[tauri::command]
+fn save_genre_mapping(app: tauri::AppHandle, mapping: genre_map::GenreMapping) -> Result<(), String> {
+    genre_map::save(&app, &mapping)
+}
+
+/// Titles across `files` with leading track markers ("01. ", "B2 - ")
+/// stripped and moved into the track number; apply with
+/// `apply_tag_transform`.
+#[tauri::command]
+fn preview_track_markers(files: Vec<AudioFile>) -> Vec<transforms::TransformPreview> {
+    transforms::extract_track_markers(&files)
+}
+
+#[tauri::command]
+async fn apply_tag_transform(app: tauri::AppHandle, previews: Vec<transforms::TransformPreview>, backup: bool) -> Result<Vec<changeset::ChangeResult>, String> {
+    let settings = load_settings(app.clone())?;
+    let scanner = FileScanner::from_settings(&settings);
+    let guard = field_guard(&app, &settings)?;
+    run_blocking(move || Ok(transforms::apply(&scanner, &previews, backup, &guard))).await
+}
+
+/// Removes stray APE tags, migrating their fields into ID3v2 first when
+/// `migrate` is set. With `backup` set, a file's tags are backed up before
+/// its APE tag is touched.
+#[tauri::command]
+async fn clean_ape_tags(app: tauri::AppHandle, file_paths: Vec<String>, migrate: bool, backup: bool) -> Result<Vec<changeset::ChangeResult>, String> {
+    let scanner = FileScanner::from_settings(&load_settings(app)?);
+    run_blocking(move || {
+        let results = file_paths
+            .into_iter()
+            .map(|file_path| {
+                let path = path_codec::decode(&file_path);
+                let cleaned = match backup && ape::has_ape_tag(&path) {
+                    true => scanner.backup_current_metadata(&path).and_then(|_| ape::clean_ape_tag(&path, migrate)),
+                    false => ape::clean_ape_tag(&path, migrate),
+                };
+                let outcome = match cleaned {
+                    Ok(_) => changeset::ChangeOutcome::Applied,
+                    Err(e) => changeset::ChangeOutcome::Failed(e),
+                };
+                changeset::ChangeResult { file_path: path, outcome }
+            })
+            .collect();
+        Ok(results)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn convert_files(app: tauri::AppHandle, file_paths: Vec<String>, target_format: String) -> Result<Vec<convert::ConversionResult>, String> {
+    let settings = load_settings(app.clone())?;
+    let ffmpeg = convert::ffmpeg_binary(&settings)
+        .ok_or("Format conversion requires an ffmpeg path in settings")?;
+    let scanner = FileScanner::from_settings(&settings);
+    let keep_original = settings.keep_converted_originals;
+    let work_dir = workdir::ensure(&settings, "convert")?;
+    
+    run_blocking(move || {
+        let paths: Vec<PathBuf> = file_paths.iter().map(|path| path_codec::decode(path)).collect();
+        let _locks = library_lock::acquire_all(paths.iter().filter_map(|path| path.parent()), &settings.library_folders)?;
+        convert::check_space(&paths, &target_format, &work_dir)?;
+        Ok(convert_all(&app, &ffmpeg, &scanner, file_paths, &target_format, keep_original, &work_dir))
+    })
+    .await
+}
+
+fn convert_all(
+    app: &tauri::AppHandle,
+    ffmpeg: &Path,
+    scanner: &FileScanner,
+    file_paths: Vec<String>,
+    target_format: &str,
+    keep_original: bool,
+    work_dir: &Path,
+) -> Vec<convert::ConversionResult> {
+    let mut results = Vec::new();
+    for file_path in file_paths {
+        let path = path_codec::decode(&file_path);
+        let result = match convert::convert_file(ffmpeg, scanner, &path, target_format, keep_original, work_dir) {
+            Ok(output_path) => {
+                let _ = library_db::update(app, |db| db.move_path(&path, &output_path, keep_original));
+                convert::ConversionResult {
+                    file_path: path,
+                    output_path: Some(output_path),
+                    outcome: changeset::ChangeOutcome::Applied,
+                }
+            }
+            Err(e) => convert::ConversionResult {
+                file_path: path,
+                output_path: None,
+                outcome: changeset::ChangeOutcome::Failed(e),
+            },
+        };
+        results.push(result);
+    }
+    results
+}
+
+#[tauri::command]
+async fn import_archive(app: tauri::AppHandle, archive_path: String, base_folder: Option<String>) -> Result<import::ImportSummary, String> {
+    let settings = load_settings(app.clone())?;
+    let scanner = organizing_scanner(&app, &settings);
+    let inbox = if settings.inbox_folder.trim().is_empty() {
+        settings::get_data_path(&app, "inbox")?
+    } else {
+        PathBuf::from(&settings.inbox_folder)
+    };
+    let base = base_folder.map(|folder| path_codec::decode(&folder));
+    
+    run_blocking(move || {
+        import::import_archive(&scanner, &settings, &path_codec::decode(&archive_path), &inbox, base.as_deref())
+    })
+    .await
+}
+
+#[tauri::command]
+async fn guess_from_filenames(app: tauri::AppHandle, file_paths: Vec<String>) -> Result<Vec<filename::FilenameGuess>, String> {
+    let settings = load_settings(app)?;
+    run_blocking(move || {
+        let paths: Vec<PathBuf> = file_paths.iter().map(|path| path_codec::decode(path)).collect();
+        filename::guess_all(&paths, &settings.filename_rules)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn export_to_usb(app: tauri::AppHandle, options: usb_export::UsbExportOptions) -> Result<usb_export::UsbExportResult, String> {
+    let settings = load_settings(app.clone())?;
+    let scanner = FileScanner::from_settings(&settings);
+    let ffmpeg = convert::ffmpeg_binary(&settings);
+    
+    run_blocking(move || {
+        usb_export::export_to_usb(&options, &scanner, ffmpeg.as_deref(), |progress| {
+            let _ = app.emit("usb_export://progress", progress);
+        })
+    })
+    .await
+}
+
+#[tauri::command]
+async fn split_cue_image(app: tauri::AppHandle, cue_path: String, output_dir: Option<String>) -> Result<Vec<String>, String> {
+    let settings = load_settings(app.clone())?;
+    let ffmpeg = convert::ffmpeg_binary(&settings).unwrap_or_else(|| PathBuf::from("ffmpeg"));
+    
+    run_blocking(move || {
+        let cue_path = path_codec::decode(&cue_path);
+        let sheet = cue::CueSheet::load(&cue_path)?;
+        let output_dir = match output_dir {
+            Some(dir) => path_codec::decode(&dir),
+            None => cue_path.parent().ok_or("Cannot determine parent directory")?.to_path_buf(),
+        };
+
+        let permissions = permissions::FilePermissions::from_settings(&settings);
+        let outputs = cue::split(&ffmpeg, &sheet, &output_dir, &permissions, |progress| {
+            let _ = app.emit("split://progress", progress);
+        })?;
+        Ok(outputs.iter().map(|path| path_codec::encode(path)).collect())
+    })
+    .await
 }
 
 #[tauri::command]
@@ -145,21 +1676,126 @@ fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
+/// Runs a headless analysis worker instead of the app when the process was
+/// started as one by a sharded batch. Returns whether it did.
+pub fn run_analysis_worker() -> bool {
+    // Not `args()`: that panics on an argument that is not UTF-8, such as a
+    // file opened through a file association.
+    let mut args = std::env::args_os().skip(1);
+    if args.next().as_deref() != Some(std::ffi::OsStr::new(analysis::WORKER_ARG)) {
+        return false;
+    }
+    let args: Vec<String> = args.map(|arg| arg.to_string_lossy().into_owned()).collect();
+    analysis::run_worker(&args);
+    true
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
+        .setup(|app| {
+            if let Ok(settings) = load_settings(app.handle().clone()) {
+                simulation::set_enabled(settings.simulate);
+                // Leftovers from a crashed session; failing to remove them
+                // is not worth blocking startup over.
+                let _ = workdir::cleanup(&settings);
+            }
+            // Without the journal the app still works, only without undo.
+            if let Ok(path) = settings::get_data_path(app.handle(), "journal.jsonl") {
+                let _ = journal::open(path);
+            }
+            
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let health = check_provider_health(handle.clone()).await;
+                let _ = handle.emit("providers://health", health);
+            });
+            maintenance::spawn_scheduler(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
             scan_folder, 
+            cancel_scan,
+            scan_folders,
             fetch_metadata,
+            fetch_metadata_batch,
+            enqueue_fetch_jobs,
+            list_fetch_jobs,
+            set_job_priority,
+            move_job,
+            clear_fetch_jobs,
+            check_provider_health,
+            clear_lookup_cache,
+            export_lookup_cache,
+            import_lookup_cache,
+            pin_provider_match,
+            unpin_provider_match,
+            get_provider_pin,
+            lock_fields,
+            unlock_fields,
+            get_locked_fields,
+            list_artist_genres,
+            forget_artist_genre,
+            fetch_missing_artwork,
+            fetch_cover_art,
+            embed_cover_art,
+            list_artwork_reviews,
+            resolve_artwork_review,
+            probe_audio,
+            benchmark_analysis,
+            analyze_bpm,
+            analyze_key,
+            audio_waveform,
             update_metadata,
+            process_file,
             organize_files,
+            organize_files_batch,
+            preview_organize,
+            preview_rename,
+            find_misplaced_files,
+            reorganize_files,
+            find_duplicate_folders,
+            merge_duplicate_folders,
             rename_file,
+            undo_last_operation,
+            undo_session,
             restore_from_backup,
+            preview_restore,
+            restore_backups,
+            list_orphaned_backups,
+            clean_orphaned_backups,
+            list_backups,
+            delete_backups,
             find_duplicates,
+            resolve_duplicates,
+            find_similar_tracks,
+            library_insights,
+            generate_report,
+            get_session_stats,
+            reset_session_stats,
+            run_maintenance,
+            export_changeset,
+            apply_changeset,
+            set_simulation_mode,
+            get_simulation_log,
+            preview_tag_cleanup,
+            preview_track_markers,
+            backfill_genres,
+            get_genre_mapping,
+            save_genre_mapping,
+            apply_tag_transform,
+            clean_ape_tags,
+            split_cue_image,
+            convert_files,
+            export_to_usb,
+            import_archive,
+            guess_from_filenames,
+            library_lock_status,
+            break_library_lock,
             save_settings,
             load_settings
         ])
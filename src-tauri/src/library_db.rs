@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+
+use crate::artwork::ArtworkCandidate;
+use crate::provenance::Provenance;
+use crate::settings::get_data_path;
+
+const LIBRARY_DB_FILE: &str = "library.json";
+
+// Serializes read-modify-write cycles on the database file.
+static LIBRARY_DB_LOCK: Mutex<()> = Mutex::new(());
+
+/// A user-chosen provider match that re-runs must reuse instead of searching.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderPin {
+    pub source: String,
+    pub track_id: String,
+    pub pinned_at: u64,
+}
+
+/// Per-library state that is not stored in the audio files themselves,
+/// kept as JSON in the app data dir.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LibraryDb {
+    #[serde(default, with = "crate::path_codec::keys")]
+    pub pins: HashMap<PathBuf, ProviderPin>,
+    /// Files whose artwork match was too uncertain to embed unattended,
+    /// with the covers on offer.
+    #[serde(default, with = "crate::path_codec::keys")]
+    pub artwork_reviews: HashMap<PathBuf, Vec<ArtworkCandidate>>,
+    /// The match each file was last tagged from, whether or not it was also
+    /// written into the file.
+    #[serde(default, with = "crate::path_codec::keys")]
+    pub processed: HashMap<PathBuf, Provenance>,
+    /// Where files the app moved or renamed went, old path to new, so
+    /// backups left at the old path can be found again.
+    #[serde(default, with = "crate::path_codec::map")]
+    pub moves: HashMap<PathBuf, PathBuf>,
+    /// How often each genre was confirmed for an artist, keyed by the
+    /// artist in lowercase.
+    #[serde(default)]
+    pub artist_genres: HashMap<String, HashMap<String, u32>>,
+    /// Fields of each file corrected by hand, which processing leaves as
+    /// they are until unlocked. Names as in the field scope.
+    #[serde(default, with = "crate::path_codec::keys")]
+    pub locked_fields: HashMap<PathBuf, BTreeSet<String>>,
+}
+
+impl LibraryDb {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(LibraryDb::default());
+        }
+
+        let json = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read library database: {}", e))?;
+
+        serde_json::from_str(&json)
+            .map_err(|e| format!("Failed to parse library database: {}", e))
+    }
+
+    /// Carries per-file entries over to a file's new path, keeping the old
+    /// entry too when the original still exists.
+    pub fn move_path(&mut self, from: &Path, to: &Path, keep_original: bool) {
+        let pin = if keep_original {
+            self.pins.get(from).cloned()
+        } else {
+            self.pins.remove(from)
+        };
+        if let Some(pin) = pin {
+            self.pins.insert(to.to_path_buf(), pin);
+        }
+
+        let review = if keep_original {
+            self.artwork_reviews.get(from).cloned()
+        } else {
+            self.artwork_reviews.remove(from)
+        };
+        if let Some(review) = review {
+            self.artwork_reviews.insert(to.to_path_buf(), review);
+        }
+
+        let processed = if keep_original {
+            self.processed.get(from).cloned()
+        } else {
+            self.processed.remove(from)
+        };
+        if let Some(processed) = processed {
+            self.processed.insert(to.to_path_buf(), processed);
+        }
+
+        let locked = if keep_original {
+            self.locked_fields.get(from).cloned()
+        } else {
+            self.locked_fields.remove(from)
+        };
+        if let Some(locked) = locked {
+            self.locked_fields.insert(to.to_path_buf(), locked);
+        }
+
+        if !keep_original {
+            self.moves.insert(from.to_path_buf(), to.to_path_buf());
+        }
+    }
+
+    /// The path a file moved from `path` ended up at, following moves in
+    /// turn; `None` if it was never moved.
+    pub fn relocated(&self, path: &Path) -> Option<PathBuf> {
+        let mut current = self.moves.get(path)?;
+        // Bounded so a move back and forth cannot loop forever.
+        for _ in 0..self.moves.len() {
+            match self.moves.get(current) {
+                Some(next) => current = next,
+                None => break,
+            }
+        }
+        Some(current.clone())
+    }
+
+    /// Counts one more confirmation of `genre` for `artist`.
+    pub fn confirm_genre(&mut self, artist: &str, genre: &str) {
+        let (artist, genre) = (artist.trim(), genre.trim());
+        if artist.is_empty() || genre.is_empty() {
+            return;
+        }
+        *self.artist_genres
+            .entry(artist.to_lowercase())
+            .or_default()
+            .entry(genre.to_string())
+            .or_insert(0) += 1;
+    }
+
+    /// The genre confirmed for `artist` at least `min_confirmations` times,
+    /// if it also accounts for most of their confirmations; artists spread
+    /// across genres get no suggestion.
+    pub fn remembered_genre(&self, artist: &str, min_confirmations: u32) -> Option<String> {
+        if min_confirmations == 0 {
+            return None;
+        }
+        let genres = self.artist_genres.get(&artist.trim().to_lowercase())?;
+        let total: u32 = genres.values().sum();
+        let (genre, count) = genres
+            .iter()
+            .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))?;
+        (*count >= min_confirmations && *count * 2 > total).then(|| genre.clone())
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize library database: {}", e))?;
+
+        fs::write(path, json)
+            .map_err(|e| format!("Failed to write library database: {}", e))
+    }
+}
+
+pub fn db_path(app: &AppHandle) -> Result<PathBuf, String> {
+    get_data_path(app, LIBRARY_DB_FILE)
+}
+
+pub fn read(app: &AppHandle) -> Result<LibraryDb, String> {
+    let _guard = LIBRARY_DB_LOCK.lock().unwrap();
+    LibraryDb::load(&db_path(app)?)
+}
+
+/// Loads the database, applies `change` and saves it back atomically with
+/// respect to other commands in this process.
+pub fn update<T, F>(app: &AppHandle, change: F) -> Result<T, String>
+where
+    F: FnOnce(&mut LibraryDb) -> T,
+{
+    let _guard = LIBRARY_DB_LOCK.lock().unwrap();
+    let path = db_path(app)?;
+    let mut db = LibraryDb::load(&path)?;
+    let result = change(&mut db);
+    db.save(&path)?;
+    Ok(result)
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
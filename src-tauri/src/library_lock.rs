@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::simulation;
+
+const LOCK_FILENAME: &str = ".autogenre.lock";
+
+// How often a held lock's heartbeat is refreshed.
+const HEARTBEAT_SECS: u64 = 30;
+// Locks whose heartbeat is older than this are assumed to belong to a
+// crashed or disconnected machine and are taken over.
+const STALE_LOCK_SECS: u64 = 4 * HEARTBEAT_SECS;
+// Lock files from versions without a heartbeat only have the time they
+// were taken, so they get the old, longer grace period.
+const STALE_LEGACY_LOCK_SECS: u64 = 15 * 60;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockOwner {
+    pub hostname: String,
+    pub pid: u32,
+    pub acquired_at: u64,
+    /// Refreshed every `HEARTBEAT_SECS` while the lock is held; 0 in lock
+    /// files from versions without a heartbeat.
+    #[serde(default)]
+    pub heartbeat_at: u64,
+}
+
+/// Advisory lock on a library folder shared between machines (e.g. on a NAS).
+/// A background thread keeps its heartbeat fresh, so a long organize is not
+/// taken for a crashed one. The lock file is removed when the guard is
+/// dropped.
+pub struct LibraryLock {
+    path: Option<PathBuf>,
+    // Dropping the sender stops the heartbeat thread.
+    heartbeat: Option<(Sender<()>, JoinHandle<()>)>,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn hostname() -> String {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown host".to_string())
+}
+
+fn lock_path(library_root: &Path) -> PathBuf {
+    library_root.join(LOCK_FILENAME)
+}
+
+/// The folder whose lock guards `folder`: the outermost configured library
+/// folder containing it, or the folder itself. Organize, maintenance and
+/// folder merges all lock through this, so they exclude each other even
+/// when one works on a subfolder.
+pub fn lock_root(folder: &Path, library_folders: &[String]) -> PathBuf {
+    let canonical = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let target = canonical(folder);
+    library_folders
+        .iter()
+        .map(|root| canonical(Path::new(root)))
+        .filter(|root| target.starts_with(root))
+        .min_by_key(|root| root.components().count())
+        .unwrap_or(target)
+}
+
+/// Locks the folder guarding each of `folders`, once each, for commands
+/// that touch files across several library folders. Nothing stays locked
+/// when any of them is in use.
+pub fn acquire_all<'a>(folders: impl IntoIterator<Item = &'a Path>, library_folders: &[String]) -> Result<Vec<LibraryLock>, String> {
+    let roots: BTreeSet<PathBuf> = folders
+        .into_iter()
+        .map(|folder| lock_root(folder, library_folders))
+        .collect();
+    roots.iter().map(|root| LibraryLock::acquire(root)).collect()
+}
+
+pub fn read_lock(library_root: &Path) -> Option<LockOwner> {
+    fs::read_to_string(lock_path(library_root))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+}
+
+pub fn break_lock(library_root: &Path) -> Result<(), String> {
+    match fs::remove_file(lock_path(library_root)) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Failed to remove library lock: {}", e)),
+    }
+}
+
+fn in_use_error(owner: &LockOwner) -> String {
+    format!(
+        "Library in use by {} (pid {}) since {}",
+        owner.hostname, owner.pid, owner.acquired_at
+    )
+}
+
+fn is_stale(owner: &LockOwner) -> bool {
+    if owner.heartbeat_at == 0 {
+        return now_secs().saturating_sub(owner.acquired_at) > STALE_LEGACY_LOCK_SECS;
+    }
+    now_secs().saturating_sub(owner.heartbeat_at) > STALE_LOCK_SECS
+}
+
+fn write_owner(path: &Path, owner: &LockOwner) -> Result<(), String> {
+    let json = serde_json::to_string(owner)
+        .map_err(|e| format!("Failed to serialize lock owner: {}", e))?;
+    fs::write(path, json).map_err(|e| format!("Failed to write library lock: {}", e))
+}
+
+/// Refreshes the heartbeat until the lock is released, or stops when the
+/// lock file no longer names this owner because someone broke the lock.
+fn spawn_heartbeat(path: PathBuf, library_root: PathBuf, mut owner: LockOwner) -> (Sender<()>, JoinHandle<()>) {
+    let (stop, stopped) = mpsc::channel::<()>();
+    let handle = std::thread::spawn(move || {
+        while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(Duration::from_secs(HEARTBEAT_SECS)) {
+            if read_lock(&library_root).as_ref() != Some(&owner) {
+                return;
+            }
+            owner.heartbeat_at = now_secs();
+            if write_owner(&path, &owner).is_err() {
+                return;
+            }
+        }
+    });
+    (stop, handle)
+}
+
+impl LibraryLock {
+    pub fn acquire(library_root: &Path) -> Result<Self, String> {
+        if simulation::is_enabled() {
+            return match read_lock(library_root) {
+                Some(existing) if !is_stale(&existing) => Err(in_use_error(&existing)),
+                _ => Ok(LibraryLock { path: None, heartbeat: None }),
+            };
+        }
+
+        let path = lock_path(library_root);
+        let owner = LockOwner {
+            hostname: hostname(),
+            pid: std::process::id(),
+            acquired_at: now_secs(),
+            heartbeat_at: now_secs(),
+        };
+
+        for _ in 0..2 {
+            let file = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path);
+
+            match file {
+                Ok(mut file) => {
+                    let json = serde_json::to_string(&owner)
+                        .map_err(|e| format!("Failed to serialize lock owner: {}", e))?;
+                    if let Err(e) = file.write_all(json.as_bytes()) {
+                        let _ = fs::remove_file(&path);
+                        return Err(format!("Failed to write library lock: {}", e));
+                    }
+                    let heartbeat = spawn_heartbeat(path.clone(), library_root.to_path_buf(), owner);
+                    return Ok(LibraryLock { path: Some(path), heartbeat: Some(heartbeat) });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    match read_lock(library_root) {
+                        Some(existing) if !is_stale(&existing) => {
+                            return Err(in_use_error(&existing));
+                        }
+                        Some(_) => break_lock(library_root)?,
+                        // Unreadable lock: another machine may be mid-write, so
+                        // only recover it once the file itself is old.
+                        None => {
+                            let modified = fs::metadata(&path)
+                                .and_then(|m| m.modified())
+                                .ok()
+                                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                                .map(|d| d.as_secs())
+                                .unwrap_or(0);
+                            if now_secs().saturating_sub(modified) <= STALE_LOCK_SECS {
+                                return Err("Library is being locked by another machine".to_string());
+                            }
+                            break_lock(library_root)?;
+                        }
+                    }
+                }
+                Err(e) => return Err(format!("Failed to create library lock: {}", e)),
+            }
+        }
+
+        Err("Failed to acquire library lock".to_string())
+    }
+}
+
+impl Drop for LibraryLock {
+    fn drop(&mut self) {
+        // The heartbeat has to stop first, or it could write the file back.
+        if let Some((stop, handle)) = self.heartbeat.take() {
+            drop(stop);
+            let _ = handle.join();
+        }
+        if let Some(path) = &self.path {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
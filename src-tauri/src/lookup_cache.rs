@@ -0,0 +1,196 @@
+use deunicode::deunicode;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+
+use crate::api_client::MetadataResult;
+use crate::settings::get_data_path;
+
+const LOOKUP_CACHE_FILE: &str = "lookup_cache.jsonl";
+
+// Loaded on first use and kept for the rest of the session.
+static LOOKUP_CACHE: Mutex<Option<LookupCache>> = Mutex::new(None);
+
+/// A provider's search outcome for one artist and title. `result` is `None`
+/// when the provider had no match, which is worth remembering too.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub source: String,
+    pub key: String,
+    pub result: Option<MetadataResult>,
+    pub cached_at: u64,
+}
+
+/// Search outcomes by source and normalized artist and title, kept in the
+/// app data dir as one JSON entry per line so each store only appends.
+struct LookupCache {
+    path: PathBuf,
+    entries: HashMap<(String, String), CacheEntry>,
+    /// Lines in the file, counting ones later entries have replaced.
+    lines: usize,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Artist and title folded to ASCII lowercase words, so "Beyoncé – Halo"
+/// and "beyonce - halo " share an entry.
+pub fn cache_key(artist: &str, title: &str) -> String {
+    let fold = |s: &str| {
+        deunicode(s)
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    format!("{}\t{}", fold(artist), fold(title))
+}
+
+impl LookupCache {
+    /// Reads the cache file, skipping unreadable lines, and rewrites it
+    /// without replaced entries once they make up most of it.
+    fn load(path: PathBuf) -> Result<Self, String> {
+        let mut cache = LookupCache { path, entries: HashMap::new(), lines: 0 };
+        if !cache.path.exists() {
+            return Ok(cache);
+        }
+
+        let data = fs::read_to_string(&cache.path)
+            .map_err(|e| format!("Failed to read lookup cache: {}", e))?;
+        for line in data.lines() {
+            if let Ok(entry) = serde_json::from_str::<CacheEntry>(line) {
+                cache.lines += 1;
+                cache.entries.insert((entry.source.clone(), entry.key.clone()), entry);
+            }
+        }
+
+        if cache.lines > cache.entries.len() * 2 {
+            cache.compact()?;
+        }
+        Ok(cache)
+    }
+
+    /// Every entry, one JSON line each.
+    fn to_lines(&self) -> Result<String, String> {
+        let mut data = String::new();
+        for entry in self.entries.values() {
+            let line = serde_json::to_string(entry)
+                .map_err(|e| format!("Failed to serialize lookup cache: {}", e))?;
+            data.push_str(&line);
+            data.push('\n');
+        }
+        Ok(data)
+    }
+
+    fn compact(&mut self) -> Result<(), String> {
+        fs::write(&self.path, self.to_lines()?)
+            .map_err(|e| format!("Failed to write lookup cache: {}", e))?;
+        self.lines = self.entries.len();
+        Ok(())
+    }
+
+    fn append(&mut self, entry: CacheEntry) -> Result<(), String> {
+        let mut line = serde_json::to_string(&entry)
+            .map_err(|e| format!("Failed to serialize lookup cache: {}", e))?;
+        line.push('\n');
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|e| format!("Failed to write lookup cache: {}", e))?;
+
+        self.lines += 1;
+        self.entries.insert((entry.source.clone(), entry.key.clone()), entry);
+        Ok(())
+    }
+}
+
+fn with_cache<T>(app: &AppHandle, f: impl FnOnce(&mut LookupCache) -> Result<T, String>) -> Result<T, String> {
+    let mut guard = LOOKUP_CACHE.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(LookupCache::load(get_data_path(app, LOOKUP_CACHE_FILE)?)?);
+    }
+    f(guard.as_mut().unwrap())
+}
+
+/// The cached outcome of `source`'s search for `key`, if it is younger than
+/// `max_age_days`. `Some(None)` means the provider had no match.
+pub fn get(app: &AppHandle, source: &str, key: &str, max_age_days: u64) -> Option<Option<MetadataResult>> {
+    let max_age = max_age_days.saturating_mul(24 * 60 * 60);
+    with_cache(app, |cache| {
+        Ok(cache.entries
+            .get(&(source.to_string(), key.to_string()))
+            .filter(|entry| now_secs().saturating_sub(entry.cached_at) < max_age)
+            .map(|entry| entry.result.clone()))
+    })
+    .ok()
+    .flatten()
+}
+
+pub fn put(app: &AppHandle, source: &str, key: &str, result: Option<MetadataResult>) -> Result<(), String> {
+    with_cache(app, |cache| {
+        cache.append(CacheEntry {
+            source: source.to_string(),
+            key: key.to_string(),
+            result,
+            cached_at: now_secs(),
+        })
+    })
+}
+
+/// Writes every cached outcome to `output_path`, in the cache's own format,
+/// for `import` on another machine. Returns how many were written.
+pub fn export(app: &AppHandle, output_path: &Path) -> Result<usize, String> {
+    with_cache(app, |cache| {
+        fs::write(output_path, cache.to_lines()?)
+            .map_err(|e| format!("Failed to write lookup cache export: {}", e))?;
+        Ok(cache.entries.len())
+    })
+}
+
+/// Merges an exported cache into this one. Entries keep the age they had,
+/// and replace local ones only when newer; unreadable lines are skipped.
+/// Returns how many entries were taken.
+pub fn import(app: &AppHandle, input_path: &Path) -> Result<usize, String> {
+    let data = fs::read_to_string(input_path)
+        .map_err(|e| format!("Failed to read lookup cache export: {}", e))?;
+
+    with_cache(app, |cache| {
+        let mut imported = 0;
+        for entry in data.lines().filter_map(|line| serde_json::from_str::<CacheEntry>(line).ok()) {
+            let newer = cache.entries
+                .get(&(entry.source.clone(), entry.key.clone()))
+                .is_none_or(|existing| existing.cached_at < entry.cached_at);
+            if newer {
+                cache.entries.insert((entry.source.clone(), entry.key.clone()), entry);
+                imported += 1;
+            }
+        }
+        if imported > 0 {
+            cache.compact()?;
+        }
+        Ok(imported)
+    })
+}
+
+/// Forgets every cached outcome. Returns how many there were.
+pub fn clear(app: &AppHandle) -> Result<usize, String> {
+    with_cache(app, |cache| {
+        let count = cache.entries.len();
+        cache.entries.clear();
+        cache.compact()?;
+        Ok(count)
+    })
+}
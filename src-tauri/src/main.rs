@@ -2,5 +2,8 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    if autogenre_pro_lib::run_analysis_worker() {
+        return;
+    }
     autogenre_pro_lib::run()
 }
@@ -0,0 +1,207 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter};
+
+use crate::backup;
+use crate::library_lock::LibraryLock;
+use crate::scanner::{self, FileScanner, RootOverlap};
+use crate::settings::{self, get_data_path, load_settings, AppSettings};
+
+const SNAPSHOT_FILE: &str = "library_snapshot.json";
+const SCHEDULER_POLL_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MaintenanceSummary {
+    pub started_at: u64,
+    pub finished_at: u64,
+    pub new_files: usize,
+    pub changed_files: usize,
+    pub removed_files: usize,
+    pub pruned_backups: usize,
+    #[serde(with = "crate::path_codec::vec")]
+    pub unreadable_files: Vec<PathBuf>,
+    #[serde(default)]
+    pub overlapping_roots: Vec<RootOverlap>,
+    pub errors: Vec<String>,
+}
+
+/// Modification times of every library file seen by the last run, used to
+/// make the rescan incremental.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LibrarySnapshot {
+    last_run: u64,
+    #[serde(with = "crate::path_codec::keys")]
+    files: HashMap<PathBuf, u64>,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn modified_secs(path: &Path) -> Option<u64> {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+}
+
+fn load_snapshot(path: &Path) -> LibrarySnapshot {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_snapshot(path: &Path, snapshot: &LibrarySnapshot) -> Result<(), String> {
+    let json = serde_json::to_string(snapshot)
+        .map_err(|e| format!("Failed to serialize library snapshot: {}", e))?;
+
+    fs::write(path, json)
+        .map_err(|e| format!("Failed to write library snapshot: {}", e))
+}
+
+/// Rescans the library, checking only new or modified files for readable
+/// tags, then prunes backups older than the retention period.
+pub fn run_maintenance(settings: &AppSettings, snapshot_path: &Path) -> MaintenanceSummary {
+    let scanner = FileScanner::new();
+    let previous = load_snapshot(snapshot_path);
+    let mut summary = MaintenanceSummary {
+        started_at: now_secs(),
+        ..Default::default()
+    };
+    let mut current = LibrarySnapshot::default();
+
+    // Nested library folders would be walked and locked twice.
+    let configured: Vec<PathBuf> = settings.library_folders.iter().map(PathBuf::from).collect();
+    let (roots, overlaps) = scanner::collapse_roots(&configured);
+    summary.overlapping_roots = overlaps;
+
+    for root in &roots {
+        let root = root.as_path();
+        if !root.is_dir() {
+            summary.errors.push(format!("Library folder not found: {}", root.display()));
+            carry_over(&previous, root, &mut current);
+            continue;
+        }
+
+        let _lock = match LibraryLock::acquire(root) {
+            Ok(lock) => lock,
+            Err(e) => {
+                summary.errors.push(format!("Skipped {}: {}", root.display(), e));
+                carry_over(&previous, root, &mut current);
+                continue;
+            }
+        };
+
+        for path in scanner.list_audio_files(root) {
+            let modified = modified_secs(&path).unwrap_or(0);
+
+            let needs_check = match previous.files.get(&path) {
+                None => {
+                    summary.new_files += 1;
+                    true
+                }
+                Some(&known) if known != modified => {
+                    summary.changed_files += 1;
+                    true
+                }
+                Some(_) => false,
+            };
+
+            if needs_check && scanner.read_metadata(&path).is_err() {
+                summary.unreadable_files.push(path.clone());
+            }
+
+            current.files.insert(path, modified);
+        }
+
+        summary.pruned_backups += prune_backups(root, settings.backup_retention_days, &mut summary.errors);
+    }
+
+    summary.removed_files = previous.files
+        .keys()
+        .filter(|path| !current.files.contains_key(*path))
+        .count();
+
+    summary.finished_at = now_secs();
+    current.last_run = summary.finished_at;
+
+    if let Err(e) = save_snapshot(snapshot_path, &current) {
+        summary.errors.push(e);
+    }
+
+    summary
+}
+
+/// Keeps what the last run saw under a root skipped this time, so its files
+/// are neither counted as removed now nor as new on the next run.
+fn carry_over(previous: &LibrarySnapshot, root: &Path, current: &mut LibrarySnapshot) {
+    let known = previous.files.iter().filter(|(path, _)| path.starts_with(root));
+    current.files.extend(known.map(|(path, &modified)| (path.clone(), modified)));
+}
+
+fn prune_backups(root: &Path, retention_days: u64, errors: &mut Vec<String>) -> usize {
+    if retention_days == 0 {
+        return 0;
+    }
+
+    let cutoff = now_secs().saturating_sub(retention_days * 24 * 60 * 60);
+    let deletion = backup::delete_older_than(&[root.to_path_buf()], cutoff);
+    errors.extend(deletion.errors);
+    deletion.deleted
+}
+
+pub fn run_for_app(app: &AppHandle) -> Result<MaintenanceSummary, String> {
+    let settings = load_settings(app.clone())?;
+    let snapshot_path = get_data_path(app, SNAPSHOT_FILE)?;
+    Ok(run_maintenance(&settings, &snapshot_path))
+}
+
+/// Polls the configured interval, and re-checks as soon as settings are
+/// saved, so changes take effect without a restart. Emits
+/// `maintenance://summary` after each scheduled run.
+pub fn spawn_scheduler(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs(SCHEDULER_POLL_SECS)) => {}
+                _ = settings::changed() => {}
+            }
+
+            let interval_hours = match load_settings(app.clone()) {
+                Ok(settings) if settings.maintenance_interval_hours > 0 => settings.maintenance_interval_hours,
+                _ => continue,
+            };
+
+            let last_run = match get_data_path(&app, SNAPSHOT_FILE) {
+                Ok(path) => load_snapshot(&path).last_run,
+                Err(_) => continue,
+            };
+
+            if now_secs() < last_run + interval_hours * 60 * 60 {
+                continue;
+            }
+
+            let handle = app.clone();
+            let result = tauri::async_runtime::spawn_blocking(move || run_for_app(&handle)).await;
+            match result {
+                Ok(Ok(summary)) => {
+                    let _ = app.emit("maintenance://summary", summary);
+                }
+                Ok(Err(e)) => {
+                    let _ = app.emit("maintenance://error", e);
+                }
+                Err(e) => {
+                    let _ = app.emit("maintenance://error", format!("Maintenance task failed: {}", e));
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,169 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::path::{Path, PathBuf};
+
+// Marks an encoded path. No path can contain it, so a plain path is never
+// mistaken for one.
+const ENCODED_PREFIX: char = '\0';
+
+/// The path as a string for the frontend. A path that is not valid UTF-8
+/// (old Linux rips, mostly) gets `ENCODED_PREFIX` and has its invalid bytes,
+/// and any `%`, written as `%XX`, so `decode` gives back the same bytes.
+#[cfg(unix)]
+pub fn encode(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    let bytes = path.as_os_str().as_bytes();
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return text.to_string();
+    }
+
+    let mut encoded = String::with_capacity(bytes.len() + 1);
+    encoded.push(ENCODED_PREFIX);
+    for chunk in bytes.utf8_chunks() {
+        encoded.push_str(&chunk.valid().replace('%', "%25"));
+        for byte in chunk.invalid() {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Windows paths are UTF-16 and only lose unpaired surrogates, which no
+/// ripper produces, so they are sent as they are.
+#[cfg(not(unix))]
+pub fn encode(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+/// The path `encode` turned into `value`; any other string is taken as the
+/// path it spells.
+#[cfg(unix)]
+pub fn decode(value: &str) -> PathBuf {
+    use std::ffi::OsString;
+    use std::os::unix::ffi::OsStringExt;
+
+    let Some(encoded) = value.strip_prefix(ENCODED_PREFIX) else {
+        return PathBuf::from(value);
+    };
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut rest = encoded.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = (byte == b'%')
+            .then(|| tail.get(..2))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(escaped) => {
+                bytes.push(escaped);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    PathBuf::from(OsString::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+pub fn decode(value: &str) -> PathBuf {
+    PathBuf::from(value)
+}
+
+/// For `#[serde(with = "crate::path_codec")]` on paths the frontend hands
+/// back.
+pub fn serialize<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&encode(path))
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PathBuf, D::Error> {
+    String::deserialize(deserializer).map(|value| decode(&value))
+}
+
+/// The same for an optional path.
+pub mod option {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::path::PathBuf;
+
+    pub fn serialize<S: Serializer>(path: &Option<PathBuf>, serializer: S) -> Result<S::Ok, S::Error> {
+        match path {
+            Some(path) => serializer.serialize_some(&super::encode(path)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<PathBuf>, D::Error> {
+        Option::<String>::deserialize(deserializer).map(|value| value.map(|value| super::decode(&value)))
+    }
+}
+
+// Collection elements and map keys go through these.
+struct Encoded<'a>(&'a Path);
+
+impl Serialize for Encoded<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize(self.0, serializer)
+    }
+}
+
+#[derive(PartialEq, Eq, Hash)]
+struct Decoded(PathBuf);
+
+impl<'de> Deserialize<'de> for Decoded {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize(deserializer).map(Decoded)
+    }
+}
+
+/// The same for a list of paths.
+pub mod vec {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::path::PathBuf;
+
+    use super::{Decoded, Encoded};
+
+    pub fn serialize<S: Serializer>(paths: &[PathBuf], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(paths.iter().map(|path| Encoded(path)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<PathBuf>, D::Error> {
+        Vec::<Decoded>::deserialize(deserializer).map(|paths| paths.into_iter().map(|path| path.0).collect())
+    }
+}
+
+/// The same for the keys of a map keyed by path.
+pub mod keys {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    use super::{Decoded, Encoded};
+
+    pub fn serialize<S: Serializer, V: Serialize>(map: &HashMap<PathBuf, V>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(map.iter().map(|(path, value)| (Encoded(path), value)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, V: Deserialize<'de>>(deserializer: D) -> Result<HashMap<PathBuf, V>, D::Error> {
+        HashMap::<Decoded, V>::deserialize(deserializer)
+            .map(|map| map.into_iter().map(|(path, value)| (path.0, value)).collect())
+    }
+}
+
+/// The same for a map from paths to paths.
+pub mod map {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    use super::{Decoded, Encoded};
+
+    pub fn serialize<S: Serializer>(map: &HashMap<PathBuf, PathBuf>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(map.iter().map(|(from, to)| (Encoded(from), Encoded(to))))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HashMap<PathBuf, PathBuf>, D::Error> {
+        HashMap::<Decoded, Decoded>::deserialize(deserializer)
+            .map(|map| map.into_iter().map(|(from, to)| (from.0, to.0)).collect())
+    }
+}
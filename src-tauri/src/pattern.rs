@@ -0,0 +1,132 @@
+pub const DEFAULT_FILENAME_PATTERN: &str = "{artist} - {title}";
+
+/// Fields folder and filename patterns may use.
+const FIELDS: &[&str] = &[
+    "genre", "artist", "artist_sort", "album_artist", "album", "track", "disc", "composer",
+    "label", "title", "key", "bpm", "release_type", "year", "date", "decade",
+];
+
+// Left empty rather than "Unknown" when missing, as they are usually
+// decoration around other fields.
+const OPTIONAL_FIELDS: &[&str] = &["track", "disc", "key", "bpm"];
+
+/// One `{...}` in a pattern: a field, then optionally `[0]` for its first
+/// letter, `:NN` to zero-pad a number to NN digits, and `|text` to use when
+/// the field is missing (`{album|}` drops it).
+#[derive(Debug, Clone, PartialEq)]
+struct Placeholder<'a> {
+    field: &'a str,
+    first_letter: bool,
+    width: Option<usize>,
+    fallback: Option<&'a str>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment<'a> {
+    Text(&'a str),
+    Field(Placeholder<'a>),
+}
+
+fn parse_placeholder<'a>(inner: &'a str, pattern: &str) -> Result<Placeholder<'a>, String> {
+    let (spec, fallback) = match inner.split_once('|') {
+        Some((spec, fallback)) => (spec, Some(fallback)),
+        None => (inner, None),
+    };
+    let (name, width) = match spec.split_once(':') {
+        Some((name, width)) => {
+            let width = width.parse()
+                .map_err(|_| format!("Invalid width in {{{}}} in pattern: {}", inner, pattern))?;
+            (name, Some(width))
+        }
+        None => (spec, None),
+    };
+    let (field, first_letter) = match name.strip_suffix(']') {
+        Some(indexed) => {
+            let (field, index) = indexed.split_once('[')
+                .ok_or_else(|| format!("Invalid placeholder {{{}}} in pattern: {}", inner, pattern))?;
+            if index != "0" {
+                return Err(format!("Only [0], the first letter, is supported in {{{}}} in pattern: {}", inner, pattern));
+            }
+            (field, true)
+        }
+        None => (name, false),
+    };
+
+    if !FIELDS.contains(&field) {
+        return Err(format!("Unknown placeholder {{{}}} in pattern: {}", field, pattern));
+    }
+    Ok(Placeholder { field, first_letter, width, fallback })
+}
+
+fn parse(pattern: &str) -> Result<Vec<Segment<'_>>, String> {
+    let mut segments = Vec::new();
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        if start > 0 {
+            segments.push(Segment::Text(&rest[..start]));
+        }
+        let end = rest[start..].find('}')
+            .ok_or_else(|| format!("Unclosed placeholder in pattern: {}", pattern))?;
+        segments.push(Segment::Field(parse_placeholder(&rest[start + 1..start + end], pattern)?));
+        rest = &rest[start + end + 1..];
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Text(rest));
+    }
+    Ok(segments)
+}
+
+/// Checks that a pattern parses and, for filename patterns, that it names
+/// no folders.
+pub fn validate(pattern: &str, filename: bool) -> Result<(), String> {
+    if pattern.trim().is_empty() {
+        return Err("Pattern is empty".to_string());
+    }
+    if filename && pattern.contains(['/', '\\']) {
+        return Err(format!("Filename pattern cannot contain folders: {}", pattern));
+    }
+    parse(pattern).map(|_| ())
+}
+
+/// The bucket a value files under: its first letter uppercased, or "#"
+/// when it starts with anything else.
+fn first_letter(value: &str) -> String {
+    match value.trim_start().chars().next() {
+        Some(c) if c.is_alphabetic() => c.to_uppercase().collect(),
+        _ => "#".to_string(),
+    }
+}
+
+/// Fills in `pattern` with `value` giving each field's tag value. Values
+/// other than first letters pass through `sanitize`; fields with no value
+/// and no fallback become `missing`, or nothing for track, disc, key and
+/// BPM.
+pub fn expand(pattern: &str, value: impl Fn(&str) -> Option<String>, sanitize: impl Fn(&str) -> String, missing: &str) -> Result<String, String> {
+    let mut expanded = String::with_capacity(pattern.len());
+    for segment in parse(pattern)? {
+        let placeholder = match segment {
+            Segment::Text(text) => {
+                expanded.push_str(text);
+                continue;
+            }
+            Segment::Field(placeholder) => placeholder,
+        };
+
+        match value(placeholder.field).filter(|v| !v.trim().is_empty()) {
+            Some(value) if placeholder.first_letter => expanded.push_str(&first_letter(&value)),
+            Some(value) => {
+                // Track numbers keep their customary two digits.
+                let width = placeholder.width.or((placeholder.field == "track").then_some(2));
+                let value = match width {
+                    Some(width) if value.chars().all(|c| c.is_ascii_digit()) => format!("{:0>width$}", value),
+                    _ => value,
+                };
+                expanded.push_str(&sanitize(&value));
+            }
+            None => expanded.push_str(placeholder.fallback.unwrap_or(
+                if OPTIONAL_FIELDS.contains(&placeholder.field) { "" } else { missing },
+            )),
+        }
+    }
+    Ok(expanded)
+}
@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::settings::AppSettings;
+
+const DEFAULT_FILE_MODE: u32 = 0o664;
+const DEFAULT_FOLDER_MODE: u32 = 0o775;
+
+/// What ownership and mode files and folders get when organize, rename,
+/// convert or a CUE split puts them in the library. Only applies on Unix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionPolicy {
+    /// Whatever the process umask gives.
+    Umask,
+    /// The source file's mode and owner; new folders take their parent's.
+    /// Ownership is only changed as far as the process is allowed to.
+    Preserve,
+    /// The configured file and folder modes.
+    Fixed,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FilePermissions {
+    pub policy: PermissionPolicy,
+    pub file_mode: u32,
+    pub folder_mode: u32,
+}
+
+impl Default for FilePermissions {
+    fn default() -> Self {
+        FilePermissions {
+            policy: PermissionPolicy::Umask,
+            file_mode: DEFAULT_FILE_MODE,
+            folder_mode: DEFAULT_FOLDER_MODE,
+        }
+    }
+}
+
+impl FilePermissions {
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        FilePermissions {
+            policy: settings.permission_policy,
+            file_mode: parse_mode(&settings.file_mode).unwrap_or(DEFAULT_FILE_MODE),
+            folder_mode: parse_mode(&settings.folder_mode).unwrap_or(DEFAULT_FOLDER_MODE),
+        }
+    }
+
+    /// Applies the policy to `destination`, just moved, copied or encoded
+    /// from a file with the `source` metadata.
+    pub fn apply_to_file(&self, source: &fs::Metadata, destination: &Path) -> Result<(), String> {
+        let mode = match self.policy {
+            PermissionPolicy::Umask => return Ok(()),
+            PermissionPolicy::Preserve => None,
+            PermissionPolicy::Fixed => Some(self.file_mode),
+        };
+        apply(source, destination, mode)
+    }
+
+    /// Creates `dir` and any missing parents, applying the policy to each
+    /// folder created.
+    pub fn create_dir_all(&self, dir: &Path) -> Result<(), String> {
+        let missing: Vec<PathBuf> = dir
+            .ancestors()
+            .take_while(|ancestor| !ancestor.exists())
+            .map(Path::to_path_buf)
+            .collect();
+
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create folder structure: {}", e))?;
+
+        let mode = match self.policy {
+            PermissionPolicy::Umask => return Ok(()),
+            PermissionPolicy::Preserve => None,
+            PermissionPolicy::Fixed => Some(self.folder_mode),
+        };
+        // Outermost first, so each folder copies an already settled parent.
+        for folder in missing.iter().rev() {
+            let Some(parent) = folder.parent() else { continue };
+            let parent = fs::metadata(parent)
+                .map_err(|e| format!("Failed to read permissions of {}: {}", parent.display(), e))?;
+            apply(&parent, folder, mode)?;
+        }
+        Ok(())
+    }
+}
+
+/// An octal mode such as "664" or "0o775"; `None` when it is not one.
+pub fn parse_mode(mode: &str) -> Option<u32> {
+    let mode = mode.trim();
+    let digits = mode.strip_prefix("0o").unwrap_or(mode);
+    u32::from_str_radix(digits, 8).ok().filter(|mode| *mode <= 0o7777)
+}
+
+/// Gives `path` the `mode`, or `source`'s mode and owner when there is none.
+#[cfg(unix)]
+fn apply(source: &fs::Metadata, path: &Path, mode: Option<u32>) -> Result<(), String> {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let permissions = match mode {
+        Some(mode) => fs::Permissions::from_mode(mode),
+        None => {
+            // Only root may give a file away; an ordinary user can still
+            // hand it to a group they belong to.
+            if std::os::unix::fs::chown(path, Some(source.uid()), Some(source.gid())).is_err() {
+                let _ = std::os::unix::fs::chown(path, None, Some(source.gid()));
+            }
+            source.permissions()
+        }
+    };
+    fs::set_permissions(path, permissions)
+        .map_err(|e| format!("Failed to set permissions of {}: {}", path.display(), e))
+}
+
+#[cfg(not(unix))]
+fn apply(_source: &fs::Metadata, _path: &Path, _mode: Option<u32>) -> Result<(), String> {
+    Ok(())
+}
@@ -0,0 +1,209 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use crate::changeset::ChangeOutcome;
+use crate::convert;
+use crate::field_scope;
+use crate::library_lock::{lock_root, LibraryLock};
+use crate::scanner::{Collision, DuplicateKept, FileScanner, Metadata, OrganizeMode, Placement};
+use crate::settings::AppSettings;
+use crate::workdir;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessResult {
+    #[serde(with = "crate::path_codec")]
+    pub original_path: PathBuf,
+    #[serde(with = "crate::path_codec")]
+    pub final_path: PathBuf,
+    #[serde(default, with = "crate::path_codec::option")]
+    pub backup_path: Option<PathBuf>,
+    pub converted: bool,
+    pub renamed: bool,
+    pub organized: bool,
+    /// Organizing found the file already where the pattern puts it.
+    #[serde(default)]
+    pub already_organized: bool,
+    /// Set when renaming or organizing met a different file at the
+    /// destination; organizing's when both did.
+    #[serde(default)]
+    pub collision: Option<Collision>,
+    /// The tags written once the field scope was applied.
+    pub metadata: Metadata,
+    /// The steps run after the tags were written, in order. Only the last
+    /// can have failed, and the file is then left at `final_path`.
+    #[serde(default)]
+    pub steps: Vec<StepOutcome>,
+}
+
+/// A step `process_file` takes once the tags are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessStep {
+    Convert,
+    Rename,
+    Organize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepOutcome {
+    pub step: ProcessStep,
+    pub outcome: ChangeOutcome,
+}
+
+impl ProcessResult {
+    fn renamed(&mut self, placement: Placement) {
+        self.renamed = placement.collision != Some(Collision::Skipped);
+        self.collision = placement.collision.or(self.collision);
+        self.final_path = placement.path;
+    }
+
+    fn applied(&mut self, step: ProcessStep) {
+        self.steps.push(StepOutcome { step, outcome: ChangeOutcome::Applied });
+    }
+
+    /// The error of the step that failed, if one did.
+    pub fn failure(&self) -> Option<String> {
+        self.steps.iter().find_map(|step| match &step.outcome {
+            ChangeOutcome::Failed(e) | ChangeOutcome::Conflict(e) => Some(e.clone()),
+            ChangeOutcome::Applied => None,
+        })
+    }
+}
+
+fn has_genre(metadata: &Metadata) -> bool {
+    metadata.genre.as_deref().is_some_and(|genre| !genre.trim().is_empty())
+}
+
+/// Writes tags, converts the format if a conversion rule matches, and then
+/// renames/organizes the file as configured in settings, so every caller
+/// (UI, batch jobs, watch folders) behaves the same. Fields the field scope
+/// protects, and the file's `locked` fields, keep their current values
+/// whatever the caller sends; a file left with no genre gets the configured
+/// fallback.
+/// Organizing uses `base_folder`, falling back to the first library folder.
+/// Only a failure to back up or write the tags is an error; a later step
+/// that fails is reported in `steps`, with the file left where it got to.
+pub fn process_file(
+    scanner: &FileScanner,
+    settings: &AppSettings,
+    path: &Path,
+    metadata: &Metadata,
+    locked: &BTreeSet<String>,
+    base_folder: Option<&Path>,
+) -> Result<ProcessResult, String> {
+    let backup_path = if settings.backup_before_changes {
+        Some(scanner.backup_current_metadata(path)?)
+    } else {
+        None
+    };
+
+    let mut proposed = metadata.clone();
+    let fallback_genre = if has_genre(&proposed) { None } else { scanner.fallback_genre(path) };
+    let metadata = if settings.field_scope.is_empty() && locked.is_empty() && fallback_genre.is_none() {
+        proposed
+    } else {
+        let current = scanner.read_metadata(path)?;
+        // The fallback never replaces a genre the file already has.
+        if !has_genre(&current) {
+            proposed.genre = proposed.genre.or(fallback_genre);
+        }
+        field_scope::reconcile(&settings.field_scope, locked, &current, &proposed)
+    };
+    scanner.write_metadata(path, &metadata)?;
+
+    let mut result = ProcessResult {
+        original_path: path.to_path_buf(),
+        final_path: path.to_path_buf(),
+        backup_path,
+        converted: false,
+        renamed: false,
+        organized: false,
+        already_organized: false,
+        collision: None,
+        metadata: Metadata::default(),
+        steps: Vec::new(),
+    };
+
+    if let Err((step, e)) = place(scanner, settings, &metadata, base_folder, &mut result) {
+        result.steps.push(StepOutcome { step, outcome: ChangeOutcome::Failed(e) });
+    }
+    result.metadata = metadata;
+    Ok(result)
+}
+
+/// Converts, renames and organizes the tagged file as configured, recording
+/// each step in `result`. Stops at the first step that fails.
+fn place(
+    scanner: &FileScanner,
+    settings: &AppSettings,
+    metadata: &Metadata,
+    base_folder: Option<&Path>,
+    result: &mut ProcessResult,
+) -> Result<(), (ProcessStep, String)> {
+    let extension = result.final_path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if let Some(target_format) = settings.conversion_rules.get(&extension) {
+        result.final_path = convert(scanner, settings, &result.final_path, &extension, target_format)
+            .map_err(|e| (ProcessStep::Convert, e))?;
+        result.converted = true;
+        result.applied(ProcessStep::Convert);
+    }
+
+    // A copy leaves the source as it was, so only the copy is renamed.
+    let copy_first = settings.organize_files && settings.organize_mode == OrganizeMode::Copy;
+    if settings.rename_files && !copy_first {
+        let placement = scanner.rename_file(&result.final_path, metadata)
+            .map_err(|e| (ProcessStep::Rename, e))?;
+        result.renamed(placement);
+        result.applied(ProcessStep::Rename);
+    }
+
+    // When organizing made no copy, `final_path` is the source or a file
+    // already in the library, neither of which is renamed.
+    let mut no_copy = false;
+    if settings.organize_files {
+        let placement = organize(scanner, settings, &result.final_path, metadata, base_folder)
+            .map_err(|e| (ProcessStep::Organize, e))?;
+        no_copy = placement.collision == Some(Collision::Skipped)
+            || placement.duplicate == Some(DuplicateKept::Existing);
+        result.final_path = placement.path;
+        result.organized = placement.collision != Some(Collision::Skipped);
+        result.already_organized = placement.already_in_place;
+        result.collision = placement.collision.or(result.collision);
+        result.applied(ProcessStep::Organize);
+    }
+
+    if settings.rename_files && copy_first && !no_copy {
+        let placement = scanner.rename_file(&result.final_path, metadata)
+            .map_err(|e| (ProcessStep::Rename, e))?;
+        result.renamed(placement);
+        result.applied(ProcessStep::Rename);
+    }
+
+    Ok(())
+}
+
+fn convert(scanner: &FileScanner, settings: &AppSettings, path: &Path, extension: &str, target_format: &str) -> Result<PathBuf, String> {
+    let ffmpeg = convert::ffmpeg_binary(settings)
+        .ok_or_else(|| format!("Converting {} files requires an ffmpeg path in settings", extension))?;
+    convert::convert_file(
+        &ffmpeg,
+        scanner,
+        path,
+        target_format,
+        settings.keep_converted_originals,
+        &workdir::ensure(settings, "convert")?,
+    )
+}
+
+fn organize(scanner: &FileScanner, settings: &AppSettings, path: &Path, metadata: &Metadata, base_folder: Option<&Path>) -> Result<Placement, String> {
+    let base = base_folder
+        .map(Path::to_path_buf)
+        .or_else(|| settings.library_folders.first().map(PathBuf::from))
+        .ok_or("Organizing is enabled but no base folder is configured")?;
+
+    let _lock = LibraryLock::acquire(&lock_root(&base, &settings.library_folders))?;
+    scanner.organize_file(path, metadata, &base, &settings.folder_pattern)
+}
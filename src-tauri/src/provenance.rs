@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::api_client::Confidence;
+
+/// Where the app's values for a file came from, written as one compact
+/// custom tag (`genre=Techno;src=Beatport;conf=High;date=2024-05-01`) so
+/// machine-assigned values can be told apart from hand-curated ones later.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    pub genre: Option<String>,
+    pub source: String,
+    pub confidence: Confidence,
+    /// YYYY-MM-DD; filled with today's date when written if empty.
+    #[serde(default)]
+    pub date: String,
+}
+
+impl Provenance {
+    /// A copy dated today if it has no date yet.
+    pub fn stamped(&self) -> Self {
+        let mut stamped = self.clone();
+        if stamped.date.is_empty() {
+            stamped.date = today();
+        }
+        stamped
+    }
+
+    pub fn encode(&self) -> String {
+        // ';' and '=' delimit the fields, so they cannot appear in values.
+        let clean = |value: &str| value.replace([';', '='], ",");
+
+        let mut fields = Vec::new();
+        if let Some(genre) = &self.genre {
+            fields.push(format!("genre={}", clean(genre)));
+        }
+        fields.push(format!("src={}", clean(&self.source)));
+        fields.push(format!("conf={:?}", self.confidence));
+        fields.push(format!("date={}", clean(&self.stamped().date)));
+        fields.join(";")
+    }
+
+    /// Reads an encoded tag value; unknown fields are ignored so older
+    /// builds can read tags written by newer ones.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut genre = None;
+        let mut source = None;
+        let mut confidence = None;
+        let mut date = String::new();
+
+        for field in value.split(';') {
+            let Some((key, value)) = field.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim() {
+                "genre" => genre = Some(value.to_string()),
+                "src" => source = Some(value.to_string()),
+                "conf" => {
+                    confidence = match value {
+                        "High" => Some(Confidence::High),
+                        "Medium" => Some(Confidence::Medium),
+                        "Low" => Some(Confidence::Low),
+                        _ => None,
+                    }
+                }
+                "date" => date = value.to_string(),
+                _ => {}
+            }
+        }
+
+        Some(Provenance {
+            genre,
+            source: source?,
+            confidence: confidence?,
+            date,
+        })
+    }
+}
+
+/// Today's UTC date as YYYY-MM-DD.
+fn today() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// Howard Hinnant's days-to-civil algorithm for the proleptic Gregorian
+// calendar, counting from 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
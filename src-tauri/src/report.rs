@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use crate::scanner::{AudioFile, DuplicateStrategy, FileScanner, Metadata};
+use crate::stats::SessionStats;
+
+const REPORT_STYLE: &str = r#"
+body { font-family: -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; color: #111; margin: 2em; }
+h1 { font-size: 1.6em; margin-bottom: 0.2em; }
+h2 { font-size: 1.2em; border-bottom: 1px solid #ccc; padding-bottom: 0.2em; margin-top: 2em; }
+table { border-collapse: collapse; width: 100%; font-size: 0.9em; }
+th, td { text-align: left; padding: 0.25em 0.5em; border-bottom: 1px solid #eee; vertical-align: top; }
+td.count { text-align: right; width: 6em; }
+.muted { color: #666; }
+.flag { display: inline-block; padding: 0 0.4em; margin-right: 0.3em; border-radius: 3px; background: #fde68a; font-size: 0.8em; }
+@media print { body { margin: 0; } h2 { page-break-after: avoid; } tr { page-break-inside: avoid; } }
+"#;
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn field_or_unknown(value: Option<&String>) -> String {
+    value
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+        .unwrap_or("Unknown")
+        .to_string()
+}
+
+fn missing_fields(metadata: Option<&Metadata>) -> Vec<&'static str> {
+    let metadata = match metadata {
+        Some(m) => m,
+        None => return vec!["no tags"],
+    };
+
+    let is_blank = |value: &Option<String>| value.as_deref().map(str::trim).unwrap_or("").is_empty();
+
+    let mut missing = Vec::new();
+    if is_blank(&metadata.title) {
+        missing.push("title");
+    }
+    if is_blank(&metadata.artist) {
+        missing.push("artist");
+    }
+    if is_blank(&metadata.genre) {
+        missing.push("genre");
+    }
+    if metadata.year.is_none() {
+        missing.push("year");
+    }
+    missing
+}
+
+fn count_table(out: &mut String, heading: &str, counts: &BTreeMap<String, usize>) {
+    let _ = writeln!(out, "<h2>{} <span class=\"muted\">({})</span></h2>", escape_html(heading), counts.len());
+    out.push_str("<table>\n");
+
+    let mut rows: Vec<(&String, &usize)> = counts.iter().collect();
+    rows.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+
+    for (name, count) in rows {
+        let _ = writeln!(out, "<tr><td>{}</td><td class=\"count\">{}</td></tr>", escape_html(name), count);
+    }
+    out.push_str("</table>\n");
+}
+
+/// Builds a self-contained, print-friendly HTML report; "Print to PDF" in
+/// any browser produces the archival version.
+pub fn render_report(files: &[AudioFile], title: &str, session: Option<&SessionStats>) -> String {
+    let scanner = FileScanner::new();
+    let mut by_genre: BTreeMap<String, usize> = BTreeMap::new();
+    let mut by_artist: BTreeMap<String, usize> = BTreeMap::new();
+    let mut incomplete = Vec::new();
+
+    for file in files {
+        let metadata = file.current_metadata.as_ref();
+        *by_genre.entry(field_or_unknown(metadata.and_then(|m| m.genre.as_ref()))).or_insert(0) += 1;
+        *by_artist.entry(field_or_unknown(metadata.and_then(|m| m.artist.as_ref()))).or_insert(0) += 1;
+
+        let missing = missing_fields(metadata);
+        if !missing.is_empty() {
+            incomplete.push((file, missing));
+        }
+    }
+
+    let duplicates = scanner.find_duplicates(files, DuplicateStrategy::Tags);
+
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    let _ = writeln!(out, "<title>{}</title>", escape_html(title));
+    let _ = writeln!(out, "<style>{}</style>", REPORT_STYLE);
+    out.push_str("</head>\n<body>\n");
+    let _ = writeln!(out, "<h1>{}</h1>", escape_html(title));
+    let _ = writeln!(
+        out,
+        "<p class=\"muted\">{} tracks &middot; {} genres &middot; {} artists &middot; {} with missing data &middot; {} duplicate groups</p>",
+        files.len(),
+        by_genre.len(),
+        by_artist.len(),
+        incomplete.len(),
+        duplicates.len()
+    );
+
+    if let Some(session) = session {
+        out.push_str("<h2>Session</h2>\n<table>\n");
+        for (label, count) in [
+            ("Files processed", session.files_processed),
+            ("Genres written", session.genres_written),
+            ("API calls", session.api_calls),
+            ("Cache hits", session.cache_hits),
+            ("Errors", session.errors),
+        ] {
+            let _ = writeln!(out, "<tr><td>{}</td><td class=\"count\">{}</td></tr>", label, count);
+        }
+        out.push_str("</table>\n");
+    }
+
+    count_table(&mut out, "Genres", &by_genre);
+    count_table(&mut out, "Artists", &by_artist);
+
+    let _ = writeln!(out, "<h2>Missing data <span class=\"muted\">({})</span></h2>", incomplete.len());
+    out.push_str("<table>\n");
+    for (file, missing) in &incomplete {
+        let flags: String = missing
+            .iter()
+            .map(|field| format!("<span class=\"flag\">{}</span>", field))
+            .collect();
+        let _ = writeln!(
+            out,
+            "<tr><td>{}</td><td>{}</td></tr>",
+            escape_html(&file.path.to_string_lossy()),
+            flags
+        );
+    }
+    out.push_str("</table>\n");
+
+    let _ = writeln!(out, "<h2>Duplicates <span class=\"muted\">({})</span></h2>", duplicates.len());
+    out.push_str("<table>\n");
+    for group in &duplicates {
+        let paths: Vec<String> = group
+            .iter()
+            .map(|&index| escape_html(&files[index].path.to_string_lossy()))
+            .collect();
+        let label = files[group[0]].current_metadata.as_ref()
+            .map(|m| format!(
+                "{} - {}",
+                field_or_unknown(m.artist.as_ref()),
+                field_or_unknown(m.title.as_ref())
+            ))
+            .unwrap_or_default();
+        let _ = writeln!(
+            out,
+            "<tr><td>{}</td><td>{}</td></tr>",
+            escape_html(&label),
+            paths.join("<br>")
+        );
+    }
+    out.push_str("</table>\n</body>\n</html>\n");
+
+    out
+}
+
+pub fn generate_report(files: &[AudioFile], title: &str, session: Option<&SessionStats>, output_path: &Path) -> Result<(), String> {
+    let html = render_report(files, title, session);
+
+    fs::write(output_path, html)
+        .map_err(|e| format!("Failed to write report: {}", e))
+}
@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+
+use crate::changeset::{ChangeOutcome, ChangeResult};
+use crate::scanner::{FileScanner, Metadata};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreRequest {
+    #[serde(with = "crate::path_codec")]
+    pub file_path: PathBuf,
+    #[serde(with = "crate::path_codec")]
+    pub backup_path: PathBuf,
+}
+
+/// A field restoring would overwrite, with display values.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub current: Option<String>,
+    pub backup: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestorePreview {
+    #[serde(with = "crate::path_codec")]
+    pub file_path: PathBuf,
+    #[serde(with = "crate::path_codec")]
+    pub backup_path: PathBuf,
+    pub changes: Vec<FieldChange>,
+    /// Why no preview could be made, e.g. an unreadable backup.
+    pub error: Option<String>,
+}
+
+fn display(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        Value::Array(items) if items.is_empty() => None,
+        other => Some(other.to_string()),
+    }
+}
+
+/// Fields whose backup value differs from the current one. Fields the
+/// backup leaves empty are not listed: restoring writes only the fields a
+/// backup has, so those keep their current value. Nested objects such as
+/// the external ids are compared per entry ("external_ids.catalog_number").
+pub fn field_changes(current: &Metadata, backup: &Metadata) -> Vec<FieldChange> {
+    let (Ok(Value::Object(current)), Ok(Value::Object(backup))) =
+        (serde_json::to_value(current), serde_json::to_value(backup))
+    else {
+        return Vec::new();
+    };
+
+    let mut changes = Vec::new();
+    for (field, backup_value) in &backup {
+        let current_value = current.get(field).unwrap_or(&Value::Null);
+        match (backup_value, current_value) {
+            (Value::Object(backup_entries), current_value) => {
+                for (entry, backup_entry) in backup_entries {
+                    let current_entry = current_value.get(entry).unwrap_or(&Value::Null);
+                    if display(backup_entry).is_some() && backup_entry != current_entry {
+                        changes.push(FieldChange {
+                            field: format!("{}.{}", field, entry),
+                            current: display(current_entry),
+                            backup: display(backup_entry),
+                        });
+                    }
+                }
+            }
+            (backup_value, current_value) => {
+                if display(backup_value).is_some() && backup_value != current_value {
+                    changes.push(FieldChange {
+                        field: field.clone(),
+                        current: display(current_value),
+                        backup: display(backup_value),
+                    });
+                }
+            }
+        }
+    }
+    changes
+}
+
+/// What restoring `request` would change, without writing anything.
+pub fn preview(scanner: &FileScanner, request: &RestoreRequest) -> RestorePreview {
+    let diff = scanner.read_backup(&request.backup_path).map(|backup| {
+        let current = scanner.read_metadata(&request.file_path).unwrap_or_default();
+        field_changes(&current, &backup.metadata)
+    });
+
+    let (changes, error) = match diff {
+        Ok(changes) => (changes, None),
+        Err(e) => (Vec::new(), Some(e)),
+    };
+    RestorePreview {
+        file_path: request.file_path.clone(),
+        backup_path: request.backup_path.clone(),
+        changes,
+        error,
+    }
+}
+
+/// Restores each backup onto its file; files the user excluded after
+/// previewing are simply left out of `requests`.
+pub fn restore_all(scanner: &FileScanner, requests: &[RestoreRequest]) -> Vec<ChangeResult> {
+    requests
+        .iter()
+        .map(|request| ChangeResult {
+            file_path: request.file_path.clone(),
+            outcome: match scanner.restore_from_backup(&request.backup_path, &request.file_path) {
+                Ok(()) => ChangeOutcome::Applied,
+                Err(e) => ChangeOutcome::Failed(e),
+            },
+        })
+        .collect()
+}
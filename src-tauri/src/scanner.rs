@@ -1,33 +1,662 @@
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::fs;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+use rayon::prelude::*;
 use walkdir::WalkDir;
 use id3::TagLike;
+use id3::frame::{ExtendedText, UniqueFileIdentifier};
 use lofty::prelude::*;
 use lofty::config::{ParseOptions, WriteOptions};
+use lofty::id3::v2::Id3v2Tag;
+use lofty::picture::{MimeType, Picture, PictureType};
 use lofty::probe::Probe;
-use lofty::tag::{Tag, TagType};
+use lofty::tag::{ItemKey, ItemValue, Tag, TagItem, TagType};
+
+use crate::api_client::{self, GenreCase};
+use crate::ape;
+use crate::artists::ArtistRules;
+use crate::artwork::{self, ArtworkPolicy};
+use crate::backup::{self, Backup, RawTagRef, BACKUP_VERSION};
+use crate::bwf::{self, BextChunk};
+use crate::collation;
+use crate::comments::{CommentPolicy, CommentRule};
+use crate::decode::{self, DecodeOptions};
+use crate::duplicates;
+use crate::dsf;
+use crate::genre_fallback::GenreFallbacks;
+use crate::genres::{GenreRules, GenreStorage};
+use crate::id3v1::{self, Id3v1Policy, TagConflict};
+use crate::journal::{self, JournalOperation};
+use crate::pattern;
+use crate::permissions::FilePermissions;
+use crate::provenance::Provenance;
+use crate::settings::AppSettings;
+use crate::simulation::{self, SimulatedOperation};
+use crate::workdir;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AudioFile {
+    /// Stable across rescans and platforms; see `file_id`.
+    #[serde(default)]
+    pub id: String,
+    #[serde(with = "crate::path_codec")]
     pub path: PathBuf,
     pub filename: String,
     pub extension: String,
     pub current_metadata: Option<Metadata>,
+    pub bext: Option<BextChunk>,
+    #[serde(default)]
+    pub tag_conflicts: Vec<TagConflict>,
+    #[serde(default)]
+    pub has_ape_tag: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Metadata {
     pub title: Option<String>,
     pub artist: Option<String>,
+    /// The individual artists credited in `artist`, from a multi-valued
+    /// ARTISTS tag or else split by the artist rules.
+    #[serde(default)]
+    pub artists: Vec<String>,
+    #[serde(default)]
+    pub album_artist: Option<String>,
     pub album: Option<String>,
+    #[serde(default)]
+    pub track_number: Option<u32>,
+    #[serde(default)]
+    pub disc_number: Option<u32>,
+    #[serde(default)]
+    pub composer: Option<String>,
+    /// Record label (TPUB in ID3).
+    #[serde(default)]
+    pub label: Option<String>,
     pub genre: Option<String>,
+    /// The individual genres in `genre`, from a multi-valued genre field or
+    /// else split by the genre rules.
+    #[serde(default)]
+    pub genres: Vec<String>,
     pub year: Option<i32>,
+    /// Full release date (YYYY, YYYY-MM or YYYY-MM-DD) when the tags or
+    /// provider have more precision than the year.
+    #[serde(default)]
+    pub date: Option<String>,
+    #[serde(default)]
+    pub release_type: Option<ReleaseType>,
     pub bpm: Option<f32>,
+    pub key: Option<String>,
+    /// The file's comment; when writing, text to merge into it per the
+    /// comment policy.
+    #[serde(default)]
+    pub comment: Option<String>,
+    #[serde(default)]
+    pub external_ids: ExternalIds,
+    /// Where the app's values came from; only written when enabled.
+    #[serde(default)]
+    pub provenance: Option<Provenance>,
+    /// The embedded front cover, if any. Read-only: artwork is written with
+    /// `embed_artwork`.
+    #[serde(default)]
+    pub cover_art: Option<CoverArt>,
+}
+
+/// What a file's embedded cover is, without the image itself; that is read
+/// with `read_front_cover`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoverArt {
+    pub mime_type: String,
+    /// Zero when the image header cannot be read.
+    pub width: u32,
+    pub height: u32,
+    pub size: usize,
+}
+
+impl CoverArt {
+    fn describe(mime_type: &str, data: &[u8]) -> Self {
+        let (width, height) = artwork::dimensions(data).unwrap_or((0, 0));
+        CoverArt {
+            mime_type: mime_type.to_string(),
+            width,
+            height,
+            size: data.len(),
+        }
+    }
+}
+
+/// Identifiers of the provider match a file was tagged from, so later
+/// lookups can be exact and other taggers can pick them up.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExternalIds {
+    pub musicbrainz_recording_id: Option<String>,
+    pub musicbrainz_release_id: Option<String>,
+    pub musicbrainz_artist_id: Option<String>,
+    pub beatport_track_id: Option<String>,
+    pub catalog_number: Option<String>,
+}
+
+/// What kind of release a track came out on, for telling singles from
+/// albums in folder patterns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReleaseType {
+    Album,
+    Single,
+    Ep,
+    Compilation,
+}
+
+impl ReleaseType {
+    /// Lowercase name as MusicBrainz and Picard write it.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReleaseType::Album => "album",
+            ReleaseType::Single => "single",
+            ReleaseType::Ep => "ep",
+            ReleaseType::Compilation => "compilation",
+        }
+    }
+
+    /// Name for folder patterns.
+    pub fn label(&self) -> &'static str {
+        match self {
+            ReleaseType::Album => "Album",
+            ReleaseType::Single => "Single",
+            ReleaseType::Ep => "EP",
+            ReleaseType::Compilation => "Compilation",
+        }
+    }
+
+    /// Parses MusicBrainz types, Picard's multi-valued tag ("album;
+    /// compilation") and the Spotify and Deezer album types ("compile").
+    /// A compilation secondary type beats the primary one.
+    pub fn parse(value: &str) -> Option<Self> {
+        let types: Vec<String> = value
+            .split([';', ',', '/', '\0'])
+            .map(|part| part.trim().to_lowercase())
+            .collect();
+        if types.iter().any(|t| t == "compilation" || t == "compile") {
+            return Some(ReleaseType::Compilation);
+        }
+        types.iter().find_map(|t| match t.as_str() {
+            "album" => Some(ReleaseType::Album),
+            "single" => Some(ReleaseType::Single),
+            "ep" => Some(ReleaseType::Ep),
+            _ => None,
+        })
+    }
+}
+
+// Picard's ID3v2 conventions: the recording id lives in a UFID frame, the
+// rest in TXXX frames.
+const MUSICBRAINZ_UFID_OWNER: &str = "http://musicbrainz.org";
+const MUSICBRAINZ_RELEASE_TXXX: &str = "MusicBrainz Album Id";
+const MUSICBRAINZ_ARTIST_TXXX: &str = "MusicBrainz Artist Id";
+const BEATPORT_TRACK_ID_FIELD: &str = "BEATPORT_TRACK_ID";
+const CATALOG_NUMBER_TXXX: &str = "CATALOGNUMBER";
+const RELEASE_TYPE_TXXX: &str = "MusicBrainz Album Type";
+const RELEASE_TYPE_VORBIS: &str = "RELEASETYPE";
+const PROVENANCE_FIELD: &str = "AUTOGENRE";
+// Picard's multi-artist field. Formats without repeatable fields keep the
+// values in one field, NUL-separated as in ID3v2.4.
+const ARTISTS_FIELD: &str = "ARTISTS";
+const MULTI_VALUE_SEPARATOR: char = '\0';
+// Enough audio to tell tracks of equal length apart without decoding them
+// whole.
+const DUPLICATE_HASH_SECONDS: u64 = 30;
+// Bytes `same_content` reads from each file at a time.
+const CONTENT_BLOCK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanSort {
+    Path,
+    Artist,
+    Title,
+    Album,
+    Genre,
+}
+
+/// What organize and rename do when another file already sits at a file's
+/// destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrganizeCollision {
+    /// Move in beside it as "name (2).ext".
+    Suffix,
+    /// If both hold the same audio, keep the higher-bitrate copy (the
+    /// existing one on a tie) and move the other to the quarantine folder;
+    /// anything else is suffixed.
+    KeepBest,
+    /// Leave the file where it is.
+    Skip,
+    /// Replace the file at the destination.
+    Overwrite,
 }
 
+/// What happened to a file whose destination held a different file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Collision {
+    /// Left where it was.
+    Skipped,
+    /// Moved in as "name (2).ext".
+    Suffixed,
+    /// Moved in over the other file.
+    Overwritten,
+}
+
+/// Whether organize moves files into the library or copies them there.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrganizeMode {
+    /// Moved, by copying and deleting when the library is on another drive.
+    #[default]
+    Move,
+    /// Copied, leaving the source where it is, e.g. to build a second
+    /// organized library.
+    Copy,
+}
+
+/// How `find_duplicates` decides two files are the same track.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateStrategy {
+    /// Matching artist and title tags.
+    #[default]
+    Tags,
+    /// The same duration and decoded audio at the start, whatever the
+    /// tags say. Catches untagged copies and ignores covers.
+    Content,
+}
+
+/// Which copy survived when organize found the same track at the
+/// destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateKept {
+    /// The file being organized was quarantined.
+    Existing,
+    /// The file at the destination was quarantined and replaced.
+    Incoming,
+}
+
+/// The path with symlinks and `..` resolved, or as given when it cannot be.
+fn canonical_path(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Hex FNV-1a hash of the canonical path, so the same file gets the same id
+/// on every scan regardless of how the folder was opened.
+pub fn file_id(path: &Path) -> String {
+    backup::content_hash(canonical_path(path).to_string_lossy().as_bytes())
+}
+
+/// A requested scan root that was dropped because it is the same folder as,
+/// or lies inside, another root.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RootOverlap {
+    #[serde(with = "crate::path_codec")]
+    pub root: PathBuf,
+    #[serde(with = "crate::path_codec")]
+    pub covered_by: PathBuf,
+}
+
+/// Where organizing or renaming left a file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Placement {
+    #[serde(with = "crate::path_codec")]
+    pub path: PathBuf,
+    /// The pattern resolved to where the file already was, so nothing moved.
+    pub already_in_place: bool,
+    /// Set when the destination held the same audio and one copy was
+    /// quarantined under `OrganizeCollision::KeepBest`.
+    #[serde(default)]
+    pub duplicate: Option<DuplicateKept>,
+    /// Set when the destination held a different file.
+    #[serde(default)]
+    pub collision: Option<Collision>,
+    /// The file was copied, so the source is still where it was.
+    #[serde(default)]
+    pub copied: bool,
+}
+
+impl Placement {
+    fn in_place(path: &Path) -> Self {
+        Placement { path: path.to_path_buf(), already_in_place: true, duplicate: None, collision: None, copied: false }
+    }
+
+    /// Whether the file still has to be moved or copied to `path`.
+    fn pending(&self) -> bool {
+        !self.already_in_place
+            && self.duplicate != Some(DuplicateKept::Existing)
+            && self.collision != Some(Collision::Skipped)
+    }
+}
+
+/// A file under the base folder that organize would now put elsewhere,
+/// typically because its tags were edited after it was placed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MisplacedFile {
+    #[serde(with = "crate::path_codec")]
+    pub path: PathBuf,
+    #[serde(with = "crate::path_codec")]
+    pub expected_path: PathBuf,
+}
+
+/// Where organize or rename would move a file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathPreview {
+    #[serde(with = "crate::path_codec")]
+    pub path: PathBuf,
+    #[serde(with = "crate::path_codec")]
+    pub destination: PathBuf,
+    /// Another file is already there or would be moved there too, so
+    /// organize would suffix the name and rename would refuse.
+    pub collision: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultiScanResult {
+    pub files: Vec<AudioFile>,
+    pub overlaps: Vec<RootOverlap>,
+}
+
+/// Canonicalizes the roots and drops any that repeat or sit inside another,
+/// so each file is walked once. Unresolvable roots are kept as given.
+pub fn collapse_roots(roots: &[PathBuf]) -> (Vec<PathBuf>, Vec<RootOverlap>) {
+    let mut resolved: Vec<(PathBuf, PathBuf)> = roots
+        .iter()
+        .map(|root| (root.clone(), fs::canonicalize(root).unwrap_or_else(|_| root.clone())))
+        .collect();
+    // Parents sort before their children, so the outermost root wins.
+    resolved.sort_by(|a, b| a.1.cmp(&b.1));
+
+    let mut kept: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let mut overlaps = Vec::new();
+    for (root, canonical) in resolved {
+        match kept.iter().find(|(_, parent)| canonical.starts_with(parent)) {
+            Some((covering, _)) => overlaps.push(RootOverlap {
+                root,
+                covered_by: covering.clone(),
+            }),
+            None => kept.push((root, canonical)),
+        }
+    }
+
+    (kept.into_iter().map(|(root, _)| root).collect(), overlaps)
+}
+
+/// Reduces a tag or provider date to its YYYY[-MM[-DD]] prefix, dropping
+/// any time component; returns `None` if it does not start with a year.
+pub fn normalize_date(value: &str) -> Option<String> {
+    let date = value.trim().split(['T', ' ']).next().unwrap_or("");
+    let parts: Vec<&str> = date.split('-').take(3).collect();
+
+    let valid = parts.first().map(|year| year.len() == 4).unwrap_or(false)
+        && parts.iter().all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()));
+    if !valid {
+        return None;
+    }
+
+    Some(parts.join("-"))
+}
+
+/// Cleans up what missing fields leave in an expanded filename: empty
+/// brackets, doubled separators and separators at either end.
+fn tidy_filename(name: &str) -> String {
+    let mut name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    loop {
+        let tidied = name
+            .replace("( ", "(")
+            .replace(" )", ")")
+            .replace("[ ", "[")
+            .replace(" ]", "]")
+            .replace("()", "")
+            .replace("[]", "")
+            .replace(" - - ", " - ")
+            .replace("  ", " ");
+        if tidied == name {
+            break;
+        }
+        name = tidied;
+    }
+    name.trim_matches(|c: char| c.is_whitespace() || c == '-').to_string()
+}
+
+/// Folds folder levels cut by the depth limit into the file name, so
+/// "Artist", "Album" and "Title.mp3" become "Artist - Album - Title.mp3".
+/// Levels the name already contains are not repeated.
+fn flatten_into_name(folded: &[&str], filename: &str) -> String {
+    let lower_name = filename.to_lowercase();
+    let mut parts: Vec<String> = folded
+        .iter()
+        .map(|component| component.trim().to_string())
+        .filter(|component| !lower_name.contains(&component.to_lowercase()))
+        .collect();
+    parts.push(filename.to_string());
+    parts.join(" - ")
+}
+
+/// Whether both paths lead to the same file once links, `.` and `..` are
+/// resolved. False when either does not exist.
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Whether two files have the same bytes. Sizes are compared first, then
+/// both files are read side by side a block at a time.
+pub fn same_content(a: &Path, b: &Path) -> Result<bool, String> {
+    if fs::metadata(a).map(|m| m.len()).ok() != fs::metadata(b).map(|m| m.len()).ok() {
+        return Ok(false);
+    }
+    let open = |path: &Path| fs::File::open(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e));
+    let (mut a_reader, mut b_reader) = (open(a)?, open(b)?);
+    let (mut a_block, mut b_block) = (vec![0u8; CONTENT_BLOCK_SIZE], vec![0u8; CONTENT_BLOCK_SIZE]);
+    loop {
+        let read = read_block(&mut a_reader, &mut a_block)
+            .map_err(|e| format!("Failed to read {}: {}", a.display(), e))?;
+        let other = read_block(&mut b_reader, &mut b_block)
+            .map_err(|e| format!("Failed to read {}: {}", b.display(), e))?;
+        if a_block[..read] != b_block[..other] {
+            return Ok(false);
+        }
+        if read == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+/// Fills `block` as far as the reader allows, so two readers are compared
+/// at the same offsets; fewer bytes only at the end of the file.
+fn read_block(reader: &mut impl std::io::Read, block: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < block.len() {
+        match reader.read(&mut block[filled..])? {
+            0 => break,
+            read => filled += read,
+        }
+    }
+    Ok(filled)
+}
+
+/// Average bitrate in kbit/s, `None` when the file cannot be read.
+pub fn read_bitrate(path: &Path) -> Option<u32> {
+    Probe::open(path)
+        .and_then(|probe| probe.read())
+        .ok()
+        .and_then(|tagged_file| lofty::file::AudioFile::properties(&tagged_file).audio_bitrate())
+}
+
+/// Average bitrate in kbit/s, 0 when the file cannot be read.
+pub fn audio_bitrate(path: &Path) -> u32 {
+    read_bitrate(path).unwrap_or(0)
+}
+
+/// Whole seconds of audio, `None` when the file cannot be read.
+fn audio_duration(path: &Path) -> Option<u64> {
+    Probe::open(path)
+        .and_then(|probe| probe.read())
+        .ok()
+        .map(|tagged_file| lofty::file::AudioFile::properties(&tagged_file).duration().as_secs())
+}
+
+/// Hash of the decoded samples in the first `DUPLICATE_HASH_SECONDS`, so
+/// retagged copies of a file hash the same.
+fn audio_hash(path: &Path) -> Option<String> {
+    let pcm = decode::decode(path, &DecodeOptions {
+        length: Some(Duration::from_secs(DUPLICATE_HASH_SECONDS)),
+        ..DecodeOptions::default()
+    }).ok()?;
+    if pcm.samples.is_empty() {
+        return None;
+    }
+    let bytes: Vec<u8> = pcm.samples.iter().flat_map(|sample| sample.to_le_bytes()).collect();
+    Some(backup::content_hash(&bytes))
+}
+
+/// Groups files with the same duration and audio hash. Only files sharing
+/// a duration with another are decoded.
+fn find_content_duplicates(files: &[AudioFile]) -> Vec<Vec<usize>> {
+    let durations: Vec<Option<u64>> = files.par_iter().map(|file| audio_duration(&file.path)).collect();
+    let mut by_duration: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (index, duration) in durations.iter().enumerate() {
+        if let Some(duration) = duration {
+            by_duration.entry(*duration).or_default().push(index);
+        }
+    }
+
+    let candidates: Vec<usize> = by_duration.into_values()
+        .filter(|group| group.len() > 1)
+        .flatten()
+        .collect();
+    let hashes: Vec<(usize, Option<String>)> = candidates
+        .into_par_iter()
+        .map(|index| (index, audio_hash(&files[index].path)))
+        .collect();
+
+    let mut by_content: HashMap<(u64, String), Vec<usize>> = HashMap::new();
+    for (index, hash) in hashes {
+        if let (Some(duration), Some(hash)) = (durations[index], hash) {
+            by_content.entry((duration, hash)).or_default().push(index);
+        }
+    }
+
+    let mut duplicates: Vec<Vec<usize>> = by_content.into_values()
+        .filter(|group| group.len() > 1)
+        .map(|mut group| {
+            group.sort_unstable();
+            group
+        })
+        .collect();
+    duplicates.sort_unstable();
+    duplicates
+}
+
+/// `path` if nothing is there yet, else the first free "name (2).ext",
+/// "name (3).ext" beside it.
+pub fn free_destination(path: &Path) -> PathBuf {
+    if !path.exists() {
+        return path.to_path_buf();
+    }
+
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let extension = path.extension().map(|ext| format!(".{}", ext.to_string_lossy())).unwrap_or_default();
+    (2..)
+        .map(|n| path.with_file_name(format!("{} ({}){}", stem, n, extension)))
+        .find(|candidate| !candidate.exists())
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// Renames a file to a name differing only in case, through a temporary
+/// name so case-insensitive filesystems do not treat it as a no-op.
+fn rename_case(from: &Path, to: &Path) -> Result<(), String> {
+    let temporary = free_destination(&to.with_extension(format!(
+        "{}.rename",
+        to.extension().unwrap_or_default().to_string_lossy()
+    )));
+    fs::rename(from, &temporary)
+        .map_err(|e| format!("Failed to rename file: {}", e))?;
+    fs::rename(&temporary, to)
+        .map_err(|e| {
+            let _ = fs::rename(&temporary, from);
+            format!("Failed to rename file: {}", e)
+        })
+}
+
+fn picture_mime_type(data: &[u8]) -> MimeType {
+    match image::guess_format(data) {
+        Ok(image::ImageFormat::Png) => MimeType::Png,
+        _ => MimeType::Jpeg,
+    }
+}
+
+impl Metadata {
+    /// The year, falling back to the year of the full date.
+    pub fn release_year(&self) -> Option<i32> {
+        self.year.or_else(|| {
+            self.date.as_deref()
+                .and_then(|date| date.get(..4))
+                .and_then(|year| year.parse().ok())
+        })
+    }
+}
+
+pub const SCAN_CANCELLED: &str = "Scan cancelled";
+
+/// Hooks for reporting on a running scan and stopping it early.
+pub struct ScanMonitor<'a> {
+    pub cancel: &'a AtomicBool,
+    /// Called with each supported file as the walk finds it, and how many
+    /// have been found so far.
+    pub on_found: &'a (dyn Fn(&Path, usize) + Sync),
+    /// Called with each file once its tags are read, the number read so far
+    /// and the total.
+    pub on_read: &'a (dyn Fn(&Path, usize, usize) + Sync),
+}
+
+impl<'a> ScanMonitor<'a> {
+    pub fn silent(cancel: &'a AtomicBool) -> Self {
+        ScanMonitor {
+            cancel,
+            on_found: &|_, _| {},
+            on_read: &|_, _, _| {},
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::SeqCst)
+    }
+}
+
+#[derive(Clone)]
 pub struct FileScanner {
     supported_extensions: Vec<String>,
+    id3v1_policy: Id3v1Policy,
+    prefer_album_artist: bool,
+    sort_locale: String,
+    artwork_policy: ArtworkPolicy,
+    comment_rule: CommentRule,
+    write_provenance: bool,
+    artist_rules: ArtistRules,
+    genre_case: GenreCase,
+    genre_rules: GenreRules,
+    scan_workers: usize,
+    organize_max_depth: usize,
+    extension_patterns: HashMap<String, String>,
+    organize_collision: OrganizeCollision,
+    organize_mode: OrganizeMode,
+    permissions: FilePermissions,
+    filename_pattern: String,
+    genre_fallbacks: GenreFallbacks,
+    quarantine: Option<PathBuf>,
 }
 
 impl FileScanner {
@@ -39,78 +668,688 @@ impl FileScanner {
                 "wav".to_string(),
                 "m4a".to_string(),
                 "aiff".to_string(),
+                "aif".to_string(),
                 "ogg".to_string(),
+                "opus".to_string(),
+                "wv".to_string(),
+                "ape".to_string(),
+                "dsf".to_string(),
             ],
+            id3v1_policy: Id3v1Policy::Keep,
+            prefer_album_artist: false,
+            sort_locale: "en".to_string(),
+            artwork_policy: ArtworkPolicy::unlimited(),
+            comment_rule: CommentRule::default(),
+            write_provenance: false,
+            artist_rules: ArtistRules::default(),
+            genre_case: GenreCase::AsIs,
+            genre_rules: GenreRules::default(),
+            scan_workers: 0,
+            organize_max_depth: 0,
+            extension_patterns: HashMap::new(),
+            organize_collision: OrganizeCollision::Suffix,
+            organize_mode: OrganizeMode::Move,
+            permissions: FilePermissions::default(),
+            filename_pattern: pattern::DEFAULT_FILENAME_PATTERN.to_string(),
+            genre_fallbacks: GenreFallbacks::default(),
+            quarantine: None,
+        }
+    }
+
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        FileScanner {
+            id3v1_policy: settings.id3v1_policy,
+            prefer_album_artist: settings.prefer_album_artist,
+            sort_locale: settings.sort_locale.clone(),
+            artwork_policy: ArtworkPolicy::from_settings(settings),
+            comment_rule: CommentRule::from_settings(settings),
+            write_provenance: settings.write_provenance,
+            artist_rules: ArtistRules::from_settings(settings),
+            genre_case: settings.genre_case,
+            genre_rules: GenreRules::from_settings(settings),
+            scan_workers: settings.scan_workers,
+            organize_max_depth: settings.organize_max_depth,
+            extension_patterns: settings.extension_patterns.clone(),
+            organize_collision: settings.organize_collision,
+            organize_mode: settings.organize_mode,
+            permissions: FilePermissions::from_settings(settings),
+            filename_pattern: settings.filename_pattern.clone(),
+            genre_fallbacks: GenreFallbacks::from_settings(settings),
+            ..Self::new()
+        }
+    }
+
+    /// Where KeepBest moves the copy it drops. Without one, KeepBest keeps
+    /// both copies.
+    pub fn with_quarantine(self, folder: PathBuf) -> Self {
+        FileScanner { quarantine: Some(folder), ..self }
+    }
+
+    /// A copy that writes comments as given rather than merging them under
+    /// the comment policy, for writes whose metadata already holds the whole
+    /// comment the file should end up with.
+    pub fn verbatim_comments(&self) -> Self {
+        FileScanner {
+            comment_rule: CommentRule { policy: CommentPolicy::Replace, ..self.comment_rule.clone() },
+            ..self.clone()
+        }
+    }
+
+    /// The configured fallback genre for a file that has none.
+    pub fn fallback_genre(&self, path: &Path) -> Option<String> {
+        self.genre_fallbacks.genre_for(path)
+    }
+
+    /// Ownership and mode given to files and folders placed in the library.
+    pub fn permissions(&self) -> &FilePermissions {
+        &self.permissions
+    }
+
+    /// Sorts scan results by the collation key of the given field (see
+    /// `collation::sort_key`), with missing values last and the path as a
+    /// tie-breaker.
+    pub fn sort_files(&self, files: &mut [AudioFile], sort: ScanSort) {
+        let field = |file: &AudioFile| -> Option<String> {
+            let metadata = file.current_metadata.as_ref()?;
+            let value = match sort {
+                ScanSort::Path => return None,
+                ScanSort::Artist => metadata.artist.as_ref(),
+                ScanSort::Title => metadata.title.as_ref(),
+                ScanSort::Album => metadata.album.as_ref(),
+                ScanSort::Genre => metadata.genre.as_ref(),
+            };
+            value.map(|v| collation::sort_key(v, &self.sort_locale)).filter(|v| !v.is_empty())
+        };
+
+        files.sort_by(|a, b| {
+            let by_field = match (field(a), field(b)) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            };
+            by_field.then_with(|| a.path.cmp(&b.path))
+        });
+    }
+
+    /// Scans several roots at once, skipping roots covered by another and
+    /// files reached twice through symlinks. Files count as the same by
+    /// canonical path; identical copies at different paths are all listed.
+    pub fn scan_roots(&self, roots: &[PathBuf]) -> Result<MultiScanResult, String> {
+        let (roots, overlaps) = collapse_roots(roots);
+        let mut seen = HashSet::new();
+        let mut files = Vec::new();
+
+        for root in &roots {
+            for file in self.scan_directory(root)? {
+                if seen.insert(canonical_path(&file.path)) {
+                    files.push(file);
+                }
+            }
         }
+
+        Ok(MultiScanResult { files, overlaps })
     }
 
     pub fn scan_directory(&self, path: &Path) -> Result<Vec<AudioFile>, String> {
-        let mut audio_files = Vec::new();
+        self.scan_directory_monitored(path, &ScanMonitor::silent(&AtomicBool::new(false)))
+    }
+
+    /// Reads every supported file under `path` on a pool of `scan_workers`
+    /// threads, reporting to `monitor` as files are found and read. Results
+    /// keep the sorted order of `list_audio_files`.
+    pub fn scan_directory_monitored(&self, path: &Path, monitor: &ScanMonitor) -> Result<Vec<AudioFile>, String> {
+        let mut file_paths = Vec::new();
+        for file_path in self.audio_files_under(path) {
+            if monitor.is_cancelled() {
+                return Err(SCAN_CANCELLED.to_string());
+            }
+            (monitor.on_found)(&file_path, file_paths.len() + 1);
+            file_paths.push(file_path);
+        }
 
-        for entry in WalkDir::new(path)
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.scan_workers)
+            .build()
+            .map_err(|e| format!("Failed to start scan workers: {}", e))?;
+
+        let total = file_paths.len();
+        let done = AtomicUsize::new(0);
+        pool.install(|| {
+            file_paths
+                .into_par_iter()
+                .map(|file_path| {
+                    if monitor.is_cancelled() {
+                        return Err(SCAN_CANCELLED.to_string());
+                    }
+                    let file = self.scan_file(file_path);
+                    let done = done.fetch_add(1, Ordering::SeqCst) + 1;
+                    (monitor.on_read)(&file.path, done, total);
+                    Ok(file)
+                })
+                .collect()
+        })
+    }
+
+    fn scan_file(&self, file_path: PathBuf) -> AudioFile {
+        let filename = file_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let extension = file_path
+            .extension()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_lowercase();
+        let current_metadata = self.read_metadata(&file_path).ok();
+        let bext = if extension == "wav" {
+            bwf::read_bext(&file_path).ok().flatten()
+        } else {
+            None
+        };
+        let tag_conflicts = match (&current_metadata, extension.as_str()) {
+            (Some(metadata), "mp3") => id3v1::read(&file_path)
+                .map(|v1_tag| id3v1::conflicts(&v1_tag, metadata))
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        let has_ape_tag = extension == "mp3" && ape::has_ape_tag(&file_path);
+
+        AudioFile {
+            id: file_id(&file_path),
+            path: file_path,
+            filename,
+            extension,
+            current_metadata,
+            bext,
+            tag_conflicts,
+            has_ape_tag,
+        }
+    }
+
+    /// Lists supported files in a deterministic (sorted) order.
+    pub fn list_audio_files(&self, path: &Path) -> Vec<PathBuf> {
+        self.audio_files_under(path).collect()
+    }
+
+    /// Supported files under `path`, each once: a file also reached through
+    /// a symlink is listed at the first path found.
+    fn audio_files_under<'a>(&'a self, path: &Path) -> impl Iterator<Item = PathBuf> + 'a {
+        let mut seen = HashSet::new();
+        WalkDir::new(path)
             .follow_links(true)
+            .sort_by_file_name()
             .into_iter()
             .filter_map(|e| e.ok())
-        {
-            let file_path = entry.path();
-            
-            if file_path.is_file() {
-                if let Some(extension) = file_path.extension() {
-                    let ext = extension.to_string_lossy().to_lowercase();
-                    
-                    if self.supported_extensions.contains(&ext) {
-                        let filename = file_path
-                            .file_name()
-                            .unwrap_or_default()
-                            .to_string_lossy()
-                            .to_string();
-
-                        let audio_file = AudioFile {
-                            path: file_path.to_path_buf(),
-                            filename,
-                            extension: ext,
-                            current_metadata: self.read_metadata(file_path).ok(),
-                        };
-
-                        audio_files.push(audio_file);
-                    }
-                }
-            }
-        }
+            .map(|entry| entry.into_path())
+            .filter(|file_path| file_path.is_file() && self.is_supported(file_path))
+            .filter(move |file_path| seen.insert(canonical_path(file_path)))
+    }
 
-        Ok(audio_files)
+    pub fn is_supported(&self, path: &Path) -> bool {
+        path.extension()
+            .map(|ext| self.supported_extensions.contains(&ext.to_string_lossy().to_lowercase()))
+            .unwrap_or(false)
     }
 
-    fn read_metadata(&self, path: &Path) -> Result<Metadata, String> {
+    pub fn read_metadata(&self, path: &Path) -> Result<Metadata, String> {
         let ext = path.extension().and_then(|s| s.to_str());
-        match ext {
+        let mut metadata = match ext {
             Some("mp3") => self.read_mp3_metadata(path),
             Some("flac") => self.read_flac_metadata(path),
             Some("wav") => self.read_wav_metadata(path),
             Some("ogg") => self.read_ogg_metadata(path),
             Some("m4a") => self.read_m4a_metadata(path),
+            Some("aiff") | Some("aif") | Some("opus") | Some("wv") | Some("ape") => self.read_lofty_metadata(path),
+            // DSF carries a plain ID3v2 tag, read the same way as an MP3's.
+            Some("dsf") => self.read_mp3_metadata(path),
             _ => Ok(Metadata {
                 title: None,
                 artist: None,
+                artists: Vec::new(),
+                album_artist: None,
                 album: None,
+                track_number: None,
+                disc_number: None,
+                composer: None,
+                label: None,
                 genre: None,
+                genres: Vec::new(),
                 year: None,
+                date: None,
+                release_type: None,
                 bpm: None,
+                key: None,
+                comment: None,
+                external_ids: ExternalIds::default(),
+                provenance: None,
+                cover_art: None,
+            })
+        }?;
+
+        if metadata.artists.is_empty() {
+            if let Some(ref artist) = metadata.artist {
+                metadata.artists = self.artist_rules.split(artist);
+            }
+        }
+        if metadata.genres.len() > 1 {
+            metadata.genre = Some(self.genre_rules.join(&metadata.genres));
+        } else if let Some(ref genre) = metadata.genre {
+            metadata.genres = self.genre_rules.split(genre);
+        }
+        Ok(metadata)
+    }
+
+    /// BPM as tag text. ID3 TBPM and the MP4 tmpo atom only hold whole
+    /// numbers; elsewhere one decimal is kept.
+    fn bpm_text(bpm: f32, integer: bool) -> String {
+        if integer || bpm.fract() == 0.0 {
+            format!("{}", bpm.round() as u32)
+        } else {
+            format!("{:.1}", bpm)
+        }
+    }
+
+    fn lofty_bpm(tag: &Tag) -> Option<f32> {
+        tag.get_string(&Self::format_key(tag.tag_type(), ItemKey::Bpm))
+            .or_else(|| tag.get_string(&ItemKey::IntegerBpm))
+            .and_then(|s| s.trim().parse::<f32>().ok())
+    }
+
+    /// `key` as the tag format stores it. lofty has no APEv2 mapping for
+    /// BPM, initial key or date, so APE tags get the free-form fields
+    /// foobar2000 writes.
+    fn format_key(tag_type: TagType, key: ItemKey) -> ItemKey {
+        let ape_field = match key {
+            ItemKey::Bpm | ItemKey::IntegerBpm => "BPM",
+            ItemKey::InitialKey => "INITIALKEY",
+            ItemKey::RecordingDate => "DATE",
+            _ => return key,
+        };
+        if tag_type == TagType::Ape {
+            ItemKey::Unknown(ape_field.to_string())
+        } else {
+            key
+        }
+    }
+
+    /// Sets a text field through `format_key`; `insert_text` would drop the
+    /// free-form APE ones.
+    fn insert_format_text(tag: &mut Tag, key: ItemKey, value: String) {
+        let key = Self::format_key(tag.tag_type(), key);
+        if matches!(key, ItemKey::Unknown(_)) {
+            tag.insert_unchecked(TagItem::new(key, ItemValue::Text(value)));
+        } else {
+            tag.insert_text(key, value);
+        }
+    }
+
+    /// Key for a free-form text field in the tag format's own convention;
+    /// formats without free-form fields (RIFF INFO, AIFF text) get `None`.
+    fn custom_item_key(tag_type: TagType, name: &str) -> Option<ItemKey> {
+        match tag_type {
+            TagType::Id3v2 | TagType::VorbisComments | TagType::Ape => Some(ItemKey::Unknown(name.to_string())),
+            TagType::Mp4Ilst => Some(ItemKey::Unknown(format!("----:com.apple.iTunes:{}", name))),
+            _ => None,
+        }
+    }
+
+    /// Where Picard keeps the release type in each tag format.
+    fn release_type_key(tag_type: TagType) -> Option<ItemKey> {
+        match tag_type {
+            TagType::VorbisComments | TagType::Ape => Some(ItemKey::Unknown(RELEASE_TYPE_VORBIS.to_string())),
+            _ => Self::custom_item_key(tag_type, RELEASE_TYPE_TXXX),
+        }
+    }
+
+    /// Genres stored as separate values (ID3v2.4 null-separated, repeated
+    /// Vorbis fields); empty when there is only one, which `read_metadata`
+    /// splits by the genre rules instead.
+    fn multi_value_genres<'a>(values: impl Iterator<Item = &'a str>) -> Vec<String> {
+        let genres: Vec<String> = values
+            .flat_map(|value| value.split(MULTI_VALUE_SEPARATOR))
+            .map(str::trim)
+            .filter(|genre| !genre.is_empty())
+            .map(str::to_string)
+            .collect();
+        if genres.len() > 1 { genres } else { Vec::new() }
+    }
+
+    /// The genres to write for `metadata`, in the configured capitalization.
+    fn genres_to_write(&self, metadata: &Metadata) -> Vec<String> {
+        self.genre_rules
+            .for_writing(metadata.genre.as_deref(), &metadata.genres)
+            .iter()
+            .map(|genre| api_client::apply_genre_case(genre, self.genre_case))
+            .collect()
+    }
+
+    fn lofty_metadata(tag: &Tag) -> Metadata {
+        let text = |key: ItemKey| tag.get_string(&key).map(|s| s.to_string());
+
+        Metadata {
+            title: tag.title().map(|s| s.to_string()),
+            artist: tag.artist().map(|s| s.to_string()),
+            artists: Self::custom_item_key(tag.tag_type(), ARTISTS_FIELD)
+                .map(|key| {
+                    tag.get_strings(&key)
+                        .flat_map(|value| value.split(MULTI_VALUE_SEPARATOR))
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            album_artist: text(ItemKey::AlbumArtist),
+            album: tag.album().map(|s| s.to_string()),
+            track_number: tag.track(),
+            disc_number: tag.disk(),
+            composer: text(ItemKey::Composer),
+            label: text(ItemKey::Label),
+            genre: tag.genre().map(|s| s.to_string()),
+            genres: Self::multi_value_genres(tag.get_strings(&ItemKey::Genre)),
+            year: tag.year().map(|y| y as i32),
+            // Most formats keep the year in the same field, so only report a
+            // date that says more than the year does.
+            date: text(Self::format_key(tag.tag_type(), ItemKey::RecordingDate))
+                .and_then(|d| normalize_date(&d))
+                .filter(|d| d.len() > 4),
+            release_type: Self::release_type_key(tag.tag_type())
+                .and_then(text)
+                .and_then(|value| ReleaseType::parse(&value)),
+            bpm: Self::lofty_bpm(tag),
+            key: text(Self::format_key(tag.tag_type(), ItemKey::InitialKey)),
+            comment: tag.comment().map(|s| s.to_string()),
+            external_ids: ExternalIds {
+                musicbrainz_recording_id: text(ItemKey::MusicBrainzRecordingId),
+                musicbrainz_release_id: text(ItemKey::MusicBrainzReleaseId),
+                musicbrainz_artist_id: text(ItemKey::MusicBrainzArtistId),
+                beatport_track_id: Self::custom_item_key(tag.tag_type(), BEATPORT_TRACK_ID_FIELD)
+                    .and_then(text),
+                catalog_number: text(ItemKey::CatalogNumber),
+            },
+            provenance: Self::custom_item_key(tag.tag_type(), PROVENANCE_FIELD)
+                .and_then(text)
+                .and_then(|value| Provenance::parse(&value)),
+            cover_art: tag.pictures()
+                .iter()
+                .find(|picture| picture.pic_type() == PictureType::CoverFront)
+                .or_else(|| tag.pictures().first())
+                .map(|picture| {
+                    let mime_type = picture.mime_type().cloned()
+                        .unwrap_or_else(|| picture_mime_type(picture.data()));
+                    CoverArt::describe(mime_type.as_str(), picture.data())
+                }),
+        }
+    }
+
+    /// Sets every field present in `metadata`; absent fields keep their
+    /// current value. Ids the tag format has no mapping for are skipped.
+    /// Embedded artwork is brought within the artwork policy.
+    fn apply_lofty_metadata(&self, tag: &mut Tag, metadata: &Metadata) {
+        if let Some(ref title) = metadata.title {
+            tag.set_title(title.clone());
+        }
+
+        let (artist, artists) = self.credited_artists(metadata);
+        if let Some(artist) = artist {
+            tag.set_artist(artist);
+        }
+        if let Some(key) = Self::custom_item_key(tag.tag_type(), ARTISTS_FIELD) {
+            if let Some(artists) = artists {
+                tag.remove_key(&key);
+                if artists.len() > 1 {
+                    let values = if tag.tag_type() == TagType::VorbisComments {
+                        artists
+                    } else {
+                        vec![artists.join(&MULTI_VALUE_SEPARATOR.to_string())]
+                    };
+                    for value in values {
+                        tag.push_unchecked(TagItem::new(key.clone(), ItemValue::Text(value)));
+                    }
+                }
+            }
+        }
+
+        if let Some(ref album_artist) = metadata.album_artist {
+            tag.insert_text(ItemKey::AlbumArtist, album_artist.clone());
+        }
+
+        if let Some(ref album) = metadata.album {
+            tag.set_album(album.clone());
+        }
+
+        if let Some(track) = metadata.track_number {
+            tag.set_track(track);
+        }
+
+        if let Some(disc) = metadata.disc_number {
+            tag.set_disk(disc);
+        }
+
+        if let Some(ref composer) = metadata.composer {
+            tag.insert_text(ItemKey::Composer, composer.clone());
+        }
+
+        if let Some(ref label) = metadata.label {
+            tag.insert_text(ItemKey::Label, label.clone());
+        }
+
+        let genres = self.genres_to_write(metadata);
+        let multi_value = self.genre_rules.storage == GenreStorage::MultiValue
+            && matches!(tag.tag_type(), TagType::Id3v2 | TagType::VorbisComments);
+        if multi_value && genres.len() > 1 {
+            tag.remove_key(&ItemKey::Genre);
+            for genre in genres {
+                tag.push(TagItem::new(ItemKey::Genre, ItemValue::Text(genre)));
+            }
+        } else if !genres.is_empty() {
+            tag.set_genre(self.genre_rules.join(&genres));
+        }
+
+        if let Some(year) = metadata.year {
+            tag.set_year(year as u32);
+        }
+
+        if let Some(date) = metadata.date.as_deref().and_then(normalize_date) {
+            Self::insert_format_text(tag, ItemKey::RecordingDate, date);
+        }
+
+        let release_type_key = Self::release_type_key(tag.tag_type());
+        if let (Some(key), Some(release_type)) = (release_type_key, metadata.release_type) {
+            tag.insert_unchecked(TagItem::new(key, ItemValue::Text(release_type.as_str().to_string())));
+        }
+
+        if let Some(bpm) = metadata.bpm.filter(|bpm| *bpm > 0.0) {
+            // Vorbis comments and APE take a free-form BPM field; ID3v2 and
+            // MP4 only have the integer one (TBPM, tmpo).
+            if matches!(tag.tag_type(), TagType::VorbisComments | TagType::Ape) {
+                Self::insert_format_text(tag, ItemKey::Bpm, Self::bpm_text(bpm, false));
+            } else {
+                tag.remove_key(&ItemKey::Bpm);
+                tag.insert_text(ItemKey::IntegerBpm, Self::bpm_text(bpm, true));
+            }
+        }
+
+        if let Some(ref key) = metadata.key {
+            Self::insert_format_text(tag, ItemKey::InitialKey, key.clone());
+        }
+
+        let existing = tag.comment().map(|comment| comment.to_string());
+        if let Some(comment) = self.comment_rule.merge(existing.as_deref(), metadata.comment.as_deref()) {
+            tag.set_comment(comment);
+        }
+
+        let ids = &metadata.external_ids;
+        let id_fields = [
+            (ItemKey::MusicBrainzRecordingId, &ids.musicbrainz_recording_id),
+            (ItemKey::MusicBrainzReleaseId, &ids.musicbrainz_release_id),
+            (ItemKey::MusicBrainzArtistId, &ids.musicbrainz_artist_id),
+            (ItemKey::CatalogNumber, &ids.catalog_number),
+        ];
+        let beatport_key = Self::custom_item_key(tag.tag_type(), BEATPORT_TRACK_ID_FIELD);
+        let id_fields = id_fields
+            .into_iter()
+            .chain(beatport_key.map(|key| (key, &ids.beatport_track_id)));
+
+        // `insert_text` silently drops free-form keys and ones lofty maps
+        // through a special case (the ID3v2 UFID recording id); unwritable
+        // keys are still filtered out when the tag is saved.
+        for (key, value) in id_fields {
+            if let Some(value) = value {
+                tag.insert_unchecked(TagItem::new(key, ItemValue::Text(value.clone())));
+            }
+        }
+
+        let provenance_key = Self::custom_item_key(tag.tag_type(), PROVENANCE_FIELD);
+        if let (Some(key), Some(value)) = (provenance_key, self.provenance_value(metadata)) {
+            tag.insert_unchecked(TagItem::new(key, ItemValue::Text(value)));
+        }
+
+        for index in 0..tag.pictures().len() {
+            let picture = &tag.pictures()[index];
+            if let Some(data) = self.artwork_policy.fit(picture.data()) {
+                let resized = Picture::new_unchecked(
+                    picture.pic_type(),
+                    Some(MimeType::Jpeg),
+                    picture.description().map(str::to_string),
+                    data,
+                );
+                tag.set_picture(index, resized);
+            }
+        }
+    }
+
+    /// The organize pattern configured for the file's extension, if any.
+    /// Keys may be written with or without a leading dot, in any case.
+    fn pattern_for(&self, path: &Path) -> Option<&str> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        self.extension_patterns
+            .iter()
+            .find(|(key, pattern)| {
+                key.trim().trim_start_matches('.').eq_ignore_ascii_case(&extension) && !pattern.trim().is_empty()
             })
+            .map(|(_, pattern)| pattern.as_str())
+    }
+
+    /// The artist credit and individual artists to write. A missing credit
+    /// is joined from the artists and missing artists are split from the
+    /// credit; `None` leaves the field alone. A single artist is written as
+    /// the credit alone, clearing any ARTISTS field.
+    fn credited_artists(&self, metadata: &Metadata) -> (Option<String>, Option<Vec<String>>) {
+        let artist = metadata.artist.clone().or_else(|| {
+            Some(self.artist_rules.join(&metadata.artists)).filter(|a| !a.is_empty())
+        });
+        let artists = if metadata.artists.is_empty() {
+            artist.as_deref().map(|a| self.artist_rules.split(a))
+        } else {
+            Some(metadata.artists.clone())
+        };
+        (artist, artists)
+    }
+
+    /// The encoded provenance tag to write, if enabled. A write without
+    /// provenance leaves an existing tag as it is.
+    fn provenance_value(&self, metadata: &Metadata) -> Option<String> {
+        if !self.write_provenance {
+            return None;
+        }
+        metadata.provenance.as_ref().map(Provenance::encode)
+    }
+
+    /// Whether the file's tag is ID3v2 handled with the id3 crate: MP3s, and
+    /// DSF files, whose trailing ID3v2 chunk lofty cannot read.
+    pub fn has_id3_tag(path: &Path) -> bool {
+        matches!(path.extension().and_then(|s| s.to_str()), Some("mp3") | Some("dsf"))
+    }
+
+    fn is_dsf(path: &Path) -> bool {
+        path.extension().and_then(|s| s.to_str()) == Some("dsf")
+    }
+
+    fn read_id3_tag(path: &Path) -> id3::Result<id3::Tag> {
+        if Self::is_dsf(path) {
+            dsf::read_tag(path)
+        } else {
+            id3::Tag::read_from_path(path)
         }
     }
 
+    fn write_id3_tag(path: &Path, tag: &id3::Tag) -> id3::Result<()> {
+        if Self::is_dsf(path) {
+            dsf::write_tag(path, tag)
+        } else {
+            tag.write_to_path(path, id3::Version::Id3v24)
+        }
+    }
+
+    /// The plain comment: the first without a description, which is what
+    /// other taggers show, else the first of any kind.
+    fn mp3_comment(tag: &id3::Tag) -> Option<&id3::frame::Comment> {
+        tag.comments()
+            .find(|comment| comment.description.is_empty())
+            .or_else(|| tag.comments().next())
+    }
+
+    fn mp3_extended_text(tag: &id3::Tag, description: &str) -> Option<String> {
+        tag.extended_texts()
+            .find(|text| text.description == description)
+            .map(|text| text.value.clone())
+    }
+
     fn read_mp3_metadata(&self, path: &Path) -> Result<Metadata, String> {
-        let tag = id3::Tag::read_from_path(path)
+        let tag = Self::read_id3_tag(path)
             .map_err(|e| format!("Failed to read ID3 tags: {}", e))?;
 
+        let musicbrainz_recording_id = tag.unique_file_identifiers()
+            .find(|ufid| ufid.owner_identifier == MUSICBRAINZ_UFID_OWNER)
+            .map(|ufid| String::from_utf8_lossy(&ufid.identifier).to_string());
+        let cover_art = tag.pictures()
+            .find(|picture| picture.picture_type == id3::frame::PictureType::CoverFront)
+            .or_else(|| tag.pictures().next())
+            .map(|picture| CoverArt::describe(&picture.mime_type, &picture.data));
+
         Ok(Metadata {
             title: tag.title().map(|s| s.to_string()),
             artist: tag.artist().map(|s| s.to_string()),
+            artists: Self::mp3_extended_text(&tag, ARTISTS_FIELD)
+                .map(|value| value.split(MULTI_VALUE_SEPARATOR).map(str::to_string).collect())
+                .unwrap_or_default(),
+            album_artist: tag.album_artist().map(|s| s.to_string()),
             album: tag.album().map(|s| s.to_string()),
-            genre: tag.genre().map(|s| s.to_string()),
+            track_number: tag.track(),
+            disc_number: tag.disc(),
+            composer: tag.get("TCOM")
+                .and_then(|frame| frame.content().text())
+                .map(|s| s.to_string()),
+            label: tag.get("TPUB")
+                .and_then(|frame| frame.content().text())
+                .map(|s| s.to_string()),
+            genre: tag.genres()
+                .and_then(|genres| genres.first().map(|s| s.to_string())),
+            genres: Self::multi_value_genres(tag.genres().unwrap_or_default().into_iter()),
             year: tag.year(),
-            bpm: None,
+            date: tag.date_recorded()
+                .or_else(|| tag.date_released())
+                .and_then(|ts| normalize_date(&ts.to_string())),
+            release_type: Self::mp3_extended_text(&tag, RELEASE_TYPE_TXXX)
+                .and_then(|value| ReleaseType::parse(&value)),
+            bpm: tag.get("TBPM")
+                .and_then(|frame| frame.content().text())
+                .and_then(|text| text.trim().parse::<f32>().ok()),
+            key: tag.get("TKEY")
+                .and_then(|frame| frame.content().text())
+                .map(|s| s.to_string()),
+            comment: Self::mp3_comment(&tag).map(|comment| comment.text.clone()),
+            external_ids: ExternalIds {
+                musicbrainz_recording_id,
+                musicbrainz_release_id: Self::mp3_extended_text(&tag, MUSICBRAINZ_RELEASE_TXXX),
+                musicbrainz_artist_id: Self::mp3_extended_text(&tag, MUSICBRAINZ_ARTIST_TXXX),
+                beatport_track_id: Self::mp3_extended_text(&tag, BEATPORT_TRACK_ID_FIELD),
+                catalog_number: Self::mp3_extended_text(&tag, CATALOG_NUMBER_TXXX),
+            },
+            provenance: Self::mp3_extended_text(&tag, PROVENANCE_FIELD)
+                .and_then(|value| Provenance::parse(&value)),
+            cover_art,
         })
     }
 
@@ -125,14 +1364,7 @@ impl FileScanner {
             .or_else(|| tagged_file.first_tag())
             .ok_or("No tags found in FLAC file")?;
 
-        Ok(Metadata {
-            title: tag.title().map(|s| s.to_string()),
-            artist: tag.artist().map(|s| s.to_string()),
-            album: tag.album().map(|s| s.to_string()),
-            genre: tag.genre().map(|s| s.to_string()),
-            year: tag.year().map(|y| y as i32),
-            bpm: None,
-        })
+        Ok(Self::lofty_metadata(tag))
     }
 
     fn read_wav_metadata(&self, path: &Path) -> Result<Metadata, String> {
@@ -146,14 +1378,7 @@ impl FileScanner {
             .or_else(|| tagged_file.first_tag())
             .ok_or("No tags found in WAV file")?;
 
-        Ok(Metadata {
-            title: tag.title().map(|s| s.to_string()),
-            artist: tag.artist().map(|s| s.to_string()),
-            album: tag.album().map(|s| s.to_string()),
-            genre: tag.genre().map(|s| s.to_string()),
-            year: tag.year().map(|y| y as i32),
-            bpm: None,
-        })
+        Ok(Self::lofty_metadata(tag))
     }
 
     fn read_ogg_metadata(&self, path: &Path) -> Result<Metadata, String> {
@@ -167,14 +1392,7 @@ impl FileScanner {
             .or_else(|| tagged_file.first_tag())
             .ok_or("No tags found in OGG file")?;
 
-        Ok(Metadata {
-            title: tag.title().map(|s| s.to_string()),
-            artist: tag.artist().map(|s| s.to_string()),
-            album: tag.album().map(|s| s.to_string()),
-            genre: tag.genre().map(|s| s.to_string()),
-            year: tag.year().map(|y| y as i32),
-            bpm: None,
-        })
+        Ok(Self::lofty_metadata(tag))
     }
 
     fn read_m4a_metadata(&self, path: &Path) -> Result<Metadata, String> {
@@ -188,17 +1406,62 @@ impl FileScanner {
             .or_else(|| tagged_file.first_tag())
             .ok_or("No tags found in M4A file")?;
 
-        Ok(Metadata {
-            title: tag.title().map(|s| s.to_string()),
-            artist: tag.artist().map(|s| s.to_string()),
-            album: tag.album().map(|s| s.to_string()),
-            genre: tag.genre().map(|s| s.to_string()),
-            year: tag.year().map(|y| y as i32),
-            bpm: None,
-        })
+        Ok(Self::lofty_metadata(tag))
+    }
+
+    /// AIFF, Opus, WavPack and Monkey's Audio, read from whichever tag
+    /// lofty treats as the format's own (an ID3 chunk, Vorbis comments,
+    /// APEv2).
+    fn read_lofty_metadata(&self, path: &Path) -> Result<Metadata, String> {
+        let tagged_file = Probe::open(path)
+            .map_err(|e| format!("Failed to open file: {}", e))?
+            .options(ParseOptions::new())
+            .read()
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+
+        let tag = tagged_file.primary_tag()
+            .or_else(|| tagged_file.first_tag())
+            .ok_or("No tags found in file")?;
+
+        Ok(Self::lofty_metadata(tag))
     }
 
     pub fn write_metadata(&self, path: &Path, metadata: &Metadata) -> Result<(), String> {
+        if simulation::is_enabled() {
+            simulation::record(SimulatedOperation::WriteTags {
+                path: path.to_path_buf(),
+                metadata: Box::new(metadata.clone()),
+            });
+            return Ok(());
+        }
+
+        self.journaled_write(path, || self.write_metadata_to_file(path, metadata))
+    }
+
+    /// Runs `write` on the file's tags, journaling the tags it had before.
+    fn journaled_write(&self, path: &Path, write: impl FnOnce() -> Result<(), String>) -> Result<(), String> {
+        // A file with no tag yet reads as empty. Tags that cannot be read at
+        // all cannot be put back either, so such a write goes unjournaled
+        // rather than failing.
+        let before = journal::is_recording()
+            .then(|| {
+                let raw_tag = match Self::has_id3_tag(path) {
+                    true => Some(Self::read_raw_id3_tag(path).ok()?
+                        .map_or_else(|| Self::encode_id3_tag(&id3::Tag::new()), Ok)
+                        .ok()?),
+                    false => None,
+                };
+                Some((self.read_metadata(path).unwrap_or_default(), self.read_tag_snapshot(path).ok()?, raw_tag))
+            })
+            .flatten();
+        write()?;
+        if let Some((metadata, tags, raw_tag)) = before {
+            journal::record_tag_write(path, metadata, tags, raw_tag);
+        }
+        Ok(())
+    }
+
+    fn write_metadata_to_file(&self, path: &Path, metadata: &Metadata) -> Result<(), String> {
         let ext = path.extension().and_then(|s| s.to_str());
         match ext {
             Some("mp3") => self.write_mp3_metadata(path, metadata),
@@ -206,37 +1469,162 @@ impl FileScanner {
             Some("wav") => self.write_wav_metadata(path, metadata),
             Some("ogg") => self.write_ogg_metadata(path, metadata),
             Some("m4a") => self.write_m4a_metadata(path, metadata),
+            Some("aiff") | Some("aif") | Some("opus") | Some("wv") | Some("ape") => {
+                self.edit_primary_tag(path, |tag| self.apply_lofty_metadata(tag, metadata))
+            }
+            Some("dsf") => self.write_mp3_metadata(path, metadata),
             _ => Err(format!("Unsupported file format for writing: {:?}", ext))
         }
     }
 
     fn write_mp3_metadata(&self, path: &Path, metadata: &Metadata) -> Result<(), String> {
-        let mut tag = id3::Tag::read_from_path(path)
+        let mut tag = Self::read_id3_tag(path)
             .unwrap_or_else(|_| id3::Tag::new());
 
         if let Some(ref title) = metadata.title {
             tag.set_title(title);
         }
 
-        if let Some(ref artist) = metadata.artist {
+        let (artist, artists) = self.credited_artists(metadata);
+        if let Some(artist) = artist {
             tag.set_artist(artist);
         }
+        if let Some(artists) = artists {
+            tag.remove_extended_text(Some(ARTISTS_FIELD), None);
+            if artists.len() > 1 {
+                tag.add_frame(ExtendedText {
+                    description: ARTISTS_FIELD.to_string(),
+                    value: artists.join(&MULTI_VALUE_SEPARATOR.to_string()),
+                });
+            }
+        }
+
+        if let Some(ref album_artist) = metadata.album_artist {
+            tag.set_album_artist(album_artist);
+        }
 
         if let Some(ref album) = metadata.album {
             tag.set_album(album);
         }
 
-        if let Some(ref genre) = metadata.genre {
-            tag.set_genre(genre);
+        if let Some(track) = metadata.track_number {
+            tag.set_track(track);
+        }
+
+        if let Some(disc) = metadata.disc_number {
+            tag.set_disc(disc);
+        }
+
+        if let Some(ref composer) = metadata.composer {
+            tag.set_text("TCOM", composer);
+        }
+
+        if let Some(ref label) = metadata.label {
+            tag.set_text("TPUB", label);
+        }
+
+        let genres = self.genres_to_write(metadata);
+        if self.genre_rules.storage == GenreStorage::MultiValue && genres.len() > 1 {
+            tag.set_text_values("TCON", genres);
+        } else if !genres.is_empty() {
+            tag.set_genre(self.genre_rules.join(&genres));
         }
 
         if let Some(year) = metadata.year {
             tag.set_year(year);
         }
 
-        tag.write_to_path(path, id3::Version::Id3v24)
+        if let Some(date) = metadata.date.as_deref().and_then(normalize_date) {
+            let timestamp = date.parse::<id3::Timestamp>()
+                .map_err(|e| format!("Invalid release date {}: {}", date, e))?;
+            tag.set_date_recorded(timestamp);
+        }
+
+        if let Some(release_type) = metadata.release_type {
+            tag.add_frame(ExtendedText {
+                description: RELEASE_TYPE_TXXX.to_string(),
+                value: release_type.as_str().to_string(),
+            });
+        }
+
+        if let Some(bpm) = metadata.bpm.filter(|bpm| *bpm > 0.0) {
+            tag.set_text("TBPM", Self::bpm_text(bpm, true));
+        }
+
+        if let Some(ref key) = metadata.key {
+            tag.set_text("TKEY", key);
+        }
+
+        let existing = Self::mp3_comment(&tag).map(|comment| comment.text.clone());
+        if let Some(comment) = self.comment_rule.merge(existing.as_deref(), metadata.comment.as_deref()) {
+            tag.remove_comment(Some(""), None);
+            tag.add_frame(id3::frame::Comment {
+                lang: "eng".to_string(),
+                description: String::new(),
+                text: comment,
+            });
+        }
+
+        let ids = &metadata.external_ids;
+        if let Some(ref recording_id) = ids.musicbrainz_recording_id {
+            tag.remove_unique_file_identifier_by_owner_identifier(MUSICBRAINZ_UFID_OWNER);
+            tag.add_frame(UniqueFileIdentifier {
+                owner_identifier: MUSICBRAINZ_UFID_OWNER.to_string(),
+                identifier: recording_id.as_bytes().to_vec(),
+            });
+        }
+
+        let txxx_fields = [
+            (MUSICBRAINZ_RELEASE_TXXX, &ids.musicbrainz_release_id),
+            (MUSICBRAINZ_ARTIST_TXXX, &ids.musicbrainz_artist_id),
+            (BEATPORT_TRACK_ID_FIELD, &ids.beatport_track_id),
+            (CATALOG_NUMBER_TXXX, &ids.catalog_number),
+        ];
+        for (description, value) in txxx_fields {
+            if let Some(value) = value {
+                tag.add_frame(ExtendedText {
+                    description: description.to_string(),
+                    value: value.clone(),
+                });
+            }
+        }
+
+        if let Some(value) = self.provenance_value(metadata) {
+            tag.add_frame(ExtendedText {
+                description: PROVENANCE_FIELD.to_string(),
+                value,
+            });
+        }
+
+        let pictures: Vec<id3::frame::Picture> = tag.pictures().cloned().collect();
+        let resized: Vec<Option<Vec<u8>>> = pictures.iter()
+            .map(|picture| self.artwork_policy.fit(&picture.data))
+            .collect();
+        if resized.iter().any(Option::is_some) {
+            tag.remove("APIC");
+            for (mut picture, data) in pictures.into_iter().zip(resized) {
+                if let Some(data) = data {
+                    picture.mime_type = "image/jpeg".to_string();
+                    picture.data = data;
+                }
+                tag.add_frame(picture);
+            }
+        }
+
+        Self::write_id3_tag(path, &tag)
             .map_err(|e| format!("Failed to write ID3 tags: {}", e))?;
 
+        // ID3v1 tags only ever trail MP3s. Undoing this write puts back the
+        // ID3v2 tag only, leaving the ID3v1 tag as stripped or synced.
+        if Self::is_dsf(path) {
+            return Ok(());
+        }
+        match self.id3v1_policy {
+            Id3v1Policy::Keep => {}
+            Id3v1Policy::Strip => id3v1::strip(path)?,
+            Id3v1Policy::Sync => id3v1::sync(path, &self.read_mp3_metadata(path)?)?,
+        }
+
         Ok(())
     }
 
@@ -257,25 +1645,7 @@ impl FileScanner {
             }
         };
 
-        if let Some(ref title) = metadata.title {
-            tag.set_title(title.clone());
-        }
-
-        if let Some(ref artist) = metadata.artist {
-            tag.set_artist(artist.clone());
-        }
-
-        if let Some(ref album) = metadata.album {
-            tag.set_album(album.clone());
-        }
-
-        if let Some(ref genre) = metadata.genre {
-            tag.set_genre(genre.clone());
-        }
-
-        if let Some(year) = metadata.year {
-            tag.set_year(year as u32);
-        }
+        self.apply_lofty_metadata(tag, metadata);
 
         tag.save_to_path(path, WriteOptions::default())
             .map_err(|e| format!("Failed to write FLAC tags: {}", e))?;
@@ -284,6 +1654,8 @@ impl FileScanner {
     }
 
     fn write_wav_metadata(&self, path: &Path, metadata: &Metadata) -> Result<(), String> {
+        let bext = bwf::read_raw_bext(path)?;
+
         let mut tagged_file = Probe::open(path)
             .map_err(|e| format!("Failed to open WAV file: {}", e))?
             .options(ParseOptions::new())
@@ -300,28 +1672,20 @@ impl FileScanner {
             }
         };
 
-        if let Some(ref title) = metadata.title {
-            tag.set_title(title.clone());
-        }
+        self.apply_lofty_metadata(tag, metadata);
 
-        if let Some(ref artist) = metadata.artist {
-            tag.set_artist(artist.clone());
-        }
-
-        if let Some(ref album) = metadata.album {
-            tag.set_album(album.clone());
-        }
-
-        if let Some(ref genre) = metadata.genre {
-            tag.set_genre(genre.clone());
-        }
-
-        if let Some(year) = metadata.year {
-            tag.set_year(year as u32);
-        }
+        // The generic save path cannot write the MusicBrainz recording id as
+        // an ID3v2 UFID frame; converting to a concrete ID3v2 tag first can.
+        let saved = if tag.tag_type() == TagType::Id3v2 {
+            Id3v2Tag::from(tag.clone()).save_to_path(path, WriteOptions::default())
+        } else {
+            tag.save_to_path(path, WriteOptions::default())
+        };
+        saved.map_err(|e| format!("Failed to write WAV tags: {}", e))?;
 
-        tag.save_to_path(path, WriteOptions::default())
-            .map_err(|e| format!("Failed to write WAV tags: {}", e))?;
+        if let Some(bext) = bext {
+            bwf::ensure_bext(path, &bext)?;
+        }
 
         Ok(())
     }
@@ -343,25 +1707,7 @@ impl FileScanner {
             }
         };
 
-        if let Some(ref title) = metadata.title {
-            tag.set_title(title.clone());
-        }
-
-        if let Some(ref artist) = metadata.artist {
-            tag.set_artist(artist.clone());
-        }
-
-        if let Some(ref album) = metadata.album {
-            tag.set_album(album.clone());
-        }
-
-        if let Some(ref genre) = metadata.genre {
-            tag.set_genre(genre.clone());
-        }
-
-        if let Some(year) = metadata.year {
-            tag.set_year(year as u32);
-        }
+        self.apply_lofty_metadata(tag, metadata);
 
         tag.save_to_path(path, WriteOptions::default())
             .map_err(|e| format!("Failed to write OGG tags: {}", e))?;
@@ -386,53 +1732,311 @@ impl FileScanner {
             }
         };
 
-        if let Some(ref title) = metadata.title {
-            tag.set_title(title.clone());
+        self.apply_lofty_metadata(tag, metadata);
+
+        tag.save_to_path(path, WriteOptions::default())
+            .map_err(|e| format!("Failed to write M4A tags: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Whether any of the file's tags carries an embedded picture.
+    pub fn has_artwork(&self, path: &Path) -> Result<bool, String> {
+        if Self::has_id3_tag(path) {
+            return match Self::read_id3_tag(path) {
+                Ok(tag) => Ok(tag.pictures().next().is_some()),
+                Err(e) if matches!(e.kind, id3::ErrorKind::NoTag) => Ok(false),
+                Err(e) => Err(format!("Failed to read ID3 tags: {}", e)),
+            };
         }
 
-        if let Some(ref artist) = metadata.artist {
-            tag.set_artist(artist.clone());
+        let tagged_file = Probe::open(path)
+            .map_err(|e| format!("Failed to open file: {}", e))?
+            .read()
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+
+        Ok(tagged_file.tags().iter().any(|tag| !tag.pictures().is_empty()))
+    }
+
+    /// The front cover (or else the first picture) and its MIME type.
+    pub fn read_front_cover(&self, path: &Path) -> Result<Option<(MimeType, Vec<u8>)>, String> {
+        if Self::has_id3_tag(path) {
+            let tag = match Self::read_id3_tag(path) {
+                Ok(tag) => tag,
+                Err(e) if matches!(e.kind, id3::ErrorKind::NoTag) => return Ok(None),
+                Err(e) => return Err(format!("Failed to read ID3 tags: {}", e)),
+            };
+            let cover = tag.pictures()
+                .find(|picture| picture.picture_type == id3::frame::PictureType::CoverFront)
+                .or_else(|| tag.pictures().next());
+            return Ok(cover.map(|picture| (MimeType::from_str(&picture.mime_type), picture.data.clone())));
         }
 
-        if let Some(ref album) = metadata.album {
-            tag.set_album(album.clone());
+        let tagged_file = Probe::open(path)
+            .map_err(|e| format!("Failed to open file: {}", e))?
+            .read()
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+
+        let pictures: Vec<&Picture> = tagged_file.tags().iter().flat_map(|tag| tag.pictures()).collect();
+        let cover = pictures.iter()
+            .find(|picture| picture.pic_type() == PictureType::CoverFront)
+            .or_else(|| pictures.first());
+        Ok(cover.map(|picture| {
+            let mime_type = picture.mime_type().cloned()
+                .unwrap_or_else(|| picture_mime_type(picture.data()));
+            (mime_type, picture.data().to_vec())
+        }))
+    }
+
+    /// Embeds `data` as the front cover, replacing any existing one. The
+    /// image is fitted to the artwork policy first.
+    pub fn embed_artwork(&self, path: &Path, data: &[u8]) -> Result<(), String> {
+        let (mime_type, data) = match self.artwork_policy.fit(data) {
+            Some(fitted) => (MimeType::Jpeg, fitted),
+            None => (picture_mime_type(data), data.to_vec()),
+        };
+        self.embed_picture(path, mime_type, data)
+    }
+
+    /// Writes a musical key to TKEY (ID3) or the format's initial key
+    /// field, leaving the rest of the tag alone.
+    pub fn write_key(&self, path: &Path, key: &str) -> Result<(), String> {
+        if simulation::is_enabled() {
+            simulation::record(SimulatedOperation::WriteField {
+                path: path.to_path_buf(),
+                field: "key".to_string(),
+                value: key.to_string(),
+            });
+            return Ok(());
         }
 
-        if let Some(ref genre) = metadata.genre {
-            tag.set_genre(genre.clone());
+        self.journaled_write(path, || {
+            if Self::has_id3_tag(path) {
+                let mut tag = Self::read_id3_tag(path)
+                    .unwrap_or_else(|_| id3::Tag::new());
+                tag.set_text("TKEY", key);
+                return Self::write_id3_tag(path, &tag)
+                    .map_err(|e| format!("Failed to write ID3 tags: {}", e));
+            }
+
+            self.edit_primary_tag(path, |tag| {
+                Self::insert_format_text(tag, ItemKey::InitialKey, key.to_string());
+            })
+        })
+    }
+
+    fn embed_picture(&self, path: &Path, mime_type: MimeType, data: Vec<u8>) -> Result<(), String> {
+        if simulation::is_enabled() {
+            simulation::record(SimulatedOperation::EmbedArtwork {
+                path: path.to_path_buf(),
+                bytes: data.len(),
+            });
+            return Ok(());
         }
 
-        if let Some(year) = metadata.year {
-            tag.set_year(year as u32);
+        self.journaled_write(path, || {
+            if Self::has_id3_tag(path) {
+                let mut tag = Self::read_id3_tag(path)
+                    .unwrap_or_else(|_| id3::Tag::new());
+                tag.remove_picture_by_type(id3::frame::PictureType::CoverFront);
+                tag.add_frame(id3::frame::Picture {
+                    mime_type: mime_type.as_str().to_string(),
+                    picture_type: id3::frame::PictureType::CoverFront,
+                    description: String::new(),
+                    data,
+                });
+                return Self::write_id3_tag(path, &tag)
+                    .map_err(|e| format!("Failed to write ID3 tags: {}", e));
+            }
+
+            self.edit_primary_tag(path, |tag| {
+                tag.remove_picture_type(PictureType::CoverFront);
+                tag.push_picture(Picture::new_unchecked(PictureType::CoverFront, Some(mime_type), None, data));
+            })
+        })
+    }
+
+    /// Saving a generic tag writes the APE disc number as "Disk", which
+    /// lofty (like most players) only reads back from "Disc", so the number
+    /// is moved into a "Disc" field of its own.
+    fn fix_ape_disc(tag: &mut Tag) {
+        let Some(disc) = tag.disk() else {
+            return;
+        };
+        let value = match tag.disk_total() {
+            Some(total) => format!("{}/{}", disc, total),
+            None => disc.to_string(),
+        };
+        tag.remove_disk();
+        tag.remove_disk_total();
+        tag.insert_unchecked(TagItem::new(ItemKey::Unknown("Disc".to_string()), ItemValue::Text(value)));
+    }
+
+    /// Applies `edit` to the file's primary tag (created if missing) and
+    /// saves it, keeping a WAV file's bext chunk.
+    fn edit_primary_tag(&self, path: &Path, edit: impl FnOnce(&mut Tag)) -> Result<(), String> {
+        let bext = match path.extension().and_then(|s| s.to_str()) {
+            Some("wav") => bwf::read_raw_bext(path)?,
+            _ => None,
+        };
+
+        let mut tagged_file = Probe::open(path)
+            .map_err(|e| format!("Failed to open file: {}", e))?
+            .options(ParseOptions::new())
+            .read()
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+
+        if tagged_file.primary_tag().is_none() {
+            let new_tag = Tag::new(tagged_file.primary_tag_type());
+            tagged_file.insert_tag(new_tag);
         }
+        let tag = tagged_file.primary_tag_mut()
+            .ok_or("Failed to create new tag")?;
 
-        tag.save_to_path(path, WriteOptions::default())
-            .map_err(|e| format!("Failed to write M4A tags: {}", e))?;
+        edit(tag);
+
+        if tag.tag_type() == TagType::Ape {
+            Self::fix_ape_disc(tag);
+        }
+        let saved = if tag.tag_type() == TagType::Id3v2 {
+            Id3v2Tag::from(tag.clone()).save_to_path(path, WriteOptions::default())
+        } else {
+            tag.save_to_path(path, WriteOptions::default())
+        };
+        saved.map_err(|e| format!("Failed to write tags: {}", e))?;
+
+        if let Some(bext) = bext {
+            bwf::ensure_bext(path, &bext)?;
+        }
 
         Ok(())
     }
 
+    /// Every text field of the file's tag, keyed by the format's own field
+    /// name: ID3 frame ids (`TXXX:<description>` for user text), Vorbis
+    /// field names or MP4 atoms.
+    pub fn read_tag_snapshot(&self, path: &Path) -> Result<BTreeMap<String, Vec<String>>, String> {
+        let mut snapshot: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+        if Self::has_id3_tag(path) {
+            let tag = match Self::read_id3_tag(path) {
+                Ok(tag) => tag,
+                Err(e) if matches!(e.kind, id3::ErrorKind::NoTag) => return Ok(snapshot),
+                Err(e) => return Err(format!("Failed to read ID3 tags: {}", e)),
+            };
+            for frame in tag.frames() {
+                if let Some(text) = frame.content().extended_text() {
+                    snapshot.insert(format!("TXXX:{}", text.description), vec![text.value.clone()]);
+                } else if let Some(values) = frame.content().text_values() {
+                    snapshot.insert(frame.id().to_string(), values.map(str::to_string).collect());
+                }
+            }
+            return Ok(snapshot);
+        }
+
+        let tagged_file = Probe::open(path)
+            .map_err(|e| format!("Failed to open file: {}", e))?
+            .options(ParseOptions::new())
+            .read()
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+
+        if let Some(tag) = tagged_file.primary_tag().or_else(|| tagged_file.first_tag()) {
+            for item in tag.items() {
+                let key = item.key().map_key(tag.tag_type(), true);
+                if let (Some(key), Some(text)) = (key, item.value().text()) {
+                    snapshot.entry(key.to_string()).or_default().push(text.to_string());
+                }
+            }
+        }
+        Ok(snapshot)
+    }
+
+    /// Sets every field in `snapshot` and removes those it lists with no
+    /// values; fields it does not list are left as they are.
+    fn write_tag_snapshot(&self, path: &Path, snapshot: &BTreeMap<String, Vec<String>>) -> Result<(), String> {
+        if snapshot.is_empty() {
+            return Ok(());
+        }
+
+        if Self::has_id3_tag(path) {
+            let mut tag = Self::read_id3_tag(path)
+                .unwrap_or_else(|_| id3::Tag::new());
+            for (key, values) in snapshot {
+                if let Some(description) = key.strip_prefix("TXXX:") {
+                    tag.remove_extended_text(Some(description), None);
+                    if !values.is_empty() {
+                        tag.add_frame(ExtendedText {
+                            description: description.to_string(),
+                            value: values.join(&MULTI_VALUE_SEPARATOR.to_string()),
+                        });
+                    }
+                } else if key.starts_with('T') {
+                    if values.is_empty() {
+                        tag.remove(key);
+                    } else {
+                        tag.set_text_values(key, values.iter().cloned());
+                    }
+                }
+            }
+            return Self::write_id3_tag(path, &tag)
+                .map_err(|e| format!("Failed to write ID3 tags: {}", e));
+        }
+
+        self.edit_primary_tag(path, |tag| {
+            let tag_type = tag.tag_type();
+            for (key, values) in snapshot {
+                let item_key = ItemKey::from_key(tag_type, key);
+                tag.remove_key(&item_key);
+                for value in values {
+                    tag.push_unchecked(TagItem::new(item_key.clone(), ItemValue::Text(value.clone())));
+                }
+            }
+        })
+    }
+
     pub fn backup_metadata(&self, path: &Path, metadata: &Metadata) -> Result<PathBuf, String> {
         let backup_dir = path.parent()
             .ok_or("Cannot determine parent directory")?
-            .join(".autogenre_backups");
-        
-        fs::create_dir_all(&backup_dir)
-            .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+            .join(backup::BACKUP_DIR);
 
         let filename = path.file_name()
-            .ok_or("Cannot determine filename")?
-            .to_string_lossy();
+            .ok_or("Cannot determine filename")?;
         
         let timestamp = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_secs();
         
-        let backup_filename = format!("{}.{}.json", filename, timestamp);
-        let backup_path = backup_dir.join(backup_filename);
+        let backup_path = backup_dir.join(backup::backup_file_name(filename, timestamp));
+
+        if simulation::is_enabled() {
+            simulation::record(SimulatedOperation::Backup {
+                path: path.to_path_buf(),
+                backup_path: backup_path.clone(),
+            });
+            return Ok(backup_path);
+        }
+
+        fs::create_dir_all(&backup_dir)
+            .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+
+        let artwork = match self.read_front_cover(path)? {
+            Some((mime_type, data)) => Some(backup::store_artwork(&backup_path, mime_type.as_str(), &data)?),
+            None => None,
+        };
+        let raw_tag = match Self::read_raw_id3_tag(path)? {
+            Some(data) => Some(backup::store_raw_tag(&backup_path, &data)?),
+            None => None,
+        };
+        let backup = Backup {
+            version: BACKUP_VERSION,
+            metadata: metadata.clone(),
+            tags: self.read_tag_snapshot(path)?,
+            artwork,
+            raw_tag,
+        };
 
-        let json_data = serde_json::to_string_pretty(metadata)
+        let json_data = serde_json::to_string_pretty(&backup)
             .map_err(|e| format!("Failed to serialize metadata: {}", e))?;
 
         fs::write(&backup_path, json_data)
@@ -441,88 +2045,447 @@ impl FileScanner {
         Ok(backup_path)
     }
 
-    pub fn organize_file(&self, path: &Path, metadata: &Metadata, base_folder: &Path, pattern: &str) -> Result<PathBuf, String> {
+    /// The file's whole ID3v2 tag, encoded, for MP3 and DSF files that have
+    /// one.
+    fn read_raw_id3_tag(path: &Path) -> Result<Option<Vec<u8>>, String> {
+        if !Self::has_id3_tag(path) {
+            return Ok(None);
+        }
+        match Self::read_id3_tag(path) {
+            Ok(tag) => Self::encode_id3_tag(&tag).map(Some),
+            Err(e) if matches!(e.kind, id3::ErrorKind::NoTag) => Ok(None),
+            Err(e) => Err(format!("Failed to read ID3 tag: {}", e)),
+        }
+    }
+
+    fn encode_id3_tag(tag: &id3::Tag) -> Result<Vec<u8>, String> {
+        let mut data = Vec::new();
+        tag.write_to(&mut data, tag.version())
+            .map_err(|e| format!("Failed to encode ID3 tag: {}", e))?;
+        Ok(data)
+    }
+
+    pub fn backup_current_metadata(&self, path: &Path) -> Result<PathBuf, String> {
+        let current = self.read_metadata(path)
+            .map_err(|_| "Cannot read current metadata for backup".to_string())?;
+        self.backup_metadata(path, &current)
+    }
+
+    /// Extracts the creation timestamp encoded in a backup filename
+    /// (`<original name>.<unix seconds>.json`).
+    pub fn backup_timestamp(backup_path: &Path) -> Option<u64> {
+        if backup_path.extension()? != "json" {
+            return None;
+        }
+        Path::new(backup_path.file_stem()?).extension()?.to_str()?.parse().ok()
+    }
+
+    /// A field's tag value for folder and filename patterns, before
+    /// sanitizing.
+    fn pattern_value(&self, field: &str, metadata: &Metadata, artist: Option<&String>) -> Option<String> {
+        match field {
+            "genre" => self.genres_to_write(metadata).first().cloned(),
+            "artist" => artist.cloned(),
+            "artist_sort" => artist.map(|a| collation::sort_name(a, &self.sort_locale)),
+            "album_artist" => metadata.album_artist.clone().or_else(|| artist.cloned()),
+            "album" => metadata.album.clone(),
+            "track" => metadata.track_number.map(|n| n.to_string()),
+            "disc" => metadata.disc_number.map(|n| n.to_string()),
+            "composer" => metadata.composer.clone(),
+            "label" => metadata.label.clone(),
+            "title" => metadata.title.clone(),
+            "key" => metadata.key.clone(),
+            "bpm" => metadata.bpm.map(|b| format!("{:.0}", b)),
+            "release_type" => metadata.release_type.map(|t| t.label().to_string()),
+            "year" => metadata.release_year().map(|y| y.to_string()),
+            "date" => metadata.date.clone().or_else(|| metadata.release_year().map(|y| y.to_string())),
+            "decade" => metadata.release_year().map(|y| format!("{}s", y - y.rem_euclid(10))),
+            _ => None,
+        }
+    }
+
+    /// The folder and full path organize would give the file, whether or
+    /// not it is there already.
+    fn organized_path(&self, path: &Path, metadata: &Metadata, base_folder: &Path, pattern: &str) -> Result<(PathBuf, PathBuf), String> {
+        let pattern = self.pattern_for(path).unwrap_or(pattern);
         let sanitize = |s: &str| -> String {
             s.chars()
-                .map(|c| if c.is_alphanumeric() || c == ' ' || c == '-' { c } else { '_' })
+                .map(|c| if c.is_alphanumeric() || c == ' ' || c == '-' || c == ',' { c } else { '_' })
                 .collect()
         };
 
-        let expanded_pattern = pattern
-            .replace("{genre}", &metadata.genre.as_ref().map(|g| sanitize(g)).unwrap_or_else(|| "Unknown".to_string()))
-            .replace("{artist}", &metadata.artist.as_ref().map(|a| sanitize(a)).unwrap_or_else(|| "Unknown".to_string()))
-            .replace("{album}", &metadata.album.as_ref().map(|a| sanitize(a)).unwrap_or_else(|| "Unknown".to_string()))
-            .replace("{title}", &metadata.title.as_ref().map(|t| sanitize(t)).unwrap_or_else(|| "Unknown".to_string()))
-            .replace("{year}", &metadata.year.map(|y| y.to_string()).unwrap_or_else(|| "Unknown".to_string()));
+        let artist = if self.prefer_album_artist {
+            metadata.album_artist.as_ref()
+                .filter(|a| !a.trim().is_empty())
+                .or(metadata.artist.as_ref())
+        } else {
+            metadata.artist.as_ref()
+        };
+        let value = |field: &str| match self.pattern_value(field, metadata, artist) {
+            None if field == "genre" => self.genre_fallbacks.genre_for(path),
+            value => value,
+        };
+        let expanded_pattern = pattern::expand(pattern, value, sanitize, "Unknown")?;
 
-        let folder_path = base_folder.join(&expanded_pattern);
-        
-        fs::create_dir_all(&folder_path)
-            .map_err(|e| format!("Failed to create folder structure: {}", e))?;
+        let mut folders: Vec<&str> = expanded_pattern
+            .split(['/', '\\'])
+            .filter(|component| !component.trim().is_empty())
+            .collect();
 
         let filename = path.file_name()
-            .ok_or("Cannot determine filename")?;
-        
-        let new_path = folder_path.join(filename);
+            .ok_or("Cannot determine filename")?
+            .to_string_lossy()
+            .to_string();
+        let filename = if self.organize_max_depth > 0 && folders.len() > self.organize_max_depth {
+            let folded = folders.split_off(self.organize_max_depth);
+            flatten_into_name(&folded, &filename)
+        } else {
+            filename
+        };
+
+        let folder_path = folders
+            .iter()
+            .fold(base_folder.to_path_buf(), |folder, component| folder.join(component));
+        let destination = folder_path.join(&filename);
+        Ok((folder_path, destination))
+    }
+
+    /// Moves the file under `base_folder` into the folder `pattern` expands
+    /// to, or the pattern configured for the file's extension if any.
+    pub fn organize_file(&self, path: &Path, metadata: &Metadata, base_folder: &Path, pattern: &str) -> Result<Placement, String> {
+        let (folder_path, destination) = self.organized_path(path, metadata, base_folder, pattern)?;
+        if same_file(path, &destination) {
+            return Ok(Placement::in_place(path));
+        }
+
+        let copy = self.organize_mode == OrganizeMode::Copy;
+        let mut placement = self.arrive(path, destination)?;
+        placement.copied = copy && placement.pending();
+        if placement.duplicate == Some(DuplicateKept::Existing) && !copy {
+            // A copy leaves the source alone either way.
+            self.quarantine_file(path)?;
+        }
+        if !placement.pending() {
+            return Ok(placement);
+        }
+
+        if simulation::is_enabled() {
+            if !folder_path.is_dir() {
+                simulation::record(SimulatedOperation::CreateDir { path: folder_path });
+            }
+            let (from, to) = (path.to_path_buf(), placement.path.clone());
+            simulation::record(if copy {
+                SimulatedOperation::Copy { from, to }
+            } else {
+                SimulatedOperation::Move { from, to }
+            });
+            return Ok(placement);
+        }
+
+        let source = fs::metadata(path)
+            .map_err(|e| format!("Failed to read file metadata: {}", e))?;
+        self.permissions.create_dir_all(&folder_path)?;
+
+        if copy {
+            fs::copy(path, &placement.path).map_err(|e| {
+                let _ = fs::remove_file(&placement.path);
+                format!("Failed to copy file: {}", e)
+            })?;
+            journal::record(JournalOperation::Copy { from: path.to_path_buf(), to: placement.path.clone() });
+        } else {
+            workdir::move_into_place(path, &placement.path)?;
+            journal::record_move(path, &placement.path);
+        }
+        self.permissions.apply_to_file(&source, &placement.path)?;
+
+        Ok(placement)
+    }
+
+    /// Where `path` ends up when moved to `destination` under the collision
+    /// policy. A copy kept at the destination replaces it, so it is
+    /// quarantined here; the incoming file is left to the caller.
+    fn arrive(&self, path: &Path, destination: PathBuf) -> Result<Placement, String> {
+        let mut placement = Placement { path: destination, already_in_place: false, duplicate: None, collision: None, copied: false };
+        if !placement.path.exists() {
+            return Ok(placement);
+        }
+
+        if self.organize_collision == OrganizeCollision::KeepBest && placement.path.is_file() {
+            placement.duplicate = self.better_copy(path, &placement.path)?;
+            if placement.duplicate == Some(DuplicateKept::Incoming) {
+                self.quarantine_file(&placement.path)?;
+            }
+            if placement.duplicate.is_some() {
+                return Ok(placement);
+            }
+        }
+
+        // Moving or copying over a file replaces it, so overwriting needs
+        // no delete first.
+        placement.collision = Some(match self.organize_collision {
+            OrganizeCollision::Skip => {
+                placement.path = path.to_path_buf();
+                Collision::Skipped
+            }
+            OrganizeCollision::Overwrite if placement.path.is_file() => Collision::Overwritten,
+            _ => {
+                placement.path = free_destination(&placement.path);
+                Collision::Suffixed
+            }
+        });
+        Ok(placement)
+    }
 
-        if new_path.exists() {
-            return Err(format!("File already exists at destination: {}", new_path.display()));
+    /// When `incoming` and `existing` hold the same audio, by identical
+    /// bytes or the same duration and audio hash, which of the two to keep.
+    /// Matching tags alone are not enough, and both are kept when there is
+    /// no quarantine folder or either bitrate cannot be read.
+    fn better_copy(&self, incoming: &Path, existing: &Path) -> Result<Option<DuplicateKept>, String> {
+        if self.quarantine.is_none() {
+            return Ok(None);
+        }
+        if same_content(incoming, existing)? {
+            return Ok(Some(DuplicateKept::Existing));
         }
 
-        fs::rename(path, &new_path)
-            .map_err(|e| format!("Failed to move file: {}", e))?;
+        let same_audio = audio_duration(incoming).is_some()
+            && audio_duration(incoming) == audio_duration(existing)
+            && audio_hash(incoming).is_some_and(|hash| audio_hash(existing) == Some(hash));
+        if !same_audio {
+            return Ok(None);
+        }
+        Ok(match (read_bitrate(incoming), read_bitrate(existing)) {
+            (Some(incoming), Some(existing)) if incoming > existing => Some(DuplicateKept::Incoming),
+            (Some(_), Some(_)) => Some(DuplicateKept::Existing),
+            _ => None,
+        })
+    }
 
-        Ok(new_path)
+    /// Moves the copy KeepBest dropped to the quarantine folder, journaled
+    /// so it can be undone.
+    fn quarantine_file(&self, path: &Path) -> Result<(), String> {
+        let quarantine = self.quarantine.as_deref().ok_or("No quarantine folder configured")?;
+        duplicates::quarantine_file(quarantine, path).map(|_| ())
     }
 
-    pub fn rename_file(&self, path: &Path, metadata: &Metadata) -> Result<PathBuf, String> {
+    /// The path rename gives the file beside it, from the filename pattern.
+    /// Falls back to the current name when the tags fill in nothing.
+    fn renamed_path(&self, path: &Path, metadata: &Metadata) -> Result<PathBuf, String> {
         let sanitize = |s: &str| -> String {
             s.chars()
                 .map(|c| if c.is_alphanumeric() || c == ' ' || c == '-' || c == '.' { c } else { '_' })
                 .collect()
         };
 
-        let artist = metadata.artist.as_ref()
-            .map(|a| sanitize(a))
-            .unwrap_or_else(|| "Unknown Artist".to_string());
-        
-        let title = metadata.title.as_ref()
-            .map(|t| sanitize(t))
-            .unwrap_or_else(|| "Unknown Title".to_string());
-
         let extension = path.extension()
             .and_then(|s| s.to_str())
             .ok_or("Cannot determine file extension")?;
 
-        let new_filename = format!("{} - {}.{}", artist, title, extension);
+        let expanded = pattern::expand(&self.filename_pattern, |field| self.pattern_value(field, metadata, metadata.artist.as_ref()), sanitize, "")?;
+        let stem = tidy_filename(&expanded);
+        let stem = if stem.is_empty() {
+            path.file_stem().unwrap_or_default().to_string_lossy().to_string()
+        } else {
+            stem
+        };
+        let new_filename = format!("{}.{}", stem, extension);
         
-        let new_path = path.parent()
+        Ok(path.parent()
             .ok_or("Cannot determine parent directory")?
-            .join(&new_filename);
+            .join(new_filename))
+    }
+
+    pub fn rename_file(&self, path: &Path, metadata: &Metadata) -> Result<Placement, String> {
+        let new_path = self.renamed_path(path, metadata)?;
+        let new_filename = new_path.file_name().unwrap_or_default().to_string_lossy();
+
+        // A case fix on a case-insensitive filesystem resolves to the file
+        // itself, but still has to be applied.
+        let case_only = path.file_name()
+            .map(|name| name.to_string_lossy())
+            .is_some_and(|name| name != new_filename && name.to_lowercase() == new_filename.to_lowercase());
+        let in_place = same_file(path, &new_path);
+        if in_place && !case_only {
+            return Ok(Placement::in_place(path));
+        }
+        let placement = if in_place {
+            Placement { path: new_path, already_in_place: false, duplicate: None, collision: None, copied: false }
+        } else {
+            self.arrive(path, new_path)?
+        };
+        if placement.duplicate == Some(DuplicateKept::Existing) {
+            self.quarantine_file(path)?;
+        }
+        if !placement.pending() {
+            return Ok(placement);
+        }
 
-        if new_path.exists() {
-            return Err(format!("File already exists: {}", new_path.display()));
+        if simulation::is_enabled() {
+            simulation::record(SimulatedOperation::Move {
+                from: path.to_path_buf(),
+                to: placement.path.clone(),
+            });
+            return Ok(placement);
         }
 
-        fs::rename(path, &new_path)
-            .map_err(|e| format!("Failed to rename file: {}", e))?;
+        let source = fs::metadata(path)
+            .map_err(|e| format!("Failed to read file metadata: {}", e))?;
+        if in_place {
+            rename_case(path, &placement.path)?;
+        } else {
+            fs::rename(path, &placement.path)
+                .map_err(|e| format!("Failed to rename file: {}", e))?;
+        }
+        journal::record_move(path, &placement.path);
+        self.permissions.apply_to_file(&source, &placement.path)?;
 
-        Ok(new_path)
+        Ok(placement)
     }
 
-    pub fn restore_from_backup(&self, backup_path: &Path, original_path: &Path) -> Result<(), String> {
+    pub fn read_backup(&self, backup_path: &Path) -> Result<Backup, String> {
         let backup_data = fs::read_to_string(backup_path)
             .map_err(|e| format!("Failed to read backup file: {}", e))?;
 
-        let metadata: Metadata = serde_json::from_str(&backup_data)
-            .map_err(|e| format!("Failed to parse backup data: {}", e))?;
+        Backup::parse(&backup_data)
+    }
+
+    /// Writes a backup's fields back onto the file. Version 1 backups only
+    /// hold the modelled fields; later ones also restore the other tag
+    /// fields and the cover art, and from version 3 an MP3 or DSF file gets
+    /// its whole ID3 tag back.
+    pub fn restore_from_backup(&self, backup_path: &Path, original_path: &Path) -> Result<(), String> {
+        let backup = self.read_backup(backup_path)?;
+        match &backup.raw_tag {
+            Some(raw_tag) if Self::has_id3_tag(original_path) => {
+                self.restore_raw_tag(backup_path, original_path, raw_tag, &backup.metadata)?;
+            }
+            _ => self.restore_tags(original_path, &backup.metadata, &backup.tags)?,
+        }
 
-        self.write_metadata(original_path, &metadata)?;
+        if let Some(artwork) = &backup.artwork {
+            let current_hash = self.read_front_cover(original_path)?
+                .map(|(_, data)| backup::content_hash(&data));
+            if current_hash.as_deref() != Some(artwork.hash.as_str()) {
+                let data = backup::load_artwork(backup_path, artwork)?;
+                self.embed_picture(original_path, MimeType::from_str(&artwork.mime_type), data)?;
+            }
+        }
 
         Ok(())
     }
 
-    pub fn find_duplicates(&self, files: &[AudioFile]) -> Vec<Vec<usize>> {
+    /// Replaces the file's ID3 tag with the one stored for `backup_path`.
+    fn restore_raw_tag(&self, backup_path: &Path, path: &Path, raw_tag: &RawTagRef, metadata: &Metadata) -> Result<(), String> {
+        if simulation::is_enabled() {
+            simulation::record(SimulatedOperation::WriteTags {
+                path: path.to_path_buf(),
+                metadata: Box::new(metadata.clone()),
+            });
+            return Ok(());
+        }
+
+        self.restore_raw_id3_tag(path, &backup::load_raw_tag(backup_path, raw_tag)?)
+    }
+
+    /// Replaces the file's ID3 tag with the encoded tag `data`, as it is.
+    pub fn restore_raw_id3_tag(&self, path: &Path, data: &[u8]) -> Result<(), String> {
+        let tag = match id3::Tag::read_from2(std::io::Cursor::new(data)) {
+            Ok(tag) => tag,
+            // Saved from a file that had no tag.
+            Err(e) if matches!(e.kind, id3::ErrorKind::NoTag) => id3::Tag::new(),
+            Err(e) => return Err(format!("Failed to read saved ID3 tag: {}", e)),
+        };
+        self.journaled_write(path, || {
+            Self::write_id3_tag(path, &tag)
+                .map_err(|e| format!("Failed to write ID3 tag: {}", e))
+        })
+    }
+
+    /// Writes back tags saved earlier: every field in the `tags` snapshot,
+    /// then the modelled `metadata`. The comment is put back as saved
+    /// rather than merged under the comment policy.
+    pub fn restore_tags(&self, path: &Path, metadata: &Metadata, tags: &BTreeMap<String, Vec<String>>) -> Result<(), String> {
+        if simulation::is_enabled() {
+            return self.write_metadata(path, metadata);
+        }
+        let verbatim = self.verbatim_comments();
+        self.journaled_write(path, || {
+            self.write_tag_snapshot(path, tags)?;
+            verbatim.write_metadata_to_file(path, metadata)
+        })
+    }
+
+    /// Files under `base_folder` whose tags no longer match the folder
+    /// `pattern` put them in. Untagged files are left out.
+    pub fn find_misplaced(&self, files: &[AudioFile], base_folder: &Path, pattern: &str) -> Vec<MisplacedFile> {
+        files
+            .iter()
+            .filter(|file| file.path.starts_with(base_folder))
+            .filter_map(|file| {
+                let metadata = file.current_metadata.as_ref()?;
+                let (_, expected_path) = self.organized_path(&file.path, metadata, base_folder, pattern).ok()?;
+                if expected_path == file.path || same_file(&file.path, &expected_path) {
+                    return None;
+                }
+                Some(MisplacedFile { path: file.path.clone(), expected_path })
+            })
+            .collect()
+    }
+
+    /// Where organize would move each file from its current tags, leaving
+    /// out files already in place. Nothing is moved or created.
+    pub fn preview_organize(&self, files: &[AudioFile], base_folder: &Path, pattern: &str) -> Vec<PathPreview> {
+        self.preview_moves(files, |path, metadata| {
+            self.organized_path(path, metadata, base_folder, pattern).map(|(_, destination)| destination)
+        })
+    }
+
+    /// Where rename would move each file from its current tags, leaving out
+    /// files already named that way. Nothing is renamed.
+    pub fn preview_rename(&self, files: &[AudioFile]) -> Vec<PathPreview> {
+        self.preview_moves(files, |path, metadata| self.renamed_path(path, metadata))
+    }
+
+    fn preview_moves<F>(&self, files: &[AudioFile], destination: F) -> Vec<PathPreview>
+    where
+        F: Fn(&Path, &Metadata) -> Result<PathBuf, String>,
+    {
+        let planned: Vec<(&Path, PathBuf)> = files
+            .iter()
+            .filter_map(|file| {
+                let metadata = file.current_metadata.clone().unwrap_or_default();
+                let destination = destination(&file.path, &metadata).ok()?;
+                let in_place = destination == file.path
+                    || (destination.file_name() == file.path.file_name() && same_file(&file.path, &destination));
+                (!in_place).then_some((file.path.as_path(), destination))
+            })
+            .collect();
+
+        let mut arrivals: HashMap<&Path, usize> = HashMap::new();
+        for (_, destination) in &planned {
+            *arrivals.entry(destination.as_path()).or_insert(0) += 1;
+        }
+
+        planned
+            .iter()
+            .map(|(path, destination)| PathPreview {
+                path: path.to_path_buf(),
+                destination: destination.clone(),
+                collision: arrivals[destination.as_path()] > 1
+                    || (destination.exists() && !same_file(path, destination)),
+            })
+            .collect()
+    }
+
+    /// Groups of indices into `files` that hold the same track.
+    pub fn find_duplicates(&self, files: &[AudioFile], strategy: DuplicateStrategy) -> Vec<Vec<usize>> {
+        match strategy {
+            DuplicateStrategy::Tags => self.find_tag_duplicates(files),
+            DuplicateStrategy::Content => find_content_duplicates(files),
+        }
+    }
+
+    fn find_tag_duplicates(&self, files: &[AudioFile]) -> Vec<Vec<usize>> {
         let mut duplicates: Vec<Vec<usize>> = Vec::new();
         let mut visited = vec![false; files.len()];
 
@@ -577,3 +2540,194 @@ impl FileScanner {
         artist_match && title_match
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{self, FORMATS};
+    use proptest::prelude::*;
+
+    const TEXT: &str = "[A-Za-z][A-Za-z0-9 &'.-]{0,30}[A-Za-z0-9]";
+
+    fn text() -> impl Strategy<Value = Option<String>> {
+        proptest::option::of(TEXT)
+    }
+
+    fn id(pattern: &'static str) -> impl Strategy<Value = Option<String>> {
+        proptest::option::of(pattern)
+    }
+
+    prop_compose! {
+        fn external_ids()(
+            musicbrainz_recording_id in id("[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}"),
+            musicbrainz_release_id in id("[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}"),
+            musicbrainz_artist_id in id("[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}"),
+            beatport_track_id in id("[1-9][0-9]{4,8}"),
+            catalog_number in id("[A-Z]{2,5}[0-9]{3}"),
+        ) -> ExternalIds {
+            ExternalIds {
+                musicbrainz_recording_id,
+                musicbrainz_release_id,
+                musicbrainz_artist_id,
+                beatport_track_id,
+                catalog_number,
+            }
+        }
+    }
+
+    prop_compose! {
+        fn metadata()(
+            // Always set one field, so every case actually writes a tag.
+            title in TEXT,
+            artist in text(),
+            album_artist in text(),
+            album in text(),
+            track_number in proptest::option::of(1u32..100),
+            disc_number in proptest::option::of(1u32..10),
+            composer in text(),
+            label in text(),
+            genre in text(),
+            release in proptest::option::of((1950i32..2030, proptest::option::of((1u32..=12, 1u32..=28)))),
+            // Whole numbers, since ID3 and MP4 only store those.
+            bpm in proptest::option::of(60u32..200),
+            key in id("(1[0-2]|[1-9])[AB]"),
+            release_type in proptest::option::of(proptest::sample::select(vec![
+                ReleaseType::Album,
+                ReleaseType::Single,
+                ReleaseType::Ep,
+                ReleaseType::Compilation,
+            ])),
+            comment in text(),
+            external_ids in external_ids(),
+        ) -> Metadata {
+            let year = release.map(|(year, _)| year);
+            let date = release.and_then(|(year, day)| {
+                day.map(|(month, day)| format!("{:04}-{:02}-{:02}", year, month, day))
+            });
+
+            // Reading splits the credit when there is no ARTISTS field, so
+            // only a consistent pair round-trips.
+            let artists = artist.as_deref()
+                .map(|artist| ArtistRules::default().split(artist))
+                .unwrap_or_default();
+            let genres = genre.as_deref()
+                .map(|genre| GenreRules::default().split(genre))
+                .unwrap_or_default();
+
+            Metadata {
+                title: Some(title),
+                artist,
+                artists,
+                album_artist,
+                album,
+                track_number,
+                disc_number,
+                composer,
+                label,
+                genre,
+                genres,
+                year,
+                date,
+                release_type,
+                bpm: bpm.map(|bpm| bpm as f32),
+                key,
+                comment,
+                external_ids,
+                provenance: None,
+                cover_art: None,
+            }
+        }
+    }
+
+    fn round_trip(extension: &str, metadata: &Metadata) -> Result<Metadata, String> {
+        let dir = tempfile::tempdir().map_err(|e| e.to_string())?;
+        let path = test_fixtures::write_fixture(dir.path(), "fixture", extension);
+        let scanner = FileScanner::new();
+
+        scanner.write_metadata(&path, metadata)?;
+        scanner.read_metadata(&path)
+    }
+
+    fn check_format(extension: &str) {
+        let config = ProptestConfig {
+            cases: 24,
+            failure_persistence: None,
+            ..ProptestConfig::default()
+        };
+        proptest::test_runner::TestRunner::new(config)
+            .run(&metadata(), |metadata| {
+                let read_back = round_trip(extension, &metadata)
+                    .map_err(TestCaseError::fail)?;
+                prop_assert_eq!(read_back, metadata);
+                Ok(())
+            })
+            .unwrap_or_else(|e| panic!(".{} round trip failed: {}", extension, e));
+    }
+
+    #[test]
+    fn every_supported_format_has_a_fixture() {
+        let scanner = FileScanner::new();
+        for extension in &scanner.supported_extensions {
+            assert!(FORMATS.contains(&extension.as_str()), "no fixture for .{}", extension);
+        }
+    }
+
+    #[test]
+    fn fixtures_are_picked_up_by_scan() {
+        let dir = tempfile::tempdir().unwrap();
+        for extension in FORMATS {
+            test_fixtures::write_fixture(dir.path(), "fixture", extension);
+        }
+        assert_eq!(FileScanner::new().list_audio_files(dir.path()).len(), FORMATS.len());
+    }
+
+    #[test]
+    fn mp3_round_trip() {
+        check_format("mp3");
+    }
+
+    #[test]
+    fn flac_round_trip() {
+        check_format("flac");
+    }
+
+    #[test]
+    fn wav_round_trip() {
+        check_format("wav");
+    }
+
+    #[test]
+    fn ogg_round_trip() {
+        check_format("ogg");
+    }
+
+    #[test]
+    fn m4a_round_trip() {
+        check_format("m4a");
+    }
+
+    #[test]
+    fn opus_round_trip() {
+        check_format("opus");
+    }
+
+    #[test]
+    fn wavpack_round_trip() {
+        check_format("wv");
+    }
+
+    #[test]
+    fn ape_round_trip() {
+        check_format("ape");
+    }
+
+    #[test]
+    fn dsf_round_trip() {
+        check_format("dsf");
+    }
+
+    #[test]
+    fn aiff_round_trip() {
+        check_format("aiff");
+    }
+}
@@ -1,16 +1,158 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
-use tauri::{AppHandle, Manager};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Notify;
+
+use crate::api_client::GenreCase;
+use crate::artists;
+use crate::comments::CommentPolicy;
+use crate::consensus;
+use crate::field_scope::{self, FieldPolicy};
+use crate::filename::{self, NameRule};
+use crate::genre_fallback::GenreFallback;
+use crate::genres::{self, GenreStorage};
+use crate::id3v1::Id3v1Policy;
+use crate::keys::KeyNotation;
+use crate::pattern;
+use crate::permissions::{self, PermissionPolicy};
+use crate::scanner::{OrganizeCollision, OrganizeMode};
+
+// Bumped on every save, so state built from older settings (cached provider
+// tokens) can tell it is stale and waiting tasks re-read them.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+static CHANGED: Notify = Notify::const_new();
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AppSettings {
     pub spotify_client_id: String,
     pub spotify_client_secret: String,
+    pub lastfm_api_key: String,
+    /// Country code (e.g. "US", "DE") for provider searches, so matches are
+    /// tracks available there. Only Spotify takes a market; empty leaves it
+    /// to the provider.
+    pub market: String,
+    /// AcoustID application key; audio fingerprinting is skipped when empty.
+    pub acoustid_api_key: String,
     pub folder_pattern: String,
+    /// What rename calls files, from the same placeholders as
+    /// `folder_pattern` plus `{key}` and `{bpm}`.
+    pub filename_pattern: String,
     pub backup_before_changes: bool,
     pub organize_files: bool,
     pub rename_files: bool,
+    pub library_folders: Vec<String>,
+    pub maintenance_interval_hours: u64,
+    pub backup_retention_days: u64,
+    pub simulate: bool,
+    pub genre_locale: String,
+    pub id3v1_policy: Id3v1Policy,
+    /// Expand `{artist}` in folder patterns from the album artist when the
+    /// file has one, so compilations stay in a single folder.
+    pub prefer_album_artist: bool,
+    /// Path to an ffmpeg binary; format conversion is disabled when empty.
+    pub ffmpeg_path: String,
+    /// Path to Chromaprint's fpcalc binary; `fpcalc` on the PATH when empty.
+    pub fpcalc_path: String,
+    /// Source extension to target extension, applied when processing files
+    /// (e.g. "wav" -> "flac").
+    pub conversion_rules: HashMap<String, String>,
+    pub keep_converted_originals: bool,
+    /// Where imported archives are unpacked; the app data dir's `inbox`
+    /// folder when empty.
+    pub inbox_folder: String,
+    /// Where duplicates resolved by quarantine are moved; the app data
+    /// dir's `quarantine` folder when empty.
+    pub quarantine_folder: String,
+    /// Regex rules that tidy promo-style filenames before artist and title
+    /// are guessed from them.
+    pub filename_rules: Vec<NameRule>,
+    /// Locale whose articles `{artist_sort}` and sorted scans move to the
+    /// end of a name ("The Beatles" -> "Beatles, The").
+    pub sort_locale: String,
+    /// Scratch space for conversions and other temporary artifacts; the
+    /// system temp dir when empty. Emptied on startup.
+    pub working_dir: String,
+    /// Embedded artwork larger than this many pixels on its longest side is
+    /// scaled down when tags are written; 0 for no limit.
+    pub artwork_max_dimension: u32,
+    /// Embedded artwork heavier than this is re-encoded as JPEG until it
+    /// fits; 0 for no limit.
+    pub artwork_max_kb: u32,
+    pub artwork_jpeg_quality: u8,
+    /// Whether generated text may change a file's comment, and what
+    /// separates it from the user's own text when appended.
+    pub comment_policy: CommentPolicy,
+    pub comment_separator: String,
+    /// Record the source and confidence of applied matches in a custom
+    /// AUTOGENRE tag.
+    pub write_provenance: bool,
+    /// Separators that split a combined artist credit ("A & B", "A x B")
+    /// into individual artists, and what joins them back into one credit.
+    pub artist_separators: Vec<String>,
+    pub artist_join: String,
+    /// Capitalization enforced on genres written to tags and folder names.
+    pub genre_case: GenreCase,
+    /// Separators that split a combined genre ("Techno; Acid") into
+    /// individual genres, what joins them back into one value, and whether
+    /// they are written as one value or several.
+    pub genre_separators: Vec<String>,
+    pub genre_join: String,
+    pub genre_storage: GenreStorage,
+    /// Share of a folder's tagged tracks (0 to 1) that must agree on a
+    /// genre before it is suggested for the folder's untagged ones.
+    pub genre_backfill_share: f32,
+    /// Times a genre must be confirmed for an artist before it is proposed
+    /// for their new tracks without a lookup; 0 turns this off.
+    pub artist_genre_confirmations: u32,
+    /// Threads reading tags during a scan; 0 for one per CPU core. Lower it
+    /// for libraries on spinning disks or network shares.
+    pub scan_workers: usize,
+    /// Threads analyzing audio (BPM, key) in each analysis process; 0 for
+    /// one per CPU core, split between the processes.
+    pub analysis_workers: usize,
+    /// Processes a large analysis batch is sharded across. 1 keeps it in
+    /// the app; more sidesteps allocator and decoder contention on big
+    /// machines.
+    pub analysis_processes: usize,
+    /// How many tracks a batch metadata fetch looks up at once.
+    pub fetch_concurrency: usize,
+    /// How long provider search outcomes are reused from the lookup cache
+    /// before searching again; 0 disables the cache.
+    pub lookup_cache_days: u64,
+    /// Most folder levels organize creates under the base folder; deeper
+    /// pattern levels are folded into the file name. 0 for no limit.
+    pub organize_max_depth: usize,
+    /// Extension to folder pattern (e.g. "wav" -> "Lossless/{genre}"),
+    /// used by organize instead of `folder_pattern` for those formats.
+    pub extension_patterns: HashMap<String, String>,
+    /// What organize and rename do when a different file already has the
+    /// destination name.
+    pub organize_collision: OrganizeCollision,
+    /// Whether organize moves files into the library or copies them,
+    /// leaving the source layout as it was.
+    pub organize_mode: OrganizeMode,
+    /// Ownership and mode of files and folders placed in the library, and
+    /// the octal modes (e.g. "664", "775") the fixed policy sets.
+    pub permission_policy: PermissionPolicy,
+    pub file_mode: String,
+    pub folder_mode: String,
+    /// Notation detected keys are written to tags in.
+    pub key_notation: KeyNotation,
+    /// Field name to how far processing may change it (e.g. "genre" ->
+    /// fill_empty, "year" -> keep); unlisted fields are overwritten.
+    pub field_scope: HashMap<String, FieldPolicy>,
+    /// Provider name to how much its genre counts towards the recommended
+    /// one (e.g. "Beatport" -> 3.0 for an electronic library); providers
+    /// not listed keep their built-in weight, and 0 ignores one.
+    pub source_weights: HashMap<String, f32>,
+    /// Genre given to files no provider found one for, and the per-library
+    /// folder overrides of it.
+    pub genre_fallback: GenreFallback,
+    pub library_genre_fallbacks: HashMap<String, GenreFallback>,
 }
 
 impl Default for AppSettings {
@@ -18,15 +160,67 @@ impl Default for AppSettings {
         Self {
             spotify_client_id: String::new(),
             spotify_client_secret: String::new(),
+            lastfm_api_key: String::new(),
+            market: String::new(),
+            acoustid_api_key: String::new(),
             folder_pattern: "{genre}".to_string(),
+            filename_pattern: pattern::DEFAULT_FILENAME_PATTERN.to_string(),
             backup_before_changes: true,
             organize_files: false,
             rename_files: false,
+            library_folders: Vec::new(),
+            maintenance_interval_hours: 0,
+            backup_retention_days: 30,
+            simulate: false,
+            genre_locale: "en".to_string(),
+            id3v1_policy: Id3v1Policy::Keep,
+            prefer_album_artist: false,
+            ffmpeg_path: String::new(),
+            fpcalc_path: String::new(),
+            conversion_rules: HashMap::new(),
+            keep_converted_originals: true,
+            inbox_folder: String::new(),
+            quarantine_folder: String::new(),
+            filename_rules: filename::default_rules(),
+            sort_locale: "en".to_string(),
+            working_dir: String::new(),
+            artwork_max_dimension: 0,
+            artwork_max_kb: 0,
+            artwork_jpeg_quality: 85,
+            comment_policy: CommentPolicy::Append,
+            comment_separator: " | ".to_string(),
+            write_provenance: false,
+            artist_separators: artists::default_separators(),
+            artist_join: " & ".to_string(),
+            genre_case: GenreCase::AsIs,
+            genre_separators: genres::default_separators(),
+            genre_join: "; ".to_string(),
+            genre_storage: GenreStorage::Joined,
+            genre_backfill_share: 0.75,
+            artist_genre_confirmations: 3,
+            scan_workers: 0,
+            analysis_workers: 0,
+            analysis_processes: 1,
+            fetch_concurrency: 4,
+            lookup_cache_days: 30,
+            organize_max_depth: 0,
+            extension_patterns: HashMap::new(),
+            organize_collision: OrganizeCollision::Suffix,
+            organize_mode: OrganizeMode::Move,
+            permission_policy: PermissionPolicy::Preserve,
+            file_mode: "664".to_string(),
+            folder_mode: "775".to_string(),
+            key_notation: KeyNotation::Standard,
+            field_scope: HashMap::new(),
+            source_weights: consensus::default_source_weights(),
+            genre_fallback: GenreFallback::Blank,
+            library_genre_fallbacks: HashMap::new(),
         }
     }
 }
 
-fn get_settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+/// A file next to settings.json, for configuration users may edit by hand.
+pub fn get_config_path(app: &AppHandle, filename: &str) -> Result<PathBuf, String> {
     let app_dir = app
         .path()
         .app_config_dir()
@@ -35,12 +229,41 @@ fn get_settings_path(app: &AppHandle) -> Result<PathBuf, String> {
     fs::create_dir_all(&app_dir)
         .map_err(|e| format!("Failed to create config directory: {}", e))?;
     
-    Ok(app_dir.join("settings.json"))
+    Ok(app_dir.join(filename))
+}
+
+fn get_settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    get_config_path(app, "settings.json")
+}
+
+pub fn get_data_path(app: &AppHandle, filename: &str) -> Result<PathBuf, String> {
+    let data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    
+    fs::create_dir_all(&data_dir)
+        .map_err(|e| format!("Failed to create data directory: {}", e))?;
+    
+    Ok(data_dir.join(filename))
 }
 
 #[tauri::command]
 pub fn save_settings(app: AppHandle, settings: AppSettings) -> Result<(), String> {
     let settings_path = get_settings_path(&app)?;
+
+    pattern::validate(&settings.folder_pattern, false)?;
+    for pattern in settings.extension_patterns.values() {
+        pattern::validate(pattern, false)?;
+    }
+    pattern::validate(&settings.filename_pattern, true)?;
+    field_scope::validate(&settings.field_scope)?;
+    consensus::validate_weights(&settings.source_weights)?;
+    for mode in [&settings.file_mode, &settings.folder_mode] {
+        if permissions::parse_mode(mode).is_none() {
+            return Err(format!("Invalid permission mode: {}", mode));
+        }
+    }
     
     let json = serde_json::to_string_pretty(&settings)
         .map_err(|e| format!("Failed to serialize settings: {}", e))?;
@@ -48,9 +271,24 @@ pub fn save_settings(app: AppHandle, settings: AppSettings) -> Result<(), String
     fs::write(&settings_path, json)
         .map_err(|e| format!("Failed to write settings file: {}", e))?;
     
+    crate::simulation::set_enabled(settings.simulate);
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+    CHANGED.notify_waiters();
+    let _ = app.emit("settings://changed", &settings);
+    
     Ok(())
 }
 
+/// How many times settings have been saved since startup.
+pub fn generation() -> u64 {
+    GENERATION.load(Ordering::SeqCst)
+}
+
+/// Resolves the next time settings are saved.
+pub async fn changed() {
+    CHANGED.notified().await
+}
+
 #[tauri::command]
 pub fn load_settings(app: AppHandle) -> Result<AppSettings, String> {
     let settings_path = get_settings_path(&app)?;
@@ -62,8 +300,9 @@ pub fn load_settings(app: AppHandle) -> Result<AppSettings, String> {
     let json = fs::read_to_string(&settings_path)
         .map_err(|e| format!("Failed to read settings file: {}", e))?;
     
-    let settings: AppSettings = serde_json::from_str(&json)
+    let mut settings: AppSettings = serde_json::from_str(&json)
         .map_err(|e| format!("Failed to parse settings: {}", e))?;
+    filename::upgrade_rules(&mut settings.filename_rules);
     
     Ok(settings)
 }
@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use crate::keys::MusicalKey;
+use crate::scanner::{AudioFile, Metadata};
+
+const BPM_WEIGHT: f32 = 0.35;
+const KEY_WEIGHT: f32 = 0.25;
+const GENRE_WEIGHT: f32 = 0.25;
+const YEAR_WEIGHT: f32 = 0.10;
+const ARTIST_WEIGHT: f32 = 0.05;
+const TOTAL_WEIGHT: f32 = BPM_WEIGHT + KEY_WEIGHT + GENRE_WEIGHT + YEAR_WEIGHT + ARTIST_WEIGHT;
+
+// Tempo difference (relative) at which two tracks no longer count as mixable.
+const BPM_TOLERANCE: f32 = 0.08;
+const YEAR_TOLERANCE: f32 = 10.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimilarTrack {
+    pub index: usize,
+    #[serde(with = "crate::path_codec")]
+    pub path: PathBuf,
+    pub score: f32,
+}
+
+struct TrackFeatures {
+    bpm: Option<f32>,
+    key: Option<MusicalKey>,
+    genre_tokens: HashSet<String>,
+    year: Option<i32>,
+    artist: Option<String>,
+}
+
+impl TrackFeatures {
+    fn from_metadata(metadata: &Metadata) -> Self {
+        TrackFeatures {
+            bpm: metadata.bpm.filter(|bpm| *bpm > 0.0),
+            key: metadata.key.as_deref().and_then(MusicalKey::parse),
+            genre_tokens: metadata.genre.as_deref()
+                .map(|genre| {
+                    genre.to_lowercase()
+                        .split(|c: char| !c.is_alphanumeric())
+                        .filter(|token| !token.is_empty())
+                        .map(|token| token.to_string())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            year: metadata.year,
+            artist: metadata.artist.as_ref()
+                .map(|artist| artist.to_lowercase().trim().to_string())
+                .filter(|artist| !artist.is_empty()),
+        }
+    }
+
+    /// Weighted similarity in 0.0..=1.0. Features missing on either side
+    /// contribute nothing, so sparsely tagged tracks rank lower.
+    fn similarity(&self, other: &TrackFeatures) -> f32 {
+        let mut score = 0.0;
+
+        if let (Some(a), Some(b)) = (self.bpm, other.bpm) {
+            score += BPM_WEIGHT * bpm_similarity(a, b);
+        }
+
+        if let (Some(a), Some(b)) = (&self.key, &other.key) {
+            score += KEY_WEIGHT * a.compatibility(b);
+        }
+
+        if !self.genre_tokens.is_empty() && !other.genre_tokens.is_empty() {
+            let shared = self.genre_tokens.intersection(&other.genre_tokens).count() as f32;
+            let total = self.genre_tokens.union(&other.genre_tokens).count() as f32;
+            score += GENRE_WEIGHT * shared / total;
+        }
+
+        if let (Some(a), Some(b)) = (self.year, other.year) {
+            let distance = (a - b).abs() as f32;
+            score += YEAR_WEIGHT * (1.0 - distance / YEAR_TOLERANCE).max(0.0);
+        }
+
+        if let (Some(a), Some(b)) = (&self.artist, &other.artist) {
+            if a == b {
+                score += ARTIST_WEIGHT;
+            }
+        }
+
+        score / TOTAL_WEIGHT
+    }
+}
+
+/// Compares tempos allowing for half/double-time mixing.
+fn bpm_similarity(a: f32, b: f32) -> f32 {
+    [b, b * 2.0, b / 2.0]
+        .iter()
+        .map(|candidate| {
+            let difference = (a - candidate).abs() / a;
+            (1.0 - difference / BPM_TOLERANCE).max(0.0)
+        })
+        .fold(0.0, f32::max)
+}
+
+pub fn find_similar(files: &[AudioFile], target: usize, limit: usize) -> Vec<SimilarTrack> {
+    let features: Vec<Option<TrackFeatures>> = files
+        .iter()
+        .map(|file| file.current_metadata.as_ref().map(TrackFeatures::from_metadata))
+        .collect();
+
+    let target_features = match features.get(target) {
+        Some(Some(f)) => f,
+        _ => return Vec::new(),
+    };
+
+    let mut matches: Vec<SimilarTrack> = features
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| *index != target)
+        .filter_map(|(index, candidate)| {
+            let score = target_features.similarity(candidate.as_ref()?);
+            if score > 0.0 {
+                Some(SimilarTrack {
+                    index,
+                    path: files[index].path.clone(),
+                    score,
+                })
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+    matches.truncate(limit);
+    matches
+}
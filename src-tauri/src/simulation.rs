@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::scanner::Metadata;
+
+static SIMULATE: AtomicBool = AtomicBool::new(false);
+static SIMULATED_OPERATIONS: Mutex<Vec<SimulatedOperation>> = Mutex::new(Vec::new());
+
+/// A disk change that would have happened if simulation mode were off.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SimulatedOperation {
+    WriteTags {
+        #[serde(with = "crate::path_codec")]
+        path: PathBuf,
+        metadata: Box<Metadata>,
+    },
+    RemoveTag {
+        #[serde(with = "crate::path_codec")]
+        path: PathBuf,
+        tag: String,
+    },
+    Backup {
+        #[serde(with = "crate::path_codec")]
+        path: PathBuf,
+        #[serde(with = "crate::path_codec")]
+        backup_path: PathBuf,
+    },
+    CreateDir {
+        #[serde(with = "crate::path_codec")]
+        path: PathBuf,
+    },
+    Move {
+        #[serde(with = "crate::path_codec")]
+        from: PathBuf,
+        #[serde(with = "crate::path_codec")]
+        to: PathBuf,
+    },
+    Copy {
+        #[serde(with = "crate::path_codec")]
+        from: PathBuf,
+        #[serde(with = "crate::path_codec")]
+        to: PathBuf,
+    },
+    Delete {
+        #[serde(with = "crate::path_codec")]
+        path: PathBuf,
+    },
+    Encode {
+        #[serde(with = "crate::path_codec")]
+        from: PathBuf,
+        #[serde(with = "crate::path_codec")]
+        to: PathBuf,
+    },
+    EmbedArtwork {
+        #[serde(with = "crate::path_codec")]
+        path: PathBuf,
+        bytes: usize,
+    },
+    WriteField {
+        #[serde(with = "crate::path_codec")]
+        path: PathBuf,
+        field: String,
+        value: String,
+    },
+}
+
+pub fn is_enabled() -> bool {
+    SIMULATE.load(Ordering::SeqCst)
+}
+
+pub fn set_enabled(enabled: bool) {
+    SIMULATE.store(enabled, Ordering::SeqCst);
+}
+
+pub fn record(operation: SimulatedOperation) {
+    SIMULATED_OPERATIONS.lock().unwrap().push(operation);
+}
+
+/// Returns and clears everything recorded since the last call.
+pub fn take_log() -> Vec<SimulatedOperation> {
+    std::mem::take(&mut *SIMULATED_OPERATIONS.lock().unwrap())
+}
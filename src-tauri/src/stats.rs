@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use tauri::{AppHandle, Emitter};
+
+use crate::library_db::now_secs;
+use crate::settings::get_data_path;
+
+const SESSION_STATS_FILE: &str = "session_stats.json";
+
+/// What a session's counters track.
+#[derive(Debug, Clone, Copy)]
+pub enum Counter {
+    FilesProcessed,
+    GenresWritten,
+    ApiCalls,
+    CacheHits,
+    Errors,
+}
+
+static FILES_PROCESSED: AtomicUsize = AtomicUsize::new(0);
+static GENRES_WRITTEN: AtomicUsize = AtomicUsize::new(0);
+static API_CALLS: AtomicUsize = AtomicUsize::new(0);
+static CACHE_HITS: AtomicUsize = AtomicUsize::new(0);
+static ERRORS: AtomicUsize = AtomicUsize::new(0);
+// 0 until the first count of the session.
+static STARTED_AT: AtomicU64 = AtomicU64::new(0);
+
+/// Running totals since the app started or the counters were last reset.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionStats {
+    pub files_processed: usize,
+    pub genres_written: usize,
+    /// Requests sent to providers, including token refreshes.
+    pub api_calls: usize,
+    /// Provider searches answered from the lookup cache.
+    pub cache_hits: usize,
+    pub errors: usize,
+    pub started_at: u64,
+    pub updated_at: u64,
+}
+
+fn counter(counter: Counter) -> &'static AtomicUsize {
+    match counter {
+        Counter::FilesProcessed => &FILES_PROCESSED,
+        Counter::GenresWritten => &GENRES_WRITTEN,
+        Counter::ApiCalls => &API_CALLS,
+        Counter::CacheHits => &CACHE_HITS,
+        Counter::Errors => &ERRORS,
+    }
+}
+
+pub fn bump(which: Counter) {
+    let _ = STARTED_AT.compare_exchange(0, now_secs(), Ordering::Relaxed, Ordering::Relaxed);
+    counter(which).fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn snapshot() -> SessionStats {
+    let count = |which| counter(which).load(Ordering::Relaxed);
+    SessionStats {
+        files_processed: count(Counter::FilesProcessed),
+        genres_written: count(Counter::GenresWritten),
+        api_calls: count(Counter::ApiCalls),
+        cache_hits: count(Counter::CacheHits),
+        errors: count(Counter::Errors),
+        started_at: STARTED_AT.load(Ordering::Relaxed),
+        updated_at: now_secs(),
+    }
+}
+
+pub fn reset() {
+    for which in [Counter::FilesProcessed, Counter::GenresWritten, Counter::ApiCalls, Counter::CacheHits, Counter::Errors] {
+        counter(which).store(0, Ordering::Relaxed);
+    }
+    STARTED_AT.store(0, Ordering::Relaxed);
+}
+
+/// Sends the current totals to the frontend as `stats://update`.
+pub fn emit(app: &AppHandle) {
+    let _ = app.emit("stats://update", snapshot());
+}
+
+/// Saves the current totals as the last finished run, for the report.
+pub fn persist(app: &AppHandle) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(&snapshot())
+        .map_err(|e| format!("Failed to serialize session stats: {}", e))?;
+    fs::write(get_data_path(app, SESSION_STATS_FILE)?, json)
+        .map_err(|e| format!("Failed to write session stats: {}", e))
+}
+
+/// The totals saved when the last run finished, if any run has.
+pub fn last_run(app: &AppHandle) -> Option<SessionStats> {
+    let data = fs::read_to_string(get_data_path(app, SESSION_STATS_FILE).ok()?).ok()?;
+    serde_json::from_str(&data).ok()
+}
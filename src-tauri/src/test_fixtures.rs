@@ -0,0 +1,419 @@
+//! Tiny, valid, untagged audio files for tag round-trip tests. Each one holds
+//! a fraction of a second of silence and just enough structure for the tag
+//! libraries to parse and rewrite it.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SAMPLE_RATE: u32 = 44100;
+
+/// Every extension a fixture can be generated for.
+pub const FORMATS: &[&str] = &["mp3", "flac", "wav", "ogg", "m4a", "aiff", "aif", "opus", "wv", "ape", "dsf"];
+
+/// Writes an untagged fixture named `name.<extension>` into `dir`.
+pub fn write_fixture(dir: &Path, name: &str, extension: &str) -> PathBuf {
+    let bytes = match extension {
+        "mp3" => mp3(),
+        "flac" => flac(),
+        "wav" => wav(),
+        "ogg" => ogg_vorbis(),
+        "m4a" => m4a(),
+        "aiff" | "aif" => aiff(),
+        "opus" => ogg_opus(),
+        "wv" => wavpack(),
+        "ape" => monkeys_audio(),
+        "dsf" => dsf(),
+        other => panic!("No fixture for .{}", other),
+    };
+    let path = dir.join(format!("{}.{}", name, extension));
+    fs::write(&path, bytes).expect("write fixture");
+    path
+}
+
+fn be24(value: u32) -> [u8; 3] {
+    let bytes = value.to_be_bytes();
+    [bytes[1], bytes[2], bytes[3]]
+}
+
+/// Ten MPEG-1 Layer III frames at 128 kbit/s, 44.1 kHz, with zeroed side
+/// information so they decode as silence.
+fn mp3() -> Vec<u8> {
+    const FRAME_LEN: usize = 144 * 128_000 / SAMPLE_RATE as usize;
+    let mut bytes = Vec::with_capacity(FRAME_LEN * 10);
+    for _ in 0..10 {
+        let mut frame = vec![0u8; FRAME_LEN];
+        frame[..4].copy_from_slice(&[0xFF, 0xFB, 0x90, 0x00]);
+        bytes.extend(frame);
+    }
+    bytes
+}
+
+/// 16-bit mono, laid out the way the reference encoder writes it: STREAMINFO,
+/// an empty VORBIS_COMMENT carrying only the vendor string, then PADDING.
+/// There are no audio frames.
+fn flac() -> Vec<u8> {
+    let mut bytes = b"fLaC".to_vec();
+
+    bytes.push(0x00);
+    bytes.extend(be24(34));
+    bytes.extend(4096u16.to_be_bytes());
+    bytes.extend(4096u16.to_be_bytes());
+    bytes.extend(be24(0));
+    bytes.extend(be24(0));
+    // Sample rate (20 bits), channels - 1 (3), bits per sample - 1 (5),
+    // total samples (36).
+    let packed = ((SAMPLE_RATE as u64) << 44) | (15u64 << 36) | 4410;
+    bytes.extend(packed.to_be_bytes());
+    bytes.extend([0u8; 16]);
+
+    let vendor = b"autogenre fixture";
+    bytes.push(0x04);
+    bytes.extend(be24(vendor.len() as u32 + 8));
+    bytes.extend((vendor.len() as u32).to_le_bytes());
+    bytes.extend(vendor);
+    bytes.extend(0u32.to_le_bytes());
+
+    // Last-metadata-block flag, type 1 (PADDING).
+    bytes.push(0x81);
+    bytes.extend(be24(64));
+    bytes.extend([0u8; 64]);
+    bytes
+}
+
+/// 16-bit mono PCM.
+fn wav() -> Vec<u8> {
+    let data = vec![0u8; 4410 * 2];
+    let mut bytes = b"RIFF".to_vec();
+    bytes.extend((4 + 8 + 16 + 8 + data.len() as u32).to_le_bytes());
+    bytes.extend(b"WAVE");
+    bytes.extend(b"fmt ");
+    bytes.extend(16u32.to_le_bytes());
+    bytes.extend(1u16.to_le_bytes());
+    bytes.extend(1u16.to_le_bytes());
+    bytes.extend(SAMPLE_RATE.to_le_bytes());
+    bytes.extend((SAMPLE_RATE * 2).to_le_bytes());
+    bytes.extend(2u16.to_le_bytes());
+    bytes.extend(16u16.to_le_bytes());
+    bytes.extend(b"data");
+    bytes.extend((data.len() as u32).to_le_bytes());
+    bytes.extend(data);
+    bytes
+}
+
+/// 80-bit IEEE extended float, as AIFF stores its sample rate.
+fn extended_float(value: u32) -> [u8; 10] {
+    let exponent = 16383 + 31 - value.leading_zeros() as u16;
+    let mantissa = (value as u64) << (63 - (31 - value.leading_zeros()));
+    let mut bytes = [0u8; 10];
+    bytes[..2].copy_from_slice(&exponent.to_be_bytes());
+    bytes[2..].copy_from_slice(&mantissa.to_be_bytes());
+    bytes
+}
+
+/// 16-bit mono PCM.
+fn aiff() -> Vec<u8> {
+    let frames = 4410u32;
+    let mut comm = Vec::new();
+    comm.extend(1u16.to_be_bytes());
+    comm.extend(frames.to_be_bytes());
+    comm.extend(16u16.to_be_bytes());
+    comm.extend(extended_float(SAMPLE_RATE));
+
+    let mut ssnd = vec![0u8; 8];
+    ssnd.extend(vec![0u8; frames as usize * 2]);
+
+    let mut body = b"AIFF".to_vec();
+    body.extend(chunk(b"COMM", &comm));
+    body.extend(chunk(b"SSND", &ssnd));
+
+    chunk(b"FORM", &body)
+}
+
+/// A big-endian IFF chunk, padded to an even length.
+fn chunk(id: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    let mut bytes = id.to_vec();
+    bytes.extend((payload.len() as u32).to_be_bytes());
+    bytes.extend(payload);
+    if payload.len() % 2 == 1 {
+        bytes.push(0);
+    }
+    bytes
+}
+
+/// Ogg's CRC-32: polynomial 0x04C11DB7, no reflection, zero initial value.
+fn ogg_crc(data: &[u8]) -> u32 {
+    data.iter().fold(0u32, |crc, &byte| {
+        (0..8).fold(crc ^ ((byte as u32) << 24), |crc, _| {
+            if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04C1_1DB7
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+fn ogg_page(header_type: u8, granule: u64, sequence: u32, packets: &[&[u8]]) -> Vec<u8> {
+    let mut segments = Vec::new();
+    for packet in packets {
+        segments.extend(std::iter::repeat_n(255u8, packet.len() / 255));
+        segments.push((packet.len() % 255) as u8);
+    }
+
+    let mut page = b"OggS".to_vec();
+    page.push(0);
+    page.push(header_type);
+    page.extend(granule.to_le_bytes());
+    page.extend(0x1234_5678u32.to_le_bytes());
+    page.extend(sequence.to_le_bytes());
+    page.extend([0u8; 4]);
+    page.push(segments.len() as u8);
+    page.extend(segments);
+    for packet in packets {
+        page.extend(*packet);
+    }
+
+    let crc = ogg_crc(&page);
+    page[22..26].copy_from_slice(&crc.to_le_bytes());
+    page
+}
+
+/// Vorbis headers followed by a single empty audio packet. The setup header
+/// is a stub; tag libraries carry it over without decoding it.
+fn ogg_vorbis() -> Vec<u8> {
+    let mut ident = b"\x01vorbis".to_vec();
+    ident.extend(0u32.to_le_bytes());
+    ident.push(1);
+    ident.extend(SAMPLE_RATE.to_le_bytes());
+    ident.extend(0i32.to_le_bytes());
+    ident.extend(128_000i32.to_le_bytes());
+    ident.extend(0i32.to_le_bytes());
+    ident.push(0xB8);
+    ident.push(1);
+
+    let vendor = b"autogenre fixture";
+    let mut comment = b"\x03vorbis".to_vec();
+    comment.extend((vendor.len() as u32).to_le_bytes());
+    comment.extend(vendor);
+    comment.extend(0u32.to_le_bytes());
+    comment.push(1);
+
+    let mut setup = b"\x05vorbis".to_vec();
+    setup.extend([0u8; 8]);
+
+    let mut bytes = ogg_page(0x02, 0, 0, &[&ident]);
+    bytes.extend(ogg_page(0x00, 0, 1, &[&comment, &setup]));
+    bytes.extend(ogg_page(0x04, 4410, 2, &[&[0u8]]));
+    bytes
+}
+
+/// Opus headers followed by a single silent 20 ms packet.
+fn ogg_opus() -> Vec<u8> {
+    let mut head = b"OpusHead".to_vec();
+    head.push(1);
+    head.push(1);
+    head.extend(312u16.to_le_bytes());
+    head.extend(48_000u32.to_le_bytes());
+    head.extend(0i16.to_le_bytes());
+    head.push(0);
+
+    let vendor = b"autogenre fixture";
+    let mut tags = b"OpusTags".to_vec();
+    tags.extend((vendor.len() as u32).to_le_bytes());
+    tags.extend(vendor);
+    tags.extend(0u32.to_le_bytes());
+
+    let mut bytes = ogg_page(0x02, 0, 0, &[&head]);
+    bytes.extend(ogg_page(0x00, 0, 1, &[&tags]));
+    // TOC byte: SILK-only, 20 ms frame, mono, one frame.
+    bytes.extend(ogg_page(0x04, 960 + 312, 2, &[&[0x08]]));
+    bytes
+}
+
+/// One WavPack 4 block header for 16-bit mono with no sub-blocks.
+fn wavpack() -> Vec<u8> {
+    const SAMPLES: u32 = 4410;
+    // Bytes per sample - 1, mono, initial and final block, sample rate
+    // index 9 (44.1 kHz).
+    let flags: u32 = 0x1 | 0x4 | 0x800 | 0x1000 | (9 << 23);
+
+    let mut bytes = b"wvpk".to_vec();
+    bytes.extend(24u32.to_le_bytes());
+    bytes.extend(0x410u16.to_le_bytes());
+    bytes.extend([0u8; 2]);
+    bytes.extend(SAMPLES.to_le_bytes());
+    bytes.extend(0u32.to_le_bytes());
+    bytes.extend(SAMPLES.to_le_bytes());
+    bytes.extend(flags.to_le_bytes());
+    bytes.extend(0u32.to_le_bytes());
+    bytes
+}
+
+/// A Monkey's Audio 3.99 descriptor and header announcing one frame of
+/// 16-bit mono; the frame data is zeros.
+fn monkeys_audio() -> Vec<u8> {
+    let mut bytes = b"MAC ".to_vec();
+    bytes.extend(3990u16.to_le_bytes());
+    bytes.extend([0u8; 2]);
+    // Descriptor and header lengths; seek table, WAV header, audio and
+    // terminating data lengths, and the MD5, are left zero.
+    bytes.extend(52u32.to_le_bytes());
+    bytes.extend(24u32.to_le_bytes());
+    bytes.extend([0u8; 36]);
+
+    bytes.extend(2000u16.to_le_bytes());
+    bytes.extend(0u16.to_le_bytes());
+    bytes.extend(73_728u32.to_le_bytes());
+    bytes.extend(4410u32.to_le_bytes());
+    bytes.extend(1u32.to_le_bytes());
+    bytes.extend(16u16.to_le_bytes());
+    bytes.extend(1u16.to_le_bytes());
+    bytes.extend(SAMPLE_RATE.to_le_bytes());
+    bytes.extend([0u8; 64]);
+    bytes
+}
+
+/// 1-bit mono DSD64 with one block of silence and no metadata chunk.
+fn dsf() -> Vec<u8> {
+    const BLOCK_SIZE: u32 = 4096;
+    let data = vec![0x69u8; BLOCK_SIZE as usize];
+
+    let mut fmt = b"fmt ".to_vec();
+    fmt.extend(52u64.to_le_bytes());
+    fmt.extend(1u32.to_le_bytes());
+    fmt.extend(0u32.to_le_bytes());
+    fmt.extend(1u32.to_le_bytes());
+    fmt.extend(1u32.to_le_bytes());
+    fmt.extend(2_822_400u32.to_le_bytes());
+    fmt.extend(1u32.to_le_bytes());
+    fmt.extend((BLOCK_SIZE as u64 * 8).to_le_bytes());
+    fmt.extend(BLOCK_SIZE.to_le_bytes());
+    fmt.extend(0u32.to_le_bytes());
+
+    let mut chunk = b"data".to_vec();
+    chunk.extend((12 + data.len() as u64).to_le_bytes());
+    chunk.extend(data);
+
+    let total = 28 + fmt.len() + chunk.len();
+    let mut bytes = b"DSD ".to_vec();
+    bytes.extend(28u64.to_le_bytes());
+    bytes.extend((total as u64).to_le_bytes());
+    bytes.extend(0u64.to_le_bytes());
+    bytes.extend(fmt);
+    bytes.extend(chunk);
+    bytes
+}
+
+fn atom(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    let mut bytes = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+    bytes.extend(kind);
+    bytes.extend(payload);
+    bytes
+}
+
+fn full_atom(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    let mut body = vec![0u8; 4];
+    body.extend(payload);
+    atom(kind, &body)
+}
+
+const IDENTITY_MATRIX: [u32; 9] = [0x0001_0000, 0, 0, 0, 0x0001_0000, 0, 0, 0, 0x4000_0000];
+
+fn matrix() -> Vec<u8> {
+    IDENTITY_MATRIX.iter().flat_map(|v| v.to_be_bytes()).collect()
+}
+
+fn descriptor(tag: u8, payload: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![tag, payload.len() as u8];
+    bytes.extend(payload);
+    bytes
+}
+
+/// An AAC-LC track with no samples: just the boxes a parser needs to find
+/// the audio track and its properties.
+fn m4a() -> Vec<u8> {
+    let mut ftyp = b"M4A ".to_vec();
+    ftyp.extend(0u32.to_be_bytes());
+    ftyp.extend(b"M4A mp42isom");
+
+    let mut mvhd = Vec::new();
+    mvhd.extend([0u8; 8]);
+    mvhd.extend(1000u32.to_be_bytes());
+    mvhd.extend(100u32.to_be_bytes());
+    mvhd.extend(0x0001_0000u32.to_be_bytes());
+    mvhd.extend(0x0100u16.to_be_bytes());
+    mvhd.extend([0u8; 10]);
+    mvhd.extend(matrix());
+    mvhd.extend([0u8; 24]);
+    mvhd.extend(2u32.to_be_bytes());
+
+    let mut tkhd = vec![0, 0, 0, 1];
+    tkhd.extend([0u8; 8]);
+    tkhd.extend(1u32.to_be_bytes());
+    tkhd.extend([0u8; 4]);
+    tkhd.extend(100u32.to_be_bytes());
+    tkhd.extend([0u8; 8]);
+    tkhd.extend([0u8; 4]);
+    tkhd.extend(0x0100u16.to_be_bytes());
+    tkhd.extend([0u8; 2]);
+    tkhd.extend(matrix());
+    tkhd.extend([0u8; 8]);
+    let tkhd = atom(b"tkhd", &tkhd);
+
+    let mut mdhd = Vec::new();
+    mdhd.extend([0u8; 8]);
+    mdhd.extend(SAMPLE_RATE.to_be_bytes());
+    mdhd.extend(4410u32.to_be_bytes());
+    mdhd.extend(0x55C4u16.to_be_bytes());
+    mdhd.extend([0u8; 2]);
+
+    let mut hdlr = vec![0u8; 4];
+    hdlr.extend(b"soun");
+    hdlr.extend([0u8; 12]);
+    hdlr.push(0);
+
+    let mut decoder_config = vec![0x40, 0x15];
+    decoder_config.extend(be24(0));
+    decoder_config.extend(128_000u32.to_be_bytes());
+    decoder_config.extend(128_000u32.to_be_bytes());
+    // AudioSpecificConfig: AAC-LC, 44.1 kHz, stereo.
+    decoder_config.extend(descriptor(0x05, &[0x12, 0x10]));
+
+    let mut es = vec![0, 1, 0];
+    es.extend(descriptor(0x04, &decoder_config));
+    es.extend(descriptor(0x06, &[0x02]));
+    let esds = full_atom(b"esds", &descriptor(0x03, &es));
+
+    let mut mp4a = vec![0u8; 6];
+    mp4a.extend(1u16.to_be_bytes());
+    mp4a.extend([0u8; 8]);
+    mp4a.extend(2u16.to_be_bytes());
+    mp4a.extend(16u16.to_be_bytes());
+    mp4a.extend([0u8; 4]);
+    mp4a.extend((SAMPLE_RATE << 16).to_be_bytes());
+    mp4a.extend(esds);
+
+    let mut stsd = 1u32.to_be_bytes().to_vec();
+    stsd.extend(atom(b"mp4a", &mp4a));
+
+    let stbl = [
+        full_atom(b"stsd", &stsd),
+        full_atom(b"stts", &0u32.to_be_bytes()),
+        full_atom(b"stsc", &0u32.to_be_bytes()),
+        full_atom(b"stsz", &[0u8; 8]),
+        full_atom(b"stco", &0u32.to_be_bytes()),
+    ]
+    .concat();
+
+    let minf = [full_atom(b"smhd", &[0u8; 4]), atom(b"stbl", &stbl)].concat();
+    let mdia = [
+        full_atom(b"mdhd", &mdhd),
+        full_atom(b"hdlr", &hdlr),
+        atom(b"minf", &minf),
+    ]
+    .concat();
+    let trak = [tkhd, atom(b"mdia", &mdia)].concat();
+    let moov = [full_atom(b"mvhd", &mvhd), atom(b"trak", &trak)].concat();
+
+    [atom(b"ftyp", &ftyp), atom(b"moov", &moov), atom(b"mdat", &[0u8; 16])].concat()
+}
@@ -0,0 +1,231 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::changeset::{ChangeOutcome, ChangeResult};
+use crate::field_scope::FieldGuard;
+use crate::scanner::{AudioFile, FileScanner, Metadata};
+
+/// A leading track marker: "01. ", "7) ", "03 " (zero-padded numbers
+/// only, so "99 Problems" keeps its number) or a vinyl position such as
+/// "B2 - ".
+static TRACK_MARKER: OnceLock<Regex> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransformPreview {
+    #[serde(with = "crate::path_codec")]
+    pub path: PathBuf,
+    pub before: Metadata,
+    pub after: Metadata,
+    pub changed_fields: Vec<String>,
+}
+
+/// Straightens typographic quotes and dashes, drops zero-width characters and
+/// collapses runs of whitespace.
+pub fn clean_text(value: &str) -> String {
+    let replaced: String = value
+        .chars()
+        .filter_map(|c| match c {
+            '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' | '\u{FEFF}' => None,
+            '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}' | '\u{2032}' => Some('\''),
+            '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{201F}' | '\u{2033}' => Some('"'),
+            '\u{2010}' | '\u{2011}' | '\u{2012}' | '\u{2013}' | '\u{2014}' | '\u{2015}' => Some('-'),
+            '\u{00A0}' | '\u{2007}' | '\u{202F}' => Some(' '),
+            c => Some(c),
+        })
+        .collect();
+
+    replaced.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn clean_field(value: &Option<String>) -> Option<String> {
+    value.as_deref()
+        .map(clean_text)
+        .filter(|v| !v.is_empty())
+}
+
+/// Like `clean_field`, but line by line, since comments keep their line
+/// breaks.
+fn clean_comment(value: &Option<String>) -> Option<String> {
+    value.as_deref()
+        .map(|comment| comment.lines().map(clean_text).collect::<Vec<_>>().join("\n").trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+pub fn cleanup_metadata(metadata: &Metadata) -> Metadata {
+    Metadata {
+        title: clean_field(&metadata.title),
+        artist: clean_field(&metadata.artist),
+        album_artist: clean_field(&metadata.album_artist),
+        album: clean_field(&metadata.album),
+        composer: clean_field(&metadata.composer),
+        label: clean_field(&metadata.label),
+        genre: clean_field(&metadata.genre),
+        key: clean_field(&metadata.key),
+        comment: clean_comment(&metadata.comment),
+        ..metadata.clone()
+    }
+}
+
+/// Where a title's leading marker puts the track.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TrackMarker {
+    Number(u32),
+    /// Side letter and position on that side.
+    Vinyl(char, u32),
+}
+
+/// Splits a leading track marker off `title`, returning it and the rest of
+/// the title.
+fn split_track_marker(title: &str) -> Option<(TrackMarker, String)> {
+    let pattern = TRACK_MARKER.get_or_init(|| {
+        Regex::new(r"^\s*(?:(?P<side>[A-H])(?P<position>\d{1,2})\s*[.):-]\s+|(?P<number>\d{1,3})\s*[.):-]\s+|(?P<padded>0\d{1,2})\s+)(?P<rest>\S.*)$").unwrap()
+    });
+    let captures = pattern.captures(title)?;
+    let marker = if let (Some(side), Some(position)) = (captures.name("side"), captures.name("position")) {
+        TrackMarker::Vinyl(side.as_str().chars().next()?, position.as_str().parse().ok()?)
+    } else {
+        let number = captures.name("number").or_else(|| captures.name("padded"))?;
+        TrackMarker::Number(number.as_str().parse().ok()?)
+    };
+    Some((marker, captures["rest"].trim().to_string()))
+}
+
+/// Strips leading track markers from titles and moves them into the track
+/// number where the file has none. Vinyl positions are numbered through
+/// per album, counting the tracks this batch has on earlier sides, so "B2"
+/// after a four-track A side becomes 6.
+pub fn extract_track_markers(files: &[AudioFile]) -> Vec<TransformPreview> {
+    let mut side_lengths: HashMap<(Option<String>, char), u32> = HashMap::new();
+    for metadata in files.iter().filter_map(|file| file.current_metadata.as_ref()) {
+        if let Some((TrackMarker::Vinyl(side, position), _)) = metadata.title.as_deref().and_then(split_track_marker) {
+            let length = side_lengths.entry((metadata.album.clone(), side)).or_insert(0);
+            *length = (*length).max(position);
+        }
+    }
+
+    preview(files, |metadata| {
+        let Some((marker, rest)) = metadata.title.as_deref().and_then(split_track_marker) else {
+            return metadata.clone();
+        };
+        let number = match marker {
+            TrackMarker::Number(number) => number,
+            TrackMarker::Vinyl(side, position) => {
+                let earlier: u32 = side_lengths
+                    .iter()
+                    .filter(|((album, other), _)| *album == metadata.album && *other < side)
+                    .map(|(_, length)| length)
+                    .sum();
+                earlier + position
+            }
+        };
+        Metadata {
+            title: Some(rest),
+            track_number: metadata.track_number.or(Some(number).filter(|n| *n > 0)),
+            ..metadata.clone()
+        }
+    })
+}
+
+fn changed_fields(before: &Metadata, after: &Metadata) -> Vec<String> {
+    let mut changed = Vec::new();
+    let mut check = |name: &str, a: &Option<String>, b: &Option<String>| {
+        if a != b {
+            changed.push(name.to_string());
+        }
+    };
+    check("title", &before.title, &after.title);
+    check("artist", &before.artist, &after.artist);
+    check("album_artist", &before.album_artist, &after.album_artist);
+    check("album", &before.album, &after.album);
+    check("composer", &before.composer, &after.composer);
+    check("label", &before.label, &after.label);
+    check("genre", &before.genre, &after.genre);
+    check("key", &before.key, &after.key);
+    check("comment", &before.comment, &after.comment);
+    if before.track_number != after.track_number {
+        changed.push("track_number".to_string());
+    }
+    changed
+}
+
+/// Returns only the files the transform would actually change.
+pub fn preview<F>(files: &[AudioFile], transform: F) -> Vec<TransformPreview>
+where
+    F: Fn(&Metadata) -> Metadata,
+{
+    files
+        .iter()
+        .filter_map(|file| {
+            let before = file.current_metadata.as_ref()?;
+            let after = transform(before);
+            let changed_fields = changed_fields(before, &after);
+            if changed_fields.is_empty() {
+                return None;
+            }
+
+            Some(TransformPreview {
+                path: file.path.clone(),
+                before: before.clone(),
+                after,
+                changed_fields,
+            })
+        })
+        .collect()
+}
+
+/// Writes each preview's tags, leaving the fields `guard` protects as the
+/// file has them. A preview's comment is the file's own, transformed, so it
+/// replaces the comment rather than being merged into it.
+pub fn apply(scanner: &FileScanner, previews: &[TransformPreview], backup: bool, guard: &FieldGuard) -> Vec<ChangeResult> {
+    let scanner = scanner.verbatim_comments();
+    previews
+        .iter()
+        .map(|preview| {
+            let result = if backup {
+                scanner.backup_current_metadata(&preview.path).map(|_| ())
+            } else {
+                Ok(())
+            }
+            .and_then(|_| scanner.read_metadata(&preview.path))
+            .and_then(|current| {
+                let metadata = guard.reconcile(&preview.path, &current, &preview.after);
+                scanner.write_metadata(&preview.path, &metadata)
+            });
+
+            ChangeResult {
+                file_path: preview.path.clone(),
+                outcome: match result {
+                    Ok(()) => ChangeOutcome::Applied,
+                    Err(e) => ChangeOutcome::Failed(e),
+                },
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cleanup_covers_album_artist_and_comment() {
+        let metadata = Metadata {
+            artist: Some("Daft\u{00A0}Punk".to_string()),
+            album_artist: Some("  Daft \u{201C}Punk\u{201D}\u{200B} ".to_string()),
+            comment: Some("Ripped  from\u{2014}vinyl\n  Side\u{00A0}A ".to_string()),
+            ..Metadata::default()
+        };
+
+        let cleaned = cleanup_metadata(&metadata);
+        assert_eq!(cleaned.artist.as_deref(), Some("Daft Punk"));
+        assert_eq!(cleaned.album_artist.as_deref(), Some("Daft \"Punk\""));
+        assert_eq!(cleaned.comment.as_deref(), Some("Ripped from-vinyl\nSide A"));
+
+        let blank = cleanup_metadata(&Metadata { album_artist: Some(" \u{200B}".to_string()), comment: Some("\n \n".to_string()), ..Metadata::default() });
+        assert_eq!(blank.album_artist, None);
+        assert_eq!(blank.comment, None);
+    }
+}
@@ -0,0 +1,410 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use crate::changeset::ChangeOutcome;
+use crate::convert;
+use crate::diskspace::{self, SpaceRequirements};
+use crate::scanner::FileScanner;
+use crate::simulation::{self, SimulatedOperation};
+
+// Older CDJ firmware truncates or rejects long names; FAT32 caps full
+// paths at 255 UTF-16 units.
+const MAX_NAME_LEN: usize = 64;
+const MAX_PATH_LEN: usize = 240;
+const FAT32_MAX_FILE_SIZE: u64 = u32::MAX as u64;
+
+const CONTENTS_DIR: &str = "Contents";
+const PLAYLISTS_DIR: &str = "Playlists";
+
+// Formats every CDJ since the CDJ-2000 can play; FLAC only on newer models.
+const CDJ_FORMATS: &[&str] = &["mp3", "m4a", "wav", "aiff", "aif"];
+
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportPlaylist {
+    pub name: String,
+    #[serde(with = "crate::path_codec::vec")]
+    pub file_paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsbExportOptions {
+    #[serde(with = "crate::path_codec")]
+    pub destination: PathBuf,
+    #[serde(default, with = "crate::path_codec::vec")]
+    pub tracks: Vec<PathBuf>,
+    #[serde(default)]
+    pub playlists: Vec<ExportPlaylist>,
+    /// Decode formats the player can't read to WAV (requires ffmpeg).
+    #[serde(default)]
+    pub convert_unsupported: bool,
+    /// Newer players (CDJ-2000NXS2, CDJ-3000) read FLAC natively.
+    #[serde(default)]
+    pub flac_supported: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedTrack {
+    #[serde(with = "crate::path_codec")]
+    pub source_path: PathBuf,
+    #[serde(default, with = "crate::path_codec::option")]
+    pub destination_path: Option<PathBuf>,
+    pub converted: bool,
+    pub verified: bool,
+    pub outcome: ChangeOutcome,
+}
+
+/// Where a playlist was written; names that clash once made FAT32-safe get
+/// a numeric suffix.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedPlaylist {
+    pub name: String,
+    #[serde(with = "crate::path_codec")]
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsbExportResult {
+    pub tracks: Vec<ExportedTrack>,
+    pub playlists: Vec<ExportedPlaylist>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsbExportProgress {
+    pub stage: String,
+    pub current: usize,
+    pub total: usize,
+    #[serde(with = "crate::path_codec")]
+    pub file_path: PathBuf,
+}
+
+/// Reduces a name to printable ASCII that FAT32 and CDJ browsers accept,
+/// transliterating accents rather than dropping them.
+pub fn cdj_safe_name(name: &str, max_len: usize) -> String {
+    let ascii = deunicode::deunicode(name);
+    let cleaned: String = ascii
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_ascii_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    let mut safe: String = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    safe.truncate(max_len);
+    let safe = safe.trim_end_matches(['.', ' ']).to_string();
+
+    if safe.is_empty() {
+        return "_".to_string();
+    }
+    if RESERVED_NAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(&safe)) {
+        return format!("_{}", safe);
+    }
+    safe
+}
+
+fn unique_path(dir: &Path, stem: &str, extension: &str, taken: &mut HashSet<PathBuf>) -> PathBuf {
+    let mut candidate = dir.join(format!("{}.{}", stem, extension));
+    let mut n = 2;
+    while taken.contains(&candidate) || candidate.exists() {
+        candidate = dir.join(format!("{} ({}).{}", stem, n, extension));
+        n += 1;
+    }
+    taken.insert(candidate.clone());
+    candidate
+}
+
+fn files_match(a: &Path, b: &Path) -> Result<bool, String> {
+    let open = |path: &Path| {
+        File::open(path)
+            .map(BufReader::new)
+            .map_err(|e| format!("Failed to open {} for verification: {}", path.display(), e))
+    };
+    let (mut a, mut b) = (open(a)?, open(b)?);
+    let mut buf_a = vec![0u8; 64 * 1024];
+    let mut buf_b = vec![0u8; 64 * 1024];
+
+    loop {
+        let read_a = a.read(&mut buf_a).map_err(|e| format!("Failed to verify copy: {}", e))?;
+        if read_a == 0 {
+            let read_b = b.read(&mut buf_b[..1]).map_err(|e| format!("Failed to verify copy: {}", e))?;
+            return Ok(read_b == 0);
+        }
+        if b.read_exact(&mut buf_b[..read_a]).is_err() || buf_a[..read_a] != buf_b[..read_a] {
+            return Ok(false);
+        }
+    }
+}
+
+struct PlannedTrack {
+    source: PathBuf,
+    destination: PathBuf,
+    /// Length of the path as the player sees it, relative to the drive root.
+    drive_path_len: usize,
+    playable: bool,
+    convert: bool,
+}
+
+fn plan_tracks(options: &UsbExportOptions, scanner: &FileScanner) -> Vec<PlannedTrack> {
+    let contents = options.destination.join(CONTENTS_DIR);
+    let mut seen = HashSet::new();
+    let mut taken = HashSet::new();
+    let mut planned = Vec::new();
+
+    let sources = options.tracks
+        .iter()
+        .chain(options.playlists.iter().flat_map(|p| p.file_paths.iter()));
+
+    for source in sources {
+        if !seen.insert(source.clone()) {
+            continue;
+        }
+
+        let extension = source.extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let playable = CDJ_FORMATS.contains(&extension.as_str())
+            || (extension == "flac" && options.flac_supported);
+        let convert = !playable && options.convert_unsupported;
+
+        let metadata = scanner.read_metadata(source).ok();
+        let artist = metadata.as_ref()
+            .and_then(|m| m.artist.as_deref())
+            .unwrap_or("Unknown Artist");
+        let stem = source.file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let dir = contents.join(cdj_safe_name(artist, MAX_NAME_LEN));
+        let extension = if convert { "wav".to_string() } else { extension };
+        let destination = unique_path(&dir, &cdj_safe_name(&stem, MAX_NAME_LEN), &extension, &mut taken);
+
+        let drive_path_len = destination.strip_prefix(&options.destination)
+            .map(|relative| relative.as_os_str().len())
+            .unwrap_or(usize::MAX);
+
+        planned.push(PlannedTrack { source: source.clone(), destination, drive_path_len, playable, convert });
+    }
+
+    planned
+}
+
+fn export_track(track: &PlannedTrack, ffmpeg: Option<&Path>, scanner: &FileScanner) -> Result<(), String> {
+    if track.drive_path_len > MAX_PATH_LEN {
+        return Err(format!("Destination path too long for FAT32: {}", track.destination.display()));
+    }
+
+    if !track.playable && !track.convert {
+        return Err("Format is not playable on CDJs; enable conversion to export it".to_string());
+    }
+
+    let dir = track.destination.parent().ok_or("Cannot determine parent directory")?;
+    if simulation::is_enabled() {
+        if !dir.is_dir() {
+            simulation::record(SimulatedOperation::CreateDir { path: dir.to_path_buf() });
+        }
+    } else {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create folder on drive: {}", e))?;
+    }
+
+    if track.convert {
+        let ffmpeg = ffmpeg.ok_or("Converting for CDJ export requires an ffmpeg path in settings")?;
+        convert::transcode(ffmpeg, &track.source, &track.destination, "wav")?;
+        if let Ok(metadata) = scanner.read_metadata(&track.source) {
+            scanner.write_metadata(&track.destination, &metadata)?;
+        }
+    } else {
+        let size = fs::metadata(&track.source)
+            .map_err(|e| format!("Failed to read file size: {}", e))?
+            .len();
+        if size > FAT32_MAX_FILE_SIZE {
+            return Err("File is larger than FAT32's 4 GB limit".to_string());
+        }
+
+        if simulation::is_enabled() {
+            simulation::record(SimulatedOperation::Copy {
+                from: track.source.clone(),
+                to: track.destination.clone(),
+            });
+        } else {
+            fs::copy(&track.source, &track.destination)
+                .map_err(|e| format!("Failed to copy file: {}", e))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Fails before anything is copied when the drive cannot hold every track
+/// that would be written to it.
+fn check_space(destination: &Path, planned: &[PlannedTrack]) -> Result<(), String> {
+    let mut requirements = SpaceRequirements::new();
+    let required = planned
+        .iter()
+        .filter(|track| track.playable || track.convert)
+        .map(|track| match track.convert {
+            true => diskspace::estimate_encoded_size(&track.source, "wav"),
+            false => diskspace::file_size(&track.source),
+        })
+        .sum();
+    requirements.add(destination, required);
+    requirements.check()
+}
+
+fn verify_track(track: &PlannedTrack, scanner: &FileScanner) -> Result<bool, String> {
+    if simulation::is_enabled() {
+        return Ok(false);
+    }
+    if track.convert {
+        // Re-encoded files can't be compared byte for byte; make sure the
+        // result is non-empty and parses.
+        let non_empty = fs::metadata(&track.destination).map(|m| m.len() > 0).unwrap_or(false);
+        return Ok(non_empty && scanner.read_metadata(&track.destination).is_ok());
+    }
+    files_match(&track.source, &track.destination)
+}
+
+/// A playlist file name not yet used in this export. FAT32 ignores case,
+/// so names are compared case-insensitively; a playlist left by an earlier
+/// export is overwritten.
+fn playlist_path(dir: &Path, name: &str, taken: &mut HashSet<String>) -> PathBuf {
+    let stem = cdj_safe_name(name, MAX_NAME_LEN);
+    let file_name = std::iter::once(format!("{}.m3u8", stem))
+        .chain((2..).map(|n| format!("{} ({}).m3u8", stem, n)))
+        .find(|candidate| !taken.contains(&candidate.to_lowercase()))
+        .unwrap_or_default();
+    taken.insert(file_name.to_lowercase());
+    dir.join(file_name)
+}
+
+fn write_playlist(
+    destination: &Path,
+    playlist: &ExportPlaylist,
+    path: &Path,
+    exported: &[(PathBuf, PathBuf)],
+) -> Result<(), String> {
+    let dir = destination.join(PLAYLISTS_DIR);
+
+    let mut body = String::from("#EXTM3U\n");
+    for source in &playlist.file_paths {
+        let target = exported.iter().find(|(from, _)| from == source).map(|(_, to)| to);
+        if let Some(relative) = target.and_then(|to| to.strip_prefix(destination).ok()) {
+            body.push_str("../");
+            body.push_str(&relative.to_string_lossy().replace('\\', "/"));
+            body.push('\n');
+        }
+    }
+
+    if simulation::is_enabled() {
+        simulation::record(SimulatedOperation::CreateDir { path: dir });
+        return Ok(());
+    }
+
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create playlist folder: {}", e))?;
+    fs::write(path, body)
+        .map_err(|e| format!("Failed to write playlist {}: {}", playlist.name, e))
+}
+
+/// Copies tracks and playlists to a USB drive laid out for CDJs: ASCII,
+/// length-limited FAT32-safe names under `Contents/<Artist>/`, M3U8
+/// playlists under `Playlists/`. Supported formats are copied bit for bit
+/// and unsupported ones decoded to WAV, so gain is never altered. Every
+/// written file is re-read in a verification pass, and one that fails it is
+/// deleted from the drive; if that fails too, its `destination_path` stays
+/// set so the result shows what is left behind.
+pub fn export_to_usb(
+    options: &UsbExportOptions,
+    scanner: &FileScanner,
+    ffmpeg: Option<&Path>,
+    mut on_progress: impl FnMut(UsbExportProgress),
+) -> Result<UsbExportResult, String> {
+    if !options.destination.is_dir() {
+        return Err(format!("Destination not found: {}", options.destination.display()));
+    }
+
+    let planned = plan_tracks(options, scanner);
+    check_space(&options.destination, &planned)?;
+    let total = planned.len();
+    let mut results: Vec<ExportedTrack> = Vec::with_capacity(total);
+
+    for (i, track) in planned.iter().enumerate() {
+        on_progress(UsbExportProgress {
+            stage: "copy".to_string(),
+            current: i + 1,
+            total,
+            file_path: track.source.clone(),
+        });
+
+        let outcome = match export_track(track, ffmpeg, scanner) {
+            Ok(()) => ChangeOutcome::Applied,
+            Err(e) => ChangeOutcome::Failed(e),
+        };
+        let written = matches!(outcome, ChangeOutcome::Applied);
+
+        results.push(ExportedTrack {
+            source_path: track.source.clone(),
+            destination_path: written.then(|| track.destination.clone()),
+            converted: written && track.convert,
+            verified: false,
+            outcome,
+        });
+    }
+
+    for (i, (track, result)) in planned.iter().zip(results.iter_mut()).enumerate() {
+        if result.destination_path.is_none() {
+            continue;
+        }
+
+        on_progress(UsbExportProgress {
+            stage: "verify".to_string(),
+            current: i + 1,
+            total,
+            file_path: track.destination.clone(),
+        });
+
+        let error = match verify_track(track, scanner) {
+            Ok(true) => {
+                result.verified = true;
+                continue;
+            }
+            Ok(false) if simulation::is_enabled() => continue,
+            Ok(false) => "Verification failed: file on drive does not match".to_string(),
+            Err(e) => e,
+        };
+        result.outcome = ChangeOutcome::Failed(match fs::remove_file(&track.destination) {
+            Ok(()) => {
+                result.destination_path = None;
+                result.converted = false;
+                error
+            }
+            Err(e) => format!("{}; the bad copy could not be deleted: {}", error, e),
+        });
+    }
+
+    let exported: Vec<(PathBuf, PathBuf)> = results
+        .iter()
+        .filter(|r| r.verified || simulation::is_enabled())
+        .filter_map(|r| r.destination_path.clone().map(|to| (r.source_path.clone(), to)))
+        .collect();
+    let playlist_dir = options.destination.join(PLAYLISTS_DIR);
+    let mut taken = HashSet::new();
+    let mut playlists = Vec::with_capacity(options.playlists.len());
+    for playlist in &options.playlists {
+        let path = playlist_path(&playlist_dir, &playlist.name, &mut taken);
+        write_playlist(&options.destination, playlist, &path, &exported)?;
+        playlists.push(ExportedPlaylist { name: playlist.name.clone(), path });
+    }
+
+    Ok(UsbExportResult { tracks: results, playlists })
+}
@@ -0,0 +1,68 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::settings::AppSettings;
+
+// Always a dedicated subfolder, so startup cleanup never touches anything
+// else in a user-chosen directory.
+const WORK_DIR_NAME: &str = "autogenre-work";
+
+/// Where temporary artifacts go: the configured working directory, or the
+/// system temp dir when none is set.
+pub fn root(settings: &AppSettings) -> PathBuf {
+    let configured = settings.working_dir.trim();
+    let base = if configured.is_empty() {
+        std::env::temp_dir()
+    } else {
+        PathBuf::from(configured)
+    };
+    base.join(WORK_DIR_NAME)
+}
+
+/// The working directory's `purpose` subfolder, created if needed.
+pub fn ensure(settings: &AppSettings, purpose: &str) -> Result<PathBuf, String> {
+    let dir = root(settings).join(purpose);
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create working directory {}: {}", dir.display(), e))?;
+    Ok(dir)
+}
+
+/// Moves a file to its destination, falling back to copy and delete when
+/// the two are on different drives (a working directory elsewhere, or a
+/// library on another disk).
+pub fn move_into_place(from: &Path, to: &Path) -> Result<(), String> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    fs::copy(from, to)
+        .map_err(|e| {
+            let _ = fs::remove_file(to);
+            format!("Failed to move {} into place: {}", to.display(), e)
+        })?;
+    fs::remove_file(from)
+        .map_err(|e| format!("Failed to remove {} after copying it: {}", from.display(), e))
+}
+
+/// Empties the working directory of anything left by an earlier session.
+/// Returns the number of entries removed.
+pub fn cleanup(settings: &AppSettings) -> Result<usize, String> {
+    let dir = root(settings);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(0),
+    };
+
+    let mut removed = 0;
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let result = if path.is_dir() {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+        result.map_err(|e| format!("Failed to clean up {}: {}", path.display(), e))?;
+        removed += 1;
+    }
+    Ok(removed)
+}
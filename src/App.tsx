@@ -1,8 +1,9 @@
-import { useState, useEffect } from "react";
-import { Folder, Play, StopCircle, Save, Settings as SettingsIcon, Music } from "lucide-react";
+import { useState } from "react";
+import { Folder, Play, StopCircle, Save, Settings as SettingsIcon, Music, Image as ImageIcon } from "lucide-react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { open } from "@tauri-apps/plugin-dialog";
-import { EnhancedAudioFile, AppSettings } from "./types";
+import { ArtworkProgress, ArtworkSummary, EnhancedAudioFile, FetchJob, FilenameGuess, JobResult, ProcessResult, ScanFileFound, ScanProgress } from "./types";
 import { FileList } from "./components/FileList";
 import { SettingsModal } from "./components/SettingsModal";
 
@@ -13,27 +14,6 @@ function App() {
   const [progress, setProgress] = useState(0);
   const [statusMessage, setStatusMessage] = useState("Ready to scan");
   const [showSettings, setShowSettings] = useState(false);
-  const [settings, setSettings] = useState<AppSettings>({
-    spotify_client_id: '',
-    spotify_client_secret: '',
-    folder_pattern: '{genre}',
-    backup_before_changes: true,
-    organize_files: false,
-    rename_files: false,
-  });
-
-  useEffect(() => {
-    loadSettings();
-  }, []);
-
-  const loadSettings = async () => {
-    try {
-      const loadedSettings = await invoke<AppSettings>('load_settings');
-      setSettings(loadedSettings);
-    } catch (error) {
-      console.error("Error loading settings:", error);
-    }
-  };
 
   const handleSelectFolder = async () => {
     try {
@@ -60,36 +40,104 @@ function App() {
 
     setIsScanning(true);
     setStatusMessage("Scanning for audio files...");
-    setProgress(10);
+    setProgress(0);
 
     try {
-      const scannedFiles = await invoke<EnhancedAudioFile[]>("scan_folder", {
-        path: selectedFolder,
+      const unlistenFound = await listen<ScanFileFound>("scan://file-found", (event) => {
+        setStatusMessage(`Found ${event.payload.found} audio files...`);
       });
+      const unlistenProgress = await listen<ScanProgress>("scan://progress", (event) => {
+        const { done, total, path } = event.payload;
+        setProgress((done / total) * 50);
+        setStatusMessage(`Reading tags ${done}/${total}: ${path.split('/').pop()}`);
+      });
+      let scannedFiles: EnhancedAudioFile[];
+      try {
+        scannedFiles = await invoke<EnhancedAudioFile[]>("scan_folder", {
+          path: selectedFolder,
+        });
+      } finally {
+        unlistenFound();
+        unlistenProgress();
+      }
 
       setFiles(scannedFiles);
       setProgress(50);
       setStatusMessage(`Found ${scannedFiles.length} audio files. Fetching metadata...`);
 
-      for (let i = 0; i < scannedFiles.length; i++) {
-        const file = scannedFiles[i];
-        if (file.current_metadata?.artist && file.current_metadata?.title) {
-          try {
-            const metadata = await invoke("fetch_metadata", {
-              artist: file.current_metadata.artist,
-              title: file.current_metadata.title,
-            });
-            file.suggested_metadata = metadata as any;
-          } catch (error) {
-            console.error(`Error fetching metadata for ${file.filename}:`, error);
+      const untagged = scannedFiles.filter(
+        (file) => !file.current_metadata?.artist || !file.current_metadata?.title
+      );
+      const guesses = new Map<string, FilenameGuess>();
+      if (untagged.length > 0) {
+        try {
+          const results = await invoke<FilenameGuess[]>("guess_from_filenames", {
+            filePaths: untagged.map((file) => file.path),
+          });
+          results.forEach((guess) => guesses.set(guess.file_path, guess));
+        } catch (error) {
+          console.error("Error guessing names from filenames:", error);
+        }
+      }
+
+      const jobs: FetchJob[] = [];
+      for (const file of scannedFiles) {
+        const guess = guesses.get(file.path);
+        file.filename_guess = guess;
+        const artist = file.current_metadata?.artist || guess?.artist;
+        const title = file.current_metadata?.title || guess?.title;
+        if (artist && title) {
+          jobs.push({ file_path: file.path, artist, title });
+        }
+      }
+
+      let skippedCount = 0;
+      if (jobs.length > 0) {
+        const byPath = new Map(scannedFiles.map((file) => [file.path, file]));
+        let expected = jobs.length;
+        let completed = 0;
+        let finishBatch = () => {};
+        const batchDone = new Promise<void>((resolve) => (finishBatch = resolve));
+
+        const unlistenResult = await listen<JobResult>("jobs://result", (event) => {
+          const { file_path, lookup, error } = event.payload;
+          const file = byPath.get(file_path);
+          if (file && lookup) {
+            file.suggested_metadata = lookup.results;
+            file.no_match_sources = lookup.no_match;
+            file.provider_reports = lookup.providers;
+          }
+          if (error) {
+            console.error(`Error fetching metadata for ${file?.filename ?? file_path}:`, error);
           }
+          completed++;
+          setProgress(50 + (completed / expected) * 50);
+          setFiles([...scannedFiles]);
+        });
+        const unlistenIdle = await listen("jobs://idle", () => finishBatch());
+
+        try {
+          // Files already tagged from a High-confidence match are skipped.
+          const skipped = await invoke<string[]>("enqueue_fetch_jobs", { jobs, force: false });
+          skippedCount = skipped.length;
+          expected = jobs.length - skippedCount;
+          if (expected === 0) {
+            finishBatch();
+          }
+          await batchDone;
+        } finally {
+          unlistenResult();
+          unlistenIdle();
         }
-        setProgress(50 + ((i + 1) / scannedFiles.length) * 50);
       }
 
       setFiles([...scannedFiles]);
       setProgress(100);
-      setStatusMessage(`Completed! Processed ${scannedFiles.length} files`);
+      setStatusMessage(
+        skippedCount > 0
+          ? `Completed! Processed ${scannedFiles.length} files, ${skippedCount} already tagged and skipped`
+          : `Completed! Processed ${scannedFiles.length} files`
+      );
     } catch (error) {
       console.error("Error scanning folder:", error);
       setStatusMessage(`Error: ${error}`);
@@ -98,6 +146,58 @@ function App() {
     }
   };
 
+  const handleStopScan = async () => {
+    await invoke("cancel_scan");
+    await invoke("clear_fetch_jobs");
+    setIsScanning(false);
+  };
+
+  const handlePrioritizeFile = async (file: EnhancedAudioFile) => {
+    if (!isScanning || file.suggested_metadata) {
+      return;
+    }
+    try {
+      await invoke("set_job_priority", { filePath: file.path, priority: "high" });
+    } catch (error) {
+      // Usually not queued (no artist/title) or already fetched.
+      setStatusMessage(`Could not prioritize ${file.filename}: ${error}`);
+    }
+  };
+
+  const handleFetchArtwork = async () => {
+    setStatusMessage("Looking for missing artwork...");
+    setProgress(0);
+    const unlisten = await listen<ArtworkProgress>("artwork://progress", (event) => {
+      setProgress((event.payload.index / event.payload.total) * 100);
+    });
+
+    try {
+      const summary = await invoke<ArtworkSummary>("fetch_missing_artwork", {
+        filePaths: files.map((file) => file.path),
+      });
+      if (summary.errors.length > 0) {
+        console.error("Artwork errors:", summary.errors);
+      }
+
+      let message = `Artwork embedded for ${summary.embedded.length} files`;
+      if (summary.queued_for_review.length > 0) {
+        message += `, ${summary.queued_for_review.length} waiting for review`;
+      }
+      if (summary.not_found.length > 0) {
+        message += `, ${summary.not_found.length} not found`;
+      }
+      if (summary.errors.length > 0) {
+        message += `, ${summary.errors.length} failed`;
+      }
+      setStatusMessage(message);
+    } catch (error) {
+      setStatusMessage(`Artwork lookup failed: ${error}`);
+    } finally {
+      unlisten();
+      setProgress(100);
+    }
+  };
+
   const handleApplyChanges = async () => {
     setStatusMessage("Applying metadata changes...");
     setProgress(0);
@@ -117,53 +217,48 @@ function App() {
         if (suggestedGenre || suggestedArtist) {
           try {
             const updatedMetadata = {
-              title: file.current_metadata?.title || null,
-              artist: suggestedArtist || file.current_metadata?.artist || null,
+              title: file.current_metadata?.title || file.filename_guess?.title || null,
+              artist: suggestedArtist || file.current_metadata?.artist || file.filename_guess?.artist || null,
+              album_artist: file.current_metadata?.album_artist || null,
               album: file.current_metadata?.album || null,
               genre: suggestedGenre || file.current_metadata?.genre || null,
               year: file.current_metadata?.year || null,
+              date: file.suggested_metadata[0].release_date || file.current_metadata?.date || null,
               bpm: file.current_metadata?.bpm || null,
+              key: file.current_metadata?.key || null,
+              external_ids: {
+                ...file.current_metadata?.external_ids,
+                ...file.suggested_metadata[0].external_ids,
+              },
+              provenance: {
+                genre: suggestedGenre,
+                source: file.suggested_metadata[0].source,
+                confidence: file.suggested_metadata[0].confidence,
+              },
             };
 
-            await invoke("update_metadata", {
-              file_path: file.path,
+            const result = await invoke<ProcessResult>("process_file", {
+              filePath: file.path,
               metadata: updatedMetadata,
-              backup: settings.backup_before_changes,
+              baseFolder: selectedFolder || null,
             });
 
-            updatedFiles[i].current_metadata = updatedMetadata;
-            
-            let currentPath = file.path;
-
-            if (settings.rename_files) {
-              try {
-                const newPath = await invoke<string>("rename_file", {
-                  file_path: currentPath,
-                  metadata: updatedMetadata,
-                });
-                currentPath = newPath;
-                updatedFiles[i].path = newPath;
-                updatedFiles[i].filename = newPath.split('/').pop() || updatedFiles[i].filename;
-              } catch (renError) {
-                console.error(`Error renaming ${file.filename}:`, renError);
-              }
+            updatedFiles[i].current_metadata = result.metadata;
+            updatedFiles[i].path = result.final_path;
+            updatedFiles[i].filename = result.final_path.split('/').pop() || updatedFiles[i].filename;
+            if (result.organized) {
+              organizedCount++;
             }
-            
-            if (settings.organize_files && selectedFolder) {
-              try {
-                const newPath = await invoke<string>("organize_files", {
-                  file_path: currentPath,
-                  metadata: updatedMetadata,
-                  base_folder: selectedFolder,
-                });
-                updatedFiles[i].path = newPath;
-                organizedCount++;
-              } catch (orgError) {
-                console.error(`Error organizing ${file.filename}:`, orgError);
-              }
+
+            // The tags are written either way; a later step may have stopped
+            // the file short of where it was going.
+            const failedStep = result.steps?.find((step) => step.outcome.status !== 'applied');
+            if (failedStep && failedStep.outcome.status !== 'applied') {
+              errors.push(`${file.filename}: ${failedStep.step} failed: ${failedStep.outcome.message}`);
+              errorCount++;
+            } else {
+              successCount++;
             }
-            
-            successCount++;
           } catch (error) {
             const errorMsg = error instanceof Error ? error.message : String(error);
             console.error(`Error updating ${file.filename}:`, errorMsg);
@@ -211,7 +306,7 @@ function App() {
                 Select Folder
               </button>
               <button
-                onClick={isScanning ? () => setIsScanning(false) : handleStartScan}
+                onClick={isScanning ? handleStopScan : handleStartScan}
                 disabled={!selectedFolder}
                 className="px-4 py-2 bg-gold-500 hover:bg-gold-400 text-gray-900 font-semibold rounded-lg flex items-center gap-2 transition-colors disabled:opacity-50 disabled:cursor-not-allowed"
               >
@@ -256,7 +351,7 @@ function App() {
                       <h2 className="text-xl font-semibold mb-2">File List</h2>
                       <p className="text-sm text-gray-400">Folder: {selectedFolder}</p>
                     </div>
-                    <FileList files={files} />
+                    <FileList files={files} onFileClick={handlePrioritizeFile} />
                   </div>
                 )}
               </div>
@@ -273,6 +368,14 @@ function App() {
                   </div>
                   <p className="text-xs text-gray-400 mt-2">{statusMessage}</p>
                 </div>
+                <button
+                  onClick={handleFetchArtwork}
+                  disabled={files.length === 0 || isScanning}
+                  className="px-4 py-2 mr-3 bg-gray-700 hover:bg-gray-600 rounded-lg flex items-center gap-2 transition-colors disabled:opacity-50 disabled:cursor-not-allowed"
+                >
+                  <ImageIcon className="w-4 h-4" />
+                  Fetch Artwork
+                </button>
                 <button
                   onClick={handleApplyChanges}
                   disabled={files.length === 0 || isScanning}
@@ -291,7 +394,6 @@ function App() {
         isOpen={showSettings} 
         onClose={() => setShowSettings(false)}
         onSettingsSaved={() => {
-          loadSettings();
           setStatusMessage("Settings saved successfully!");
         }}
       />
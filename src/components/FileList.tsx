@@ -1,10 +1,20 @@
 import { EnhancedAudioFile } from '../types';
 
+/** Provider failures worth showing; a provider without a key is just skipped. */
+function providerFailures(file: EnhancedAudioFile): string[] {
+  return (file.provider_reports ?? []).flatMap((report) =>
+    report.status === 'error' && report.code !== 'not_configured'
+      ? [`${report.source}: ${report.message}`]
+      : []
+  );
+}
+
 interface FileListProps {
   files: EnhancedAudioFile[];
+  onFileClick?: (file: EnhancedAudioFile) => void;
 }
 
-export function FileList({ files }: FileListProps) {
+export function FileList({ files, onFileClick }: FileListProps) {
   return (
     <div className="bg-gray-900 rounded-lg border border-gray-700 overflow-hidden">
       <table className="w-full">
@@ -26,8 +36,12 @@ export function FileList({ files }: FileListProps) {
               </td>
             </tr>
           ) : (
-            files.map((file, index) => (
-              <tr key={index} className="border-b border-gray-700 hover:bg-gray-800 transition-colors">
+            files.map((file) => (
+              <tr
+                key={file.id}
+                className="border-b border-gray-700 hover:bg-gray-800 transition-colors cursor-pointer"
+                onClick={() => onFileClick?.(file)}
+              >
                 <td className="px-4 py-3 text-sm font-medium">{file.filename}</td>
                 <td className="px-4 py-3 text-sm text-gray-400">
                   {file.current_metadata?.genre || 'N/A'}
@@ -49,6 +63,13 @@ export function FileList({ files }: FileListProps) {
                       {file.suggested_metadata[0].confidence}
                     </span>
                   )}
+                  {!file.suggested_metadata?.length && providerFailures(file).length ? (
+                    <span className="text-xs text-red-400" title={providerFailures(file).join('\n')}>
+                      Lookup failed
+                    </span>
+                  ) : !file.suggested_metadata?.length && file.no_match_sources?.length ? (
+                    <span className="text-xs text-gray-500">No match</span>
+                  ) : null}
                 </td>
               </tr>
             ))
@@ -101,6 +101,52 @@ export function SettingsModal({ isOpen, onClose, onSettingsSaved }: SettingsModa
                   placeholder="Enter your Spotify Client Secret"
                 />
               </div>
+              <div>
+                <label className="block text-sm font-medium mb-2">
+                  Market
+                </label>
+                <input
+                  type="text"
+                  maxLength={2}
+                  value={settings.market ?? ''}
+                  onChange={(e) => setSettings({ ...settings, market: e.target.value.toUpperCase() })}
+                  className="w-24 px-4 py-2 bg-gray-900 border border-gray-700 rounded-lg focus:outline-none focus:ring-2 focus:ring-gold-500"
+                  placeholder="US"
+                />
+                <p className="text-xs text-gray-400 mt-1">
+                  Two-letter country code; Spotify only matches tracks available there. Leave empty for the default.
+                </p>
+              </div>
+              <div>
+                <label className="block text-sm font-medium mb-2">
+                  Last.fm API Key
+                </label>
+                <input
+                  type="password"
+                  value={settings.lastfm_api_key ?? ''}
+                  onChange={(e) => setSettings({ ...settings, lastfm_api_key: e.target.value })}
+                  className="w-full px-4 py-2 bg-gray-900 border border-gray-700 rounded-lg focus:outline-none focus:ring-2 focus:ring-gold-500"
+                  placeholder="Enter your Last.fm API key (optional)"
+                />
+                <p className="text-xs text-gray-400 mt-1">
+                  Create one at <a href="https://www.last.fm/api/account/create" target="_blank" rel="noopener noreferrer" className="text-gold-400 hover:underline">Last.fm API</a>. Leave empty to skip Last.fm tag lookups.
+                </p>
+              </div>
+              <div>
+                <label className="block text-sm font-medium mb-2">
+                  AcoustID API Key
+                </label>
+                <input
+                  type="password"
+                  value={settings.acoustid_api_key ?? ''}
+                  onChange={(e) => setSettings({ ...settings, acoustid_api_key: e.target.value })}
+                  className="w-full px-4 py-2 bg-gray-900 border border-gray-700 rounded-lg focus:outline-none focus:ring-2 focus:ring-gold-500"
+                  placeholder="Enter your AcoustID API key (optional)"
+                />
+                <p className="text-xs text-gray-400 mt-1">
+                  Identifies files with missing or placeholder tags by their audio. Requires Chromaprint's fpcalc; register an application at <a href="https://acoustid.org/new-application" target="_blank" rel="noopener noreferrer" className="text-gold-400 hover:underline">AcoustID</a>.
+                </p>
+              </div>
             </div>
             <div className="mt-4 p-4 bg-blue-900 bg-opacity-30 border border-blue-700 rounded-lg">
               <p className="text-sm text-blue-200">
@@ -131,7 +177,23 @@ export function SettingsModal({ isOpen, onClose, onSettingsSaved }: SettingsModa
                   placeholder="e.g., {genre}/{artist}/{title}"
                 />
                 <p className="text-xs text-gray-400 mt-2">
-                  Available placeholders: {'{genre}'}, {'{artist}'}, {'{title}'}, {'{album}'}, {'{year}'}
+                  Available placeholders: {'{genre}'}, {'{artist}'}, {'{artist_sort}'}, {'{title}'}, {'{album}'}, {'{album_artist}'}, {'{track}'}, {'{disc}'}, {'{composer}'}, {'{label}'}, {'{key}'}, {'{bpm}'}, {'{release_type}'}, {'{year}'}, {'{date}'}, {'{decade}'}.
+                  Add {'|text'} for a fallback ({'{album|Unknown Album}'}, or {'{album|}'} to drop it), {'[0]'} for the first letter ({'{artist_sort[0]}'}) and {'{track:03}'} to pad numbers.
+                </p>
+              </div>
+              <div>
+                <label className="block text-sm font-medium mb-2">
+                  Filename Pattern
+                </label>
+                <input
+                  type="text"
+                  value={settings.filename_pattern ?? '{artist} - {title}'}
+                  onChange={(e) => setSettings({ ...settings, filename_pattern: e.target.value })}
+                  className="w-full px-4 py-2 bg-gray-900 border border-gray-700 rounded-lg focus:outline-none focus:ring-2 focus:ring-gold-500"
+                  placeholder="e.g., {track} {artist} - {title} [{key} {bpm}]"
+                />
+                <p className="text-xs text-gray-400 mt-2">
+                  Used when renaming files; same placeholders as the folder pattern. Empty brackets and separators left by missing tags are dropped.
                 </p>
               </div>
               <div className="flex items-center gap-2">
@@ -158,6 +220,30 @@ export function SettingsModal({ isOpen, onClose, onSettingsSaved }: SettingsModa
                   Automatically organize files into folders
                 </label>
               </div>
+              <div className="flex items-center gap-2">
+                <input
+                  type="checkbox"
+                  id="organize-copy"
+                  checked={settings.organize_mode === 'copy'}
+                  onChange={(e) => setSettings({ ...settings, organize_mode: e.target.checked ? 'copy' : 'move' })}
+                  className="w-4 h-4 text-gold-500 bg-gray-900 border-gray-700 rounded focus:ring-gold-500"
+                />
+                <label htmlFor="organize-copy" className="text-sm">
+                  Copy files when organizing, leaving the originals where they are
+                </label>
+              </div>
+              <div className="flex items-center gap-2">
+                <input
+                  type="checkbox"
+                  id="prefer-album-artist"
+                  checked={settings.prefer_album_artist ?? false}
+                  onChange={(e) => setSettings({ ...settings, prefer_album_artist: e.target.checked })}
+                  className="w-4 h-4 text-gold-500 bg-gray-900 border-gray-700 rounded focus:ring-gold-500"
+                />
+                <label htmlFor="prefer-album-artist" className="text-sm">
+                  Use album artist for {'{artist}'} in folder paths (falls back to track artist)
+                </label>
+              </div>
               <div className="flex items-center gap-2">
                 <input
                   type="checkbox"
@@ -170,6 +256,18 @@ export function SettingsModal({ isOpen, onClose, onSettingsSaved }: SettingsModa
                   Rename files to "Artist - Song Name" format
                 </label>
               </div>
+              <div className="flex items-center gap-2">
+                <input
+                  type="checkbox"
+                  id="write-provenance"
+                  checked={settings.write_provenance ?? false}
+                  onChange={(e) => setSettings({ ...settings, write_provenance: e.target.checked })}
+                  className="w-4 h-4 text-gold-500 bg-gray-900 border-gray-700 rounded focus:ring-gold-500"
+                />
+                <label htmlFor="write-provenance" className="text-sm">
+                  Record the source of applied genres in an AUTOGENRE tag
+                </label>
+              </div>
             </div>
           </div>
 
@@ -1,36 +1,681 @@
 export interface AudioFile {
+  id: string;
+  /** Pass back unchanged: a path that is not UTF-8 arrives encoded, so
+   *  show `filename` rather than taking this apart. */
   path: string;
   filename: string;
   extension: string;
   current_metadata: Metadata | null;
+  bext: BextChunk | null;
+  tag_conflicts: TagConflict[];
+  has_ape_tag: boolean;
+}
+
+export type ScanSort = 'path' | 'artist' | 'title' | 'album' | 'genre';
+
+export type DuplicateStrategy = 'tags' | 'content';
+
+export interface TagConflict {
+  field: string;
+  id3v1: string;
+  id3v2: string;
+}
+
+export interface BextChunk {
+  description: string;
+  originator: string;
+  originator_reference: string;
+  origination_date: string;
+  origination_time: string;
+  time_reference: number;
+  coding_history: string;
 }
 
 export interface Metadata {
   title: string | null;
   artist: string | null;
+  /** Individual artists credited in `artist`. */
+  artists?: string[];
+  album_artist?: string | null;
   album: string | null;
+  track_number?: number | null;
+  disc_number?: number | null;
+  composer?: string | null;
+  /** Record label. */
+  label?: string | null;
   genre: string | null;
+  /** Individual genres in `genre`. */
+  genres?: string[];
   year: number | null;
+  date?: string | null;
+  release_type?: ReleaseType | null;
   bpm: number | null;
+  key: string | null;
+  comment?: string | null;
+  external_ids?: ExternalIds;
+  provenance?: Provenance | null;
+  /** The embedded front cover; read-only, set with `embed_cover_art`. */
+  cover_art?: CoverArt | null;
+}
+
+export type ReleaseType = 'album' | 'single' | 'ep' | 'compilation';
+
+export interface CoverArt {
+  mime_type: string;
+  width: number;
+  height: number;
+  size: number;
+}
+
+export interface Provenance {
+  genre: string | null;
+  source: string;
+  confidence: 'High' | 'Medium' | 'Low';
+  /** YYYY-MM-DD; stamped with today's date when empty. */
+  date?: string;
+}
+
+export interface ExternalIds {
+  musicbrainz_recording_id?: string | null;
+  musicbrainz_release_id?: string | null;
+  musicbrainz_artist_id?: string | null;
+  beatport_track_id?: string | null;
+  catalog_number?: string | null;
 }
 
 export interface MetadataResult {
   genre: string | null;
+  /** Every genre the provider gave, main genre first. */
+  genres?: string[];
+  /** The provider's own genre when the genre mapping changed it. */
+  source_genre?: string;
   artist: string | null;
   confidence: 'High' | 'Medium' | 'Low';
   source: string;
+  track_id?: string | null;
+  external_ids?: ExternalIds;
+  release_date?: string | null;
+  release_type?: ReleaseType | null;
+  artwork_url?: string | null;
+}
+
+export interface MetadataLookup {
+  results: MetadataResult[];
+  /** Providers that were searched and found nothing. */
+  no_match: string[];
+  /** How each provider's search went, including failures. */
+  providers?: ProviderReport[];
+  /** The genre the results agree on, weighted by source. */
+  resolved?: ResolvedMetadata | null;
+}
+
+export interface GenreVote {
+  source: string;
+  genre: string;
+  weight: number;
+  /** Whether this vote went to the recommended genre. */
+  agrees: boolean;
+}
+
+export interface ResolvedMetadata {
+  genre: string;
+  /** From how strongly the providers agree. */
+  confidence: 'High' | 'Medium' | 'Low';
+  /** Share of the total vote (0 to 1) behind `genre`. */
+  agreement: number;
+  votes: GenreVote[];
+  /** The most trusted agreeing result, ready to apply. */
+  result: MetadataResult;
+}
+
+export type ProviderErrorCode =
+  | 'not_configured'
+  | 'auth'
+  | 'rate_limited'
+  | 'unreachable'
+  | 'bad_response'
+  | 'other';
+
+export type ProviderReport =
+  | { source: string; status: 'matched' }
+  | { source: string; status: 'no_match' }
+  | { source: string; status: 'error'; code: ProviderErrorCode; message: string };
+
+export type JobPriority = 'low' | 'normal' | 'high';
+
+export interface FetchJob {
+  file_path: string;
+  artist: string;
+  title: string;
+  priority?: JobPriority;
+}
+
+export interface TrackQuery {
+  artist: string;
+  title: string;
+  file_path?: string | null;
+}
+
+export interface TrackResult {
+  index: number;
+  query: TrackQuery;
+  lookup: MetadataLookup | null;
+  error: string | null;
+}
+
+export interface JobResult {
+  file_path: string;
+  lookup: MetadataLookup | null;
+  error: string | null;
+}
+
+export interface ProviderPin {
+  source: 'Spotify' | 'Beatport' | 'MusicBrainz' | 'Deezer';
+  track_id: string;
+  pinned_at: number;
+}
+
+export interface SimilarTrack {
+  index: number;
+  path: string;
+  score: number;
+}
+
+export interface LibraryInsights {
+  track_count: number;
+  tracks_without_bpm: number;
+  tracks_without_key: number;
+  bpm_histogram: { start: number; end: number; count: number }[];
+  camelot_wheel: { camelot: string; count: number }[];
+  years: { year: number; track_count: number; average_bpm: number | null }[];
+}
+
+export type ProviderStatus =
+  | { status: 'ok' }
+  | { status: 'not_configured' }
+  | { status: 'auth_error'; message: string }
+  | { status: 'unreachable'; message: string };
+
+export interface ProviderHealth {
+  source: string;
+  status: ProviderStatus;
+}
+
+export interface PendingChange {
+  file_path: string;
+  metadata: Metadata;
+}
+
+export type ChangeOutcome =
+  | { status: 'applied' }
+  | { status: 'conflict'; message: string }
+  | { status: 'failed'; message: string };
+
+export interface ChangeResult {
+  file_path: string;
+  outcome: ChangeOutcome;
+}
+
+export interface RestoreRequest {
+  file_path: string;
+  backup_path: string;
+}
+
+export interface FieldChange {
+  field: string;
+  current: string | null;
+  backup: string | null;
+}
+
+export interface RestorePreview {
+  file_path: string;
+  backup_path: string;
+  changes: FieldChange[];
+  error: string | null;
+}
+
+export interface OrphanedBackup {
+  backup_path: string;
+  original_path: string;
+  relocated_to: string | null;
+}
+
+export interface OrphanCleanup {
+  relinked: number;
+  removed: number;
+  errors: string[];
+}
+
+export interface BackupEntry {
+  backup_path: string;
+  /** Unix seconds. */
+  created_at: number;
+  changes: FieldChange[];
+  error: string | null;
+}
+
+export interface BackupDeletion {
+  deleted: number;
+  errors: string[];
+}
+
+export interface LockOwner {
+  hostname: string;
+  pid: number;
+  acquired_at: number;
+  /** Refreshed while the lock is held; 0 from versions without a heartbeat. */
+  heartbeat_at?: number;
+}
+
+export interface ConversionResult {
+  file_path: string;
+  output_path: string | null;
+  outcome: ChangeOutcome;
+}
+
+export type GenreFallback = 'blank' | 'unknown' | 'parent_folder';
+
+export type FieldPolicy = 'overwrite' | 'fill_empty' | 'keep';
+
+export interface ProcessResult {
+  original_path: string;
+  final_path: string;
+  backup_path: string | null;
+  converted: boolean;
+  renamed: boolean;
+  organized: boolean;
+  /** Organizing found the file already where the pattern puts it. */
+  already_organized?: boolean;
+  /** Set when renaming or organizing met a different file at the destination. */
+  collision?: Collision | null;
+  /** The tags written once the field scope was applied. */
+  metadata: Metadata;
+  /** Steps run after the tags were written; only the last can have failed, leaving the file at final_path. */
+  steps?: StepOutcome[];
+}
+
+export type ProcessStep = 'convert' | 'rename' | 'organize';
+
+export interface StepOutcome {
+  step: ProcessStep;
+  outcome: ChangeOutcome;
+}
+
+export type Collision = 'skipped' | 'suffixed' | 'overwritten';
+
+export interface Placement {
+  path: string;
+  already_in_place: boolean;
+  /** Which copy survived when the destination held the same audio; the other was quarantined. */
+  duplicate?: 'existing' | 'incoming' | null;
+  /** Set when the destination held a different file. */
+  collision?: Collision | null;
+  /** The file was copied, so the source is still where it was. */
+  copied?: boolean;
+}
+
+export type DuplicateAction = 'quarantine' | 'delete';
+
+export interface RemovedDuplicate {
+  path: string;
+  bitrate: number;
+  /** Where the file was moved under quarantine; null when deleted. */
+  quarantined_to: string | null;
+  /** Null in a dry run. */
+  outcome: ChangeOutcome | null;
+}
+
+export interface DuplicateResolution {
+  kept: string;
+  kept_bitrate: number;
+  removed: RemovedDuplicate[];
+}
+
+/** Where organize or rename would move a file. */
+export interface PathPreview {
+  path: string;
+  destination: string;
+  /** Another file is there already or would be moved there too. */
+  collision: boolean;
+}
+
+/** A file whose tags no longer match the folder organize put it in. */
+export interface MisplacedFile {
+  path: string;
+  expected_path: string;
+}
+
+/** Sibling folders whose names differ only in case or punctuation. */
+export interface EquivalentFolders {
+  target: string;
+  others: string[];
+}
+
+export interface MergeSummary {
+  moved: number;
+  /** New paths of files moved in beside a different file of the same name. */
+  renamed: string[];
+  duplicates_removed: number;
+  folders_removed: string[];
+}
+
+export interface TransformPreview {
+  path: string;
+  before: Metadata;
+  after: Metadata;
+  changed_fields: string[];
 }
 
 export interface EnhancedAudioFile extends AudioFile {
   suggested_metadata?: MetadataResult[];
+  no_match_sources?: string[];
+  provider_reports?: ProviderReport[];
   selected_genre?: string;
+  filename_guess?: FilenameGuess;
+}
+
+export interface NameRule {
+  pattern: string;
+  replacement: string;
+}
+
+export interface FilenameGuess {
+  file_path: string;
+  cleaned_name: string;
+  artist: string | null;
+  title: string | null;
 }
 
 export interface AppSettings {
   spotify_client_id: string;
   spotify_client_secret: string;
+  lastfm_api_key?: string;
+  market?: string;
+  acoustid_api_key?: string;
   folder_pattern: string;
+  /** Rename's file name, e.g. "{track} {artist} - {title} [{key} {bpm}]". */
+  filename_pattern?: string;
   backup_before_changes: boolean;
   organize_files: boolean;
   rename_files: boolean;
+  library_folders?: string[];
+  maintenance_interval_hours?: number;
+  backup_retention_days?: number;
+  simulate?: boolean;
+  genre_locale?: string;
+  id3v1_policy?: 'keep' | 'strip' | 'sync';
+  prefer_album_artist?: boolean;
+  ffmpeg_path?: string;
+  fpcalc_path?: string;
+  conversion_rules?: Record<string, string>;
+  keep_converted_originals?: boolean;
+  inbox_folder?: string;
+  quarantine_folder?: string;
+  filename_rules?: NameRule[];
+  sort_locale?: string;
+  working_dir?: string;
+  artwork_max_dimension?: number;
+  artwork_max_kb?: number;
+  artwork_jpeg_quality?: number;
+  comment_policy?: 'preserve' | 'append' | 'replace';
+  comment_separator?: string;
+  write_provenance?: boolean;
+  artist_separators?: string[];
+  artist_join?: string;
+  genre_case?: 'as_is' | 'lower' | 'title' | 'title_ampersand';
+  genre_separators?: string[];
+  genre_join?: string;
+  genre_storage?: 'joined' | 'multi_value';
+  genre_backfill_share?: number;
+  artist_genre_confirmations?: number;
+  scan_workers?: number;
+  analysis_workers?: number;
+  analysis_processes?: number;
+  fetch_concurrency?: number;
+  lookup_cache_days?: number;
+  organize_max_depth?: number;
+  extension_patterns?: Record<string, string>;
+  organize_collision?: 'suffix' | 'keep_best' | 'skip' | 'overwrite';
+  organize_mode?: 'move' | 'copy';
+  permission_policy?: 'umask' | 'preserve' | 'fixed';
+  /** Octal, e.g. "664". */
+  file_mode?: string;
+  folder_mode?: string;
+  key_notation?: 'standard' | 'camelot';
+  /** Field name to how far processing may change it; unlisted fields are overwritten. */
+  field_scope?: Record<string, FieldPolicy>;
+  /** Provider name to how much its genre counts towards the recommended one. */
+  source_weights?: Record<string, number>;
+  /** Genre given to files no provider found one for. */
+  genre_fallback?: GenreFallback;
+  /** Library folder to its own genre fallback. */
+  library_genre_fallbacks?: Record<string, GenreFallback>;
+}
+
+export type SimulatedOperation =
+  | { kind: 'write_tags'; path: string; metadata: Metadata }
+  | { kind: 'remove_tag'; path: string; tag: string }
+  | { kind: 'backup'; path: string; backup_path: string }
+  | { kind: 'create_dir'; path: string }
+  | { kind: 'move'; from: string; to: string }
+  | { kind: 'copy'; from: string; to: string }
+  | { kind: 'delete'; path: string }
+  | { kind: 'encode'; from: string; to: string }
+  | { kind: 'embed_artwork'; path: string; bytes: number }
+  | { kind: 'write_field'; path: string; field: string; value: string };
+
+export type JournalOperation =
+  | { kind: 'move'; from: string; to: string }
+  | { kind: 'copy'; from: string; to: string }
+  | { kind: 'write_tags'; path: string; metadata: Metadata; tags: Record<string, string[]> };
+
+export interface UndoneOperation {
+  operation: JournalOperation;
+  outcome: ChangeOutcome;
+}
+
+export interface MaintenanceSummary {
+  started_at: number;
+  finished_at: number;
+  new_files: number;
+  changed_files: number;
+  removed_files: number;
+  pruned_backups: number;
+  unreadable_files: string[];
+  overlapping_roots?: RootOverlap[];
+  errors: string[];
+}
+
+export interface ScanFileFound {
+  path: string;
+  found: number;
+}
+
+export interface ScanProgress {
+  path: string;
+  done: number;
+  total: number;
+}
+
+export interface RootOverlap {
+  root: string;
+  covered_by: string;
+}
+
+export interface MultiScanResult {
+  files: AudioFile[];
+  overlaps: RootOverlap[];
+}
+
+export interface SplitProgress {
+  track: number;
+  total_tracks: number;
+  output_path: string;
+  done: boolean;
+}
+
+export interface ExportPlaylist {
+  name: string;
+  file_paths: string[];
+}
+
+export interface UsbExportOptions {
+  destination: string;
+  tracks?: string[];
+  playlists?: ExportPlaylist[];
+  convert_unsupported?: boolean;
+  flac_supported?: boolean;
+}
+
+export interface ExportedTrack {
+  source_path: string;
+  /** Null when nothing was written, or a copy that failed verification was deleted again. */
+  destination_path: string | null;
+  converted: boolean;
+  verified: boolean;
+  outcome: ChangeOutcome;
+}
+
+export interface ExportedPlaylist {
+  name: string;
+  /** Gets a " (2)" suffix when its FAT32-safe name clashes with another playlist's. */
+  path: string;
+}
+
+export interface UsbExportResult {
+  tracks: ExportedTrack[];
+  playlists: ExportedPlaylist[];
+}
+
+export interface UsbExportProgress {
+  stage: 'copy' | 'verify';
+  current: number;
+  total: number;
+  file_path: string;
+}
+
+export interface TagWriteProgress {
+  file_path: string;
+  size_bytes: number;
+  stage: 'started' | 'working' | 'finished';
+  elapsed_ms: number;
+}
+
+export interface ImportedFile {
+  file_path: string;
+  result: ProcessResult | null;
+  outcome: ChangeOutcome;
+}
+
+export interface ImportSummary {
+  extracted_to: string;
+  files: ImportedFile[];
+}
+
+export interface ArtworkCandidate {
+  source: string;
+  url: string;
+  confidence: 'High' | 'Medium' | 'Low';
+}
+
+export interface ArtworkSummary {
+  embedded: string[];
+  queued_for_review: string[];
+  not_found: string[];
+  already_present: string[];
+  errors: string[];
+}
+
+export interface ArtworkProgress {
+  file_path: string;
+  index: number;
+  total: number;
+}
+
+export interface FileWithMetadata {
+  file_path: string;
+  metadata: Metadata;
+}
+
+export interface OrganizedFile {
+  file_path: string;
+  placement: Placement | null;
+  outcome: ChangeOutcome;
+}
+
+export interface OrganizeProgress {
+  file_path: string;
+  index: number;
+  total: number;
+}
+
+export interface AudioInfo {
+  sample_rate: number;
+  channels: number;
+  duration?: number;
+}
+
+export type AnalysisKind = 'decode' | 'onsets' | 'bpm' | 'key';
+
+export interface AnalysisBenchmark {
+  kind: AnalysisKind;
+  files: number;
+  failed: number;
+  audio_seconds: number;
+  elapsed_ms: number;
+  files_per_second: number;
+  /** Seconds of audio analyzed per second of wall time. */
+  realtime_factor: number;
+  workers: number;
+  processes: number;
+}
+
+export interface KeyAnalysis {
+  /** In the `key_notation` setting's notation, as written to tags. */
+  key: string;
+  standard: string;
+  camelot: string;
+  /** Lead of the best key over the runner-up, 0 to 1. */
+  confidence: number;
+}
+
+export interface GenreBackfill {
+  preview: TransformPreview;
+  /** Siblings with the suggested genre. */
+  agreeing: number;
+  /** Siblings with any genre. */
+  tagged_siblings: number;
+}
+
+export interface GenreBackfillReport {
+  suggestions: GenreBackfill[];
+  /** Write outcomes, when the suggestions were applied. */
+  results: ChangeResult[];
+}
+
+export interface GenreRule {
+  /** Case-insensitive regex matched against the provider's genre. */
+  pattern: string;
+  /** Replacement genre; may use `$1`-style captures. */
+  genre: string;
+  /** Only apply to this provider. */
+  source?: string;
+}
+
+export interface GenreMapping {
+  rules: GenreRule[];
+  keep_unmapped: boolean;
+}
+
+/** Running totals for the session, sent as `stats://update`. */
+export interface SessionStats {
+  files_processed: number;
+  genres_written: number;
+  /** Requests sent to providers, including token refreshes. */
+  api_calls: number;
+  /** Provider searches answered from the lookup cache. */
+  cache_hits: number;
+  errors: number;
+  started_at: number;
+  updated_at: number;
 }